@@ -0,0 +1,133 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Actor mode
+//!
+//! A VecPack<T> can be moved into a background thread, which owns
+//! it and runs the commands sent by the VecPackActor<T> handles one
+//! by one. Handles are cloneable and can be sent to other threads,
+//! so access is serialized without exposing locks. The thread stops,
+//! and the VecPack is dropped, when the last handle is dropped.
+
+use crate::{PackError, PackResult, VecPack, VecPackMember};
+use serde::Deserialize;
+use std::sync::mpsc::{channel, Sender};
+
+// Command run by the actor thread
+type Command<T> = Box<dyn FnOnce(&mut VecPack<T>) + Send>;
+
+/// VecPackActor<T>
+/// Cloneable handle of a VecPack<T> owned by a background thread
+pub struct VecPackActor<T>
+where
+    T: VecPackMember,
+{
+    sender: Sender<Command<T>>,
+}
+
+impl<T> Clone for VecPackActor<T>
+where
+    T: VecPackMember,
+{
+    fn clone(&self) -> Self {
+        VecPackActor {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<T> VecPack<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default + Send + 'static,
+{
+    /// Move VecPack into a background thread
+    /// Returns the handle to send commands to it.
+    pub fn spawn_actor(self) -> VecPackActor<T> {
+        let (sender, commands) = channel::<Command<T>>();
+        let mut vecpack = self;
+        std::thread::spawn(move || {
+            for command in commands {
+                command(&mut vecpack);
+            }
+        });
+        VecPackActor { sender }
+    }
+}
+
+impl<T> VecPackActor<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default + Send + 'static,
+{
+    /// Run a closure on the VecPack in the actor thread,
+    /// and return its result.
+    pub fn call<F, R>(&self, f: F) -> PackResult<R>
+    where
+        F: FnOnce(&mut VecPack<T>) -> PackResult<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let (reply, result) = channel();
+        self.sender
+            .send(Box::new(move |vecpack: &mut VecPack<T>| {
+                let _ = reply.send(f(vecpack));
+            }))
+            .map_err(|_| stopped())?;
+        result.recv().map_err(|_| stopped())?
+    }
+    /// Clone of a member by ID
+    pub fn get(&self, id: &str) -> PackResult<T> {
+        let id = id.to_string();
+        self.call(move |vecpack| Ok(vecpack.find_id(&id)?.unpack().clone()))
+    }
+    /// Update member by ID through closure, then save it
+    pub fn update<F, R>(&self, id: &str, f: F) -> PackResult<R>
+    where
+        F: FnOnce(&mut T) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let id = id.to_string();
+        self.call(move |vecpack| {
+            let mut f = Some(f);
+            vecpack
+                .find_id_mut(&id)?
+                .update(|data| f.take().map(|f| f(data)))?
+                .ok_or_else(|| {
+                    PackError::InternalError("Update ran twice".to_string())
+                })
+        })
+    }
+    /// Insert a new member
+    pub fn insert(&self, item: T) -> PackResult<()> {
+        self.call(move |vecpack| vecpack.insert(item))
+    }
+    /// Remove member by ID, and return its data
+    pub fn remove(&self, id: &str) -> PackResult<T> {
+        let id = id.to_string();
+        self.call(move |vecpack| vecpack.remove_by_id(&id))
+    }
+}
+
+fn stopped() -> PackError {
+    PackError::InternalError("VecPack actor has stopped".to_string())
+}
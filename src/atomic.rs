@@ -0,0 +1,178 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Atomic file writes
+//!
+//! Files are never overwritten in place. Data is written into a
+//! temp file first, synced, then renamed over the target, so a crash
+//! or a full disk never leaves a half written file behind.
+//! Rename is only atomic inside one filesystem, so the temp directory
+//! must be on the same filesystem as the data.
+
+use crate::{PackError, PackResult, VecPack, VecPackMember};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Distinguishes the temp files of a process
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// TempConfig
+/// Where atomic writes create their temp files.
+/// By default temp files are created next to the target file.
+/// Temp file names are always hidden (start with '.'), so they
+/// are never loaded as members, and unique per process and
+/// write, so collections sharing a temp directory never write
+/// the same temp file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TempConfig {
+    dir: Option<PathBuf>,
+    prefix: String,
+}
+
+impl TempConfig {
+    /// New TempConfig
+    /// Temp files next to the target, without custom prefix.
+    pub fn new() -> Self {
+        TempConfig::default()
+    }
+    /// Set temp directory
+    /// Must be on the same filesystem as the data.
+    pub fn dir(mut self, dir: PathBuf) -> Self {
+        self.dir = Some(dir);
+        self
+    }
+    /// Set temp file name prefix
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+    /// Temp directory, None means next to the target file
+    pub fn get_dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+    /// Temp file name prefix
+    pub fn get_prefix(&self) -> &str {
+        &self.prefix
+    }
+    // Unique temp file path of a target file
+    pub(crate) fn temp_path(&self, target: &Path) -> PathBuf {
+        let name = target
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        let dir = match &self.dir {
+            Some(dir) => dir.as_path(),
+            None => target.parent().unwrap_or_else(|| Path::new(".")),
+        };
+        dir.join(format!(
+            ".{}{}.{}-{}.tmp",
+            self.prefix,
+            name,
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ))
+    }
+    // Check that temp files can be created and renamed into dir
+    // Fails if the temp directory is on another filesystem.
+    pub(crate) fn probe(&self, dir: &Path) -> PackResult<()> {
+        if let Some(temp_dir) = &self.dir {
+            std::fs::create_dir_all(temp_dir)?;
+        }
+        let target = dir.join(".temp_probe");
+        let temp = self.temp_path(&target);
+        File::create(&temp)?;
+        if let Err(err) = std::fs::rename(&temp, &target) {
+            let _ = std::fs::remove_file(&temp);
+            return Err(PackError::io(io::Error::new(
+                err.kind(),
+                format!(
+                    "Temp directory {} cannot be used for {}: {}",
+                    temp.parent().unwrap_or(dir).display(),
+                    dir.display(),
+                    err
+                ),
+            )));
+        }
+        std::fs::remove_file(&target)?;
+        Ok(())
+    }
+}
+
+// Write bytes to path atomically through a temp file
+pub(crate) fn write_atomic(
+    path: &Path,
+    bytes: &[u8],
+    temp: &TempConfig,
+) -> PackResult<()> {
+    write_atomic_with(path, temp, |file| Ok(file.write_all(bytes)?))
+}
+
+// Write path atomically, the content is written
+// into the temp file by write
+pub(crate) fn write_atomic_with<F>(
+    path: &Path,
+    temp: &TempConfig,
+    write: F,
+) -> PackResult<()>
+where
+    F: FnOnce(&mut File) -> PackResult<()>,
+{
+    let temp_path = temp.temp_path(path);
+    let res = File::create(&temp_path)
+        .map_err(PackError::from)
+        .and_then(|mut file| {
+            write(&mut file)?;
+            Ok(file.sync_all()?)
+        })
+        .and_then(|_| Ok(std::fs::rename(&temp_path, path)?));
+    if res.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    res
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Set temp file config of the atomic member writes
+    /// Checks that a temp file can be created, and renamed into
+    /// the VecPack directory, so a temp directory on another
+    /// filesystem (or a read-only one) is rejected here, and not
+    /// on the first save.
+    pub fn set_temp_config(&mut self, temp: TempConfig) -> PackResult<()> {
+        self.sync_location();
+        self.check_writable()?;
+        temp.probe(&self.path)?;
+        self.ctx.set_temp_config(temp);
+        Ok(())
+    }
+    /// Temp file config of the atomic member writes
+    pub fn temp_config(&self) -> TempConfig {
+        self.ctx.temp_config()
+    }
+}
@@ -0,0 +1,161 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Audit log
+//!
+//! With enable_audit() every insert, update and removal of a
+//! VecPack member is appended to the hidden .audit.log file of the
+//! VecPack: when, who and what happened to which ID. The log is
+//! append-only, a sequence of YAML documents; audit_iter() reads
+//! it back. "Who" is the actor set by set_audit_actor(), by default
+//! the user running the process.
+
+use crate::{unix_millis, ChangeEvent, ChangeKind, PackResult};
+use crate::{VecPack, VecPackMember};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+/// AuditEntry
+/// A recorded member change
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    /// Time of the change in milliseconds since UNIX EPOCH
+    pub at: u128,
+    /// Who made the change
+    pub actor: String,
+    /// What happened
+    pub kind: ChangeKind,
+    /// Member ID
+    pub id: String,
+}
+
+// Audit log of a VecPack, kept in its context
+#[derive(Debug)]
+pub(crate) struct AuditLog {
+    path: PathBuf,
+    actor: RwLock<String>,
+}
+
+impl AuditLog {
+    fn new(path: PathBuf) -> Self {
+        let actor = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_else(|_| "unknown".to_string());
+        AuditLog {
+            path,
+            actor: RwLock::new(actor),
+        }
+    }
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+    // Follow the VecPack directory when it is moved
+    pub(crate) fn relocate(&mut self, dir: &Path) {
+        if let Some(name) = self.path.file_name() {
+            self.path = dir.join(name);
+        }
+    }
+    pub(crate) fn set_actor(&self, actor: &str) {
+        *self.actor.write().unwrap() = actor.to_string();
+    }
+    // Append a change to the log
+    pub(crate) fn record(&self, event: &ChangeEvent) -> PackResult<()> {
+        let entry = AuditEntry {
+            at: unix_millis(SystemTime::now()),
+            actor: self.actor.read().unwrap().clone(),
+            kind: event.kind,
+            id: event.id.clone(),
+        };
+        let document = serde_yaml::to_string(&entry)?;
+        let document = match document.starts_with("---") {
+            true => document,
+            false => format!("---\n{}", document),
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        // One write per entry, so entries are never interleaved
+        file.write_all(format!("{}\n", document.trim_end()).as_bytes())?;
+        Ok(())
+    }
+}
+
+// Every entry of an audit log file
+fn read_log(path: &Path) -> PackResult<Vec<AuditEntry>> {
+    let buffer = std::fs::read_to_string(path)?;
+    let mut result = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(&buffer) {
+        result.push(AuditEntry::deserialize(document)?);
+    }
+    Ok(result)
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Enable the audit log
+    /// Every later member change is recorded.
+    pub fn enable_audit(&mut self) -> PackResult<()> {
+        self.check_writable()?;
+        let path = self.audit_path();
+        OpenOptions::new().create(true).append(true).open(&path)?;
+        self.ctx.set_audit(Some(AuditLog::new(path)));
+        Ok(())
+    }
+    /// True if the audit log is enabled
+    pub fn is_audited(&self) -> bool {
+        self.audit_path().exists()
+    }
+    /// Set who makes the next changes, e.g. the logged in user
+    /// Has no effect until the audit log is enabled.
+    pub fn set_audit_actor(&self, actor: &str) {
+        self.ctx.set_audit_actor(actor);
+    }
+    /// Iterate over the audit log, oldest first
+    pub fn audit_iter(&self) -> PackResult<impl Iterator<Item = AuditEntry>> {
+        let path = self.audit_path();
+        let entries = match path.exists() {
+            true => read_log(&path)?,
+            false => Vec::new(),
+        };
+        Ok(entries.into_iter())
+    }
+    // Restore the audit log
+    pub(crate) fn load_audit(&mut self) {
+        let path = self.audit_path();
+        if path.exists() {
+            self.ctx.set_audit(Some(AuditLog::new(path)));
+        }
+    }
+    fn audit_path(&self) -> PathBuf {
+        self.dir().join(".audit.log")
+    }
+}
@@ -0,0 +1,335 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Storage backends
+//!
+//! The raw file operations (read, write, list, delete) of Pack<T>
+//! and VecPack<T> can go through a StorageBackend, so packs can be
+//! stored elsewhere than the local filesystem, e.g. in memory or in
+//! an object store. Paths are used as keys; a backend does not need
+//! real directories.
+//!
+//! Packs loaded with load_or_init_with_backend() use their backend
+//! for loading, saving, reloading and removing members. Packs
+//! loaded with load_or_init() use the filesystem directly, the
+//! same as FsBackend. The filesystem specific features (sharding,
+//! trash, single writer lock, watch, append-only sealing, ...)
+//! always work on the local filesystem.
+
+use crate::header::{self, Stamp};
+use crate::{
+    atomic, member_files, Pack, PackError, PackResult, TempConfig, VecPack,
+    VecPackMember,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Cursor, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// StorageBackend
+/// Raw file operations used by Pack<T> and VecPack<T>
+pub trait StorageBackend: Send + Sync {
+    /// Read file content
+    /// Returns PackError::PathNotFound if it does not exist.
+    fn read(&self, path: &Path) -> PackResult<Vec<u8>>;
+    /// Write file content, creating or replacing the file
+    /// Should be atomic: readers see the old or the new content.
+    fn write(&self, path: &Path, bytes: &[u8]) -> PackResult<()>;
+    /// Write file content produced by encode, see write
+    /// The default implementation collects the content into a
+    /// buffer, then calls write; FsBackend writes it into its
+    /// temp file instead.
+    fn write_with(
+        &self,
+        path: &Path,
+        encode: &mut dyn FnMut(&mut dyn BackendWriter) -> PackResult<()>,
+    ) -> PackResult<()> {
+        let mut buffer = Cursor::new(Vec::new());
+        encode(&mut buffer)?;
+        self.write(path, buffer.get_ref())
+    }
+    /// Member files of a directory, sorted by file name
+    /// Hidden files (starting with '.') are skipped.
+    fn list(&self, dir: &Path) -> PackResult<Vec<PathBuf>>;
+    /// Delete a file
+    /// Returns PackError::PathNotFound if it does not exist.
+    fn delete(&self, path: &Path) -> PackResult<()>;
+}
+
+/// BackendWriter
+/// Seekable writer of StorageBackend::write_with
+pub trait BackendWriter: Write + Seek {}
+
+impl<W: Write + Seek> BackendWriter for W {}
+
+/// FsBackend
+/// Local filesystem backend with atomic writes
+#[derive(Debug, Clone, Default)]
+pub struct FsBackend {
+    temp: TempConfig,
+}
+
+impl FsBackend {
+    /// New FsBackend with default temp file config
+    pub fn new() -> Self {
+        FsBackend::default()
+    }
+    /// Set the temp file config of the atomic writes
+    pub fn temp_config(mut self, temp: TempConfig) -> Self {
+        self.temp = temp;
+        self
+    }
+}
+
+// Map missing files to PackError::PathNotFound
+fn fs_error(err: std::io::Error) -> PackError {
+    match err.kind() {
+        std::io::ErrorKind::NotFound => PackError::PathNotFound,
+        _ => err.into(),
+    }
+}
+
+impl StorageBackend for FsBackend {
+    fn read(&self, path: &Path) -> PackResult<Vec<u8>> {
+        std::fs::read(path).map_err(fs_error)
+    }
+    fn write(&self, path: &Path, bytes: &[u8]) -> PackResult<()> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.exists()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        atomic::write_atomic(path, bytes, &self.temp)
+    }
+    fn write_with(
+        &self,
+        path: &Path,
+        encode: &mut dyn FnMut(&mut dyn BackendWriter) -> PackResult<()>,
+    ) -> PackResult<()> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.exists()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        atomic::write_atomic_with(path, &self.temp, |file| encode(file))
+    }
+    fn list(&self, dir: &Path) -> PackResult<Vec<PathBuf>> {
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        member_files(dir)
+    }
+    fn delete(&self, path: &Path) -> PackResult<()> {
+        std::fs::remove_file(path).map_err(fs_error)
+    }
+}
+
+/// MemoryBackend
+/// Keeps the files in memory, e.g. for tests.
+/// Clones share the same files.
+#[derive(Clone, Default)]
+pub struct MemoryBackend {
+    files: Arc<Mutex<BTreeMap<PathBuf, Vec<u8>>>>,
+}
+
+impl MemoryBackend {
+    /// New empty MemoryBackend
+    pub fn new() -> Self {
+        MemoryBackend::default()
+    }
+    /// Number of stored files
+    pub fn len(&self) -> usize {
+        self.files.lock().unwrap().len()
+    }
+    /// True if there is no stored file
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl fmt::Debug for MemoryBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryBackend")
+            .field("files", &self.len())
+            .finish()
+    }
+}
+
+impl StorageBackend for MemoryBackend {
+    fn read(&self, path: &Path) -> PackResult<Vec<u8>> {
+        self.files
+            .lock()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .ok_or(PackError::PathNotFound)
+    }
+    fn write(&self, path: &Path, bytes: &[u8]) -> PackResult<()> {
+        self.files
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), bytes.to_vec());
+        Ok(())
+    }
+    fn list(&self, dir: &Path) -> PackResult<Vec<PathBuf>> {
+        Ok(self
+            .files
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|path| path.parent() == Some(dir))
+            .filter(|path| {
+                !path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|n| n.starts_with('.'))
+                    .unwrap_or(true)
+            })
+            .cloned()
+            .collect())
+    }
+    fn delete(&self, path: &Path) -> PackResult<()> {
+        self.files
+            .lock()
+            .unwrap()
+            .remove(path)
+            .map(|_| ())
+            .ok_or(PackError::PathNotFound)
+    }
+}
+
+// Object key of a path: its normal components joined by '/'
+#[cfg(any(feature = "s3", feature = "remote", feature = "wasm"))]
+pub(crate) fn path_key(path: &Path) -> String {
+    path.components()
+        .filter_map(|c| match c {
+            std::path::Component::Normal(s) => Some(s.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// URI encoding of everything but the unreserved characters
+// '/' is kept in paths, but encoded in query values.
+#[cfg(any(feature = "s3", feature = "remote"))]
+pub(crate) fn uri_encode(value: &str, encode_slash: bool) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => (b as char).to_string(),
+            b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            b'/' if !encode_slash => "/".to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+impl<T> Pack<T>
+where
+    for<'de> T: Serialize + Deserialize<'de> + Sized + Clone,
+{
+    /// Load or init Pack<T> through a storage backend
+    /// The same as load_or_init, but file operations go
+    /// through the backend.
+    pub fn load_or_init_with_backend(
+        path: PathBuf,
+        file_id: &str,
+        backend: Arc<dyn StorageBackend>,
+    ) -> PackResult<Pack<T>>
+    where
+        T: Default,
+    {
+        let path = path.join(format!("{}.yml", file_id));
+        match Pack::<T>::load_from_backend(path.clone(), backend.clone()) {
+            Err(PackError::PathNotFound) => {
+                let pack = Pack {
+                    data: T::default(),
+                    path,
+                    ctx: Default::default(),
+                    hooks: Default::default(),
+                    stamp: Default::default(),
+                };
+                pack.ctx.set_backend(backend);
+                pack.save()?;
+                Ok(pack)
+            }
+            Ok(pack) => {
+                pack.save_migrated()?;
+                Ok(pack)
+            }
+            res => res,
+        }
+    }
+    // Load Pack<T> through a backend
+    // Migrated data is not saved back yet, see save_migrated.
+    pub(crate) fn load_from_backend(
+        path: PathBuf,
+        backend: Arc<dyn StorageBackend>,
+    ) -> PackResult<Pack<T>> {
+        let load = || {
+            let bytes = backend.read(&path)?;
+            header::decode_from::<T, _>(bytes.as_slice())
+        };
+        let (data, header, migrated) =
+            load().map_err(|err: PackError| err.with_path(&path))?;
+        let pack = Pack {
+            data,
+            path,
+            ctx: Default::default(),
+            hooks: Default::default(),
+            stamp: Stamp::loaded(header, migrated),
+        };
+        pack.ctx.set_backend(backend);
+        Ok(pack)
+    }
+}
+
+impl<T> VecPack<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    /// Load or init VecPack<T> through a storage backend
+    /// The same as load_or_init, but member files are listed,
+    /// loaded, saved and removed through the backend.
+    pub fn load_or_init_with_backend(
+        path: PathBuf,
+        backend: Arc<dyn StorageBackend>,
+    ) -> PackResult<VecPack<T>> {
+        let mut result: VecPack<T> = VecPack::empty(path);
+        result.ctx.set_backend(backend);
+        for file in result.list_members()? {
+            let pack = result.load_member(file)?;
+            result.add_member(pack)?;
+        }
+        result.load_modes()?;
+        Ok(result)
+    }
+    // Member files through the backend, if any
+    pub(crate) fn list_members(&self) -> PackResult<Vec<PathBuf>> {
+        match self.ctx.backend() {
+            Some(backend) => backend.list(&self.dir()),
+            None => member_files(&self.dir()),
+        }
+    }
+}
@@ -0,0 +1,295 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Backup
+//!
+//! backup_to() writes a consistent snapshot of a Pack or a VecPack
+//! into a gzip compressed tar archive. The snapshot is taken from
+//! memory while the pack is borrowed, so no write can race it; use
+//! SharedVecPack::backup_to to hold its read lock meanwhile. The
+//! archive contains the member files with their header, under their
+//! path relative to the VecPack directory, so it can be extracted
+//! with any tar as well.
+//!
+//! restore_from() loads an archive back. Every archived file is
+//! decoded (and migrated) first, so a broken archive changes
+//! nothing; then the members are restored by the RestoreStrategy.
+
+use crate::atomic::{self, TempConfig};
+use crate::shared::{SharedPack, SharedVecPack};
+use crate::{
+    check_member_id, header, unix_millis, Pack, PackError, PackResult, VecPack,
+    VecPackMember,
+};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// RestoreStrategy
+/// How restore_from() treats the current members
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RestoreStrategy {
+    /// VecPack becomes the archived collection: members that
+    /// are not in the archive are removed, the others are
+    /// overwritten by the archived version.
+    Replace,
+    /// Archived members are added to the current ones;
+    /// the policy decides about the IDs present in both.
+    Merge(ConflictPolicy),
+}
+
+/// ConflictPolicy
+/// What to do with an archived member whose ID is taken
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConflictPolicy {
+    /// Keep the current member, skip the archived one
+    KeepExisting,
+    /// Overwrite the current member with the archived one
+    Overwrite,
+    /// Restore nothing, return PackError::IDTaken
+    Fail,
+}
+
+/// RestoreReport
+/// Result of restore_from()
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RestoreReport {
+    /// IDs of the members added from the archive
+    pub inserted: Vec<String>,
+    /// IDs of the members overwritten by the archive
+    pub overwritten: Vec<String>,
+    /// IDs of the archived members skipped by the policy
+    pub skipped: Vec<String>,
+    /// IDs of the members removed by Replace
+    pub removed: Vec<String>,
+}
+
+// Write files into a .tar.gz archive atomically
+pub(crate) fn write_archive(
+    path: &Path,
+    files: Vec<(PathBuf, Vec<u8>)>,
+) -> PackResult<()> {
+    let mtime = (unix_millis(SystemTime::now()) / 1000) as u64;
+    let mut builder =
+        tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for (file, content) in files {
+        let mut entry = tar::Header::new_gnu();
+        entry.set_size(content.len() as u64);
+        entry.set_mode(0o644);
+        entry.set_mtime(mtime);
+        entry.set_cksum();
+        builder.append_data(&mut entry, file, content.as_slice())?;
+    }
+    let bytes = builder.into_inner()?.finish()?;
+    if let Some(dir) = path.parent().filter(|dir| !dir.exists()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    atomic::write_atomic(path, &bytes, &TempConfig::default())
+}
+
+// Read and decode every file of a .tar.gz archive
+fn read_archive<T: DeserializeOwned>(path: &Path) -> PackResult<Vec<T>> {
+    let file = std::fs::File::open(path)
+        .map_err(|err| PackError::from(err).with_path(path))?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    let mut result = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.to_path_buf();
+        let mut buffer = Vec::new();
+        entry.read_to_end(&mut buffer)?;
+        let (data, _) = header::decode::<T>(&buffer)
+            .map_err(|err| err.with_path(&path.join(name)))?;
+        result.push(data);
+    }
+    Ok(result)
+}
+
+impl<T> Pack<T>
+where
+    for<'de> T: Serialize + Deserialize<'de> + Sized + Clone,
+{
+    /// Backup Pack<T> into a .tar.gz archive at path
+    /// The archive contains the pack file by its file name.
+    pub fn backup_to(&self, path: &Path) -> PackResult<()> {
+        let name = self.path.file_name().unwrap_or_default();
+        write_archive(path, vec![(name.into(), header::encode(&self.data)?)])
+    }
+    /// Restore Pack<T> from a backup_to() archive, then save it
+    pub fn restore_from(&mut self, archive: &Path) -> PackResult<()> {
+        let data = read_archive::<T>(archive)?.pop().ok_or_else(|| {
+            PackError::InternalError("Backup archive is empty".to_string())
+        })?;
+        self.update(|d| *d = data.clone())
+    }
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Backup VecPack<T> into a .tar.gz archive at path
+    /// The archive contains every member file, relative
+    /// to the VecPack directory.
+    /// Returns the number of archived members.
+    pub fn backup_to(&self, path: &Path) -> PackResult<usize> {
+        let mut files = Vec::new();
+        for pack in &self.data {
+            let file = pack.path.strip_prefix(&self.path).unwrap_or(&pack.path);
+            files.push((file.to_path_buf(), header::encode(&pack.data)?));
+        }
+        let count = files.len();
+        write_archive(path, files)?;
+        Ok(count)
+    }
+    /// Restore members from a backup_to() archive
+    /// Every archived member is decoded and validated before any
+    /// change, so an invalid archive, a duplicated or taken ID, or
+    /// an ID conflict with ConflictPolicy::Fail leaves the VecPack
+    /// untouched.
+    pub fn restore_from(
+        &mut self,
+        archive: &Path,
+        strategy: RestoreStrategy,
+    ) -> PackResult<RestoreReport>
+    where
+        for<'de> T: Deserialize<'de> + Default,
+    {
+        self.restore_members(read_archive::<T>(archive)?, strategy)
+    }
+    // Restore decoded members by the strategy
+    pub(crate) fn restore_members(
+        &mut self,
+        members: Vec<T>,
+        strategy: RestoreStrategy,
+    ) -> PackResult<RestoreReport>
+    where
+        for<'de> T: Deserialize<'de> + Default,
+    {
+        self.sync_location();
+        self.check_writable()?;
+        let mut archived: HashSet<String> = HashSet::new();
+        if !members
+            .iter()
+            .all(|m| archived.insert(m.get_id().to_string()))
+        {
+            return Err(PackError::IDTaken);
+        }
+        // Expired members not purged yet are members as well
+        let current: HashSet<String> =
+            self.data.iter().map(|m| m.get_id().to_string()).collect();
+        let policy = match strategy {
+            RestoreStrategy::Replace => ConflictPolicy::Overwrite,
+            RestoreStrategy::Merge(policy) => policy,
+        };
+        let conflicts: Vec<String> = archived
+            .iter()
+            .filter(|id| current.contains(*id))
+            .cloned()
+            .collect();
+        if policy == ConflictPolicy::Fail && !conflicts.is_empty() {
+            return Err(PackError::IDTaken);
+        }
+        let mut removed: Vec<&String> = match strategy {
+            RestoreStrategy::Replace => current
+                .iter()
+                .filter(|id| !archived.contains(*id))
+                .collect(),
+            RestoreStrategy::Merge(_) => Vec::new(),
+        };
+        removed.sort();
+        let overwrite = policy == ConflictPolicy::Overwrite;
+        if !removed.is_empty() || (overwrite && !conflicts.is_empty()) {
+            self.check_mutable()?;
+        }
+        // Check every write before the first change
+        for member in &members {
+            let id = member.get_id();
+            if !current.contains(id) && !self.check_id_available(id) {
+                return Err(PackError::IDTaken);
+            }
+            if current.contains(id) && !overwrite {
+                continue;
+            }
+            check_member_id(id)?;
+            let mut member = member.clone();
+            self.hooks.before_save(&mut member);
+            self.hooks
+                .validate(&member)
+                .map_err(|err| err.with_id(id))?;
+        }
+        let mut report = RestoreReport::default();
+        for id in removed {
+            self.remove_by_id(id)?;
+            report.removed.push(id.clone());
+        }
+        // Restored members do not keep an expiry
+        if overwrite {
+            self.forget_expiries(&conflicts)?;
+        }
+        for member in members {
+            let id = member.get_id().to_string();
+            if !current.contains(&id) {
+                self.insert(member)?;
+                report.inserted.push(id);
+            } else if policy == ConflictPolicy::Overwrite {
+                self.find_id_mut(&id)?.update(|d| *d = member.clone())?;
+                report.overwritten.push(id);
+            } else {
+                report.skipped.push(id);
+            }
+        }
+        Ok(report)
+    }
+}
+
+impl<T> SharedPack<T>
+where
+    for<'de> T: Serialize + Deserialize<'de> + Sized + Clone,
+{
+    /// The same as Pack::backup_to, holds the read lock
+    pub fn backup_to(&self, path: &Path) -> PackResult<()> {
+        self.read().backup_to(path)
+    }
+}
+
+impl<T> SharedVecPack<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    /// The same as VecPack::backup_to, holds the read lock
+    pub fn backup_to(&self, path: &Path) -> PackResult<usize> {
+        self.read().backup_to(path)
+    }
+}
@@ -0,0 +1,318 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Bulk mutation of a VecPack<T>
+//!
+//! Mutable iteration with as_mut() saves every member on drop.
+//! BatchGuard hands out members as BatchMember guards, that mark
+//! their member changed at the first mutable access, as PackGuard
+//! does. Only the marked members are written, once, at commit or
+//! drop; reading through a guard costs nothing.
+//!
+//! ```rust,ignore
+//! let mut batch = cars.batch_mut()?;
+//! for mut car in batch.iter_mut() {
+//!     if car.hp > 600 {
+//!         car.name.push_str(" (fast)");
+//!     }
+//! }
+//! let saved = batch.commit()?;
+//! ```
+//!
+//! With many changed members, commit_parallel writes them on a
+//! bounded pool of workers, and reports every failed member.
+
+use crate::{pool, Pack, PackError, PackResult, VecPack, VecPackMember};
+use serde::{Deserialize, Serialize};
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+
+// Serialized member before its first mutable access, None until
+// then. The inner None if it cannot be serialized, so it cannot
+// be rolled back.
+pub(crate) type Backup = Option<Option<String>>;
+
+/// SaveReport
+/// Result of a bulk save, e.g. BatchGuard::commit_parallel()
+#[derive(Debug, Default)]
+pub struct SaveReport {
+    /// IDs of the saved members
+    pub saved: Vec<String>,
+    /// IDs of the members failed to save, with their error
+    pub failed: Vec<(String, PackError)>,
+}
+
+impl SaveReport {
+    /// True if every member was saved
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty()
+    }
+    // Add the save result of a member
+    pub(crate) fn push(&mut self, id: &str, res: PackResult<()>) {
+        match res {
+            Ok(_) => self.saved.push(id.to_string()),
+            Err(err) => self.failed.push((id.to_string(), err)),
+        }
+    }
+}
+
+/// BatchGuard<'a, T>
+/// Created by VecPack::batch_mut(). Changed members are saved
+/// by commit(), or at drop; discard() drops the changes.
+pub struct BatchGuard<'a, T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    vecpack: &'a mut VecPack<T>,
+    // Backup of every member, by position
+    backups: Vec<Backup>,
+    // Committed or discarded, so nothing to do at drop
+    finished: bool,
+}
+
+/// BatchMember<'b, T>
+/// Member handed out by BatchGuard::iter_mut(). Derefs to T,
+/// and the first mutable access marks the member changed.
+pub struct BatchMember<'b, T>
+where
+    T: Serialize,
+{
+    data: &'b mut T,
+    backup: &'b mut Backup,
+}
+
+impl<'b, T> BatchMember<'b, T>
+where
+    T: Serialize,
+{
+    pub(crate) fn new(data: &'b mut T, backup: &'b mut Backup) -> Self {
+        BatchMember { data, backup }
+    }
+}
+
+impl<'b, T> Deref for BatchMember<'b, T>
+where
+    T: Serialize,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
+}
+
+impl<'b, T> DerefMut for BatchMember<'b, T>
+where
+    T: Serialize,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        track(self.backup, self.data);
+        self.data
+    }
+}
+
+// Keep the serialized member at its first mutable access
+fn track<T>(backup: &mut Backup, data: &T)
+where
+    T: Serialize,
+{
+    if backup.is_none() {
+        *backup = Some(serde_yaml::to_string(data).ok());
+    }
+}
+
+impl<'a, T> BatchGuard<'a, T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    /// Mutable reference of a member by ID
+    /// Returns PackError::ObjectNotFound if there is no such member.
+    pub fn get_mut(&mut self, id: &str) -> PackResult<&mut T> {
+        let position =
+            self.vecpack.position(id).ok_or(PackError::ObjectNotFound)?;
+        self.vecpack.member_touched(id);
+        let data = &mut self.vecpack.data[position].data;
+        track(&mut self.backups[position], data);
+        Ok(data)
+    }
+    /// Mutable iterator over all the members
+    /// Only the members accessed mutably are saved.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = BatchMember<'_, T>> {
+        let (members, backups) = self.members_mut();
+        members
+            .iter_mut()
+            .zip(backups.iter_mut())
+            .map(|(pack, backup)| BatchMember::new(&mut pack.data, backup))
+    }
+    /// IDs of the members accessed mutably so far
+    pub fn modified(&self) -> Vec<String> {
+        self.changed()
+            .into_iter()
+            .map(|position| self.vecpack.data[position].get_id().to_string())
+            .collect()
+    }
+    /// Save the changed members
+    /// Returns the number of saved members. If a save fails, that
+    /// member is rolled back, the others are still saved, and the
+    /// first error is returned.
+    pub fn commit(mut self) -> PackResult<usize> {
+        self.finished = true;
+        let (saved, mut errors) = self.write_changed(|packs| {
+            packs.into_iter().map(|pack| pack.save()).collect()
+        });
+        match errors.is_empty() {
+            true => Ok(saved.len()),
+            false => Err(errors.remove(0).2),
+        }
+    }
+    /// Save the changed members concurrently
+    /// The same as commit, but at most workers members are written
+    /// at once; 0 means a worker per available core. Failed members
+    /// are rolled back, and all of them are in the report.
+    pub fn commit_parallel(mut self, workers: usize) -> SaveReport
+    where
+        T: Sync,
+    {
+        self.finished = true;
+        let (saved, errors) = self.write_changed(|packs| {
+            pool::map_ordered(packs, pool::workers(workers), |pack| pack.save())
+        });
+        SaveReport {
+            saved,
+            failed: errors.into_iter().map(|(id, _, err)| (id, err)).collect(),
+        }
+    }
+    /// Discard changes
+    /// Restores the members, and nothing is saved. Returns the
+    /// first member that could not be restored, the rest are
+    /// restored anyway.
+    pub fn discard(mut self) -> PackResult<()> {
+        self.finished = true;
+        let backups = std::mem::take(&mut self.backups);
+        let mut res = Ok(());
+        for (position, backup) in backups.into_iter().enumerate() {
+            if let Some(Some(backup)) = backup {
+                let restored = self.vecpack.data[position].rollback(&backup);
+                res = res.and(restored);
+            }
+        }
+        res
+    }
+    // All the members with their backups, as any of them
+    // can be borrowed mutably
+    pub(crate) fn members_mut(&mut self) -> (&mut [Pack<T>], &mut [Backup]) {
+        self.vecpack.members_touched();
+        (&mut self.vecpack.data, &mut self.backups)
+    }
+    // Positions of the members accessed mutably
+    fn changed(&self) -> Vec<usize> {
+        self.backups
+            .iter()
+            .enumerate()
+            .filter(|(_, backup)| backup.is_some())
+            .map(|(position, _)| position)
+            .collect()
+    }
+    // Save the changed members by save, and roll back the failed ones
+    // save gets the changed members, and returns their save results
+    // in the same order. Returns the saved IDs, and the failures.
+    fn write_changed<S>(
+        &mut self,
+        save: S,
+    ) -> (Vec<String>, Vec<(String, PathBuf, PackError)>)
+    where
+        S: FnOnce(Vec<&Pack<T>>) -> Vec<PackResult<()>>,
+    {
+        let changed = self.changed();
+        let mut backups = std::mem::take(&mut self.backups);
+        for position in &changed {
+            let pack = &mut self.vecpack.data[*position];
+            pack.hooks.before_save(&mut pack.data);
+        }
+        let packs = changed.iter().map(|p| &self.vecpack.data[*p]).collect();
+        let results = save(packs);
+        let mut saved = Vec::new();
+        let mut errors = Vec::new();
+        for (position, res) in changed.into_iter().zip(results) {
+            let pack = &mut self.vecpack.data[position];
+            let id = pack.get_id().to_string();
+            match res {
+                Ok(_) => {
+                    self.vecpack.record_write(&id);
+                    saved.push(id);
+                }
+                Err(err) => {
+                    // A failed rollback is reported instead
+                    let err = match backups[position].take() {
+                        Some(Some(backup)) => {
+                            pack.rollback(&backup).err().unwrap_or(err)
+                        }
+                        _ => err,
+                    };
+                    errors.push((id, pack.path.clone(), err));
+                }
+            }
+        }
+        (saved, errors)
+    }
+}
+
+impl<'a, T> Drop for BatchGuard<'a, T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        // Drop cannot return PackError, so failures are logged,
+        // and passed to the save error hook.
+        let (_, errors) = self.write_changed(|packs| {
+            packs.into_iter().map(|pack| pack.save()).collect()
+        });
+        for (_, path, err) in errors {
+            self.vecpack.ctx.background_save_failed(&path, err);
+        }
+    }
+}
+
+impl<T> VecPack<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    /// Start a bulk mutation
+    /// Only the members accessed mutably through the guard are
+    /// saved, once, when it is committed or dropped.
+    /// Returns PackError::Immutable if VecPack is append-only.
+    pub fn batch_mut(&mut self) -> PackResult<BatchGuard<'_, T>> {
+        self.check_mutable()?;
+        self.sync_location();
+        Ok(BatchGuard {
+            backups: vec![None; self.data.len()],
+            vecpack: self,
+            finished: false,
+        })
+    }
+}
@@ -0,0 +1,85 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! storaget-server
+//!
+//! Serves a storage directory over HTTP for RemoteBackend clients.
+//!
+//! Usage: storaget-server [--insecure] <root dir> [address]
+//! e.g. STORAGET_TOKEN=... storaget-server data 127.0.0.1:7878
+//!
+//! The address defaults to 127.0.0.1:7878. Clients must send the
+//! STORAGET_TOKEN token; serving without one needs --insecure.
+//! STORAGET_MAX_BODY sets the request body limit in bytes.
+
+use std::path::PathBuf;
+use storaget::remote::RemoteServer;
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+    let insecure = match args.iter().position(|arg| arg == "--insecure") {
+        Some(pos) => {
+            args.remove(pos);
+            true
+        }
+        None => false,
+    };
+    if args.len() < 2 || args.len() > 3 {
+        eprintln!("Usage: {} [--insecure] <root dir> [address]", args[0]);
+        std::process::exit(2);
+    }
+    let root = &args[1];
+    let addr = args.get(2).map(String::as_str).unwrap_or("127.0.0.1:7878");
+    let token = std::env::var("STORAGET_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty());
+    if token.is_none() && !insecure {
+        eprintln!("Set STORAGET_TOKEN, or use --insecure to serve without it");
+        std::process::exit(2);
+    }
+    let mut server = match RemoteServer::serve_dir(addr, PathBuf::from(root)) {
+        Ok(server) => server,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    if let Some(token) = token {
+        server = server.token(&token);
+    }
+    if let Ok(max) = std::env::var("STORAGET_MAX_BODY") {
+        match max.parse() {
+            Ok(max) => server = server.max_body_size(max),
+            Err(_) => {
+                eprintln!("Invalid STORAGET_MAX_BODY: {}", max);
+                std::process::exit(2);
+            }
+        }
+    }
+    if let Some(addr) = server.local_addr() {
+        println!("Serving {} on http://{}", root, addr);
+    }
+    server.run();
+}
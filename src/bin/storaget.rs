@@ -0,0 +1,149 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! storaget
+//!
+//! Inspection and admin tool for storage directories. Works on
+//! any VecPack directory without knowing its member type.
+//!
+//! Usage:
+//!   storaget list <dir>
+//!   storaget show <dir> <id>
+//!   storaget check <dir>
+//!   storaget convert <dir> <from> <to>    e.g. convert data yaml json
+//!   storaget schema-diff <dir> <sample>
+//!   storaget bundle <file>
+//!
+//! Exits with 1 if the command failed or found problems,
+//! with 2 on invalid usage.
+
+use std::fs::File;
+use std::path::Path;
+use std::process::exit;
+use storaget::header::Format;
+use storaget::{convert, inspect, PackError, PackResult, SupportBundle};
+
+const USAGE: &str = "Usage:
+  storaget list <dir>
+  storaget show <dir> <id>
+  storaget check <dir>
+  storaget convert <dir> <from> <to>
+  storaget schema-diff <dir> <sample>
+  storaget bundle <file>";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+    let result = match args.as_slice() {
+        ["list", dir] => list(Path::new(dir)),
+        ["show", dir, id] => show(Path::new(dir), id),
+        // validate is the former name of check
+        ["check", dir] | ["validate", dir] => check(Path::new(dir)),
+        ["convert", dir, from, to] => convert(Path::new(dir), from, to),
+        ["schema-diff", dir, sample] => {
+            schema_diff(Path::new(dir), Path::new(sample))
+        }
+        ["bundle", file] => bundle(Path::new(file)),
+        _ => {
+            eprintln!("{}", USAGE);
+            exit(2);
+        }
+    };
+    match result {
+        Ok(true) => (),
+        Ok(false) => exit(1),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            exit(1);
+        }
+    }
+}
+
+// Each command returns false if it found problems
+
+fn list(dir: &Path) -> PackResult<bool> {
+    for path in inspect::member_files(dir)? {
+        match inspect::read_member(&path) {
+            Ok(member) => println!(
+                "{}\t{}\tv{}\t{} bytes",
+                member.id,
+                member.header.format.as_str(),
+                member.header.schema_version,
+                member.size
+            ),
+            Err(err) => println!("{}\tunreadable: {}", path.display(), err),
+        }
+    }
+    Ok(true)
+}
+
+fn show(dir: &Path, id: &str) -> PackResult<bool> {
+    let member = inspect::find_member(dir, id)?;
+    println!("{}", member.header);
+    print!("{}", serde_yaml::to_string(&member.value)?);
+    Ok(true)
+}
+
+fn check(dir: &Path) -> PackResult<bool> {
+    let report = storaget::verify_dir(dir)?;
+    for issue in &report.issues {
+        println!("{}", issue);
+    }
+    println!("{} files, {} problems", report.files, report.issues.len());
+    Ok(report.is_ok())
+}
+
+fn convert(dir: &Path, from: &str, to: &str) -> PackResult<bool> {
+    let report =
+        convert::convert_dir(dir, Format::parse(from)?, Format::parse(to)?)?;
+    println!(
+        "{} files converted, backup: {}",
+        report.converted,
+        report.backup.display()
+    );
+    Ok(true)
+}
+
+fn schema_diff(dir: &Path, sample: &Path) -> PackResult<bool> {
+    let sample = serde_yaml::from_str(&std::fs::read_to_string(sample)?)
+        .map_err(|err| PackError::InternalError(err.to_string()))?;
+    let diff = storaget::schema_diff_sample(dir, &sample)?;
+    for (field, count) in &diff.dropped {
+        println!("- {} (dropped from {} files)", field, count);
+    }
+    for (field, count) in &diff.defaulted {
+        println!("+ {} (defaulted in {} files)", field, count);
+    }
+    for (path, err) in &diff.failed {
+        println!("! {}: {}", path.display(), err);
+    }
+    println!("{} files checked", diff.files);
+    Ok(diff.is_clean())
+}
+
+fn bundle(file: &Path) -> PackResult<bool> {
+    print!("{}", SupportBundle::load(File::open(file)?)?);
+    Ok(true)
+}
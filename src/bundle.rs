@@ -0,0 +1,195 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Support bundle
+//!
+//! A support bundle describes a registry for bug reports, without
+//! the stored data itself: the manifest, per collection member
+//! counts and sizes, the enabled modes, and a check of the member
+//! files. Member IDs are redacted to a short hash, so the bundle can
+//! be attached to a public issue. The bundle is a gzip compressed
+//! YAML document, readable with any gunzip as well.
+
+use crate::{
+    header, member_files, unix_millis, PackError, PackResult, Registry,
+};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+/// SupportBundle
+/// Redacted description of a registry
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SupportBundle {
+    /// storaget version that created the bundle
+    pub crate_version: String,
+    /// Creation time in milliseconds since UNIX EPOCH
+    pub created_at: u128,
+    /// Collections of the registry manifest
+    pub collections: Vec<CollectionReport>,
+}
+
+/// CollectionReport
+/// Redacted description of a collection
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CollectionReport {
+    /// Collection name
+    pub name: String,
+    /// Number of member files
+    pub members: usize,
+    /// Total size of the member files in bytes
+    pub bytes: u64,
+    /// Enabled modes, e.g. append_only, sharded
+    pub modes: Vec<String>,
+    /// Number of soft removed members in the trash
+    pub trashed: usize,
+    /// Member files that are not valid YAML,
+    /// as (redacted ID, error)
+    pub unreadable: Vec<(String, String)>,
+}
+
+impl SupportBundle {
+    /// Load a support bundle
+    /// Reads the gzip compressed bundle from the reader.
+    pub fn load<R: Read>(reader: R) -> PackResult<SupportBundle> {
+        let mut buffer = String::new();
+        GzDecoder::new(reader).read_to_string(&mut buffer)?;
+        serde_yaml::from_str(&buffer).map_err(PackError::deserialize)
+    }
+    /// Write the support bundle gzip compressed
+    pub fn write<W: Write>(&self, writer: W) -> PackResult<()> {
+        let mut encoder = GzEncoder::new(writer, Compression::default());
+        encoder.write_all(serde_yaml::to_string(self)?.as_bytes())?;
+        encoder.finish()?;
+        Ok(())
+    }
+}
+
+// Human readable summary
+impl fmt::Display for SupportBundle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "storaget {} support bundle, created at {}",
+            self.crate_version, self.created_at
+        )?;
+        for c in &self.collections {
+            writeln!(
+                f,
+                "- {}: {} members, {} bytes, {} trashed, modes: [{}]",
+                c.name,
+                c.members,
+                c.bytes,
+                c.trashed,
+                c.modes.join(", ")
+            )?;
+            for (id, err) in &c.unreadable {
+                writeln!(f, "    unreadable {}: {}", id, err)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Registry {
+    /// Support bundle of the registry
+    /// See SupportBundle.
+    pub fn support_bundle(&self) -> PackResult<SupportBundle> {
+        let mut collections = Vec::new();
+        for name in self.collections() {
+            collections
+                .push(collection_report(name, &self.collection_path(name))?);
+        }
+        Ok(SupportBundle {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: unix_millis(SystemTime::now()),
+            collections,
+        })
+    }
+    /// Dump support bundle
+    /// Writes the gzip compressed support bundle to the writer.
+    pub fn dump_support_bundle<W: Write>(&self, writer: W) -> PackResult<()> {
+        self.support_bundle()?.write(writer)
+    }
+}
+
+// Report of a collection directory
+fn collection_report(name: &str, dir: &Path) -> PackResult<CollectionReport> {
+    let mut report = CollectionReport {
+        name: name.to_string(),
+        members: 0,
+        bytes: 0,
+        modes: Vec::new(),
+        trashed: 0,
+        unreadable: Vec::new(),
+    };
+    if !dir.is_dir() {
+        return Ok(report);
+    }
+    for (file, mode) in &[
+        (".order.yml", "order_index"),
+        (".append_only", "append_only"),
+        (".chain.yml", "hash_chain"),
+        (".shards.yml", "sharded"),
+        (".seq", "change_notification"),
+    ] {
+        if dir.join(file).is_file() {
+            report.modes.push(mode.to_string());
+        }
+    }
+    let trash = dir.join(".trash");
+    if trash.is_dir() {
+        report.trashed = std::fs::read_dir(&trash)?.count();
+    }
+    for file in member_files(dir)? {
+        report.members += 1;
+        report.bytes += std::fs::metadata(&file)?.len();
+        let content = std::fs::read(&file)?;
+        if let Err(err) = header::decode_value(&content) {
+            let id = file
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default();
+            report.unreadable.push((redact(id), err.to_string()));
+        }
+    }
+    Ok(report)
+}
+
+// Redacted ID: short hash of the ID
+fn redact(id: &str) -> String {
+    let hash = Sha256::digest(id.as_bytes());
+    let hex = hash
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    format!("sha256:{}", &hex[..12])
+}
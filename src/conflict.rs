@@ -0,0 +1,209 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Conflict resolution
+//!
+//! A conflict is two versions of the same data: ours, in memory,
+//! and theirs, saved meanwhile by another process or found in a
+//! diverged replica. Instead of failing, a ConflictStrategy picks
+//! one of them, or merges the two by a callback.
+//!
+//! ```rust,ignore
+//! let strategy = ConflictStrategy::merge(|ours: Counter, theirs| {
+//!     Counter { hits: ours.hits.max(theirs.hits) }
+//! });
+//! pack.update_resolving(etag, &strategy, |c| c.hits += 1)?;
+//! ```
+
+use crate::header::{self, FileHeader};
+use crate::replica::ReplicaIssue;
+use crate::{unix_millis, Pack, PackError, PackResult, VecPack, VecPackMember};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// ConflictStrategy
+/// How to resolve two versions of the same data
+pub enum ConflictStrategy<T> {
+    /// Keep our version
+    Ours,
+    /// Keep their version
+    Theirs,
+    /// Keep the version saved later
+    Newest,
+    /// Merge the versions, called as merge(ours, theirs)
+    Merge(Box<dyn Fn(T, T) -> T + Send + Sync>),
+}
+
+impl<T> ConflictStrategy<T> {
+    /// Merge strategy from a callback
+    pub fn merge<F>(f: F) -> Self
+    where
+        F: Fn(T, T) -> T + Send + Sync + 'static,
+    {
+        ConflictStrategy::Merge(Box::new(f))
+    }
+    // Resolve the conflict
+    // Times are the save times of the versions, in milliseconds.
+    pub(crate) fn resolve(
+        &self,
+        (ours, ours_time): (T, u128),
+        (theirs, theirs_time): (T, u128),
+    ) -> T {
+        match self {
+            ConflictStrategy::Ours => ours,
+            ConflictStrategy::Theirs => theirs,
+            ConflictStrategy::Newest => match theirs_time > ours_time {
+                true => theirs,
+                false => ours,
+            },
+            ConflictStrategy::Merge(merge) => merge(ours, theirs),
+        }
+    }
+}
+
+impl<T> Pack<T>
+where
+    for<'de> T: Serialize + Deserialize<'de> + Sized,
+{
+    /// Update Pack<T>, resolving a conflicting save
+    /// The same as update_if_version, but if the revision has
+    /// changed, then f is applied to our data, and the result is
+    /// resolved with the stored data by strategy, then saved.
+    /// Newest keeps our update, as it is the later one.
+    /// Returns the result of f, even if its change is dropped.
+    pub fn update_resolving<F, R>(
+        &mut self,
+        expected: u64,
+        strategy: &ConflictStrategy<T>,
+        f: F,
+    ) -> PackResult<R>
+    where
+        F: FnOnce(&mut T) -> R,
+        T: Clone,
+    {
+        if self.revision() == expected {
+            return self.update(f);
+        }
+        let theirs = match self.ctx.backend() {
+            Some(backend) => {
+                Pack::load_from_backend(self.path.clone(), backend)
+            }
+            None => Pack::read_from_path(self.path.clone()),
+        }?
+        .data;
+        let theirs_time = self
+            .ctx
+            .header(&self.path)
+            .and_then(|header| header.updated)
+            .unwrap_or(0);
+        let mut ours = self.data.clone();
+        let res = f(&mut ours);
+        let resolved = strategy.resolve(
+            (ours, unix_millis(SystemTime::now())),
+            (theirs, theirs_time),
+        );
+        self.update(|data| *data = resolved)?;
+        Ok(res)
+    }
+}
+
+impl<T> VecPack<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    /// Resolve diverged replicas
+    /// Every member whose replica copies differ from the primary
+    /// is resolved by strategy, ours being the primary, theirs
+    /// each different replica in turn. The result is saved, so
+    /// it is mirrored to every replica. Then the missing and extra
+    /// replica files are fixed as by sync_replicas().
+    /// Replica copies that cannot be loaded are overwritten.
+    /// Returns the number of resolved members.
+    pub fn resolve_replicas(
+        &mut self,
+        strategy: &ConflictStrategy<T>,
+    ) -> PackResult<usize> {
+        self.check_mutable()?;
+        let mut diverged: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+        for issue in self.check_replicas()? {
+            if let ReplicaIssue::Different { replica, file } = issue {
+                diverged.entry(file).or_default().push(replica);
+            }
+        }
+        let mut resolved = 0;
+        for (file, replicas) in diverged {
+            let path = self.path.join(&file);
+            let pos = match self.data.iter().position(|i| i.path == path) {
+                Some(pos) => pos,
+                None => continue,
+            };
+            let mut ours = self.data[pos].data.clone();
+            let mut ours_time = self
+                .ctx
+                .header(&path)
+                .and_then(|header| header.updated)
+                .unwrap_or(0);
+            for replica in replicas {
+                let (theirs, theirs_time) =
+                    match load_replica::<T>(&replica.join(&file)) {
+                        Ok(theirs) => theirs,
+                        Err(_) => continue,
+                    };
+                ours =
+                    strategy.resolve((ours, ours_time), (theirs, theirs_time));
+                ours_time = ours_time.max(theirs_time);
+            }
+            let id = self.data[pos].get_id().to_string();
+            if ours.get_id() != id {
+                return Err(PackError::InternalError(format!(
+                    "Resolved member of ID {} has ID {}",
+                    id,
+                    ours.get_id()
+                )));
+            }
+            self.data[pos].update(|data| *data = ours)?;
+            self.member_touched(&id);
+            self.record_write(&id);
+            resolved += 1;
+        }
+        self.sync_replicas()?;
+        Ok(resolved)
+    }
+}
+
+// Load a replica copy, with its save time
+fn load_replica<T>(path: &Path) -> PackResult<(T, u128)>
+where
+    for<'de> T: Deserialize<'de>,
+{
+    let buffer = std::fs::read(path)?;
+    let time = FileHeader::parse_bytes(&buffer)?
+        .and_then(|header| header.updated)
+        .unwrap_or(0);
+    let (data, _) = header::decode::<T>(&buffer)?;
+    Ok((data, time))
+}
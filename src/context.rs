@@ -0,0 +1,543 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Pack context
+//!
+//! Shared state between a VecPack and its members. Every Pack<T>
+//! holds an Arc to its context, so saves made through a member
+//! (e.g. by a PackGuard drop) can reach collection level features.
+//! A standalone Pack<T> has its own empty context.
+
+use crate::audit::AuditLog;
+use crate::backend::StorageBackend;
+use crate::header::{self, FileHeader, Format, Stamp};
+use crate::history::{self, HistoryConfig};
+use crate::logging::{self, SaveErrorHook};
+use crate::metrics::{Metrics, OpKind};
+use crate::replica::Replicas;
+use crate::signal::Notifier;
+use crate::snapshot::Snapshots;
+use crate::telemetry;
+use crate::{
+    atomic, unix_millis, ChangeEvent, ChangeKind, PackError, PackResult,
+    TempConfig,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Seek;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Instant, SystemTime};
+
+#[derive(Default)]
+pub(crate) struct PackContext {
+    // Change notification, if enabled
+    notifier: RwLock<Option<Notifier>>,
+    // Number of saves failed with StorageFull
+    storage_full: AtomicU64,
+    // Another instance holds the directory lock,
+    // so nothing may be written
+    read_only: AtomicBool,
+    // Temp file config of the atomic writes
+    temp: RwLock<TempConfig>,
+    // Change event subscribers
+    subscribers: Mutex<Vec<Sender<ChangeEvent>>>,
+    // Modification time of the member files as we know them,
+    // if read-repair is enabled.
+    versions: Mutex<Option<HashMap<PathBuf, SystemTime>>>,
+    // Number of stale members repaired on read
+    stale_reads: AtomicU64,
+    // Receives the failed saves that cannot be returned
+    error_hook: RwLock<Option<SaveErrorHook>>,
+    // Storage backend, if not the local filesystem
+    backend: RwLock<Option<Arc<dyn StorageBackend>>>,
+    // Replica directories, if replication is enabled
+    replicas: RwLock<Option<Replicas>>,
+    // Automatic snapshots, if enabled
+    snapshots: RwLock<Option<Arc<Snapshots>>>,
+    // History mode, if enabled
+    history: RwLock<Option<HistoryConfig>>,
+    // Audit log, if enabled
+    audit: RwLock<Option<AuditLog>>,
+    // Storage format of the member files
+    format: RwLock<Format>,
+    // Operational counters
+    pub(crate) metrics: Metrics,
+    // Header of the member files as last written or read,
+    // if a backend is set
+    headers: Mutex<HashMap<PathBuf, FileHeader>>,
+    // Shared location, if the VecPack is opened through a Registry
+    location: RwLock<Option<Location>>,
+}
+
+// Shared location of a VecPack opened through a Registry
+struct Location {
+    // Current directory, set by the registry
+    shared: Arc<RwLock<PathBuf>>,
+    // Directory the member paths are under
+    root: PathBuf,
+}
+
+impl PackContext {
+    // Save a member, then notify about its update
+    // Member ID is the file name without extension.
+    pub(crate) fn save<D>(
+        &self,
+        path: &Path,
+        data: D,
+        stamp: &Stamp,
+    ) -> PackResult<()>
+    where
+        D: Serialize,
+    {
+        let path = &self.resolve(path);
+        self.write(path, data, stamp)?;
+        match path.file_stem().and_then(|s| s.to_str()) {
+            Some(id) => {
+                self.member_changed(ChangeEvent::new(id, ChangeKind::Updated))
+            }
+            None => self.changed(),
+        }
+        Ok(())
+    }
+    // Write a member file without notification
+    // Its creation time and revision are taken from stamp,
+    // which is updated once the file is written.
+    pub(crate) fn write<D>(
+        &self,
+        path: &Path,
+        data: D,
+        stamp: &Stamp,
+    ) -> PackResult<()>
+    where
+        D: Serialize,
+    {
+        self.check_writable()?;
+        let path = &self.resolve(path);
+        let now = unix_millis(SystemTime::now());
+        let (created, revision) =
+            stamp.get(|| self.header(path).unwrap_or_default());
+        let header = FileHeader {
+            format: self.format(),
+            created: Some(created.unwrap_or(now)),
+            updated: Some(now),
+            revision: Some(revision.unwrap_or(0) + 1),
+            ..FileHeader::current::<D>()
+        };
+        let (created, revision) = (header.created, header.revision);
+        self.rewrite(path, data, header)?;
+        stamp.set(created, revision);
+        Ok(())
+    }
+    // Write a member file with the given header, e.g. keeping
+    // its save times and revision
+    pub(crate) fn rewrite<D>(
+        &self,
+        path: &Path,
+        data: D,
+        header: FileHeader,
+    ) -> PackResult<()>
+    where
+        D: Serialize,
+    {
+        self.check_writable()?;
+        let path = &self.resolve(path);
+        let temp = self.temp.read().unwrap().clone();
+        let started = Instant::now();
+        let res = telemetry::span("storaget.save", path, || {
+            let history = *self.history.read().unwrap();
+            // Encoded into the writer, see header::encode_to
+            let mut written = 0;
+            match self.backend() {
+                Some(backend) => backend.write_with(path, &mut |writer| {
+                    header::encode_to(&data, &header, writer)?;
+                    written = writer.stream_position()?;
+                    Ok(())
+                }),
+                None => atomic::write_atomic_with(path, &temp, |file| {
+                    let checksum = header::encode_to(&data, &header, file)?;
+                    written = file.stream_position()?;
+                    // The previous version is still in place
+                    match history {
+                        Some(config) => {
+                            history::record(path, &checksum, config)
+                        }
+                        None => Ok(()),
+                    }
+                }),
+            }
+            .map(|_| written)
+            .map_err(|err| err.with_path(path))
+        });
+        self.metrics.record(
+            OpKind::Save,
+            path,
+            started.elapsed(),
+            res.as_ref().ok().copied(),
+        );
+        res.inspect_err(|err| self.save_failed(err))?;
+        if self.backend().is_some() {
+            self.headers
+                .lock()
+                .unwrap()
+                .insert(path.to_path_buf(), header);
+        }
+        self.record_version(path);
+        if let Some(replicas) = self.replicas() {
+            if let Err(err) = replicas.mirror(path) {
+                self.background_save_failed(path, err);
+            }
+        }
+        self.snapshot_if_due(path);
+        Ok(())
+    }
+    // Called after a member has changed on disk
+    pub(crate) fn member_changed(&self, event: ChangeEvent) {
+        self.moved();
+        telemetry::change_event(&event);
+        if let Some(audit) = &*self.audit.read().unwrap() {
+            // The change is saved already, a failed record is reported
+            if let Err(err) = audit.record(&event) {
+                self.background_save_failed(audit.path(), err);
+            }
+        }
+        self.publish(event);
+        self.changed();
+    }
+    // New change event subscription
+    pub(crate) fn subscribe(&self) -> Receiver<ChangeEvent> {
+        let (sender, receiver) = channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+    // Send event to the subscribers
+    // Subscribers with a dropped Receiver are removed.
+    fn publish(&self, event: ChangeEvent) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+    // Called after a member or the collection has changed on disk
+    pub(crate) fn changed(&self) {
+        self.moved();
+        if let Some(notifier) = &*self.notifier.read().unwrap() {
+            // Data is already saved, a failed notification
+            // must not turn the save into an error.
+            let _ = notifier.notify();
+        }
+    }
+    // Start tracking member file versions
+    pub(crate) fn track_versions(&self, paths: Vec<PathBuf>) {
+        let mut versions = HashMap::new();
+        for path in paths {
+            if let Some(version) = file_version(&path) {
+                versions.insert(path, version);
+            }
+        }
+        *self.versions.lock().unwrap() = Some(versions);
+    }
+    pub(crate) fn is_tracking_versions(&self) -> bool {
+        self.versions.lock().unwrap().is_some()
+    }
+    // Remember the current version of a file, if tracking
+    pub(crate) fn record_version(&self, path: &Path) {
+        if let Some(versions) = &mut *self.versions.lock().unwrap() {
+            match file_version(path) {
+                Some(version) => versions.insert(path.to_path_buf(), version),
+                None => versions.remove(path),
+            };
+        }
+    }
+    // Returns true if the file differs from the version we know
+    pub(crate) fn is_stale(&self, path: &Path) -> bool {
+        match &*self.versions.lock().unwrap() {
+            Some(versions) => versions.get(path).cloned() != file_version(path),
+            None => false,
+        }
+    }
+    pub(crate) fn stale_read(&self) {
+        self.stale_reads.fetch_add(1, Ordering::Relaxed);
+    }
+    pub(crate) fn stale_read_count(&self) -> u64 {
+        self.stale_reads.load(Ordering::Relaxed)
+    }
+    // Called when saving a member has failed
+    pub(crate) fn save_failed(&self, err: &PackError) {
+        if let PackError::StorageFull = err {
+            self.storage_full.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    // Report a failed save that cannot be returned as error
+    pub(crate) fn background_save_failed(&self, path: &Path, err: PackError) {
+        let hook = self.error_hook.read().unwrap().clone();
+        logging::save_failed(hook, path, err);
+    }
+    // Remove a member file
+    pub(crate) fn remove(&self, path: &Path) -> PackResult<()> {
+        self.check_writable()?;
+        let path = &self.resolve(path);
+        let started = Instant::now();
+        let res = match self.backend() {
+            Some(backend) => backend.delete(path),
+            None => Ok(std::fs::remove_file(path)?),
+        };
+        self.metrics.record(
+            OpKind::Remove,
+            path,
+            started.elapsed(),
+            res.as_ref().ok().map(|_| 0),
+        );
+        res.map_err(|err| err.with_path(path))?;
+        self.forget_header(path);
+        if let Some(replicas) = self.replicas() {
+            if let Err(err) = replicas.remove(path) {
+                self.background_save_failed(path, err);
+            }
+        }
+        self.snapshot_if_due(path);
+        Ok(())
+    }
+    // Remove a file if it exists
+    // Returns false if there was no such file.
+    pub(crate) fn remove_existing(&self, path: &Path) -> PackResult<bool> {
+        let path = &self.resolve(path);
+        if self.backend().is_none() && !path.is_file() {
+            return Ok(false);
+        }
+        match self.remove(path) {
+            Ok(_) => Ok(true),
+            Err(PackError::PathNotFound) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+    // Header of a member file
+    // Files are read fresh, as other processes may have saved
+    // them; only the header line is read. Backend reads fetch the
+    // whole file, so they are cached after the first read.
+    // None if the file does not exist or has no header.
+    pub(crate) fn header(&self, path: &Path) -> Option<FileHeader> {
+        let path = &self.resolve(path);
+        let backend = match self.backend() {
+            Some(backend) => backend,
+            None => return FileHeader::read(path).ok().flatten(),
+        };
+        if let Some(header) = self.headers.lock().unwrap().get(path) {
+            return Some(header.clone());
+        }
+        let bytes = backend.read(path).ok()?;
+        let header =
+            FileHeader::parse(&String::from_utf8_lossy(&bytes)).ok()??;
+        self.headers
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), header.clone());
+        Some(header)
+    }
+    // Drop the cached header, e.g. after the file has moved
+    pub(crate) fn forget_header(&self, path: &Path) {
+        self.headers.lock().unwrap().remove(path);
+    }
+    // Take an automatic snapshot after a change, if due
+    // The change is saved already, a failed snapshot is reported.
+    fn snapshot_if_due(&self, path: &Path) {
+        let snapshots = self.snapshots.read().unwrap().clone();
+        if let Some(snapshots) = snapshots {
+            if let Err(err) = snapshots.take_if_due() {
+                self.background_save_failed(path, err);
+            }
+        }
+    }
+    pub(crate) fn set_audit(&self, audit: Option<AuditLog>) {
+        *self.audit.write().unwrap() = audit;
+    }
+    pub(crate) fn set_audit_actor(&self, actor: &str) {
+        if let Some(audit) = &*self.audit.read().unwrap() {
+            audit.set_actor(actor);
+        }
+    }
+    // Run f with history mode paused
+    pub(crate) fn without_history<R>(&self, f: impl FnOnce() -> R) -> R {
+        let config = self.history.write().unwrap().take();
+        let result = f();
+        *self.history.write().unwrap() = config;
+        result
+    }
+    pub(crate) fn set_format(&self, format: Format) {
+        *self.format.write().unwrap() = format;
+    }
+    pub(crate) fn format(&self) -> Format {
+        *self.format.read().unwrap()
+    }
+    pub(crate) fn set_history(&self, config: Option<HistoryConfig>) {
+        *self.history.write().unwrap() = config;
+    }
+    pub(crate) fn set_snapshots(&self, snapshots: Option<Snapshots>) {
+        *self.snapshots.write().unwrap() = snapshots.map(Arc::new);
+    }
+    pub(crate) fn set_replicas(&self, replicas: Option<Replicas>) {
+        *self.replicas.write().unwrap() = replicas;
+    }
+    pub(crate) fn replicas(&self) -> Option<Replicas> {
+        self.replicas.read().unwrap().clone()
+    }
+    pub(crate) fn set_backend(&self, backend: Arc<dyn StorageBackend>) {
+        *self.backend.write().unwrap() = Some(backend);
+    }
+    pub(crate) fn backend(&self) -> Option<Arc<dyn StorageBackend>> {
+        self.backend.read().unwrap().clone()
+    }
+    pub(crate) fn set_error_hook(&self, hook: SaveErrorHook) {
+        *self.error_hook.write().unwrap() = Some(hook);
+    }
+    pub(crate) fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::Relaxed);
+    }
+    pub(crate) fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::Relaxed)
+    }
+    // Returns PackError::Locked if another instance holds the lock
+    pub(crate) fn check_writable(&self) -> PackResult<()> {
+        match self.is_read_only() {
+            true => Err(PackError::Locked),
+            false => Ok(()),
+        }
+    }
+    pub(crate) fn storage_full_count(&self) -> u64 {
+        self.storage_full.load(Ordering::Relaxed)
+    }
+    pub(crate) fn set_temp_config(&self, temp: TempConfig) {
+        *self.temp.write().unwrap() = temp;
+    }
+    pub(crate) fn temp_config(&self) -> TempConfig {
+        self.temp.read().unwrap().clone()
+    }
+    pub(crate) fn set_notifier(&self, notifier: Notifier) {
+        *self.notifier.write().unwrap() = Some(notifier);
+    }
+    pub(crate) fn has_notifier(&self) -> bool {
+        self.notifier.read().unwrap().is_some()
+    }
+    // Attach the shared location of a Registry, root is the
+    // directory the member paths are under
+    pub(crate) fn set_location(
+        &self,
+        shared: Arc<RwLock<PathBuf>>,
+        root: PathBuf,
+    ) {
+        *self.location.write().unwrap() = Some(Location { shared, root });
+    }
+    // Member file path as it is now
+    // Members of a VecPack opened through a Registry keep their
+    // paths until the next mutable access of the VecPack, but the
+    // registry may have moved the directory already. Such a path is
+    // rebased to the current directory, so member saves and reads
+    // through immutable accessors never use the old directory.
+    pub(crate) fn resolve(&self, path: &Path) -> PathBuf {
+        match self.moved() {
+            Some((root, current)) => rebase(path, &root, &current),
+            None => path.to_path_buf(),
+        }
+    }
+    // Old and current directory, if the registry has moved the
+    // directory since the VecPack has followed it. The context
+    // paths follow it right away.
+    fn moved(&self) -> Option<(PathBuf, PathBuf)> {
+        let (root, current) = {
+            let location = self.location.read().unwrap();
+            let location = location.as_ref()?;
+            let current = location.shared.read().unwrap().clone();
+            (location.root.clone(), current)
+        };
+        if root == current {
+            return None;
+        }
+        self.relocate_paths(&root, &current);
+        Some((root, current))
+    }
+    // Follow the collection directory when it is moved from one
+    // directory to another, with the member paths
+    pub(crate) fn relocate(&self, from: &Path, to: &Path) {
+        self.relocate_paths(from, to);
+        if let Some(location) = &mut *self.location.write().unwrap() {
+            location.root = to.to_path_buf();
+        }
+    }
+    // Rebase every path the context holds from one directory to
+    // another; calling it again for the same move changes nothing.
+    fn relocate_paths(&self, from: &Path, to: &Path) {
+        if let Some(notifier) = &mut *self.notifier.write().unwrap() {
+            notifier.relocate(to);
+        }
+        if let Some(audit) = &mut *self.audit.write().unwrap() {
+            audit.relocate(to);
+        }
+        if let Some(replicas) = &mut *self.replicas.write().unwrap() {
+            replicas.relocate(to);
+        }
+        let snapshots = &mut *self.snapshots.write().unwrap();
+        if let Some(relocated) = snapshots.as_ref().map(|s| s.relocate(to)) {
+            *snapshots = Some(Arc::new(relocated));
+        }
+        let mut headers = self.headers.lock().unwrap();
+        *headers = std::mem::take(&mut *headers)
+            .into_iter()
+            .map(|(path, header)| (rebase(&path, from, to), header))
+            .collect();
+        if let Some(versions) = &mut *self.versions.lock().unwrap() {
+            *versions = std::mem::take(versions)
+                .into_iter()
+                .map(|(path, version)| (rebase(&path, from, to), version))
+                .collect();
+        }
+    }
+}
+
+impl fmt::Debug for PackContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PackContext")
+            .field("notifier", &self.has_notifier())
+            .field("storage_full", &self.storage_full_count())
+            .field("subscribers", &self.subscribers.lock().unwrap().len())
+            .finish()
+    }
+}
+
+// Path under from rebased under to, other paths are kept
+pub(crate) fn rebase(path: &Path, from: &Path, to: &Path) -> PathBuf {
+    match path.strip_prefix(from) {
+        Ok(rel) => to.join(rel),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+// Version of a file: its modification time
+// None if the file does not exist.
+fn file_version(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
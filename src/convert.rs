@@ -0,0 +1,223 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Format conversion
+//!
+//! convert_format() rewrites every member file of a VecPack from
+//! one storage format to another in place. Before any change the
+//! current files are checked to be in the expected format and are
+//! archived into .convert/<millis>.tar.gz, which restore_from()
+//! can load back. Each file is replaced atomically; if one fails,
+//! the files already converted are written back as they were.
+//! Conversion is not a save of the data: the save times and the
+//! revision in the file headers are kept.
+//!
+//! The chosen format is persisted in a .format marker file, so
+//! later saves keep using it. Files in any format can be loaded,
+//! the header tells which one a file is in.
+//!
+//! convert_dir() does the same without knowing the member type,
+//! keeping the schema version of every file, e.g. for the CLI.
+
+use crate::atomic::{self, TempConfig};
+use crate::backup::write_archive;
+use crate::header::{self, FileHeader, Format};
+use crate::{
+    member_files, unix_millis, PackError, PackResult, VecPack, VecPackMember,
+};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// ConvertReport
+/// Result of convert_format()
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvertReport {
+    /// Number of converted member files
+    pub converted: usize,
+    /// Archive of the member files before the conversion
+    pub backup: PathBuf,
+}
+
+/// Convert the member files of dir from one format to another
+/// The same as VecPack::convert_format, but the files are not
+/// loaded as members: each one is rewritten with its own header,
+/// so schema versions are kept and no migration is applied.
+pub fn convert_dir(
+    dir: &Path,
+    from: Format,
+    to: Format,
+) -> PackResult<ConvertReport> {
+    if dir.join(".append_only").exists() {
+        return Err(PackError::Immutable);
+    }
+    convert_files(dir, member_files(dir)?, from, to, |_, path, buffer| {
+        let header = FileHeader::parse_bytes(buffer)?.unwrap_or_default();
+        let value = header::decode_value(buffer)?;
+        let header = FileHeader {
+            format: to,
+            ..header
+        };
+        let buffer = header::encode_with(&value, &header)?;
+        atomic::write_atomic(path, &buffer, &TempConfig::default())
+    })
+}
+
+// Check, backup and convert files, writing them back on failure
+fn convert_files<F>(
+    dir: &Path,
+    files: Vec<PathBuf>,
+    from: Format,
+    to: Format,
+    mut write: F,
+) -> PackResult<ConvertReport>
+where
+    F: FnMut(usize, &Path, &[u8]) -> PackResult<()>,
+{
+    let mut originals = Vec::new();
+    for path in files {
+        let buffer = std::fs::read(&path)
+            .map_err(|err| PackError::from(err).with_path(&path))?;
+        header::verify_checksum(&buffer).map_err(|err| err.with_path(&path))?;
+        let format =
+            FileHeader::parse_bytes(&buffer)?.unwrap_or_default().format;
+        if format != from {
+            return Err(PackError::InternalError(format!(
+                "{} is stored as {}, not {}",
+                path.display(),
+                format.as_str(),
+                from.as_str()
+            )));
+        }
+        originals.push((path, buffer));
+    }
+    let backup = dir
+        .join(".convert")
+        .join(format!("{}.tar.gz", unix_millis(SystemTime::now())));
+    write_archive(
+        &backup,
+        originals
+            .iter()
+            .map(|(path, buffer)| {
+                let file = path.strip_prefix(dir).unwrap_or(path);
+                (file.to_path_buf(), buffer.clone())
+            })
+            .collect(),
+    )?;
+    write_format(dir, to)?;
+    for (i, (path, buffer)) in originals.iter().enumerate() {
+        if let Err(err) = write(i, path, buffer) {
+            // Best effort, the backup archive has every file anyway
+            for (path, buffer) in &originals[..=i] {
+                let temp = TempConfig::default();
+                let _ = atomic::write_atomic(path, buffer, &temp);
+            }
+            write_format(dir, from)?;
+            return Err(err);
+        }
+    }
+    Ok(ConvertReport {
+        converted: originals.len(),
+        backup,
+    })
+}
+
+// Persist the format of the following saves
+fn write_format(dir: &Path, format: Format) -> PackResult<()> {
+    let path = format_path(dir);
+    match format {
+        Format::Yaml if path.exists() => std::fs::remove_file(&path)?,
+        Format::Yaml => (),
+        format => atomic::write_atomic(
+            &path,
+            format.as_str().as_bytes(),
+            &TempConfig::default(),
+        )?,
+    }
+    Ok(())
+}
+
+fn format_path(dir: &Path) -> PathBuf {
+    dir.join(".format")
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Storage format of the member files
+    pub fn format(&self) -> Format {
+        self.ctx.format()
+    }
+    /// Convert member files from one format to another
+    /// Returns PackError::InternalError if a member file is not
+    /// in the from format, before changing anything. Not
+    /// available with a storage backend or in append-only mode.
+    pub fn convert_format(
+        &mut self,
+        from: Format,
+        to: Format,
+    ) -> PackResult<ConvertReport> {
+        self.sync_location();
+        self.check_writable()?;
+        if self.ctx.backend().is_some() {
+            return Err(PackError::BackendError(
+                "Format conversion needs the local filesystem".to_string(),
+            ));
+        }
+        if self.append_only {
+            return Err(PackError::Immutable);
+        }
+        let files = self.data.iter().map(|pack| pack.path.clone()).collect();
+        let previous = self.ctx.format();
+        self.ctx.set_format(to);
+        // Conversion is not a change of the data, so the save
+        // times and revision are kept, and no history version
+        // is recorded.
+        let result = self.ctx.without_history(|| {
+            convert_files(&self.dir(), files, from, to, |i, path, buffer| {
+                let header =
+                    FileHeader::parse_bytes(buffer)?.unwrap_or_default();
+                let header = FileHeader {
+                    format: to,
+                    ..header::upgraded::<T>(&header)
+                };
+                self.ctx.rewrite(path, &self.data[i].data, header)
+            })
+        });
+        if result.is_err() {
+            self.ctx.set_format(previous);
+        }
+        result
+    }
+    // Restore the format of a VecPack loaded from disk
+    pub(crate) fn load_format(&mut self) -> PackResult<()> {
+        let path = format_path(&self.dir());
+        if path.exists() {
+            let name = std::fs::read_to_string(&path)?;
+            self.ctx.set_format(Format::parse(name.trim())?);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Diff
+//!
+//! Structured diff between two states of a Pack's data, e.g. the
+//! data in memory and the saved file (diff_with_disk), or two
+//! versions of its history (diff). Both sides are compared in their
+//! serialized form, field by field. Field paths are dot separated,
+//! sequence items are addressed by index, e.g. wheels.0.size.
+//! Display prints a textual diff, one changed field per line.
+
+use crate::{header, Pack, PackError, PackGuard, PackResult};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::fmt;
+use std::path::Path;
+
+/// FieldChange
+/// A changed field between two states
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldChange {
+    /// Field exists only in the newer state
+    Added { path: String, value: Value },
+    /// Field exists only in the older state
+    Removed { path: String, value: Value },
+    /// Field value differs
+    Changed {
+        path: String,
+        old: Value,
+        new: Value,
+    },
+}
+
+impl FieldChange {
+    /// Dot separated path of the field
+    pub fn path(&self) -> &str {
+        match self {
+            FieldChange::Added { path, .. }
+            | FieldChange::Removed { path, .. }
+            | FieldChange::Changed { path, .. } => path,
+        }
+    }
+}
+
+/// Diff
+/// Changed fields from an older to a newer state,
+/// in field order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diff {
+    pub changes: Vec<FieldChange>,
+}
+
+impl Diff {
+    /// True if the two states are the same
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+// One line YAML text of a value
+fn text(value: &Value) -> String {
+    let text = serde_yaml::to_string(value).unwrap_or_default();
+    let text = text.trim_start_matches("---").trim();
+    text.lines().collect::<Vec<_>>().join(" ")
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            match change {
+                FieldChange::Added { path, value } => {
+                    writeln!(f, "+ {}: {}", path, text(value))?
+                }
+                FieldChange::Removed { path, value } => {
+                    writeln!(f, "- {}: {}", path, text(value))?
+                }
+                FieldChange::Changed { path, old, new } => {
+                    writeln!(f, "~ {}: {} -> {}", path, text(old), text(new))?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Path of a child field
+fn child(path: &str, key: &str) -> String {
+    match path.is_empty() {
+        true => key.to_string(),
+        false => format!("{}.{}", path, key),
+    }
+}
+
+// Collect the changes from old to new under path
+fn diff_values(path: &str, old: &Value, new: &Value, diff: &mut Diff) {
+    match (old, new) {
+        (Value::Mapping(old), Value::Mapping(new)) => {
+            for (key, old_value) in old {
+                let path = child(path, &text(key));
+                match new.get(key) {
+                    Some(new_value) => {
+                        diff_values(&path, old_value, new_value, diff)
+                    }
+                    None => diff.changes.push(FieldChange::Removed {
+                        path,
+                        value: old_value.clone(),
+                    }),
+                }
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    diff.changes.push(FieldChange::Added {
+                        path: child(path, &text(key)),
+                        value: new_value.clone(),
+                    });
+                }
+            }
+        }
+        (Value::Sequence(old), Value::Sequence(new)) => {
+            for i in 0..old.len().max(new.len()) {
+                let path = child(path, &i.to_string());
+                match (old.get(i), new.get(i)) {
+                    (Some(o), Some(n)) => diff_values(&path, o, n, diff),
+                    (Some(o), None) => {
+                        diff.changes.push(FieldChange::Removed {
+                            path,
+                            value: o.clone(),
+                        })
+                    }
+                    (None, Some(n)) => diff.changes.push(FieldChange::Added {
+                        path,
+                        value: n.clone(),
+                    }),
+                    (None, None) => (),
+                }
+            }
+        }
+        (old, new) if old != new => diff.changes.push(FieldChange::Changed {
+            path: path.to_string(),
+            old: old.clone(),
+            new: new.clone(),
+        }),
+        _ => (),
+    }
+}
+
+// Diff of two serializable states
+pub(crate) fn diff<T: Serialize>(old: &T, new: &T) -> PackResult<Diff> {
+    let mut diff = Diff::default();
+    diff_values(
+        "",
+        &serde_yaml::to_value(old)?,
+        &serde_yaml::to_value(new)?,
+        &mut diff,
+    );
+    Ok(diff)
+}
+
+// Diff from the saved file at path to data
+fn diff_with_file<T>(path: &Path, data: &T) -> PackResult<Diff>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let buffer = std::fs::read(path)
+        .map_err(|err| PackError::from(err).with_path(path))?;
+    let (saved, _) = header::decode::<T>(&buffer)?;
+    diff(&saved, data)
+}
+
+impl<T> Pack<T>
+where
+    for<'de> T: Serialize + Deserialize<'de> + Sized + Clone,
+{
+    /// Diff from the saved file to the data in memory
+    /// Empty if there are no unsaved changes.
+    pub fn diff_with_disk(&self) -> PackResult<Diff> {
+        diff_with_file(&self.ctx.resolve(&self.path), &self.data)
+    }
+    /// Diff between two versions of the history
+    /// See Pack::history. Returns PackError::ObjectNotFound
+    /// if a version is not in the history.
+    pub fn diff(&self, version_a: u64, version_b: u64) -> PackResult<Diff> {
+        let history = self.history()?;
+        let find = |version| {
+            history
+                .iter()
+                .find(|v| v.version == version)
+                .map(|v| &v.data)
+                .ok_or(PackError::ObjectNotFound)
+        };
+        diff(find(version_a)?, find(version_b)?)
+    }
+}
+
+impl<'a, T> PackGuard<'a, T>
+where
+    for<'de> T: Serialize + Deserialize<'de> + Sized + Clone,
+{
+    /// Diff from the saved file to the guarded data,
+    /// i.e. the changes saved at drop or commit
+    pub fn diff_with_disk(&self) -> PackResult<Diff> {
+        diff_with_file(self.path, &*self.data)
+    }
+}
@@ -0,0 +1,122 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! JSON dump
+//!
+//! dump_json() writes every member of a VecPack into a single,
+//! self-contained JSON document together with a DumpInfo about
+//! where and when it was made. Unlike a backup_to() archive the
+//! dump is plain text without the member file headers, so it can
+//! be moved between environments, read or edited by hand, and
+//! attached to a bug report. load_dump() reads it back with the
+//! same RestoreStrategy as restore_from().
+
+use crate::backup::{RestoreReport, RestoreStrategy};
+use crate::{
+    atomic::{self, TempConfig},
+    unix_millis, PackError, PackResult, VecPack, VecPackMember,
+};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::BufReader;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// DumpInfo
+/// Metadata of a JSON dump
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DumpInfo {
+    /// storaget version that created the dump
+    pub crate_version: String,
+    /// Creation time in milliseconds since UNIX EPOCH
+    pub created_at: u128,
+    /// Directory of the dumped VecPack
+    pub source: String,
+    /// Rust type name of the members
+    pub member_type: String,
+    /// Number of dumped members
+    pub count: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Dump<T> {
+    info: DumpInfo,
+    members: Vec<T>,
+}
+
+// Read and decode a dump file
+fn read_dump<T>(path: &Path) -> PackResult<Dump<T>>
+where
+    for<'de> T: Deserialize<'de>,
+{
+    let file = fs::File::open(path)?;
+    serde_json::from_reader(BufReader::new(file))
+        .map_err(|err| PackError::custom_deserialize(err.to_string()))
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Dump every member into a JSON file at path
+    /// The file is written atomically.
+    /// Returns the number of dumped members.
+    pub fn dump_json(&self, path: &Path) -> PackResult<usize> {
+        let dump = Dump {
+            info: DumpInfo {
+                crate_version: env!("CARGO_PKG_VERSION").to_string(),
+                created_at: unix_millis(SystemTime::now()),
+                source: self.path.display().to_string(),
+                member_type: std::any::type_name::<T>().to_string(),
+                count: self.data.len(),
+            },
+            members: self.data.iter().map(|pack| &pack.data).collect(),
+        };
+        let bytes = serde_json::to_vec_pretty(&dump).map_err(|err| {
+            PackError::InternalError(format!("JSON error: {}", err))
+        })?;
+        atomic::write_atomic(path, &bytes, &TempConfig::default())?;
+        Ok(dump.info.count)
+    }
+    /// Load the members of a dump_json() file
+    /// The whole dump is decoded before any change, so an
+    /// invalid dump leaves the VecPack untouched.
+    pub fn load_dump(
+        &mut self,
+        path: &Path,
+        strategy: RestoreStrategy,
+    ) -> PackResult<RestoreReport>
+    where
+        for<'de> T: Deserialize<'de> + Default,
+    {
+        let dump = read_dump::<T>(path)?;
+        self.restore_members(dump.members, strategy)
+    }
+    /// Metadata of a dump_json() file
+    /// Does not need the members to match T.
+    pub fn dump_info(path: &Path) -> PackResult<DumpInfo> {
+        Ok(read_dump::<serde::de::IgnoredAny>(path)?.info)
+    }
+}
@@ -0,0 +1,160 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Ephemeral Pack
+//!
+//! EphemeralPack<T> persists a value that must not outlive a
+//! session by much, e.g. caches and wizard progress. The value
+//! resets to T::default() and its file is deleted after a TTL
+//! since the last write, or on the next startup.
+
+use crate::{Pack, PackGuard, PackResult};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// Lifetime of an EphemeralPack value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lifetime {
+    /// Value expires this long after its last write,
+    /// also across restarts.
+    Ttl(Duration),
+    /// Value lives until the next startup, when it is
+    /// reset on load.
+    Session,
+}
+
+/// EphemeralPack<T>
+/// Pack<T> with a limited lifetime
+pub struct EphemeralPack<T>
+where
+    T: Serialize + Sized + Clone,
+{
+    pack: Pack<T>,
+    lifetime: Lifetime,
+    // Time of the last write, None if there is no saved value
+    written: Option<SystemTime>,
+}
+
+impl<T> EphemeralPack<T>
+where
+    for<'de> T: Serialize + Deserialize<'de> + Default + Sized + Clone,
+{
+    /// Load or init EphemeralPack<T>
+    /// Loads the saved value if it has not expired yet,
+    /// otherwise deletes its file and starts with T::default().
+    /// With Lifetime::Session a saved value is always reset.
+    pub fn load_or_init(
+        mut path: PathBuf,
+        file_id: &str,
+        lifetime: Lifetime,
+    ) -> PackResult<EphemeralPack<T>> {
+        if !path.exists() {
+            std::fs::create_dir_all(&path)?;
+        }
+        path.push(format!("{}.yml", file_id));
+        let mut result = EphemeralPack {
+            pack: Pack::new(path.clone())?,
+            lifetime,
+            written: None,
+        };
+        if path.is_file() {
+            result.written = Some(std::fs::metadata(&path)?.modified()?);
+            match lifetime {
+                Lifetime::Session => result.reset()?,
+                Lifetime::Ttl(_) if result.is_expired() => result.reset()?,
+                Lifetime::Ttl(_) => result.pack = Pack::load_from_path(path)?,
+            }
+        }
+        Ok(result)
+    }
+    /// Get the value
+    /// Resets it first if it has expired.
+    pub fn get(&mut self) -> PackResult<&T> {
+        self.expire()?;
+        Ok(self.pack.unpack())
+    }
+    /// Mutable guard of the value
+    /// Resets it first if it has expired. The write
+    /// restarts the TTL.
+    pub fn as_mut(&mut self) -> PackResult<PackGuard<'_, T>> {
+        self.expire()?;
+        self.written = Some(SystemTime::now());
+        Ok(self.pack.as_mut())
+    }
+    /// Update the value
+    /// Resets it first if it has expired. The write
+    /// restarts the TTL.
+    pub fn update<F, R>(&mut self, f: F) -> PackResult<R>
+    where
+        F: FnMut(&mut T) -> R,
+    {
+        self.expire()?;
+        let res = self.pack.update(f)?;
+        self.written = Some(SystemTime::now());
+        Ok(res)
+    }
+    /// Reset the value to T::default(), and delete its file
+    pub fn reset(&mut self) -> PackResult<()> {
+        let path = self.pack.path.clone();
+        if path.is_file() {
+            std::fs::remove_file(&path)?;
+        }
+        self.pack.data = T::default();
+        self.written = None;
+        Ok(())
+    }
+    // Reset if expired
+    fn expire(&mut self) -> PackResult<()> {
+        if self.is_expired() {
+            self.reset()?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> EphemeralPack<T>
+where
+    T: Serialize + Sized + Clone,
+{
+    /// Lifetime of the value
+    pub fn lifetime(&self) -> Lifetime {
+        self.lifetime
+    }
+    /// Expiration time of the saved value
+    /// None if there is no saved value, or the lifetime is Session.
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        match (self.lifetime, self.written) {
+            (Lifetime::Ttl(ttl), Some(written)) => Some(written + ttl),
+            _ => None,
+        }
+    }
+    /// Returns true if the saved value has expired
+    pub fn is_expired(&self) -> bool {
+        self.expires_at()
+            .map(|expires_at| expires_at <= SystemTime::now())
+            .unwrap_or(false)
+    }
+}
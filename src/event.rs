@@ -0,0 +1,93 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Change events
+//!
+//! Events describing a change of a VecPack member. Subscribers
+//! receive an event for every insert, update (Pack::update, and
+//! PackGuard drop) and removal, so UI layers can react to storage
+//! changes without polling.
+
+use crate::{Pack, VecPack, VecPackMember};
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::Receiver;
+
+/// ChangeKind
+/// What happened to a member
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    /// Member was added
+    Created,
+    /// Member data was changed
+    Updated,
+    /// Member was removed
+    Removed,
+}
+
+/// ChangeEvent
+/// A member change with the ID of the member
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChangeEvent {
+    /// Member ID
+    pub id: String,
+    /// Kind of the change
+    pub kind: ChangeKind,
+}
+
+impl ChangeEvent {
+    /// New ChangeEvent
+    pub fn new(id: &str, kind: ChangeKind) -> Self {
+        ChangeEvent {
+            id: id.to_string(),
+            kind,
+        }
+    }
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Subscribe to change events
+    /// Returns a Receiver of every later member change.
+    /// The subscription ends when the Receiver is dropped.
+    pub fn subscribe(&self) -> Receiver<ChangeEvent> {
+        self.ctx.subscribe()
+    }
+}
+
+impl<T> Pack<T>
+where
+    T: Serialize + Sized + Clone,
+{
+    /// Subscribe to change events
+    /// For a standalone Pack these are its own updates, where
+    /// the ID is the file name. A VecPack member shares the
+    /// subscriptions of its VecPack, so it receives the events
+    /// of every member.
+    pub fn subscribe(&self) -> Receiver<ChangeEvent> {
+        self.ctx.subscribe()
+    }
+}
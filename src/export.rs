@@ -0,0 +1,112 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! CSV export
+//!
+//! Enabled by the `csv` feature. export_csv() writes the members
+//! of a VecPack as CSV rows, e.g. for spreadsheets. Members are
+//! flattened by their serialized form: nested fields become dot
+//! separated columns (engine.hp), sequence items are indexed
+//! (tags.0, tags.1). The columns are the union of every member's
+//! fields, in the order they first occur; missing ones are empty.
+
+use crate::{PackError, PackResult, VecPack, VecPackMember};
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::io::Write;
+
+// Flatten a value into (column, text) pairs
+fn flatten(path: &str, value: &Value, row: &mut Vec<(String, String)>) {
+    let child = |key: String| match path.is_empty() {
+        true => key,
+        false => format!("{}.{}", path, key),
+    };
+    match value {
+        Value::Mapping(map) if !map.is_empty() => {
+            for (key, value) in map {
+                flatten(&child(scalar(key)), value, row);
+            }
+        }
+        Value::Sequence(seq) if !seq.is_empty() => {
+            for (i, value) in seq.iter().enumerate() {
+                flatten(&child(i.to_string()), value, row);
+            }
+        }
+        value => row.push((path.to_string(), scalar(value))),
+    }
+}
+
+// Text of a scalar value, empty for null and empty collections
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Null | Value::Mapping(_) | Value::Sequence(_) => String::new(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.clone(),
+    }
+}
+
+fn csv_error(err: csv::Error) -> PackError {
+    match err.into_kind() {
+        csv::ErrorKind::Io(err) => err.into(),
+        kind => PackError::InternalError(format!("CSV error: {:?}", kind)),
+    }
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Export members as CSV into writer
+    /// Writes a header row, then one row per member.
+    /// Returns the number of exported members.
+    pub fn export_csv<W: Write>(&self, writer: W) -> PackResult<usize> {
+        let mut columns: Vec<String> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        let mut rows = Vec::new();
+        for pack in &self.data {
+            let mut row = Vec::new();
+            flatten("", &serde_yaml::to_value(&pack.data)?, &mut row);
+            for (column, _) in &row {
+                if !index.contains_key(column) {
+                    index.insert(column.clone(), columns.len());
+                    columns.push(column.clone());
+                }
+            }
+            rows.push(row);
+        }
+        let mut writer = csv::Writer::from_writer(writer);
+        writer.write_record(&columns).map_err(csv_error)?;
+        for row in &rows {
+            let mut record = vec![""; columns.len()];
+            for (column, text) in row {
+                record[index[column]] = text;
+            }
+            writer.write_record(&record).map_err(csv_error)?;
+        }
+        writer.flush()?;
+        Ok(rows.len())
+    }
+}
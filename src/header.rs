@@ -0,0 +1,621 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! File header
+//!
+//! Every stored file starts with a header line, e.g.
+//!
+//! ```yaml
+//! #!storaget format=yaml schema_version=2 crate_version=0.8.1 checksum=sha256:5f1c..
+//! ---
+//! id: "1"
+//! ```
+//!
+//! As it is a YAML comment, the files remain plain YAML. While
+//! loading, the header tells which deserializer to use, and
+//! which migrations to apply. Files without header are treated
+//! as YAML of schema version 1.
+//!
+//! Member files also record when they were created and last
+//! updated (created=, updated=, in milliseconds since UNIX EPOCH),
+//! and their revision (revision=), bumped by every save.
+//!
+//! The checksum is the SHA-256 of the rest of the file. It is
+//! verified at every load, so a corrupted file is reported as
+//! PackError::IntegrityError instead of loading garbage. Files
+//! without checksum (older ones, or edited by hand with the
+//! checksum removed) are loaded without the check.
+//!
+//! With format=json the body is pretty printed JSON, and with
+//! format=msgpack it is binary MessagePack, structs encoded as
+//! maps. Only the header line is text then; migrations work on
+//! the same YAML value in every format.
+
+use crate::{migrate, PackError, PackResult};
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs::File;
+use std::io::{
+    self, BufRead, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write,
+};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Header magic, the start of the first line
+pub const MAGIC: &str = "#!storaget";
+
+/// Storage format of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Yaml,
+    Json,
+    /// Binary MessagePack body
+    MessagePack,
+}
+
+impl Format {
+    /// Name of the format as stored in the header
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Format::Yaml => "yaml",
+            Format::Json => "json",
+            Format::MessagePack => "msgpack",
+        }
+    }
+    /// Format by its name, e.g. yaml
+    pub fn parse(name: &str) -> PackResult<Format> {
+        match name {
+            "yaml" => Ok(Format::Yaml),
+            "json" => Ok(Format::Json),
+            "msgpack" => Ok(Format::MessagePack),
+            _ => Err(PackError::custom_deserialize(format!(
+                "Unknown file format: {}",
+                name
+            ))),
+        }
+    }
+}
+
+/// FileHeader
+/// Format, schema version and crate version of a stored file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileHeader {
+    /// Storage format
+    pub format: Format,
+    /// Schema version of the stored data
+    pub schema_version: u32,
+    /// Version of storaget that saved the file.
+    /// Empty for files without header.
+    pub crate_version: String,
+    /// Creation time in milliseconds since UNIX EPOCH.
+    /// None for files saved before it was recorded.
+    pub created: Option<u128>,
+    /// Last save time in milliseconds since UNIX EPOCH
+    pub updated: Option<u128>,
+    /// Number of saves, None for files saved before it was recorded
+    pub revision: Option<u64>,
+    /// Checksum of the file content after the header,
+    /// e.g. sha256:<hex>. None for files without checksum.
+    pub checksum: Option<String>,
+}
+
+impl Default for FileHeader {
+    // Header of files saved without header
+    fn default() -> Self {
+        FileHeader {
+            format: Format::Yaml,
+            schema_version: 1,
+            crate_version: String::new(),
+            created: None,
+            updated: None,
+            revision: None,
+            checksum: None,
+        }
+    }
+}
+
+impl FileHeader {
+    /// Header of a T file saved by the current crate version
+    pub fn current<T: ?Sized>() -> Self {
+        FileHeader {
+            format: Format::Yaml,
+            schema_version: migrate::schema_version::<T>(),
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            created: None,
+            updated: None,
+            revision: None,
+            checksum: None,
+        }
+    }
+    /// Parse header from the first line of the file content
+    /// Returns None if the file has no header.
+    pub fn parse(buffer: &str) -> PackResult<Option<FileHeader>> {
+        let line = match buffer.lines().next() {
+            Some(line) if line.starts_with(MAGIC) => &line[MAGIC.len()..],
+            _ => return Ok(None),
+        };
+        let mut header = FileHeader::default();
+        // Unknown keys are skipped, so newer headers can be read
+        for (key, value) in line.split_whitespace().filter_map(|i| {
+            let mut kv = i.splitn(2, '=');
+            Some((kv.next()?, kv.next()?))
+        }) {
+            match key {
+                "format" => header.format = Format::parse(value)?,
+                "schema_version" => {
+                    header.schema_version = value.parse().map_err(|_| {
+                        PackError::custom_deserialize(format!(
+                            "Invalid schema version: {}",
+                            value
+                        ))
+                    })?
+                }
+                "crate_version" => header.crate_version = value.to_string(),
+                "created" => header.created = Some(parse_millis(value)?),
+                "updated" => header.updated = Some(parse_millis(value)?),
+                "revision" => {
+                    header.revision = Some(value.parse().map_err(|_| {
+                        PackError::custom_deserialize(format!(
+                            "Invalid revision: {}",
+                            value
+                        ))
+                    })?)
+                }
+                "checksum" => header.checksum = Some(value.to_string()),
+                _ => (),
+            }
+        }
+        Ok(Some(header))
+    }
+    // Parse header from the first line of a file content,
+    // which may be binary after the header
+    pub(crate) fn parse_bytes(bytes: &[u8]) -> PackResult<Option<FileHeader>> {
+        let line = bytes.split(|b| *b == b'\n').next().unwrap_or_default();
+        match std::str::from_utf8(line) {
+            Ok(line) => FileHeader::parse(line),
+            Err(_) => Ok(None),
+        }
+    }
+    /// Read header of a stored file
+    /// Only the first line is read.
+    pub fn read(path: &Path) -> PackResult<Option<FileHeader>> {
+        let mut line = String::new();
+        BufReader::new(File::open(path)?).read_line(&mut line)?;
+        FileHeader::parse(&line)
+    }
+}
+
+fn parse_millis(value: &str) -> PackResult<u128> {
+    value.parse().map_err(|_| {
+        PackError::custom_deserialize(format!("Invalid timestamp: {}", value))
+    })
+}
+
+impl fmt::Display for FileHeader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} format={} schema_version={} crate_version={}",
+            MAGIC,
+            self.format.as_str(),
+            self.schema_version,
+            self.crate_version
+        )?;
+        if let Some(created) = self.created {
+            write!(f, " created={}", created)?;
+        }
+        if let Some(updated) = self.updated {
+            write!(f, " updated={}", updated)?;
+        }
+        if let Some(revision) = self.revision {
+            write!(f, " revision={}", revision)?;
+        }
+        // Checksum must be the last one, see encode_to
+        match &self.checksum {
+            Some(checksum) => write!(f, " checksum={}", checksum),
+            None => Ok(()),
+        }
+    }
+}
+
+// Encode T with header
+pub(crate) fn encode<T: serde::Serialize>(data: &T) -> PackResult<Vec<u8>> {
+    encode_with(data, &FileHeader::current::<T>())
+}
+
+// Encode T with the given header
+pub(crate) fn encode_with<T: serde::Serialize>(
+    data: &T,
+    header: &FileHeader,
+) -> PackResult<Vec<u8>> {
+    let mut buffer = Cursor::new(Vec::new());
+    encode_to(data, header, &mut buffer)?;
+    Ok(buffer.into_inner())
+}
+
+// Encode T with the given header into writer
+// JSON and MessagePack bodies are streamed into the writer;
+// serde_yaml 0.8 builds the whole YAML document in memory
+// before writing it. The header is written first with a
+// placeholder checksum of the same length, which is overwritten
+// once the body is hashed, so the writer must be seekable.
+// Returns the checksum of the body.
+pub(crate) fn encode_to<T, W>(
+    data: &T,
+    header: &FileHeader,
+    writer: &mut W,
+) -> PackResult<String>
+where
+    T: serde::Serialize,
+    W: Write + Seek + ?Sized,
+{
+    let start = writer.stream_position()?;
+    let line = FileHeader {
+        checksum: Some(format!("{}{}", CHECKSUM_PREFIX, "0".repeat(64))),
+        ..header.clone()
+    }
+    .to_string();
+    writer.write_all(line.as_bytes())?;
+    writer.write_all(b"\n")?;
+    let hasher = write_body(data, header.format, BufWriter::new(&mut *writer))?;
+    let hex = to_hex(&hasher.finalize());
+    let offset = start + (line.len() - hex.len()) as u64;
+    writer.seek(SeekFrom::Start(offset))?;
+    writer.write_all(hex.as_bytes())?;
+    writer.seek(SeekFrom::End(0))?;
+    Ok(format!("{}{}", CHECKSUM_PREFIX, hex))
+}
+
+// Serialize T into writer, returns the hash of the written bytes
+fn write_body<T, W>(data: &T, format: Format, writer: W) -> PackResult<Sha256>
+where
+    T: serde::Serialize,
+    W: Write,
+{
+    let mut body = HashWriter {
+        inner: writer,
+        hasher: Sha256::new(),
+        error: None,
+    };
+    match format {
+        Format::Yaml => serde_yaml::to_writer(&mut body, data)
+            .map_err(|err| body.io_error().unwrap_or_else(|| err.into()))?,
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut body, data).map_err(|err| {
+                match body.io_error() {
+                    Some(err) => err,
+                    None => {
+                        PackError::InternalError(format!("JSON error: {}", err))
+                    }
+                }
+            })?;
+            body.write_all(b"\n")?;
+        }
+        Format::MessagePack => rmp_serde::encode::write_named(&mut body, data)
+            .map_err(|err| match body.io_error() {
+                Some(err) => err,
+                None => PackError::InternalError(format!(
+                    "MessagePack error: {}",
+                    err
+                )),
+            })?,
+    }
+    body.flush()?;
+    Ok(body.hasher)
+}
+
+// Writer hashing everything written through it
+// Keeps the last IO error, as serializers wrap it into their own.
+struct HashWriter<W: Write> {
+    inner: W,
+    hasher: Sha256,
+    error: Option<(io::ErrorKind, String)>,
+}
+
+impl<W: Write> HashWriter<W> {
+    fn io_error(&mut self) -> Option<PackError> {
+        self.error
+            .take()
+            .map(|(kind, msg)| io::Error::new(kind, msg).into())
+    }
+}
+
+impl<W: Write> Write for HashWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf).inspect_err(|err| {
+            self.error = Some((err.kind(), err.to_string()));
+        })?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+const CHECKSUM_PREFIX: &str = "sha256:";
+
+// Checksum of a file body
+pub(crate) fn checksum(body: &[u8]) -> String {
+    format!("{}{}", CHECKSUM_PREFIX, to_hex(&Sha256::digest(body)))
+}
+
+// Verify the checksum of a file content, if it has one
+// Returns PackError::IntegrityError on mismatch.
+pub(crate) fn verify_checksum(buffer: &[u8]) -> PackResult<()> {
+    let expected = match FileHeader::parse_bytes(buffer)? {
+        Some(FileHeader {
+            checksum: Some(checksum),
+            ..
+        }) => checksum,
+        _ => return Ok(()),
+    };
+    check_checksum(&expected, &checksum(after_line(buffer)))
+}
+
+// Content after the first line
+fn after_line(buffer: &[u8]) -> &[u8] {
+    match buffer.iter().position(|b| *b == b'\n') {
+        Some(pos) => &buffer[pos + 1..],
+        None => &[],
+    }
+}
+
+// Returns PackError::IntegrityError if the checksums differ
+fn check_checksum(expected: &str, found: &str) -> PackResult<()> {
+    match found == expected {
+        true => Ok(()),
+        false => Err(PackError::IntegrityError(format!(
+            "Checksum mismatch, expected {}, found {}",
+            expected, found
+        ))),
+    }
+}
+
+// File content without the header line
+pub(crate) fn body(buffer: &[u8]) -> &[u8] {
+    match buffer.starts_with(MAGIC.as_bytes()) {
+        true => after_line(buffer),
+        false => buffer,
+    }
+}
+
+// Encode T in the format of an existing file content
+#[cfg(feature = "watch")]
+pub(crate) fn reencode<T: serde::Serialize>(
+    data: &T,
+    buffer: &[u8],
+) -> PackResult<Vec<u8>> {
+    let header = FileHeader::parse_bytes(buffer)?.unwrap_or_default();
+    encode_with(data, &upgraded::<T>(&header))
+}
+
+// Header of a T file re-saved by the current crate version,
+// keeping its format and save times
+pub(crate) fn upgraded<T: ?Sized>(header: &FileHeader) -> FileHeader {
+    FileHeader {
+        format: header.format,
+        created: header.created,
+        updated: header.updated,
+        revision: header.revision,
+        ..FileHeader::current::<T>()
+    }
+}
+
+// Creation time and revision of the file of a Pack, kept in
+// memory so a save does not read the header back. Unknown for
+// Packs not loaded from their file, then it is read once.
+#[derive(Debug, Default)]
+pub(crate) struct Stamp(Mutex<StampState>);
+
+#[derive(Debug, Default, Clone)]
+struct StampState {
+    saved: Option<(Option<u128>, Option<u64>)>,
+    // Header of a file loaded with migration,
+    // until the migrated data is saved
+    migrated: Option<FileHeader>,
+}
+
+impl Stamp {
+    pub(crate) fn new(created: Option<u128>, revision: Option<u64>) -> Self {
+        Stamp(Mutex::new(StampState {
+            saved: Some((created, revision)),
+            migrated: None,
+        }))
+    }
+    // Stamp of a loaded file
+    pub(crate) fn of(header: &FileHeader) -> Self {
+        Stamp::new(header.created, header.revision)
+    }
+    // Stamp of a loaded file, migrated or not
+    pub(crate) fn loaded(header: FileHeader, migrated: bool) -> Self {
+        let stamp = Stamp::of(&header);
+        if migrated {
+            stamp.0.lock().unwrap().migrated = Some(header);
+        }
+        stamp
+    }
+    // Creation time and revision, read by read if unknown
+    pub(crate) fn get<F>(&self, read: F) -> (Option<u128>, Option<u64>)
+    where
+        F: FnOnce() -> FileHeader,
+    {
+        let known = self.0.lock().unwrap().saved;
+        known.unwrap_or_else(|| {
+            let header = read();
+            (header.created, header.revision)
+        })
+    }
+    // The file is saved, migrated data as well
+    pub(crate) fn set(&self, created: Option<u128>, revision: Option<u64>) {
+        *self.0.lock().unwrap() = StampState {
+            saved: Some((created, revision)),
+            migrated: None,
+        };
+    }
+    // Header of the file, if it is migrated but not saved yet
+    pub(crate) fn migrated(&self) -> Option<FileHeader> {
+        self.0.lock().unwrap().migrated.clone()
+    }
+}
+
+impl Clone for Stamp {
+    fn clone(&self) -> Self {
+        Stamp(Mutex::new(self.0.lock().unwrap().clone()))
+    }
+}
+
+// Decode T by its header, migrating older schema versions.
+// Returns true as well if it was migrated.
+pub(crate) fn decode<T: DeserializeOwned>(
+    buffer: &[u8],
+) -> PackResult<(T, bool)> {
+    let (data, _, migrated) = decode_from(buffer)?;
+    Ok((data, migrated))
+}
+
+// Decode a file content by its header into a YAML value,
+// without migration, e.g. to inspect files of an unknown type
+pub(crate) fn decode_value(buffer: &[u8]) -> PackResult<serde_yaml::Value> {
+    verify_checksum(buffer)?;
+    let header = FileHeader::parse_bytes(buffer)?.unwrap_or_default();
+    read_as(header.format, body(buffer))
+}
+
+// Decode T from reader by its header, see decode
+// The body is hashed while it is read. JSON and MessagePack
+// are deserialized from the reader, serde_yaml 0.8 reads the
+// whole body into memory first. Returns the header as well.
+pub(crate) fn decode_from<T, R>(
+    mut reader: R,
+) -> PackResult<(T, FileHeader, bool)>
+where
+    T: DeserializeOwned,
+    R: BufRead,
+{
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let header = FileHeader::parse(&line)?;
+    // Without header, the first line belongs to the body
+    let first = match header {
+        Some(_) => String::new(),
+        None => line,
+    };
+    let header = header.unwrap_or_default();
+    let mut body = HashReader {
+        inner: first.as_bytes().chain(reader),
+        hasher: Sha256::new(),
+    };
+    let res = read_body::<T, _>(&header, &mut body);
+    // Hash the rest, if the deserializer has stopped early;
+    // a corrupted file is reported as such, even if it could
+    // not be deserialized.
+    io::copy(&mut body, &mut io::sink())?;
+    if let Some(expected) = &header.checksum {
+        let hex = to_hex(&body.hasher.finalize());
+        check_checksum(expected, &format!("{}{}", CHECKSUM_PREFIX, hex))?;
+    }
+    let (data, migrated) = res?;
+    Ok((data, header, migrated))
+}
+
+// Decode T from a whole file content, see decode_from
+// A MessagePack body is deserialized straight from the slice,
+// e.g. from a memory map; text formats are read through it.
+#[cfg(feature = "mmap")]
+pub(crate) fn decode_slice<T: DeserializeOwned>(
+    buffer: &[u8],
+) -> PackResult<(T, FileHeader, bool)> {
+    match FileHeader::parse_bytes(buffer)? {
+        Some(header)
+            if header.format == Format::MessagePack
+                && header.schema_version == migrate::schema_version::<T>() =>
+        {
+            verify_checksum(buffer)?;
+            let data = rmp_serde::from_slice(body(buffer))
+                .map_err(|err| msgpack_error(&err))?;
+            Ok((data, header, false))
+        }
+        _ => decode_from(buffer),
+    }
+}
+
+// Deserialize T from the body, migrating older schema versions
+fn read_body<T, R>(header: &FileHeader, body: R) -> PackResult<(T, bool)>
+where
+    T: DeserializeOwned,
+    R: Read,
+{
+    if header.schema_version == migrate::schema_version::<T>() {
+        return Ok((read_as(header.format, body)?, false));
+    }
+    let mut value = read_as(header.format, body)?;
+    migrate::migrate::<T>(header.schema_version, &mut value)?;
+    let data = serde_yaml::from_value(value).map_err(PackError::deserialize)?;
+    Ok((data, true))
+}
+
+// Deserialize T from a body in the given format
+fn read_as<T, R>(format: Format, body: R) -> PackResult<T>
+where
+    T: DeserializeOwned,
+    R: Read,
+{
+    match format {
+        Format::Yaml => {
+            serde_yaml::from_reader(body).map_err(PackError::deserialize)
+        }
+        Format::Json => serde_json::from_reader(body).map_err(|err| {
+            PackError::custom_deserialize(format!("JSON error: {}", err))
+        }),
+        Format::MessagePack => {
+            rmp_serde::from_read(body).map_err(|err| msgpack_error(&err))
+        }
+    }
+}
+
+fn msgpack_error(err: &rmp_serde::decode::Error) -> PackError {
+    PackError::custom_deserialize(format!("MessagePack error: {}", err))
+}
+
+// Reader hashing everything read through it
+struct HashReader<R: Read> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> Read for HashReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
@@ -0,0 +1,318 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Version history
+//!
+//! In history mode every save appends the previous content of the
+//! file to its history file, .history/<file name> next to it, so
+//! earlier versions can be listed and reverted to. Optionally only
+//! the last N versions are kept. Saves that do not change the
+//! content are not recorded.
+//!
+//! A Pack remembers its history mode in its history file; a
+//! VecPack in the hidden .history.yml file, for all its members.
+//!
+//! undo() steps back to the previous version, and redo() forward
+//! again, editor style. The undone versions are kept for redo()
+//! until the next change is saved.
+
+use crate::{header, unix_millis, Pack, PackError, PackResult};
+use crate::{VecPack, VecPackMember};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// History mode config, kept in the pack context
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct HistoryConfig {
+    // Number of versions to keep, None keeps all
+    keep: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct HistoryFile {
+    config: HistoryConfig,
+    versions: Vec<Entry>,
+    // Undone versions, the last one is redone first
+    #[serde(default)]
+    redo: Vec<Entry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Entry {
+    version: u64,
+    saved_at: u128,
+    // File content of the version, with header
+    content: Content,
+}
+
+// File content of a version
+// Binary content (e.g. format=msgpack) is kept base64 encoded,
+// as the history file itself is YAML.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum Content {
+    Text(String),
+    Binary { base64: String },
+}
+
+impl Content {
+    fn new(bytes: Vec<u8>) -> Content {
+        match String::from_utf8(bytes) {
+            Ok(text) => Content::Text(text),
+            Err(err) => Content::Binary {
+                base64: BASE64.encode(err.as_bytes()),
+            },
+        }
+    }
+    fn bytes(&self) -> PackResult<Cow<'_, [u8]>> {
+        match self {
+            Content::Text(text) => Ok(Cow::Borrowed(text.as_bytes())),
+            Content::Binary { base64 } => BASE64
+                .decode(base64)
+                .map(Cow::Owned)
+                .map_err(|err| PackError::custom_deserialize(err.to_string())),
+        }
+    }
+}
+
+/// Version
+/// An earlier version of a Pack's data
+#[derive(Debug, Clone, PartialEq)]
+pub struct Version<T> {
+    /// Version number, increasing from 1
+    pub version: u64,
+    /// Time when it was replaced by a newer version,
+    /// in milliseconds since UNIX EPOCH
+    pub saved_at: u128,
+    pub data: T,
+}
+
+// History file of a pack file
+pub(crate) fn history_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default();
+    match path.parent() {
+        Some(dir) => dir.join(".history").join(name),
+        None => Path::new(".history").join(name),
+    }
+}
+
+fn read_file(path: &Path) -> PackResult<Option<HistoryFile>> {
+    match std::fs::read(history_path(path)) {
+        Ok(buffer) => Ok(Some(header::decode::<HistoryFile>(&buffer)?.0)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn write_file(path: &Path, file: &HistoryFile) -> PackResult<()> {
+    let history = history_path(path);
+    if let Some(dir) = history.parent().filter(|dir| !dir.exists()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    crate::save_data_object(&history, file)
+}
+
+// Append the current content of path to its history,
+// before it is replaced by content
+pub(crate) fn record(
+    path: &Path,
+    checksum: &str,
+    config: HistoryConfig,
+) -> PackResult<()> {
+    let previous = match std::fs::read(path) {
+        Ok(previous) => previous,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+    // The header holds the save times, compare the data only
+    if header::checksum(header::body(&previous)) == checksum {
+        return Ok(());
+    }
+    let mut file = read_file(path)?.unwrap_or_default();
+    file.config = config;
+    let version = file.versions.last().map(|v| v.version + 1).unwrap_or(1);
+    file.versions.push(Entry {
+        version,
+        saved_at: unix_millis(SystemTime::now()),
+        content: Content::new(previous),
+    });
+    if let Some(keep) = config.keep {
+        let over = file.versions.len().saturating_sub(keep);
+        file.versions.drain(..over);
+    }
+    // A new change cannot be redone over
+    file.redo.clear();
+    write_file(path, &file)
+}
+
+// History config of a pack file, if it has a history
+pub(crate) fn load_config(path: &Path) -> PackResult<Option<HistoryConfig>> {
+    Ok(read_file(path)?.map(|file| file.config))
+}
+
+impl<T> Pack<T>
+where
+    for<'de> T: Serialize + Deserialize<'de> + Sized,
+{
+    /// Enable history mode
+    /// Every later save keeps the previous version;
+    /// keep limits the number of kept versions.
+    pub fn enable_history(&mut self, keep: Option<usize>) -> PackResult<()> {
+        self.ctx.check_writable()?;
+        let config = HistoryConfig { keep };
+        let mut file = read_file(&self.path)?.unwrap_or_default();
+        file.config = config;
+        write_file(&self.path, &file)?;
+        self.ctx.set_history(Some(config));
+        Ok(())
+    }
+    /// Earlier versions, oldest first
+    /// The current data is not included.
+    pub fn history(&self) -> PackResult<Vec<Version<T>>> {
+        let file =
+            read_file(&self.ctx.resolve(&self.path))?.unwrap_or_default();
+        file.versions
+            .into_iter()
+            .map(|entry| {
+                Ok(Version {
+                    version: entry.version,
+                    saved_at: entry.saved_at,
+                    data: header::decode::<T>(&entry.content.bytes()?)?.0,
+                })
+            })
+            .collect()
+    }
+    /// Revert data to an earlier version, then save it
+    /// The replaced data becomes a new version itself.
+    /// Returns PackError::ObjectNotFound if there is no such version.
+    pub fn revert_to(&mut self, version: u64) -> PackResult<()> {
+        let data = self
+            .history()?
+            .into_iter()
+            .find(|v| v.version == version)
+            .ok_or(PackError::ObjectNotFound)?
+            .data;
+        self.update(|d| *d = data)
+    }
+    /// Undo the last change
+    /// Data becomes the previous version, and the current data
+    /// can be restored by redo(). Returns false if there is no
+    /// earlier version.
+    pub fn undo(&mut self) -> PackResult<bool> {
+        self.ctx.check_writable()?;
+        let mut file = read_file(&self.path)?.unwrap_or_default();
+        let entry = match file.versions.pop() {
+            Some(entry) => entry,
+            None => return Ok(false),
+        };
+        let current = self.step_to(&entry)?;
+        file.redo.push(current);
+        write_file(&self.path, &file)?;
+        Ok(true)
+    }
+    /// Redo the last undone change
+    /// Returns false if there is nothing to redo.
+    pub fn redo(&mut self) -> PackResult<bool> {
+        self.ctx.check_writable()?;
+        let mut file = read_file(&self.path)?.unwrap_or_default();
+        let entry = match file.redo.pop() {
+            Some(entry) => entry,
+            None => return Ok(false),
+        };
+        let mut current = self.step_to(&entry)?;
+        current.version =
+            file.versions.last().map(|v| v.version + 1).unwrap_or(1);
+        file.versions.push(current);
+        write_file(&self.path, &file)?;
+        Ok(true)
+    }
+    /// True if there is an earlier version to undo to
+    pub fn can_undo(&self) -> PackResult<bool> {
+        Ok(read_file(&self.ctx.resolve(&self.path))?
+            .map(|f| !f.versions.is_empty())
+            == Some(true))
+    }
+    /// True if there is an undone change to redo
+    pub fn can_redo(&self) -> PackResult<bool> {
+        let file = read_file(&self.ctx.resolve(&self.path))?;
+        Ok(file.map(|f| !f.redo.is_empty()) == Some(true))
+    }
+    // Save the data of entry without recording it in the history
+    // Returns the replaced data as an entry.
+    fn step_to(&mut self, entry: &Entry) -> PackResult<Entry> {
+        let data = header::decode::<T>(&entry.content.bytes()?)?.0;
+        let current = Entry {
+            version: entry.version,
+            saved_at: unix_millis(SystemTime::now()),
+            content: Content::new(header::encode(&self.data)?),
+        };
+        let previous = std::mem::replace(&mut self.data, data);
+        if let Err(err) = self.ctx.without_history(|| self.save()) {
+            self.data = previous;
+            return Err(err);
+        }
+        Ok(current)
+    }
+    // Restore history mode of a loaded pack
+    pub(crate) fn load_history(&self) -> PackResult<()> {
+        if let Some(config) = load_config(&self.path)? {
+            self.ctx.set_history(Some(config));
+        }
+        Ok(())
+    }
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Enable history mode for every member
+    /// See Pack::enable_history.
+    pub fn enable_history(&mut self, keep: Option<usize>) -> PackResult<()> {
+        self.check_writable()?;
+        let config = HistoryConfig { keep };
+        crate::save_data_object(&self.history_config_path(), config)?;
+        self.ctx.set_history(Some(config));
+        Ok(())
+    }
+    // Restore history mode
+    pub(crate) fn load_history(&mut self) -> PackResult<()> {
+        let path = self.history_config_path();
+        if path.exists() {
+            let buffer = std::fs::read(path)?;
+            let config = header::decode::<HistoryConfig>(&buffer)?.0;
+            self.ctx.set_history(Some(config));
+        }
+        Ok(())
+    }
+    fn history_config_path(&self) -> PathBuf {
+        self.dir().join(".history.yml")
+    }
+}
@@ -0,0 +1,203 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Lifecycle hooks
+//!
+//! PackHooks<T> runs custom code before T is serialized, and after
+//! T is deserialized, so normalization, timestamps and derived
+//! fields are handled in one place. Hooks can be set on a Pack,
+//! or on a VecPack, where they apply to every member.
+//!
+//! before_save runs on every mutation that is saved (Pack::update,
+//! PackGuard drop, VecPack::insert), so the in-memory data always
+//! matches the saved one. after_load runs on every member loaded
+//! while the hooks are set, and once on the current data when the
+//! hooks are set.
+
+use crate::validate::Validator;
+use crate::{Pack, PackError, PackResult, VecPack, VecPackMember};
+use serde::Serialize;
+use std::fmt;
+use std::sync::Arc;
+
+/// Lifecycle hooks of a Pack<T>
+/// Both hooks are no-op by default.
+pub trait PackHooks<T> {
+    /// Called before T is serialized and saved
+    fn before_save(&self, _data: &mut T) {}
+    /// Called after T is deserialized
+    fn after_load(&self, _data: &mut T) {}
+}
+
+type HookFn<T> = Box<dyn Fn(&mut T) + Send + Sync>;
+
+/// FnHooks<T>
+/// PackHooks<T> built from closures
+/// ```rust
+/// use storaget::FnHooks;
+/// let hooks = FnHooks::new()
+///     .before_save(|name: &mut String| *name = name.trim().to_string());
+/// ```
+pub struct FnHooks<T> {
+    before_save: Option<HookFn<T>>,
+    after_load: Option<HookFn<T>>,
+}
+
+impl<T> FnHooks<T> {
+    /// New FnHooks without any hook
+    pub fn new() -> Self {
+        FnHooks {
+            before_save: None,
+            after_load: None,
+        }
+    }
+    /// Set before_save hook
+    pub fn before_save<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut T) + Send + Sync + 'static,
+    {
+        self.before_save = Some(Box::new(f));
+        self
+    }
+    /// Set after_load hook
+    pub fn after_load<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut T) + Send + Sync + 'static,
+    {
+        self.after_load = Some(Box::new(f));
+        self
+    }
+}
+
+impl<T> Default for FnHooks<T> {
+    fn default() -> Self {
+        FnHooks::new()
+    }
+}
+
+impl<T> PackHooks<T> for FnHooks<T> {
+    fn before_save(&self, data: &mut T) {
+        if let Some(f) = &self.before_save {
+            f(data);
+        }
+    }
+    fn after_load(&self, data: &mut T) {
+        if let Some(f) = &self.after_load {
+            f(data);
+        }
+    }
+}
+
+// Hooks of a Pack<T>, shared with its VecPack
+pub(crate) struct Hooks<T> {
+    pub(crate) hooks: Option<Arc<dyn PackHooks<T> + Send + Sync>>,
+    pub(crate) validator: Option<Validator<T>>,
+}
+
+impl<T> Hooks<T> {
+    pub(crate) fn before_save(&self, data: &mut T) {
+        if let Some(hooks) = &self.hooks {
+            hooks.before_save(data);
+        }
+    }
+    pub(crate) fn after_load(&self, data: &mut T) {
+        if let Some(hooks) = &self.hooks {
+            hooks.after_load(data);
+        }
+    }
+    // Validate data if validation is enabled
+    pub(crate) fn validate(&self, data: &T) -> PackResult<()> {
+        match self.validator {
+            Some(validator) => {
+                validator(data).map_err(PackError::ValidationError)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T> Default for Hooks<T> {
+    fn default() -> Self {
+        Hooks {
+            hooks: None,
+            validator: None,
+        }
+    }
+}
+
+impl<T> Clone for Hooks<T> {
+    fn clone(&self) -> Self {
+        Hooks {
+            hooks: self.hooks.clone(),
+            validator: self.validator,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Hooks<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("hooks", &self.hooks.is_some())
+            .field("validator", &self.validator.is_some())
+            .finish()
+    }
+}
+
+impl<T> Pack<T>
+where
+    T: Serialize + Sized,
+{
+    /// Set lifecycle hooks
+    /// after_load runs on the current data right away,
+    /// as it was loaded before the hooks were set.
+    pub fn set_hooks<H>(&mut self, hooks: H)
+    where
+        H: PackHooks<T> + Send + Sync + 'static,
+    {
+        self.hooks.hooks = Some(Arc::new(hooks));
+        self.hooks.after_load(&mut self.data);
+    }
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Set lifecycle hooks of the members
+    /// after_load runs on the current members right away,
+    /// then on every member loaded later (reload, restore).
+    /// before_save runs on every inserted and updated member.
+    pub fn set_hooks<H>(&mut self, hooks: H)
+    where
+        H: PackHooks<T> + Send + Sync + 'static,
+    {
+        self.hooks.hooks = Some(Arc::new(hooks));
+        self.members_touched();
+        for pack in self.data.iter_mut() {
+            pack.hooks = self.hooks.clone();
+            self.hooks.after_load(&mut pack.data);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! ID lookup
+//!
+//! VecPack<T> keeps an ID -> position map of its members, so
+//! find_id, find_id_mut and check_id_available do not scan the
+//! members. Inserts extend the map, removes other than the last
+//! one rebuild it lazily, so a run of removes costs one. Members
+//! borrowed mutably may change their ID, so they are re-checked
+//! before the next lookup; reordering rebuilds the map lazily.
+
+use crate::{Pack, VecPackMember};
+use std::collections::{HashMap, HashSet};
+
+// ID -> position map of the members
+#[derive(Debug, Default)]
+pub(crate) struct IdIndex {
+    positions: HashMap<String, usize>,
+    // IDs of the members borrowed mutably since the last lookup
+    touched: HashSet<String>,
+    // Positions are out of date, rebuild at the next lookup
+    stale: bool,
+}
+
+impl IdIndex {
+    // Member pushed to the end
+    pub(crate) fn push(&mut self, id: &str, position: usize) {
+        self.positions.insert(id.to_string(), position);
+    }
+    // Member removed from position, the later ones shift back,
+    // so the map is rebuilt at the next lookup, unless it was
+    // the last one.
+    pub(crate) fn remove(&mut self, id: &str, position: usize) {
+        self.positions.remove(id);
+        if position != self.positions.len() {
+            self.invalidate();
+        }
+    }
+    // Member may change its ID
+    pub(crate) fn touch(&mut self, id: &str) {
+        if !self.stale {
+            self.touched.insert(id.to_string());
+        }
+    }
+    // Members are reordered or replaced
+    pub(crate) fn invalidate(&mut self) {
+        self.stale = true;
+        self.touched.clear();
+    }
+    // Position of the member with ID
+    // Every hit is verified, a wrong one rebuilds the map.
+    pub(crate) fn position<T>(
+        &mut self,
+        data: &[Pack<T>],
+        id: &str,
+    ) -> Option<usize>
+    where
+        T: VecPackMember,
+    {
+        if self.stale || self.positions.len() != data.len() {
+            self.rebuild(data);
+        } else if !self.touched.is_empty() {
+            self.refresh(data);
+        }
+        match self.positions.get(id) {
+            Some(&p) if data.get(p).map(|i| i.get_id()) == Some(id) => Some(p),
+            Some(_) => {
+                self.rebuild(data);
+                self.positions.get(id).copied()
+            }
+            None => None,
+        }
+    }
+    // Re-key the touched members by their current ID
+    fn refresh<T>(&mut self, data: &[Pack<T>])
+    where
+        T: VecPackMember,
+    {
+        for id in std::mem::take(&mut self.touched) {
+            let position = match self.positions.get(&id) {
+                Some(&p) => p,
+                None => continue,
+            };
+            match data.get(position).map(|i| i.get_id()) {
+                Some(current) if current == id => (),
+                Some(current) => {
+                    self.positions.remove(&id);
+                    self.positions.insert(current.to_string(), position);
+                }
+                None => self.stale = true,
+            }
+        }
+        if self.stale {
+            self.rebuild(data);
+        }
+    }
+    fn rebuild<T>(&mut self, data: &[Pack<T>])
+    where
+        T: VecPackMember,
+    {
+        self.positions.clear();
+        for (position, pack) in data.iter().enumerate() {
+            self.positions
+                .entry(pack.get_id().to_string())
+                .or_insert(position);
+        }
+        self.touched.clear();
+        self.stale = false;
+    }
+}
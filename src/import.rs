@@ -0,0 +1,304 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Bulk import
+//!
+//! import_from_reader() parses members from a JSON array, or with
+//! the `csv` feature from CSV, and inserts them in bulk. Invalid
+//! rows do not stop the import: every row that cannot be parsed,
+//! has an empty, taken or duplicated ID, or fails to save, is
+//! reported in the ImportReport with its row number.
+//!
+//! CSV columns are the dot separated field paths written by
+//! export_csv() (engine.hp, tags.0). Cell types follow the fields
+//! of T::default(); empty cells take their value from it.
+
+use crate::{PackError, PackResult, VecPack, VecPackMember};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Read;
+
+/// ImportFormat
+/// Input format of import_from_reader()
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportFormat {
+    /// JSON array of members
+    Json,
+    /// CSV with a header row
+    #[cfg(feature = "csv")]
+    Csv,
+}
+
+/// ImportError
+/// A row that was not imported
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportError {
+    /// Row number, starting from 1
+    pub row: usize,
+    /// Member ID, if the row could be parsed
+    pub id: Option<String>,
+    pub message: String,
+}
+
+/// ImportReport
+/// Result of import_from_reader()
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportReport {
+    /// IDs of the inserted members, in row order
+    pub inserted: Vec<String>,
+    /// Rows that were not imported
+    pub errors: Vec<ImportError>,
+}
+
+// Parse a JSON array into members, one result per row
+fn parse_json<T: DeserializeOwned>(
+    reader: impl Read,
+) -> PackResult<Vec<Result<T, String>>> {
+    let rows: Vec<serde_json::Value> = serde_json::from_reader(reader)
+        .map_err(|err| PackError::custom_deserialize(err.to_string()))?;
+    Ok(rows
+        .into_iter()
+        .map(|row| serde_json::from_value(row).map_err(|e| e.to_string()))
+        .collect())
+}
+
+#[cfg(feature = "csv")]
+mod csv_rows {
+    use crate::{PackError, PackResult};
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use serde_yaml::{Mapping, Value};
+    use std::collections::HashMap;
+    use std::io::Read;
+
+    // Leaf values of a value by their dot separated path
+    fn leaves(path: &str, value: &Value, out: &mut HashMap<String, Value>) {
+        let child = |key: String| match path.is_empty() {
+            true => key,
+            false => format!("{}.{}", path, key),
+        };
+        match value {
+            Value::Mapping(map) if !map.is_empty() => {
+                for (key, value) in map {
+                    if let Value::String(key) = key {
+                        leaves(&child(key.clone()), value, out);
+                    }
+                }
+            }
+            Value::Sequence(seq) if !seq.is_empty() => {
+                for (i, value) in seq.iter().enumerate() {
+                    leaves(&child(i.to_string()), value, out);
+                }
+            }
+            value => {
+                out.insert(path.to_string(), value.clone());
+            }
+        }
+    }
+
+    // Value of a cell, typed like the template field
+    fn typed(cell: &str, template: Option<&Value>) -> Value {
+        match template {
+            Some(Value::String(_)) => Value::String(cell.to_string()),
+            _ => match serde_yaml::from_str::<Value>(cell) {
+                Ok(value @ Value::Bool(_)) | Ok(value @ Value::Number(_)) => {
+                    value
+                }
+                _ => Value::String(cell.to_string()),
+            },
+        }
+    }
+
+    // Set a nested field by its path; defaults never replace
+    // a value set by another column
+    fn insert(map: &mut Mapping, path: &[&str], value: Value, default: bool) {
+        let key = Value::String(path[0].to_string());
+        if path.len() == 1 {
+            if !default || !map.contains_key(&key) {
+                map.insert(key, value);
+            }
+            return;
+        }
+        let entry = map
+            .entry(key)
+            .or_insert_with(|| Value::Mapping(Mapping::new()));
+        if !default && !matches!(entry, Value::Mapping(_)) {
+            *entry = Value::Mapping(Mapping::new());
+        }
+        if let Value::Mapping(child) = entry {
+            insert(child, &path[1..], value, default);
+        }
+    }
+
+    // Mappings with the keys 0..n become sequences
+    fn sequences(value: Value) -> Value {
+        match value {
+            Value::Mapping(map) => {
+                let indexed = (0..map.len())
+                    .all(|i| map.contains_key(&Value::String(i.to_string())));
+                if indexed && !map.is_empty() {
+                    let mut map = map;
+                    Value::Sequence(
+                        (0..map.len())
+                            .map(|i| {
+                                map.remove(&Value::String(i.to_string()))
+                                    .map(sequences)
+                                    .unwrap_or(Value::Null)
+                            })
+                            .collect(),
+                    )
+                } else {
+                    Value::Mapping(
+                        map.into_iter()
+                            .map(|(k, v)| (k, sequences(v)))
+                            .collect(),
+                    )
+                }
+            }
+            value => value,
+        }
+    }
+
+    // Parse CSV rows into members, one result per row
+    pub(super) fn parse<T>(
+        reader: impl Read,
+    ) -> PackResult<Vec<Result<T, String>>>
+    where
+        T: DeserializeOwned + Serialize + Default,
+    {
+        let mut template = HashMap::new();
+        leaves("", &serde_yaml::to_value(T::default())?, &mut template);
+        let mut reader = csv::Reader::from_reader(reader);
+        let columns: Vec<String> = reader
+            .headers()
+            .map_err(|err| PackError::custom_deserialize(err.to_string()))?
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        let mut result = Vec::new();
+        for record in reader.records() {
+            let record = match record {
+                Ok(record) => record,
+                Err(err) => {
+                    result.push(Err(err.to_string()));
+                    continue;
+                }
+            };
+            let mut map = Mapping::new();
+            let cells: Vec<(&String, &str)> =
+                columns.iter().zip(record.iter()).collect();
+            for (column, cell) in &cells {
+                let path: Vec<&str> = column.split('.').collect();
+                if !cell.is_empty() {
+                    let value = typed(cell, template.get(*column));
+                    insert(&mut map, &path, value, false);
+                }
+            }
+            for (column, cell) in &cells {
+                let path: Vec<&str> = column.split('.').collect();
+                match template.get(*column) {
+                    Some(value) if cell.is_empty() => {
+                        insert(&mut map, &path, value.clone(), true)
+                    }
+                    _ => (),
+                }
+            }
+            result.push(
+                serde_yaml::from_value(sequences(Value::Mapping(map)))
+                    .map_err(|err| err.to_string()),
+            );
+        }
+        Ok(result)
+    }
+}
+
+impl<T> VecPack<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    /// Import members from reader
+    /// Parses every row, validates the IDs, then inserts the
+    /// valid members in bulk. Returns PackError only if the
+    /// input as a whole is unreadable; row level problems are
+    /// reported in the ImportReport.
+    pub fn import_from_reader<R: Read>(
+        &mut self,
+        format: ImportFormat,
+        reader: R,
+    ) -> PackResult<ImportReport> {
+        let rows: Vec<Result<T, String>> = match format {
+            ImportFormat::Json => parse_json(reader)?,
+            #[cfg(feature = "csv")]
+            ImportFormat::Csv => csv_rows::parse(reader)?,
+        };
+        let mut report = ImportReport::default();
+        let mut rows_by_id: HashMap<String, usize> = HashMap::new();
+        let mut items = Vec::new();
+        for (i, row) in rows.into_iter().enumerate() {
+            let row_no = i + 1;
+            let error = |id: Option<&str>, message: String| ImportError {
+                row: row_no,
+                id: id.map(|id| id.to_string()),
+                message,
+            };
+            let item = match row {
+                Ok(item) => item,
+                Err(message) => {
+                    report.errors.push(error(None, message));
+                    continue;
+                }
+            };
+            let id = item.get_id().to_string();
+            if id.is_empty() {
+                report.errors.push(error(None, "Empty ID".to_string()));
+            } else if let Some(first) = rows_by_id.get(&id) {
+                let message = format!("Duplicated ID in row {}", first);
+                report.errors.push(error(Some(&id), message));
+            } else if !self.check_id_available(&id) {
+                let message = PackError::IDTaken.to_string();
+                report.errors.push(error(Some(&id), message));
+            } else {
+                rows_by_id.insert(id, row_no);
+                items.push(item);
+            }
+        }
+        let ids: Vec<String> =
+            items.iter().map(|i| i.get_id().to_string()).collect();
+        let failures = self.insert_many(items)?;
+        for id in ids {
+            match failures.iter().find(|(failed, _)| *failed == id) {
+                Some((_, err)) => report.errors.push(ImportError {
+                    row: rows_by_id[&id],
+                    id: Some(id),
+                    message: err.to_string(),
+                }),
+                None => report.inserted.push(id),
+            }
+        }
+        report.errors.sort_by_key(|e| e.row);
+        Ok(report)
+    }
+}
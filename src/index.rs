@@ -0,0 +1,273 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Secondary indexes
+//!
+//! Users can register secondary indexes on a VecPack<T> by a key
+//! function. Indexes are maintained on insert and remove; members
+//! borrowed mutably (find_id_mut, as_vec_mut, mutable iteration)
+//! are marked dirty and re-indexed before the next lookup, so
+//! updates through Pack::update or PackGuard are picked up too.
+
+use crate::search::TextIndex;
+use crate::{Pack, PackError, PackResult, VecPack, VecPackMember};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Index key function
+pub type IndexKey<T> = Box<dyn Fn(&T) -> String + Send + Sync>;
+
+// A single secondary index
+struct SecondaryIndex<T> {
+    key: IndexKey<T>,
+    // Index key -> member IDs
+    ids_by_key: HashMap<String, BTreeSet<String>>,
+    // Member ID -> index key
+    key_by_id: HashMap<String, String>,
+}
+
+impl<T> SecondaryIndex<T> {
+    fn insert(&mut self, id: &str, data: &T) {
+        self.remove(id);
+        let key = (self.key)(data);
+        self.ids_by_key
+            .entry(key.clone())
+            .or_default()
+            .insert(id.to_string());
+        self.key_by_id.insert(id.to_string(), key);
+    }
+    fn remove(&mut self, id: &str) {
+        if let Some(key) = self.key_by_id.remove(id) {
+            if let Some(ids) = self.ids_by_key.get_mut(&key) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    self.ids_by_key.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+// Registered secondary indexes of a VecPack
+pub(crate) struct Indexes<T> {
+    indexes: HashMap<String, SecondaryIndex<T>>,
+    // Full-text index, if enabled
+    pub(crate) text: Option<TextIndex<T>>,
+    // Members that may have changed since they were indexed
+    dirty: HashSet<String>,
+    // All members may have changed
+    dirty_all: bool,
+}
+
+impl<T> Default for Indexes<T> {
+    fn default() -> Self {
+        Indexes {
+            indexes: HashMap::new(),
+            text: None,
+            dirty: HashSet::new(),
+            dirty_all: false,
+        }
+    }
+}
+
+impl<T> Indexes<T>
+where
+    T: VecPackMember,
+{
+    pub(crate) fn insert(&mut self, id: &str, data: &T) {
+        for index in self.indexes.values_mut() {
+            index.insert(id, data);
+        }
+        if let Some(text) = &mut self.text {
+            text.insert(id, data);
+        }
+        self.dirty.remove(id);
+    }
+    pub(crate) fn remove(&mut self, id: &str) {
+        for index in self.indexes.values_mut() {
+            index.remove(id);
+        }
+        if let Some(text) = &mut self.text {
+            text.remove(id);
+        }
+        self.dirty.remove(id);
+    }
+    pub(crate) fn touch(&mut self, id: &str) {
+        if !self.is_empty() && !self.dirty_all {
+            self.dirty.insert(id.to_string());
+        }
+    }
+    pub(crate) fn touch_all(&mut self) {
+        if !self.is_empty() {
+            self.dirty_all = true;
+            self.dirty.clear();
+        }
+    }
+    // No index is registered
+    fn is_empty(&self) -> bool {
+        self.indexes.is_empty() && self.text.is_none()
+    }
+    // Re-index the dirty members
+    pub(crate) fn refresh(&mut self, data: &[Pack<T>]) {
+        if self.dirty_all {
+            for index in self.indexes.values_mut() {
+                index.ids_by_key.clear();
+                index.key_by_id.clear();
+                for pack in data {
+                    index.insert(pack.get_id(), &pack.data);
+                }
+            }
+            if let Some(text) = &mut self.text {
+                text.clear();
+                for pack in data {
+                    text.insert(pack.get_id(), &pack.data);
+                }
+            }
+            self.dirty_all = false;
+        } else if !self.dirty.is_empty() {
+            let dirty = &self.dirty;
+            for pack in data.iter().filter(|p| dirty.contains(p.get_id())) {
+                for index in self.indexes.values_mut() {
+                    index.insert(pack.get_id(), &pack.data);
+                }
+                if let Some(text) = &mut self.text {
+                    text.insert(pack.get_id(), &pack.data);
+                }
+            }
+        }
+        self.dirty.clear();
+    }
+    // Index keys with their member count
+    fn sizes(&self, name: &str) -> PackResult<BTreeMap<String, usize>> {
+        match self.indexes.get(name) {
+            Some(index) => Ok(index
+                .ids_by_key
+                .iter()
+                .map(|(key, ids)| (key.clone(), ids.len()))
+                .collect()),
+            None => Err(PackError::InternalError(format!(
+                "Index not found: {}",
+                name
+            ))),
+        }
+    }
+    // Member IDs by index key
+    fn lookup(&self, name: &str, key: &str) -> PackResult<Vec<String>> {
+        match self.indexes.get(name) {
+            Some(index) => Ok(index
+                .ids_by_key
+                .get(key)
+                .map(|ids| ids.iter().cloned().collect())
+                .unwrap_or_default()),
+            None => Err(PackError::InternalError(format!(
+                "Index not found: {}",
+                name
+            ))),
+        }
+    }
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Add secondary index
+    /// Registers an index by name and key function, then indexes
+    /// the current members. An existing index with the same name
+    /// is replaced.
+    pub fn add_index<F>(&mut self, name: &str, key: F)
+    where
+        F: Fn(&T) -> String + Send + Sync + 'static,
+    {
+        let mut index = SecondaryIndex {
+            key: Box::new(key),
+            ids_by_key: HashMap::new(),
+            key_by_id: HashMap::new(),
+        };
+        for pack in &self.data {
+            index.insert(pack.get_id(), &pack.data);
+        }
+        self.indexes
+            .lock()
+            .unwrap()
+            .indexes
+            .insert(name.to_string(), index);
+    }
+    /// Remove secondary index
+    /// Returns false if there was no index with the given name.
+    pub fn remove_index(&mut self, name: &str) -> bool {
+        self.indexes.lock().unwrap().indexes.remove(name).is_some()
+    }
+    /// Find members by secondary index
+    /// Returns the members whose index key equals value,
+    /// ordered by ID. Unknown index name returns PackError.
+    pub fn find_by_index(
+        &self,
+        name: &str,
+        value: &str,
+    ) -> PackResult<Vec<&Pack<T>>> {
+        Ok(self
+            .index_lookup(name, value)?
+            .iter()
+            .filter_map(|id| self.pack_by_id(id))
+            .collect())
+    }
+    /// Group members by a computed partition key
+    /// e.g. orders by customer ID. Groups are maintained
+    /// incrementally as secondary indexes, so group queries
+    /// do not scan the VecPack.
+    pub fn group_by<F>(&mut self, group: &str, key: F)
+    where
+        F: Fn(&T) -> String + Send + Sync + 'static,
+    {
+        self.add_index(group, key);
+    }
+    /// Members in a group with the given partition key
+    pub fn members_in_group(
+        &self,
+        group: &str,
+        key: &str,
+    ) -> PackResult<Vec<&Pack<T>>> {
+        self.find_by_index(group, key)
+    }
+    /// Partition keys of a group with their member count
+    pub fn group_sizes(
+        &self,
+        group: &str,
+    ) -> PackResult<BTreeMap<String, usize>> {
+        let mut indexes = self.indexes.lock().unwrap();
+        indexes.refresh(&self.data);
+        indexes.sizes(group)
+    }
+    // Member IDs by index key, refreshing dirty members first
+    pub(crate) fn index_lookup(
+        &self,
+        name: &str,
+        value: &str,
+    ) -> PackResult<Vec<String>> {
+        let mut indexes = self.indexes.lock().unwrap();
+        indexes.refresh(&self.data);
+        indexes.lookup(name, value)
+    }
+}
@@ -0,0 +1,92 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Inspection
+//!
+//! Reads stored member files without knowing their type, as raw
+//! YAML values with their header. Used by the storaget CLI, and
+//! by anyone who needs to look into a directory whose type is not
+//! at hand. Files are read as they are: no migration is applied
+//! and nothing is written.
+
+use crate::header::{self, FileHeader};
+use crate::{PackError, PackResult};
+use serde_yaml::Value;
+use std::path::{Path, PathBuf};
+
+/// RawMember
+/// A member file read without its type
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawMember {
+    /// ID, the file name without extension
+    pub id: String,
+    pub path: PathBuf,
+    /// File header, the default one for files without header
+    pub header: FileHeader,
+    /// Stored data
+    pub value: Value,
+    /// File size in bytes
+    pub size: usize,
+}
+
+/// Member files of a VecPack directory, in file name order
+/// Shard directories are included, hidden files are not.
+pub fn member_files(dir: &Path) -> PackResult<Vec<PathBuf>> {
+    crate::member_files(dir)
+}
+
+/// Read a member file
+pub fn read_member(path: &Path) -> PackResult<RawMember> {
+    let read = || {
+        let buffer = std::fs::read(path)?;
+        let value = header::decode_value(&buffer)?;
+        let header = FileHeader::parse_bytes(&buffer)?.unwrap_or_default();
+        Ok(RawMember {
+            id: file_id(path),
+            path: path.to_path_buf(),
+            header,
+            value,
+            size: buffer.len(),
+        })
+    };
+    read().map_err(|err: PackError| err.with_path(path))
+}
+
+/// Find and read a member file by its ID
+/// Returns PackError::ObjectNotFound if there is no such file.
+pub fn find_member(dir: &Path, id: &str) -> PackResult<RawMember> {
+    member_files(dir)?
+        .into_iter()
+        .find(|path| file_id(path) == id)
+        .ok_or(PackError::ObjectNotFound)
+        .and_then(|path| read_member(&path))
+}
+
+// File name without extension
+fn file_id(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
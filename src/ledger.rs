@@ -0,0 +1,181 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Hash chained ledger
+//!
+//! On top of an append-only VecPack, every member file is hashed
+//! together with the hash of the previous member (SHA-256), and the
+//! chain is stored in .chain.yml. Any retroactive modification,
+//! removal or reordering of member files breaks the chain, and is
+//! detected by verify_chain().
+
+use crate::{
+    save_data_object, Pack, PackError, PackResult, VecPack, VecPackMember,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// ChainLink
+/// A member ID with its chained hash
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChainLink {
+    /// Member ID
+    pub id: String,
+    /// SHA-256 of the previous hash and the member file content
+    pub hash: String,
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Enable hash chain
+    /// Requires append-only mode. Chains the existing members
+    /// in their current order, then every inserted member is
+    /// appended to the chain. The chain is persisted, and
+    /// loaded with the VecPack.
+    pub fn enable_hash_chain(&mut self) -> PackResult<()> {
+        self.check_writable()?;
+        if !self.append_only {
+            return Err(PackError::InternalError(
+                "Hash chain requires an append-only VecPack".to_string(),
+            ));
+        }
+        if self.chain.is_some() {
+            return Ok(());
+        }
+        self.chain = Some(Vec::new());
+        let paths = self
+            .data
+            .iter()
+            .map(|i| (i.get_id().to_string(), i.path.clone()))
+            .collect::<Vec<(String, PathBuf)>>();
+        for (id, path) in paths {
+            self.push_link(id, &path)?;
+        }
+        self.save_chain()
+    }
+    /// Returns the hash chain, if enabled
+    pub fn chain(&self) -> Option<&Vec<ChainLink>> {
+        self.chain.as_ref()
+    }
+    /// Verify hash chain
+    /// Re-reads every chained member file from disk and recomputes
+    /// the chain. Returns PackError::IntegrityError describing the
+    /// first broken link, or a member missing from the chain.
+    pub fn verify_chain(&self) -> PackResult<()> {
+        let chain = match &self.chain {
+            Some(chain) => chain,
+            None => {
+                return Err(PackError::InternalError(
+                    "Hash chain is not enabled".to_string(),
+                ))
+            }
+        };
+        let mut prev = String::new();
+        for link in chain {
+            let path = match self.pack_by_id(&link.id) {
+                Some(pack) => pack.path.clone(),
+                None => self.member_path(&link.id),
+            };
+            let hash = match chain_hash(&prev, &path) {
+                Ok(hash) => hash,
+                Err(_) => {
+                    return Err(PackError::IntegrityError(format!(
+                        "Chained member is missing: {}",
+                        link.id
+                    )))
+                }
+            };
+            if hash != link.hash {
+                return Err(PackError::IntegrityError(format!(
+                    "Hash chain is broken at member: {}",
+                    link.id
+                )));
+            }
+            prev = hash;
+        }
+        if let Some(pack) = self
+            .data
+            .iter()
+            .find(|i| !chain.iter().any(|l| l.id == i.get_id()))
+        {
+            return Err(PackError::IntegrityError(format!(
+                "Member is not in the hash chain: {}",
+                pack.get_id()
+            )));
+        }
+        Ok(())
+    }
+    // Append a saved member to the chain, if enabled
+    pub(crate) fn extend_chain(&mut self, pack: &Pack<T>) -> PackResult<()> {
+        if self.chain.is_none() {
+            return Ok(());
+        }
+        self.push_link(pack.get_id().to_string(), &pack.path)?;
+        self.save_chain()
+    }
+    // Load persisted chain, if there is any
+    pub(crate) fn load_chain(&mut self) -> PackResult<()> {
+        let path = self.chain_path();
+        if path.is_file() {
+            let chain = serde_yaml::from_str(&std::fs::read_to_string(&path)?)
+                .map_err(PackError::deserialize)?;
+            self.chain = Some(chain);
+        }
+        Ok(())
+    }
+    fn push_link(&mut self, id: String, path: &Path) -> PackResult<()> {
+        if let Some(chain) = &mut self.chain {
+            let prev = chain.last().map(|l| l.hash.as_str()).unwrap_or("");
+            let hash = chain_hash(prev, path)?;
+            chain.push(ChainLink { id, hash });
+        }
+        Ok(())
+    }
+    fn save_chain(&self) -> PackResult<()> {
+        match &self.chain {
+            Some(chain) => save_data_object(&self.chain_path(), chain),
+            None => Ok(()),
+        }
+    }
+    fn chain_path(&self) -> PathBuf {
+        self.dir().join(".chain.yml")
+    }
+}
+
+// Hash of the previous hash and the file content
+fn chain_hash(prev: &str, path: &Path) -> PackResult<String> {
+    let content = std::fs::read(path)?;
+    let mut hasher = Sha256::new();
+    hasher.update(prev.as_bytes());
+    hasher.update(&content);
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
@@ -25,16 +25,138 @@
 
 #![feature(test)]
 
+pub mod actor;
+mod atomic;
+pub mod audit;
+pub mod backend;
+pub mod backup;
+pub mod batch;
+pub mod bundle;
+pub mod conflict;
+mod context;
+pub mod convert;
+pub mod diff;
+pub mod dump;
+pub mod ephemeral;
+pub mod event;
+#[cfg(feature = "csv")]
+mod export;
+pub mod header;
+pub mod history;
+pub mod hooks;
+mod id_index;
+pub mod import;
+pub mod index;
+pub mod inspect;
+pub mod ledger;
+mod lock;
+mod logging;
+pub mod logpack;
+pub mod lru;
+pub mod memory;
+pub mod metadata;
+pub mod metrics;
+pub mod migrate;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod ordered;
+#[cfg(feature = "rayon")]
+mod parallel;
+pub mod poly;
+mod pool;
+pub mod projection;
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+pub mod quarantine;
+pub mod query;
+pub mod registry;
+mod reload;
+mod relocate;
+#[cfg(feature = "remote")]
+pub mod remote;
+mod repair;
+pub mod replica;
+mod revision;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod schema;
+pub mod search;
+pub mod shard;
+pub mod shared;
+pub mod signal;
+#[cfg(feature = "sled")]
+pub mod sled_backend;
+pub mod snapshot;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+mod telemetry;
+pub mod testing;
+mod ttl;
+pub mod usage;
+pub mod validate;
+pub mod verify;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "watch")]
+mod watch;
+
+pub use actor::VecPackActor;
+pub use atomic::TempConfig;
+pub use audit::AuditEntry;
+pub use backend::{BackendWriter, FsBackend, MemoryBackend, StorageBackend};
+pub use backup::{ConflictPolicy, RestoreReport, RestoreStrategy};
+pub use batch::{BatchGuard, BatchMember, SaveReport};
+pub use bundle::SupportBundle;
+pub use conflict::ConflictStrategy;
+pub use convert::ConvertReport;
+pub use diff::{Diff, FieldChange};
+pub use dump::DumpInfo;
+pub use ephemeral::{EphemeralPack, Lifetime};
+pub use event::{ChangeEvent, ChangeKind};
+pub use header::{FileHeader, Format};
+pub use history::Version;
+pub use hooks::{FnHooks, PackHooks};
+pub use import::{ImportFormat, ImportReport};
+pub use logging::set_save_error_hook;
+pub use logpack::{LogIter, LogPack};
+pub use lru::LruVecPack;
+pub use memory::{CollectionLike, MemoryPack, MemoryVecPack, PackLike};
+pub use metadata::PackMetadata;
+pub use metrics::{OpKind, OpStats, SlowOp};
+pub use migrate::{register_migrations, Migratable};
+pub use ordered::OrderedVecPack;
+pub use poly::{PolyMember, PolyMigrations, PolyVecPack};
+pub use projection::Projection;
+pub use quarantine::LoadFailure;
+pub use query::Query;
+pub use registry::Registry;
+pub use replica::ReplicaIssue;
+pub use schema::{schema_diff, schema_diff_sample, SchemaDiff};
+pub use shared::{SharedPack, SharedVecPack};
+pub use signal::ChangeWatcher;
+pub use snapshot::{RetentionPolicy, Snapshot};
+pub use storaget_derive::VecPackMember;
+pub use usage::{MemberUsage, StorageStats};
+pub use validate::{Validate, Validator};
+pub use verify::{verify_dir, VerifyIssue, VerifyReport};
+
+use context::PackContext;
+use header::Stamp;
+use hooks::Hooks;
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::convert::From;
 use std::default::Default;
 use std::fmt;
 use std::fs::File;
 use std::io;
-use std::io::{BufWriter, Read, Write};
+use std::io::{BufRead, BufReader};
 use std::iter::IntoIterator;
 use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 /// PackResult<T>
 ///
@@ -57,13 +179,31 @@ pub enum PackError {
     InternalError(String),
     /// Serialize Error
     /// error occured during serialiuation
-    SerializeError(String),
+    SerializeError {
+        source: serde_yaml::Error,
+        /// File being saved, if known
+        path: Option<PathBuf>,
+        /// Member ID, if known
+        id: Option<String>,
+    },
     /// Deserialize Error
     /// error occured during deserialization
-    DeserializeError(String),
+    DeserializeError {
+        source: serde_yaml::Error,
+        /// File being loaded, if known
+        path: Option<PathBuf>,
+        /// Member ID, if known
+        id: Option<String>,
+    },
     /// IO Error
     /// error during file operations
-    IOError(String),
+    IOError {
+        source: io::Error,
+        /// File of the operation, if known
+        path: Option<PathBuf>,
+        /// Member ID, if known
+        id: Option<String>,
+    },
     /// Object not found in a storage.
     /// Usually using with get_by_id()
     ObjectNotFound,
@@ -73,13 +213,115 @@ pub enum PackError {
     /// ID Taken
     /// When VecPack ID not available
     IDTaken,
+    /// Immutable
+    /// When modifying or removing members
+    /// of an append-only VecPack
+    Immutable,
+    /// Integrity Error
+    /// Stored data does not match its recorded hash
+    IntegrityError(String),
+    /// Storage Full
+    /// No space left on the device (or quota exceeded)
+    /// Reads keep working, writes are rejected
+    StorageFull,
+    /// Validation Error
+    /// Data rejected by its Validate implementation
+    ValidationError(String),
+    /// Locked
+    /// VecPack directory is locked by another writer,
+    /// so this instance is read-only
+    Locked,
+    /// Backend Error
+    /// Storage backend operation failed,
+    /// e.g. a remote request was rejected
+    BackendError(String),
+    /// Conflict
+    /// Pack was saved since the expected revision
+    Conflict { expected: u64, found: u64 },
 }
 
 // serde_yaml::Error to PackError
 // implementation
 impl From<serde_yaml::Error> for PackError {
     fn from(from: serde_yaml::Error) -> Self {
-        PackError::SerializeError(from.to_string())
+        PackError::SerializeError {
+            source: from,
+            path: None,
+            id: None,
+        }
+    }
+}
+
+impl PackError {
+    // New IOError without context
+    pub(crate) fn io(source: io::Error) -> Self {
+        PackError::IOError {
+            source,
+            path: None,
+            id: None,
+        }
+    }
+    // New DeserializeError without context
+    pub(crate) fn deserialize(source: serde_yaml::Error) -> Self {
+        PackError::DeserializeError {
+            source,
+            path: None,
+            id: None,
+        }
+    }
+    // New DeserializeError with a custom message
+    pub(crate) fn custom_deserialize(message: String) -> Self {
+        PackError::deserialize(serde::de::Error::custom(message))
+    }
+    /// File path the error belongs to, if known
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            PackError::SerializeError { path, .. }
+            | PackError::DeserializeError { path, .. }
+            | PackError::IOError { path, .. } => path.as_deref(),
+            _ => None,
+        }
+    }
+    /// Member ID the error belongs to, if known
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            PackError::SerializeError { id, .. }
+            | PackError::DeserializeError { id, .. }
+            | PackError::IOError { id, .. } => id.as_deref(),
+            _ => None,
+        }
+    }
+    // Attach file path to the error, if it has none yet
+    pub(crate) fn with_path(mut self, file: &Path) -> Self {
+        if let PackError::SerializeError { path, .. }
+        | PackError::DeserializeError { path, .. }
+        | PackError::IOError { path, .. } = &mut self
+        {
+            path.get_or_insert_with(|| file.to_path_buf());
+        }
+        self
+    }
+    // Attach member ID to the error, if it has none yet
+    pub(crate) fn with_id(mut self, member_id: &str) -> Self {
+        if let PackError::SerializeError { id, .. }
+        | PackError::DeserializeError { id, .. }
+        | PackError::IOError { id, .. } = &mut self
+        {
+            id.get_or_insert_with(|| member_id.to_string());
+        }
+        self
+    }
+}
+
+// Error context for messages, e.g. " (ID: 1, path: data/1.yml)"
+fn error_context(path: &Option<PathBuf>, id: &Option<String>) -> String {
+    match (path, id) {
+        (Some(path), Some(id)) => {
+            format!(" (ID: {}, path: {})", id, path.display())
+        }
+        (Some(path), None) => format!(" (path: {})", path.display()),
+        (None, Some(id)) => format!(" (ID: {})", id),
+        (None, None) => String::new(),
     }
 }
 
@@ -92,18 +334,53 @@ impl fmt::Display for PackError {
             PackError::InternalError(msg) => {
                 write!(f, "Internal error: {}", msg)
             }
-            PackError::SerializeError(msg) => {
-                write!(f, "Pack serialization error: {}", msg)
-            }
-            PackError::DeserializeError(msg) => {
-                write!(f, "Pack deserialization error: {}", msg)
-            }
-            PackError::IOError(msg) => write!(f, "Pack IO error: {}", msg),
+            PackError::SerializeError { source, path, id } => write!(
+                f,
+                "Pack serialization error: {}{}",
+                source,
+                error_context(path, id)
+            ),
+            PackError::DeserializeError { source, path, id } => write!(
+                f,
+                "Pack deserialization error: {}{}",
+                source,
+                error_context(path, id)
+            ),
+            PackError::IOError { source, path, id } => write!(
+                f,
+                "Pack IO error: {}{}",
+                source,
+                error_context(path, id)
+            ),
             PackError::PathNotFound => write!(f, "Path not found"),
             PackError::ObjectNotFound => {
                 write!(f, "Storage object not found in storage.")
             }
             PackError::IDTaken => write!(f, "VecPack ID already taken"),
+            PackError::Immutable => {
+                write!(f, "VecPack is append-only, members are immutable")
+            }
+            PackError::IntegrityError(msg) => {
+                write!(f, "Pack integrity error: {}", msg)
+            }
+            PackError::StorageFull => {
+                write!(f, "No space left on storage device")
+            }
+            PackError::ValidationError(msg) => {
+                write!(f, "Pack validation error: {}", msg)
+            }
+            PackError::Locked => write!(
+                f,
+                "VecPack directory is locked by another writer, it is read-only"
+            ),
+            PackError::BackendError(msg) => {
+                write!(f, "Storage backend error: {}", msg)
+            }
+            PackError::Conflict { expected, found } => write!(
+                f,
+                "Pack conflict, expected revision {}, found {}",
+                expected, found
+            ),
         }
     }
 }
@@ -116,25 +393,78 @@ impl fmt::Debug for PackError {
             PackError::InternalError(msg) => {
                 write!(f, "Internal error: {}", msg)
             }
-            PackError::SerializeError(msg) => {
-                write!(f, "Pack serialization error: {}", msg)
-            }
-            PackError::DeserializeError(msg) => {
-                write!(f, "Pack deserialization error: {}", msg)
-            }
-            PackError::IOError(msg) => write!(f, "Pack IO error: {}", msg),
+            PackError::SerializeError { source, path, id } => write!(
+                f,
+                "Pack serialization error: {}{}",
+                source,
+                error_context(path, id)
+            ),
+            PackError::DeserializeError { source, path, id } => write!(
+                f,
+                "Pack deserialization error: {}{}",
+                source,
+                error_context(path, id)
+            ),
+            PackError::IOError { source, path, id } => write!(
+                f,
+                "Pack IO error: {}{}",
+                source,
+                error_context(path, id)
+            ),
             PackError::PathNotFound => write!(f, "Path not found"),
             PackError::ObjectNotFound => {
                 write!(f, "Storage object not found in storage.")
             }
             PackError::IDTaken => write!(f, "VecPack ID already taken"),
+            PackError::Immutable => {
+                write!(f, "VecPack is append-only, members are immutable")
+            }
+            PackError::IntegrityError(msg) => {
+                write!(f, "Pack integrity error: {}", msg)
+            }
+            PackError::StorageFull => {
+                write!(f, "No space left on storage device")
+            }
+            PackError::ValidationError(msg) => {
+                write!(f, "Pack validation error: {}", msg)
+            }
+            PackError::Locked => write!(
+                f,
+                "VecPack directory is locked by another writer, it is read-only"
+            ),
+            PackError::BackendError(msg) => {
+                write!(f, "Storage backend error: {}", msg)
+            }
+            PackError::Conflict { expected, found } => write!(
+                f,
+                "Pack conflict, expected revision {}, found {}",
+                expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PackError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PackError::SerializeError { source, .. }
+            | PackError::DeserializeError { source, .. } => Some(source),
+            PackError::IOError { source, .. } => Some(source),
+            _ => None,
         }
     }
 }
 
 impl From<io::Error> for PackError {
     fn from(err: io::Error) -> Self {
-        PackError::IOError(format!("{}", err))
+        match err.kind() {
+            // Full disk is reported distinctly,
+            // so callers can degrade gracefully.
+            io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded => {
+                PackError::StorageFull
+            }
+            _ => PackError::io(err),
+        }
     }
 }
 
@@ -144,16 +474,25 @@ impl From<io::Error> for PackError {
 #[derive(Debug, Clone)]
 pub struct Pack<T>
 where
-    T: Serialize + Sized + Clone,
+    T: Serialize + Sized,
 {
     data: T,
     path: PathBuf,
+    // Shared with the VecPack the Pack belongs to
+    ctx: Arc<PackContext>,
+    // Lifecycle hooks
+    hooks: Hooks<T>,
+    // Creation time and revision of the saved file
+    stamp: Stamp,
 }
 
 /// PackGuard<'a, T>
 /// Small mutable guard around type T
 /// that implements Drop trait, and save T
 /// to the filesystem when PackGuard is dropped.
+/// Use commit() to get the save error, or discard()
+/// to drop the changes without saving. Changes can be
+/// rolled back only by a guard from as_mut_with_backup().
 ///
 /// Implements deref, deref_mut and drop
 pub struct PackGuard<'a, T>
@@ -162,6 +501,14 @@ where
 {
     data: &'a mut T,
     path: &'a PathBuf,
+    ctx: &'a PackContext,
+    hooks: &'a Hooks<T>,
+    stamp: &'a Stamp,
+    // Data before the first mutable access, if kept
+    backup: Option<T>,
+    keep_backup: bool,
+    // Committed or discarded, so nothing to do at drop
+    finished: bool,
 }
 
 /// VecPack<T>
@@ -176,13 +523,149 @@ where
 {
     data: Vec<Pack<T>>,
     path: PathBuf,
+    // Opt-in per member access statistics.
+    // None until enable_stats() is called.
+    stats: Option<Mutex<HashMap<String, AccessStats>>>,
+    // Shared location when the VecPack is opened
+    // through a Registry. The registry can move the
+    // directory, and we follow it on the next mutable access.
+    location: Option<Arc<RwLock<PathBuf>>>,
+    // Persist member order into .order.yml
+    order_index: bool,
+    // Members can be inserted, but never modified or removed
+    append_only: bool,
+    // Hash chain of the members, if enabled
+    chain: Option<Vec<ledger::ChainLink>>,
+    // Reserved IDs, shared with the Reservations
+    reserved: Arc<Mutex<HashSet<String>>>,
+    // Registered secondary indexes
+    indexes: Mutex<index::Indexes<T>>,
+    // ID -> position map of the members
+    id_index: Mutex<id_index::IdIndex>,
+    // Context shared with the members
+    ctx: Arc<PackContext>,
+    // Lifecycle hooks of the members
+    hooks: Hooks<T>,
+    // Shard prefix width, if sharded layout is enabled
+    shard_width: Option<usize>,
+    // Directory lock, if single writer mode is enabled
+    lock: Option<lock::DirLock>,
+    // Expiry of the members, if any
+    expiry: ttl::Expiry,
+    // Filesystem watch, if enabled
+    #[cfg(feature = "watch")]
+    watch: Option<watch::FsWatch>,
+}
+
+/// AccessStats
+/// Per member read/write counters and
+/// last access times. Only collected when
+/// VecPack::enable_stats() was called.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AccessStats {
+    /// Number of immutable accesses (find_id)
+    pub reads: u64,
+    /// Number of mutable accesses and inserts
+    /// (insert, find_id_mut)
+    pub writes: u64,
+    /// Last immutable access time
+    pub last_read: Option<SystemTime>,
+    /// Last mutable access time
+    pub last_write: Option<SystemTime>,
+}
+
+/// Page<'a, T>
+/// Result of a cursor based VecPack<T> page query
+pub struct Page<'a, T>
+where
+    T: VecPackMember,
+{
+    /// Members of the page
+    pub items: Vec<&'a Pack<T>>,
+    /// Cursor of the next page
+    /// None if this is the last page
+    pub next: Option<String>,
+}
+
+/// Reservation
+/// Holds a reserved VecPack ID until it is committed
+/// with data, or dropped.
+pub struct Reservation {
+    id: String,
+    reserved: Arc<Mutex<HashSet<String>>>,
+}
+
+impl Reservation {
+    /// Reserved ID
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    /// Commit reservation
+    /// Inserts item into the VecPack the ID was reserved from.
+    /// Item ID must be the reserved ID.
+    pub fn commit<T>(self, vecpack: &mut VecPack<T>, item: T) -> PackResult<()>
+    where
+        for<'de> T: VecPackMember + Deserialize<'de> + Default,
+    {
+        if !Arc::ptr_eq(&self.reserved, &vecpack.reserved) {
+            return Err(PackError::InternalError(
+                "Reservation belongs to another VecPack".to_string(),
+            ));
+        }
+        if item.get_id() != self.id {
+            return Err(PackError::InternalError(format!(
+                "Reserved ID {} does not match item ID {}",
+                self.id,
+                item.get_id()
+            )));
+        }
+        // Release the ID, then insert it while holding &mut VecPack,
+        // so nobody else can take it in between.
+        self.reserved.lock().unwrap().remove(&self.id);
+        vecpack.insert(item)
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.reserved.lock().unwrap().remove(&self.id);
+    }
 }
 
 /// This trait defines the requirements
 /// to be a member of a VecPack<T>
+///
+/// It can be derived by marking the ID field with #[pack(id)]:
+///
+/// ```rust
+/// use serde::{Deserialize, Serialize};
+/// use storaget::*;
+///
+/// #[derive(Serialize, Deserialize, Clone, Default, VecPackMember)]
+/// struct Car {
+///     #[pack(id)]
+///     id: String,
+///     name: String,
+/// }
+/// ```
 pub trait VecPackMember: Serialize + Sized + Clone {
     // type Target: fmt::Display + std::cmp::PartialEq;
     fn get_id(&self) -> &str;
+    /// Set the ID, used by VecPack::change_id
+    /// The derive implements it. By default the ID cannot be
+    /// changed, and PackError::InternalError is returned.
+    fn set_id(&mut self, _id: &str) -> PackResult<()> {
+        Err(PackError::InternalError(
+            "ID change is not supported by this member type".to_string(),
+        ))
+    }
+    /// Validation of the members, see Validate
+    /// Checked on every insert, update and save of the VecPack.
+    /// The derive returns Validate::validate if the type
+    /// implements Validate. By default members are not validated.
+    fn validator() -> Option<Validator<Self>> {
+        None
+    }
 }
 
 pub trait TryFrom {
@@ -196,16 +679,149 @@ pub trait TryFrom {
 /// Save DATA OBJECT to its path
 /// Moved this logic into this separated private function
 /// as we use it from the Drop implementation and from save method.
-fn save_data_object<T>(path: &PathBuf, data: T) -> PackResult<()>
+/// Writes atomically through a temp file next to the target.
+fn save_data_object<T>(path: &Path, data: T) -> PackResult<()>
+where
+    T: Serialize,
+{
+    save_data_object_with(path, data, &TempConfig::default())
+}
+
+// Save DATA OBJECT to its path using the given temp file config
+pub(crate) fn save_data_object_with<T>(
+    path: &Path,
+    data: T,
+    temp: &TempConfig,
+) -> PackResult<()>
 where
     T: Serialize,
 {
-    let mut buffer = BufWriter::new(File::create(path)?);
-    buffer.write_all(serde_yaml::to_string(&data)?.as_bytes())?;
-    buffer.flush()?;
+    let header = FileHeader::current::<T>();
+    atomic::write_atomic_with(path, temp, |file| {
+        header::encode_to(&data, &header, file).map(|_| ())
+    })
+}
+
+// Collect member files of a VecPack directory
+// in file name order. Sub directories (e.g. .trash/)
+// and hidden files (e.g. .order.yml) are skipped,
+// except the shard directories of a sharded VecPack.
+pub(crate) fn member_files(path: &Path) -> PackResult<Vec<PathBuf>> {
+    let mut files = visible_files(path)?;
+    if shard::is_sharded(path) {
+        for dir in shard::shard_dirs(path)? {
+            files.extend(visible_files(&dir)?);
+        }
+    }
+    files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    Ok(files)
+}
+
+// Member ID is used as a file name, and hidden files are
+// not loaded, so it must not start with '.'
+fn check_member_id(id: &str) -> PackResult<()> {
+    if id.starts_with('.') {
+        return Err(PackError::InternalError(format!(
+            "Invalid member ID: {}",
+            id
+        )));
+    }
     Ok(())
 }
 
+// Not hidden files of a directory
+fn visible_files(path: &Path) -> PackResult<Vec<PathBuf>> {
+    Ok(std::fs::read_dir(path)?
+        .filter_map(|file| {
+            file.ok().and_then(|e| {
+                e.path().file_name().and_then(|n| {
+                    n.to_str().map(|s| {
+                        let mut p = path.to_path_buf();
+                        p.push(s);
+                        p
+                    })
+                })
+            })
+        })
+        .filter(|p| p.is_file())
+        .filter(|p| {
+            !p.file_name()
+                .and_then(|n| n.to_str())
+                .map(|n| n.starts_with('.'))
+                .unwrap_or(true)
+        })
+        .collect::<Vec<PathBuf>>())
+}
+
+// Move directory from one path to another
+// First try a simple rename; if that fails (e.g. the target is
+// on another filesystem), then copy everything, and only remove
+// the source when the copy succeeded. A failed copy is cleaned up,
+// so the source stays the only copy.
+pub(crate) fn move_dir(from: &Path, to: &Path) -> PackResult<()> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    if let Err(err) = copy_dir_all(from, to) {
+        let _ = std::fs::remove_dir_all(to);
+        return Err(err);
+    }
+    std::fs::remove_dir_all(from)?;
+    Ok(())
+}
+
+// Copy directory recursively
+pub(crate) fn copy_dir_all(from: &Path, to: &Path) -> PackResult<()> {
+    copy_dir_except(from, to, &[])
+}
+
+// Copy directory recursively, except the top level entries
+// named in except
+pub(crate) fn copy_dir_except(
+    from: &Path,
+    to: &Path,
+    except: &[&str],
+) -> PackResult<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        if except.iter().any(|name| entry.file_name() == *name) {
+            continue;
+        }
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_all(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+// Load a member file, and measure the load time
+fn load_timed<T, L>(
+    load: &L,
+    file: PathBuf,
+) -> (PathBuf, PackResult<Pack<T>>, Duration)
+where
+    T: Serialize,
+    L: Fn(PathBuf) -> PackResult<Pack<T>>,
+{
+    let started = Instant::now();
+    let res = load(file.clone());
+    (file, res, started.elapsed())
+}
+
+// Milliseconds since UNIX EPOCH
+fn unix_millis(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
 #[derive(Serialize, Deserialize, Clone, Default)]
 pub struct NOTHING;
 
@@ -231,6 +847,9 @@ where
                 let pack: Pack<T> = Pack {
                     data: data,
                     path: path,
+                    ctx: Arc::default(),
+                    hooks: Hooks::default(),
+                    stamp: Stamp::default(),
                 };
                 pack.save()?;
                 Ok(pack)
@@ -252,9 +871,9 @@ where
     }
 }
 
-impl<'a, T> Pack<T>
+impl<T> Pack<T>
 where
-    for<'de> T: Serialize + Deserialize<'de> + Default + Sized + Clone + 'a,
+    for<'de> T: Serialize + Deserialize<'de> + Default + Sized,
 {
     // New Pack<T>
     // Private function
@@ -262,58 +881,205 @@ where
         Ok(Pack {
             data: T::default(),
             path,
+            ctx: Arc::default(),
+            hooks: Hooks::default(),
+            stamp: Stamp::default(),
         })
     }
+    /// Load or init Pack<T> from Path
+    /// If Path does not exist, then it tries to create;
+    /// Otherwise call Pack::load_from_path(Path).
+    pub fn load_or_init(path: PathBuf, file_id: &str) -> PackResult<Pack<T>> {
+        Pack::load_or_init_with(path, file_id, T::default)
+    }
+}
+
+impl<'a, T> Pack<T>
+where
+    for<'de> T: Serialize + Deserialize<'de> + Sized + 'a,
+{
     pub fn from_str(buffer: &str, path: PathBuf) -> PackResult<Pack<T>> {
-        match serde_yaml::from_str::<T>(&buffer) {
-            Ok(t) => Ok(Pack { data: t, path }),
-            Err(err) => Err(PackError::DeserializeError(err.to_string())),
-        }
+        let (data, header, _) = header::decode_from::<T, _>(buffer.as_bytes())?;
+        Ok(Pack {
+            data,
+            path,
+            ctx: Arc::default(),
+            hooks: Hooks::default(),
+            stamp: Stamp::of(&header),
+        })
     }
     /// Load Pack<T> from Path
     /// If Path is file and exists, then it tries to load
-    /// then deserialize. Otherwise returns PackError.
+    /// then deserialize. Otherwise returns PackError,
+    /// PackError::PathNotFound if the file does not exist.
+    /// Data of an older schema version is migrated, and saved back.
     pub fn load_from_path(path: PathBuf) -> PackResult<Pack<T>> {
-        let mut file = File::open(&path)?;
-        let mut buffer = String::new();
-        file.read_to_string(&mut buffer)?;
-        Self::from_str(&buffer, path)
+        let pack = Pack::<T>::read_from_path(path)?;
+        pack.save_migrated()?;
+        Ok(pack)
+    }
+    // Load Pack<T> from Path, without saving back migrated data
+    // VecPack saves them once its modes are loaded.
+    pub(crate) fn read_from_path(path: PathBuf) -> PackResult<Pack<T>> {
+        let file_path = path.clone();
+        let started = Instant::now();
+        telemetry::span("storaget.load", &file_path, || {
+            let file = File::open(&path).map_err(|err| match err.kind() {
+                io::ErrorKind::NotFound => PackError::PathNotFound,
+                _ => err.into(),
+            })?;
+            let size = file.metadata()?.len();
+            let pack = Pack::<T>::decode_reader(path, BufReader::new(file))?;
+            pack.ctx.metrics.record(
+                OpKind::Load,
+                &pack.path,
+                started.elapsed(),
+                Some(size),
+            );
+            Ok(pack)
+        })
+        .and_then(|pack| pack.load_history().map(|_| pack))
+        .map_err(|err| err.with_path(&file_path))
+    }
+    // Decode the file at path from reader, see decode_from
+    pub(crate) fn decode_reader<R: BufRead>(
+        path: PathBuf,
+        reader: R,
+    ) -> PackResult<Pack<T>> {
+        Pack::decoded(path, header::decode_from::<T, _>(reader)?)
+    }
+    // Pack of the decoded content of the file at path
+    // Migrated data is not saved back yet, see save_migrated.
+    pub(crate) fn decoded(
+        path: PathBuf,
+        (data, header, migrated): (T, FileHeader, bool),
+    ) -> PackResult<Pack<T>> {
+        Ok(Pack {
+            data,
+            path,
+            ctx: Arc::default(),
+            hooks: Hooks::default(),
+            stamp: Stamp::loaded(header, migrated),
+        })
+    }
+    // Save back the data migrated at load, if it is not saved since
+    // The header is upgraded, save times and revision are kept.
+    pub(crate) fn save_migrated(&self) -> PackResult<()> {
+        let header = match self.stamp.migrated() {
+            Some(header) => header::upgraded::<T>(&header),
+            None => return Ok(()),
+        };
+        self.ctx.check_writable()?;
+        let path = self.ctx.resolve(&self.path);
+        let mut encode = |writer: &mut dyn BackendWriter| {
+            header::encode_to(&self.data, &header, writer).map(|_| ())
+        };
+        match self.ctx.backend() {
+            Some(backend) => backend.write_with(&path, &mut encode),
+            None => atomic::write_atomic_with(
+                &path,
+                &TempConfig::default(),
+                |file| encode(file),
+            ),
+        }
+        .map_err(|err| err.with_path(&path))?;
+        self.stamp.set(header.created, header.revision);
+        Ok(())
     }
     /// Load or init Pack<T> from Path
-    /// If Path does not exist, then it tries to create;
-    /// Otherwise call Pack::load_from_path(Path).
-    pub fn load_or_init(
+    /// The same as load_or_init, but the initial data
+    /// is created by init, so T does not need Default.
+    pub fn load_or_init_with<F>(
         mut path: PathBuf,
         file_id: &str,
-    ) -> PackResult<Pack<T>> {
+        init: F,
+    ) -> PackResult<Pack<T>>
+    where
+        F: FnOnce() -> T,
+    {
         if !path.exists() {
             std::fs::create_dir_all(&path)?;
         }
         path.push(&format!("{}.yml", file_id));
-        if !path.exists() {
-            Pack::<T>::new(path.clone())?.save()?;
+        // Only a missing file is initialized,
+        // a corrupt one is returned as error.
+        match Pack::try_load(path.clone())? {
+            Some(pack) => Ok(pack),
+            None => {
+                Pack {
+                    data: init(),
+                    path: path.clone(),
+                    ctx: Arc::default(),
+                    hooks: Hooks::default(),
+                    stamp: Stamp::default(),
+                }
+                .save()?;
+                Pack::load_from_path(path)
+            }
+        }
+    }
+    /// Try to load Pack<T> from Path
+    /// Returns Ok(None) if the file does not exist, so it can be
+    /// initialized. If the file exists but cannot be read or
+    /// deserialized, then returns the error, as the file must
+    /// not be overwritten.
+    pub fn try_load(path: PathBuf) -> PackResult<Option<Pack<T>>> {
+        match Pack::load_from_path(path) {
+            Ok(pack) => Ok(Some(pack)),
+            Err(PackError::PathNotFound) => Ok(None),
+            Err(err) => Err(err),
         }
-        Pack::load_from_path(path)
     }
     /// Save Pack<T> manually
     /// to FS. Returns PackError if something
     /// wrong occures.
     pub fn save(&self) -> PackResult<()> {
-        save_data_object(&self.path, &self.data)
+        self.hooks.validate(&self.data)?;
+        self.ctx.save(&self.path, &self.data, &self.stamp)
+    }
+    // Write the file of a new member, if its data is valid
+    pub(crate) fn write_new(&self) -> PackResult<()> {
+        self.hooks.validate(&self.data)?;
+        self.ctx.write(&self.path, &self.data, &self.stamp)
     }
     /// Update Pack<T>
     /// Tries to update T, if SUCCESS
     /// then tries to save to FS, if SUCCESS
     /// returns R. If Fail, then doing data T
     /// rollback to backup, then return PackError.
-    pub fn update<F, R>(&mut self, mut f: F) -> PackResult<R>
+    ///
+    /// The backup is the serialized data, so T does not
+    /// need Clone. Fields skipped by serialization get
+    /// their default value at rollback, as after a reload.
+    /// If the backup cannot be deserialized, then the change
+    /// is kept, and PackError::DeserializeError is returned.
+    pub fn update<F, R>(&mut self, f: F) -> PackResult<R>
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        self.try_update(|data| Ok(f(data)))
+    }
+    /// Fallible update of Pack<T>
+    /// The same as update, but if f returns Err, then
+    /// data is rolled back and nothing is saved.
+    /// A failed save is returned as E, converted from PackError.
+    pub fn try_update<F, R, E>(&mut self, f: F) -> Result<R, E>
     where
-        F: FnMut(&mut T) -> R,
+        F: FnOnce(&mut T) -> Result<R, E>,
+        E: From<PackError>,
     {
-        // First clone data as a backup.
-        let backup = self.data.clone();
+        // First serialize data as a backup.
+        let backup =
+            serde_yaml::to_string(&self.data).map_err(PackError::from)?;
         // Let's do the update process.
-        let res = f(&mut self.data);
+        let res = match f(&mut self.data) {
+            Ok(res) => res,
+            Err(err) => {
+                self.rollback(&backup)?;
+                return Err(err);
+            }
+        };
+        self.hooks.before_save(&mut self.data);
         // Try to save data to the FS
         match self.save() {
             // If success, then return the update result(s)
@@ -322,12 +1088,20 @@ where
             // saveing updated data
             Err(err) => {
                 // Then rollback data to the backup.
-                self.data = backup;
+                self.rollback(&backup)?;
                 // Return error
-                Err(err)
+                Err(err.into())
             }
         }
     }
+    // Restore data from its serialized backup
+    // It was serialized from a T, so it should deserialize;
+    // if not, the change is kept and the error is returned.
+    fn rollback(&mut self, backup: &str) -> PackResult<()> {
+        self.data = serde_yaml::from_str(backup)
+            .map_err(|err| PackError::deserialize(err).with_path(&self.path))?;
+        Ok(())
+    }
     /// Get(Fn) -> R
     /// Access data through closure
     /// Unmutable data access
@@ -347,10 +1121,35 @@ where
     }
     /// as_mut() -> PackGuard<'a, T>
     /// returns
-    pub fn as_mut(&mut self) -> PackGuard<'_, T> {
+    pub fn as_mut(&mut self) -> PackGuard<'_, T>
+    where
+        T: Clone,
+    {
+        self.guard(false)
+    }
+    /// as_mut_with_backup() -> PackGuard<'a, T>
+    /// The same as as_mut(), but T is cloned at the first
+    /// mutable access, so discard() and a failed commit()
+    /// restore it.
+    pub fn as_mut_with_backup(&mut self) -> PackGuard<'_, T>
+    where
+        T: Clone,
+    {
+        self.guard(true)
+    }
+    fn guard(&mut self, keep_backup: bool) -> PackGuard<'_, T>
+    where
+        T: Clone,
+    {
         PackGuard {
             data: &mut self.data,
             path: &self.path,
+            ctx: &self.ctx,
+            hooks: &self.hooks,
+            stamp: &self.stamp,
+            backup: None,
+            keep_backup,
+            finished: false,
         }
     }
     pub fn into_inner(self) -> T {
@@ -363,7 +1162,7 @@ where
 
 impl<T> Deref for Pack<T>
 where
-    T: Serialize + Sized + Clone,
+    T: Serialize + Sized,
 {
     type Target = T;
 
@@ -388,7 +1187,7 @@ where
     T: Serialize + Sized + Clone,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.data
+        self.unpack()
     }
 }
 
@@ -397,13 +1196,14 @@ where
     T: Serialize + Sized + Clone,
 {
     fn drop(&mut self) {
-        // TODO: VERY IMPORTANT
-        // Implement error LOGGING!
+        if self.finished {
+            return;
+        }
         // This auto save during drop cannot return PackError,
-        // we have two options:
-        //  - Panic(),
-        //  - & | error log
-        let _ = save_data_object(&self.path, &self.data);
+        // so failures are logged, and passed to the save error hook.
+        if let Err(err) = self.save() {
+            self.ctx.background_save_failed(self.path, err);
+        }
     }
 }
 
@@ -431,58 +1231,9 @@ where
         + std::convert::From<<T as TryFrom>::TryFrom>,
 {
     pub fn try_load_or_init(path: PathBuf) -> PackResult<VecPack<T>> {
-        // If path is a file
-        // then panic!
-        if path.is_file() {
-            panic!(
-                "Given VecPack path is not a dir. Path: {}",
-                &path.to_str().unwrap()
-            );
-        }
-        // If path does not exist,
-        // then we create it.
-        if !path.exists() {
-            std::fs::create_dir_all(&path)?;
-        }
-        // Result empty VecPack<T>
-        let mut result: VecPack<T> = VecPack::new(path.clone())?;
-        // First collect all
-        // the file names from path
-        std::fs::read_dir(path.clone())?
-            .filter_map(|file| {
-                file.ok().and_then(|e| {
-                    e.path().file_name().and_then(|n| {
-                        n.to_str().map(|s| {
-                            let mut p = path.clone();
-                            p.push(s);
-                            p
-                        })
-                    })
-                })
-            })
-            .collect::<Vec<PathBuf>>()
-            // Then iter over path vector
-            // and try to read and deserialize
-            // them.
-            .iter()
-            .for_each(|path| {
-                // Add deserialized T to VecPack<T>
-                result
-                    .insert_pack(
-                        // Create Pack<T> from T
-                        Pack::<T>::try_load_from_path(path.clone()).expect(
-                            &format!(
-                                "Cannot deserialize file with ID: {}",
-                                (&path).to_str().unwrap()
-                            ),
-                        ),
-                    )
-                    .expect(&format!(
-                        "Error while adding file to VecPack with ID: {}",
-                        (&path).to_str().unwrap()
-                    ));
-            });
-        Ok(result)
+        VecPack::load_dir(path, Pack::<T>::try_load_from_path, |_, err| {
+            Err(err)
+        })
     }
 }
 
@@ -495,147 +1246,877 @@ where
     /// Requires a PathBuf and returns an empty VecPack<T>
     pub fn new(path: PathBuf) -> PackResult<VecPack<T>> {
         // Check whether path is a dir, or a file
-        // If file, then panic!
         if path.is_file() {
-            panic!(
+            return Err(PackError::InternalError(format!(
                 "Given VecPack path is not a dir. Path: {}",
-                &path.to_str().unwrap()
-            );
+                path.display()
+            )));
         }
         // If path does not exist,
         // then create it!
         if !path.exists() {
             std::fs::create_dir_all(&path)?;
         }
-        // Create an empty VecPack<T>
-        Ok(VecPack {
+        Ok(VecPack::empty(path))
+    }
+    // Create an empty VecPack<T> without touching the filesystem
+    pub(crate) fn empty(path: PathBuf) -> VecPack<T> {
+        VecPack {
             data: Vec::new(),
             path,
-        })
+            stats: None,
+            location: None,
+            order_index: false,
+            append_only: false,
+            chain: None,
+            reserved: Arc::new(Mutex::new(HashSet::new())),
+            indexes: Mutex::new(index::Indexes::default()),
+            id_index: Mutex::default(),
+            ctx: Arc::default(),
+            hooks: Hooks {
+                validator: T::validator(),
+                ..Hooks::default()
+            },
+            shard_width: None,
+            lock: None,
+            expiry: ttl::Expiry::new(),
+            #[cfg(feature = "watch")]
+            watch: None,
+        }
     }
     /// Load or init VecPack by a given Path
     /// If path does not exist,
     /// then we create it, then loads all the files,
     /// and tries to deserialize them.
     /// If a file cannot be read, or cannot be deserialized
-    /// then we panic!
+    /// then returns its PackError; see load_or_init_lossy and
+    /// load_or_init_quarantine to load the rest anyway.
+    /// For large directories see load_or_init_parallel.
     pub fn load_or_init(path: PathBuf) -> PackResult<VecPack<T>> {
-        // If path is a file
-        // then panic!
-        if path.is_file() {
-            panic!(
-                "Given VecPack path is not a dir. Path: {}",
-                &path.to_str().unwrap()
-            );
-        }
-        // If path does not exist,
-        // then we create it.
-        if !path.exists() {
-            std::fs::create_dir_all(&path)?;
-        }
-        // Result empty VecPack<T>
+        VecPack::load_dir(path, Pack::<T>::read_from_path, |_, err| Err(err))
+    }
+    /// Load or init VecPack by a given Path, on many threads
+    /// The same as load_or_init, but the member files are read
+    /// and deserialized by a worker per available core, which is
+    /// much faster for large directories. Members are in the same
+    /// order, and the same error is returned, as by load_or_init.
+    pub fn load_or_init_parallel(path: PathBuf) -> PackResult<VecPack<T>>
+    where
+        T: Send,
+    {
         let mut result: VecPack<T> = VecPack::new(path.clone())?;
-        // First collect all
-        // the file names from path
-        std::fs::read_dir(path.clone())?
-            .filter_map(|file| {
-                file.ok().and_then(|e| {
-                    e.path().file_name().and_then(|n| {
-                        n.to_str().map(|s| {
-                            let mut p = path.clone();
-                            p.push(s);
-                            p
-                        })
-                    })
-                })
-            })
-            .collect::<Vec<PathBuf>>()
-            // Then iter over path vector
-            // and try to read and deserialize
-            // them.
-            .iter()
-            .for_each(|path| {
-                // Add deserialized T to VecPack<T>
-                result
-                    .insert_pack(
-                        // Create Pack<T> from T
-                        Pack::<T>::load_from_path(path.clone()).expect(
-                            &format!(
-                                "Cannot deserialize file with ID: {}",
-                                (&path).to_str().unwrap()
-                            ),
-                        ),
-                    )
-                    .expect(&format!(
-                        "Error while adding file to VecPack with ID: {}",
-                        (&path).to_str().unwrap()
-                    ));
+        let loaded =
+            pool::map_ordered(member_files(&path)?, pool::workers(0), |file| {
+                load_timed(&Pack::<T>::read_from_path, file)
             });
+        result.add_loaded(loaded, |_, err| Err(err))?;
+        result.load_modes()?;
         Ok(result)
     }
+    // Load every member file of path into a new VecPack<T>
+    // Files failing to load are passed to on_error, which
+    // skips them by returning Ok, or stops the load.
+    pub(crate) fn load_dir<L, E>(
+        path: PathBuf,
+        load: L,
+        on_error: E,
+    ) -> PackResult<VecPack<T>>
+    where
+        L: Fn(PathBuf) -> PackResult<Pack<T>>,
+        E: FnMut(&Path, PackError) -> PackResult<()>,
+    {
+        // Result empty VecPack<T>
+        let mut result: VecPack<T> = VecPack::new(path.clone())?;
+        let loaded = member_files(&path)?
+            .into_iter()
+            .map(|file| load_timed(&load, file));
+        result.add_loaded(loaded, on_error)?;
+        result.load_modes()?;
+        Ok(result)
+    }
+    // Add the loaded member files, in order
+    // Failed ones are passed to on_error, as by load_dir.
+    fn add_loaded<I, E>(&mut self, loaded: I, mut on_error: E) -> PackResult<()>
+    where
+        I: IntoIterator<Item = (PathBuf, PackResult<Pack<T>>, Duration)>,
+        E: FnMut(&Path, PackError) -> PackResult<()>,
+    {
+        for (file, res, elapsed) in loaded {
+            self.ctx.metrics.record(
+                OpKind::Load,
+                &file,
+                elapsed,
+                res.as_ref().ok().map(|_| metrics::file_len(&file)),
+            );
+            match res {
+                Ok(pack) => {
+                    self.add_member(pack).map_err(|err| err.with_path(&file))?
+                }
+                Err(err) => on_error(&file, err)?,
+            }
+        }
+        Ok(())
+    }
+    // Load the persisted modes of the VecPack directory
+    // Called after the members are loaded.
+    pub(crate) fn load_modes(&mut self) -> PackResult<()> {
+        self.restore_order()?;
+        self.append_only = self.append_only_path().exists();
+        self.load_chain()?;
+        self.load_notifier();
+        self.load_sharding()?;
+        self.load_lock()?;
+        self.load_replication()?;
+        self.load_snapshots()?;
+        self.load_audit();
+        self.load_format()?;
+        self.load_history()?;
+        self.load_expiry()?;
+        self.save_migrated()
+    }
+    // Load the modes that work with only part of the members in
+    // memory, e.g. for LruVecPack. Order index and replication
+    // are saved from the members in memory, so they are rejected.
+    pub(crate) fn load_partial_modes(&mut self) -> PackResult<()> {
+        if self.order_path().exists() || self.replicas_path().exists() {
+            return Err(PackError::InternalError(format!(
+                "Order index and replication need every member loaded. \
+                 Path: {}",
+                self.path.display()
+            )));
+        }
+        self.append_only = self.append_only_path().exists();
+        self.load_chain()?;
+        self.load_notifier();
+        self.load_sharding()?;
+        self.load_lock()?;
+        self.load_snapshots()?;
+        self.load_audit();
+        self.load_format()?;
+        self.load_history()?;
+        self.load_expiry()
+    }
     /// Insert a new T to VecPack<T>
     /// Only if ID is not taken
-    pub fn insert(&mut self, item: T) -> PackResult<()> {
+    /// IDs starting with '.' are rejected with
+    /// PackError::InternalError, as hidden files are not loaded.
+    pub fn insert(&mut self, mut item: T) -> PackResult<()> {
+        self.sync_location();
+        self.check_writable()?;
+        self.hooks.before_save(&mut item);
+        self.hooks.validate(&item)?;
         // Check if ID whether available
         if !&self.check_id_available(item.get_id()) {
             return Err(PackError::IDTaken);
         }
-        // TODO: Move file name creation to a central place!
-        let mut p = (&self.path).clone();
-        p.push(&format!("{}.yml", item.get_id()));
+        let p = self.new_member_path(item.get_id())?;
         let p = Pack {
             data: item,
             path: p,
+            ctx: self.ctx.clone(),
+            hooks: self.hooks.clone(),
+            // Nothing saved yet
+            stamp: Stamp::new(None, None),
         };
-        p.save()?;
-        self.data.push(p);
+        self.ctx
+            .write(&p.path, &p.data, &p.stamp)
+            .map_err(|err| err.with_id(p.get_id()))?;
+        self.seal(&p.path)?;
+        self.extend_chain(&p)?;
+        self.record_write(p.get_id());
+        self.member_added(&p);
+        let event = ChangeEvent::new(p.get_id(), ChangeKind::Created);
+        self.push_member(p);
+        self.save_order()?;
+        self.ctx.member_changed(event);
+        Ok(())
+    }
+    /// Insert or update T
+    /// If the ID of item is free, then it is inserted; otherwise
+    /// the member with the same ID is replaced by item and saved.
+    /// Returns ChangeKind::Created or ChangeKind::Updated.
+    /// A reserved ID is not free, so PackError::IDTaken is returned.
+    /// A new ID starting with '.' is rejected, as in insert.
+    pub fn upsert(&mut self, item: T) -> PackResult<ChangeKind> {
+        self.sync_location();
+        match self.position(item.get_id()) {
+            Some(pos) => {
+                self.check_mutable()?;
+                let id = item.get_id().to_string();
+                self.data[pos]
+                    .update(|data| *data = item)
+                    .map_err(|err| err.with_id(&id))?;
+                self.record_write(&id);
+                self.member_touched(&id);
+                Ok(ChangeKind::Updated)
+            }
+            None => self.insert(item).map(|_| ChangeKind::Created),
+        }
+    }
+    /// Find ID, or insert a new T created by init
+    /// Returns the member as a mutable reference, as find_id_mut.
+    /// init is called only if the ID is not taken, and must
+    /// create a T with the given ID, otherwise nothing is inserted
+    /// and PackError::InternalError is returned. An expired member
+    /// is purged first, so it is created again.
+    pub fn get_or_insert_with<F>(
+        &mut self,
+        id: &str,
+        init: F,
+    ) -> PackResult<&mut Pack<T>>
+    where
+        F: FnOnce() -> T,
+    {
+        self.sync_location();
+        self.check_mutable()?;
+        if self.is_expired(id) {
+            self.purge_expired()?;
+        }
+        if self.position(id).is_none() {
+            let item = init();
+            if item.get_id() != id {
+                return Err(PackError::InternalError(format!(
+                    "Item created for ID {} has ID {}",
+                    id,
+                    item.get_id()
+                )));
+            }
+            self.insert(item)?;
+        }
+        self.find_id_mut(id)
+    }
+    /// Insert many T to VecPack<T>
+    /// First validates all the IDs, if any of them is taken
+    /// or duplicated inside items, then nothing is inserted and
+    /// PackError::IDTaken is returned. Then saves the items one by one,
+    /// and returns the failed ones with their error. Items failed
+    /// to save are not inserted. With the rayon feature see
+    /// par_insert_many to write the files on all cores.
+    pub fn insert_many(
+        &mut self,
+        items: Vec<T>,
+    ) -> PackResult<Vec<(String, PackError)>> {
+        self.insert_many_with(items, |packs| {
+            packs.into_iter().map(|p| p.write_new()).collect()
+        })
+    }
+    // Insert many T, the new member files are written by write
+    // write gets the new members, and returns their write results
+    // in the same order. The rest is done in order, as by insert.
+    pub(crate) fn insert_many_with<W>(
+        &mut self,
+        mut items: Vec<T>,
+        write: W,
+    ) -> PackResult<Vec<(String, PackError)>>
+    where
+        W: FnOnce(Vec<&Pack<T>>) -> Vec<PackResult<()>>,
+    {
+        self.sync_location();
+        self.check_writable()?;
+        for item in items.iter_mut() {
+            self.hooks.before_save(item);
+        }
+        let mut ids: HashSet<&str> =
+            self.data.iter().map(|i| i.get_id()).collect();
+        {
+            let reserved = self.reserved.lock().unwrap();
+            for item in &items {
+                if reserved.contains(item.get_id())
+                    || !ids.insert(item.get_id())
+                {
+                    return Err(PackError::IDTaken);
+                }
+            }
+        }
+        let prepared: Vec<Result<Pack<T>, (String, PackError)>> = items
+            .into_iter()
+            .map(|item| match self.new_member_path(item.get_id()) {
+                Ok(path) => Ok(Pack {
+                    path,
+                    data: item,
+                    ctx: self.ctx.clone(),
+                    hooks: self.hooks.clone(),
+                    stamp: Stamp::new(None, None),
+                }),
+                Err(err) => Err((item.get_id().to_string(), err)),
+            })
+            .collect();
+        let mut written =
+            write(prepared.iter().flatten().collect()).into_iter();
+        let mut failures = Vec::new();
+        for p in prepared {
+            let p = match p {
+                Ok(p) => p,
+                Err(failure) => {
+                    failures.push(failure);
+                    continue;
+                }
+            };
+            let res = written.next().unwrap_or_else(|| {
+                Err(PackError::InternalError("Member not written".to_string()))
+            });
+            match res
+                .and_then(|_| self.seal(&p.path))
+                .and_then(|_| self.extend_chain(&p))
+            {
+                Ok(_) => {
+                    self.ctx.member_changed(ChangeEvent::new(
+                        p.get_id(),
+                        ChangeKind::Created,
+                    ));
+                    self.record_write(p.get_id());
+                    self.member_added(&p);
+                    self.push_member(p);
+                }
+                Err(err) => failures.push((p.get_id().to_string(), err)),
+            }
+        }
+        self.save_order()?;
+        Ok(failures)
+    }
+    /// Remove member by ID
+    /// Removes it from memory and deletes its file,
+    /// then returns the removed data.
+    pub fn remove_by_id(&mut self, id: &str) -> PackResult<T> {
+        self.sync_location();
+        self.check_mutable()?;
+        let pack = self.take_member(id)?;
+        telemetry::span("storaget.remove", &pack.path, || {
+            self.ctx.remove(&pack.path).map_err(|err| err.with_id(id))
+        })?;
+        self.ctx.record_version(&pack.path);
+        self.save_order()?;
+        self.ctx
+            .member_changed(ChangeEvent::new(id, ChangeKind::Removed));
+        Ok(pack.into_inner())
+    }
+    /// Remove the members matching the predicate
+    /// Removes them from memory and deletes their files, then
+    /// returns the removed data in member order, e.g. for data
+    /// retention jobs. If a removal fails, the members removed
+    /// before stay removed, and the error is returned.
+    /// Members are removed in one pass, and the order index and
+    /// expiry files are written once.
+    pub fn remove_where<F>(&mut self, mut predicate: F) -> PackResult<Vec<T>>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.sync_location();
+        self.check_mutable()?;
+        let matches = self
+            .data
+            .iter()
+            .map(|i| predicate(&i.data))
+            .collect::<Vec<bool>>();
+        let mut kept = Vec::with_capacity(self.data.len());
+        let mut removed = Vec::new();
+        let mut ids = Vec::new();
+        let mut result = Ok(());
+        for (pack, matched) in
+            std::mem::take(&mut self.data).into_iter().zip(matches)
+        {
+            if !matched || result.is_err() {
+                kept.push(pack);
+                continue;
+            }
+            let id = pack.get_id().to_string();
+            let res = telemetry::span("storaget.remove", &pack.path, || {
+                self.ctx.remove(&pack.path).map_err(|err| err.with_id(&id))
+            });
+            match res {
+                Ok(_) => {
+                    self.ctx.record_version(&pack.path);
+                    self.member_removed(&id);
+                    removed.push(pack.into_inner());
+                    ids.push(id);
+                }
+                Err(err) => {
+                    result = Err(err);
+                    kept.push(pack);
+                }
+            }
+        }
+        self.data = kept;
+        self.id_index.lock().unwrap().invalidate();
+        if !ids.is_empty() {
+            self.forget_expiries(&ids)?;
+            self.save_order()?;
+        }
+        for id in &ids {
+            self.ctx
+                .member_changed(ChangeEvent::new(id, ChangeKind::Removed));
+        }
+        result.map(|_| removed)
+    }
+    /// Keep only the members matching the predicate
+    /// The others are removed as by remove_where,
+    /// and their data is returned.
+    pub fn retain<F>(&mut self, mut predicate: F) -> PackResult<Vec<T>>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.remove_where(|data| !predicate(data))
+    }
+    /// Soft remove member by ID
+    /// Removes it from memory and moves its file into
+    /// the .trash/ sub folder, named as ID.TIMESTAMP.yml,
+    /// so it can be restored later.
+    pub fn soft_remove(&mut self, id: &str) -> PackResult<()> {
+        self.sync_location();
+        self.check_mutable()?;
+        let pos = match self.position(id) {
+            Some(pos) => pos,
+            None => return Err(PackError::ObjectNotFound),
+        };
+        let trash = self.trash_path();
+        if !trash.exists() {
+            std::fs::create_dir_all(&trash)?;
+        }
+        let mut to = trash;
+        to.push(format!("{}.{}.yml", id, unix_millis(SystemTime::now())));
+        // Move the file first, so we only forget the member
+        // when the file is safely in the trash.
+        std::fs::rename(&self.data[pos].path, &to)?;
+        self.ctx.record_version(&self.data[pos].path);
+        self.take_member(id)?;
+        self.save_order()?;
+        self.ctx
+            .member_changed(ChangeEvent::new(id, ChangeKind::Removed));
+        Ok(())
+    }
+    /// Restore a soft removed member by ID
+    /// If there are more trashed versions with the same ID,
+    /// then the most recently removed one is restored.
+    pub fn restore(&mut self, id: &str) -> PackResult<()> {
+        self.sync_location();
+        self.check_writable()?;
+        if !self.check_id_available(id) {
+            return Err(PackError::IDTaken);
+        }
+        let from = match self
+            .trash_entries()?
+            .into_iter()
+            .filter(|(trashed_id, _, _)| trashed_id == id)
+            .max_by_key(|(_, removed_at, _)| *removed_at)
+        {
+            Some((_, _, path)) => path,
+            None => return Err(PackError::ObjectNotFound),
+        };
+        let to = self.new_member_path(id)?;
+        std::fs::rename(&from, &to)?;
+        self.ctx.record_version(&to);
+        match self.load_member(to.clone()) {
+            Ok(pack) => {
+                self.insert_pack(pack)?;
+                self.ctx
+                    .member_changed(ChangeEvent::new(id, ChangeKind::Created));
+                Ok(())
+            }
+            Err(err) => {
+                // Put it back to the trash,
+                // we do not want to lose it.
+                std::fs::rename(&to, &from)?;
+                Err(err)
+            }
+        }
+    }
+    /// Purge trash
+    /// Permanently deletes every trashed file that was
+    /// removed more than older_than ago.
+    /// Returns the number of deleted files.
+    pub fn purge_trash(&self, older_than: Duration) -> PackResult<usize> {
+        self.check_writable()?;
+        let limit = unix_millis(SystemTime::now())
+            .saturating_sub(older_than.as_millis());
+        let mut count = 0;
+        for (_, removed_at, path) in self.trash_entries()? {
+            if removed_at <= limit {
+                std::fs::remove_file(path)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+    // Remove member from memory by ID
+    // and returns its Pack<T>.
+    fn take_member(&mut self, id: &str) -> PackResult<Pack<T>> {
+        match self.position(id) {
+            Some(pos) => {
+                self.forget_expiry(id)?;
+                self.member_removed(id);
+                self.id_index.lock().unwrap().remove(id, pos);
+                Ok(self.data.remove(pos))
+            }
+            None => Err(PackError::ObjectNotFound),
+        }
+    }
+    // Drop a member from memory, keeping its file
+    pub(crate) fn forget_member(&mut self, id: &str) -> Option<Pack<T>> {
+        let pos = self.position(id)?;
+        self.member_removed(id);
+        self.id_index.lock().unwrap().remove(id, pos);
+        Some(self.data.remove(pos))
+    }
+    // Path of the trash folder
+    fn trash_path(&self) -> PathBuf {
+        self.dir().join(".trash")
+    }
+    // List trashed files as (ID, removed at, path)
+    // Files with unknown name format are ignored.
+    fn trash_entries(&self) -> PackResult<Vec<(String, u128, PathBuf)>> {
+        let trash = self.trash_path();
+        if !trash.exists() {
+            return Ok(Vec::new());
+        }
+        let mut result = Vec::new();
+        for entry in std::fs::read_dir(&trash)? {
+            let path = entry?.path();
+            let name = match path.file_name().and_then(|n| n.to_str()) {
+                Some(name) => name,
+                None => continue,
+            };
+            // ID.TIMESTAMP.yml
+            let stem = match name.strip_suffix(".yml") {
+                Some(stem) => stem,
+                None => continue,
+            };
+            let mut parts = stem.rsplitn(2, '.');
+            let removed_at = parts.next().and_then(|t| t.parse::<u128>().ok());
+            if let (Some(removed_at), Some(id)) = (removed_at, parts.next()) {
+                result.push((id.to_string(), removed_at, path.clone()));
+            }
+        }
+        Ok(result)
+    }
+    /// Change the ID of a member
+    /// The member is saved with the new ID into its new file,
+    /// then the old file is removed, so the member is never lost;
+    /// if any step fails, the member keeps its old ID and file.
+    /// Returns PackError::IDTaken if new is not available,
+    /// PackError::ObjectNotFound if there is no member with old.
+    /// A new ID starting with '.' is rejected, as in insert.
+    pub fn change_id(&mut self, old: &str, new: &str) -> PackResult<()> {
+        self.sync_location();
+        self.check_mutable()?;
+        let pos = self.position(old).ok_or(PackError::ObjectNotFound)?;
+        if old == new {
+            return Ok(());
+        }
+        if !self.check_id_available(new) {
+            return Err(PackError::IDTaken);
+        }
+        let to = self.new_member_path(new)?;
+        if to.exists() {
+            return Err(PackError::IDTaken);
+        }
+        let (ctx, hooks) = (&self.ctx, &self.hooks);
+        let pack = &mut self.data[pos];
+        pack.data.set_id(new)?;
+        let res = hooks
+            .validate(&pack.data)
+            .and_then(|_| ctx.write(&to, &pack.data, &pack.stamp))
+            .and_then(|_| match ctx.remove(&pack.path) {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    let _ = ctx.remove(&to);
+                    Err(err)
+                }
+            });
+        if let Err(err) = res {
+            // Restore the old ID, it was set successfully already
+            let _ = pack.data.set_id(old);
+            return Err(err.with_id(old));
+        }
+        pack.path = to;
+        if let Some(stats) = &self.stats {
+            let mut stats = stats.lock().unwrap();
+            if let Some(entry) = stats.remove(old) {
+                stats.insert(new.to_string(), entry);
+            }
+        }
+        self.indexes.lock().unwrap().remove(old);
+        self.member_added(&self.data[pos]);
+        self.id_index.lock().unwrap().touch(old);
+        self.rekey_expiry(old, new)?;
+        self.save_order()?;
+        self.ctx
+            .member_changed(ChangeEvent::new(old, ChangeKind::Removed));
+        self.ctx
+            .member_changed(ChangeEvent::new(new, ChangeKind::Created));
         Ok(())
     }
-    // pub fn remove_by_id(&mut self, id: &str) -> PackResult<()> {
-    //     match self.iter().position(|i| i.get_id() == id) {
-    //         Some(p) => {
-    //             std::fs::remove_file(&self.find_id)?
-    //         }
-    //         None => Err(PackError::ObjectNotFound),
-    //     }
-    // }
     /// Insert Pack<T> to VecPack<T>
     /// Only if ID is not taken
     pub fn insert_pack(&mut self, item: Pack<T>) -> PackResult<()> {
-        if !&self.check_id_available(item.get_id()) {
+        self.check_writable()?;
+        self.add_member(item)?;
+        self.save_order()
+    }
+    // Add a loaded member, only if ID is not taken
+    // Nothing is written, e.g. the order index is saved by the caller.
+    // Save back the members migrated at load
+    // A read-only or append-only VecPack cannot save them,
+    // so they are migrated in memory only.
+    pub(crate) fn save_migrated(&self) -> PackResult<()> {
+        if self.is_read_only() || self.append_only {
+            return Ok(());
+        }
+        for pack in &self.data {
+            pack.save_migrated()?;
+        }
+        Ok(())
+    }
+    pub(crate) fn add_member(&mut self, mut item: Pack<T>) -> PackResult<()> {
+        if !self.check_id_available(item.get_id()) {
             return Err(PackError::IDTaken);
         }
-        self.data.push(item);
+        item.ctx = self.ctx.clone();
+        item.hooks = self.hooks.clone();
+        self.member_added(&item);
+        self.push_member(item);
         Ok(())
     }
     /// Find ID and returns &Pack<T>
     /// as an unmutable reference
     pub fn find_id(&self, id: &str) -> PackResult<&Pack<T>> {
-        match self.iter().position(|i| i.get_id() == id) {
-            Some(p) => Ok(&self.get(p).unwrap()),
+        match self.position(id).filter(|_| !self.is_expired(id)) {
+            Some(p) => {
+                self.record_read(id);
+                Ok(&self.get(p).unwrap())
+            }
             None => Err(PackError::ObjectNotFound),
         }
     }
+    /// Find many IDs at once
+    /// Returns the found members in the order of ids, and the
+    /// IDs that are not members, e.g. to serve "fetch these N
+    /// records" requests in one call.
+    pub fn find_ids<'a, S>(&self, ids: &'a [S]) -> (Vec<&Pack<T>>, Vec<&'a str>)
+    where
+        S: AsRef<str>,
+    {
+        let mut found = Vec::new();
+        let mut missing = Vec::new();
+        for id in ids.iter().map(|id| id.as_ref()) {
+            match self.find_id(id) {
+                Ok(pack) => found.push(pack),
+                Err(_) => missing.push(id),
+            }
+        }
+        (found, missing)
+    }
     /// Find ID and returns &mut Pack<T>
     /// as a mutable reference
     pub fn find_id_mut(&mut self, id: &str) -> PackResult<&mut Pack<T>> {
-        match &mut self.into_iter().position(|i| i.get_id() == id) {
-            Some(p) => Ok(self.as_vec_mut().get_mut(*p).unwrap()),
+        self.check_mutable()?;
+        self.sync_location();
+        match self.position(id).filter(|_| !self.is_expired(id)) {
+            Some(p) => {
+                self.record_write(id);
+                self.member_touched(id);
+                Ok(self.data.get_mut(p).unwrap())
+            }
             None => Err(PackError::ObjectNotFound),
         }
     }
+    /// Save all the members
+    /// Does not stop at the first error, so the result has an
+    /// entry for every member: its ID, and its save result.
+    /// Useful to force a full flush, e.g. before shutdown.
+    pub fn save_all(&self) -> Vec<(String, PackResult<()>)> {
+        self.data
+            .iter()
+            .map(|pack| {
+                let res = self.check_writable().and_then(|_| pack.save());
+                (pack.get_id().to_string(), res)
+            })
+            .collect()
+    }
+    /// Save all the members concurrently
+    /// The same as save_all, but at most workers members are
+    /// written at once; 0 means a worker per available core.
+    /// Every failed member is in the report with its error.
+    pub fn save_all_parallel(&self, workers: usize) -> SaveReport
+    where
+        T: Sync,
+    {
+        let results = match self.check_writable() {
+            Ok(_) => pool::map_ordered(
+                self.data.iter().collect(),
+                pool::workers(workers),
+                |pack| pack.save(),
+            ),
+            Err(_) => self.data.iter().map(|_| self.check_writable()).collect(),
+        };
+        let mut report = SaveReport::default();
+        for (pack, res) in self.data.iter().zip(results) {
+            report.push(pack.get_id(), res);
+        }
+        report
+    }
+    /// Enable per member access statistics
+    /// From now on find_id counts as a read,
+    /// insert and find_id_mut count as a write.
+    pub fn enable_stats(&mut self) {
+        if self.stats.is_none() {
+            self.stats = Some(Mutex::new(HashMap::new()));
+        }
+    }
+    /// Access statistics of a member by ID
+    /// Returns None if stats are disabled, or the
+    /// member has not been accessed since enabling.
+    pub fn stats_by_id(&self, id: &str) -> Option<AccessStats> {
+        self.stats
+            .as_ref()
+            .and_then(|stats| stats.lock().unwrap().get(id).cloned())
+    }
+    /// Number of member saves failed with PackError::StorageFull
+    /// Useful to alert on a full disk, while reads keep working
+    /// from memory.
+    pub fn storage_full_count(&self) -> u64 {
+        self.ctx.storage_full_count()
+    }
+    // Count an immutable access if stats are enabled
+    fn record_read(&self, id: &str) {
+        if let Some(stats) = &self.stats {
+            let mut stats = stats.lock().unwrap();
+            let entry = stats.entry(id.to_string()).or_default();
+            entry.reads += 1;
+            entry.last_read = Some(SystemTime::now());
+        }
+    }
+    // Count a mutable access if stats are enabled
+    fn record_write(&self, id: &str) {
+        if let Some(stats) = &self.stats {
+            let mut stats = stats.lock().unwrap();
+            let entry = stats.entry(id.to_string()).or_default();
+            entry.writes += 1;
+            entry.last_write = Some(SystemTime::now());
+        }
+    }
+    /// Find the first member matching the predicate
+    /// Expired members are skipped, as by iter_live.
+    pub fn find<F>(&self, mut predicate: F) -> Option<&Pack<T>>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.iter_live().find(|i| predicate(&i.data))
+    }
+    /// Filter members by the predicate
+    pub fn filter<F>(&self, mut predicate: F) -> Vec<&Pack<T>>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.iter_live().filter(|i| predicate(&i.data)).collect()
+    }
+    /// Returns true if any member matches the predicate
+    pub fn any<F>(&self, mut predicate: F) -> bool
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.iter_live().any(|i| predicate(&i.data))
+    }
+    /// Returns true if all members match the predicate
+    /// Empty VecPack returns true.
+    pub fn all<F>(&self, mut predicate: F) -> bool
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.iter_live().all(|i| predicate(&i.data))
+    }
+    /// Page of members
+    /// Returns at most limit members starting at offset,
+    /// in the VecPack order. Out of range offset returns
+    /// an empty page. Expired members are skipped.
+    pub fn page(&self, offset: usize, limit: usize) -> Vec<&Pack<T>> {
+        self.iter_live().skip(offset).take(limit).collect()
+    }
+    /// Cursor based page of members
+    /// Cursor is the ID of the last member of the previous page,
+    /// or None for the first page. The returned Page contains the
+    /// cursor for the next page, if there is any.
+    pub fn page_after(
+        &self,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> PackResult<Page<'_, T>> {
+        let offset = match cursor {
+            Some(id) => match self.position(id) {
+                Some(p) => p + 1,
+                None => return Err(PackError::ObjectNotFound),
+            },
+            None => 0,
+        };
+        let now = self.expiry_clock();
+        let mut live = self.data[offset..]
+            .iter()
+            .filter(|i| !self.expired_at(i.get_id(), now));
+        let items: Vec<&Pack<T>> = live.by_ref().take(limit).collect();
+        let next = match items.last() {
+            Some(last) if live.next().is_some() => {
+                Some(last.get_id().to_string())
+            }
+            _ => None,
+        };
+        Ok(Page { items, next })
+    }
+    /// Returns true if ID is a member
+    /// Reserved IDs are not members, see check_id_available.
+    pub fn contains_id(&self, id: &str) -> bool {
+        self.position(id).is_some() && !self.is_expired(id)
+    }
+    /// Member IDs in member order
+    /// Expired members are skipped, as by iter_live.
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.iter_live().map(|i| i.get_id())
+    }
     /// Check ID is available
     /// If ID is taken, returns false,
     /// otherwise returns true
+    /// Reserved IDs are not available.
     pub fn check_id_available(&self, id: &str) -> bool {
-        match self.iter().position(|i| i.get_id() == id) {
+        match self.position(id) {
             Some(_) => false,
-            None => true,
+            None => !self.reserved.lock().unwrap().contains(id),
+        }
+    }
+    /// Check many IDs at once
+    /// Returns the given IDs that are not available (taken or
+    /// reserved), in the given order. IDs are looked up in the ID
+    /// index, so checking many candidates does not scan the VecPack
+    /// for each of them.
+    pub fn check_ids_available<'a, I>(&self, ids: I) -> Vec<&'a str>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let reserved = self.reserved.lock().unwrap();
+        ids.into_iter()
+            .filter(|id| self.position(id).is_some() || reserved.contains(*id))
+            .collect()
+    }
+    /// Reserve ID
+    /// Holds the ID until the returned Reservation is committed
+    /// with data, or dropped. Meanwhile the ID is not available,
+    /// so other inserts with the same ID fail with IDTaken.
+    pub fn reserve_id(&self, id: &str) -> PackResult<Reservation> {
+        if self.position(id).is_some() {
+            return Err(PackError::IDTaken);
+        }
+        if !self.reserved.lock().unwrap().insert(id.to_string()) {
+            return Err(PackError::IDTaken);
         }
+        Ok(Reservation {
+            id: id.to_string(),
+            reserved: self.reserved.clone(),
+        })
     }
     /// Returns data as a mutable
     /// reference to Vec<Pack<T>>
+    /// Panics if VecPack is append-only.
     pub fn as_vec_mut(&mut self) -> &mut Vec<Pack<T>> {
+        self.sync_location();
+        self.assert_mutable();
+        self.members_touched();
         &mut self.data
     }
     /// Returns data as unmutable
@@ -650,6 +2131,243 @@ where
     }
 }
 
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    // Follow the shared location if the registry
+    // has moved the collection directory.
+    // Rebase the VecPack path and all the member paths.
+    fn sync_location(&mut self) {
+        let new_path = match &self.location {
+            Some(location) => location.read().unwrap().clone(),
+            None => return,
+        };
+        if new_path == self.path {
+            return;
+        }
+        self.relocated(new_path);
+    }
+    // Switch the VecPack, its members and its context to new_path,
+    // after the directory has moved there with all its files.
+    // Returns the old path.
+    pub(crate) fn relocated(&mut self, new_path: PathBuf) -> PathBuf {
+        for pack in self.data.iter_mut() {
+            pack.path = context::rebase(&pack.path, &self.path, &new_path);
+        }
+        self.ctx.relocate(&self.path, &new_path);
+        if let Some(lock) = &mut self.lock {
+            lock.relocate(&new_path);
+        }
+        let old_path = std::mem::replace(&mut self.path, new_path);
+        // A watch that cannot follow the directory is stopped
+        #[cfg(feature = "watch")]
+        if self.watch.is_some() {
+            self.watch = watch::FsWatch::start(&self.path).ok();
+        }
+        old_path
+    }
+    /// Sort members
+    /// Sorts the members by the given compare function.
+    /// If the order index is enabled, then the new order is persisted.
+    pub fn sort_by<F>(&mut self, mut compare: F) -> PackResult<()>
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        self.check_writable()?;
+        self.data.sort_by(|a, b| compare(&a.data, &b.data));
+        self.id_index.lock().unwrap().invalidate();
+        self.save_order()?;
+        self.ctx.changed();
+        Ok(())
+    }
+    /// Enable order index
+    /// Persists the current member order into .order.yml,
+    /// and keeps it up to date on insert, remove and sort.
+    /// A VecPack directory with an order index is loaded
+    /// in the persisted order, and the index stays enabled.
+    /// Without an index members are loaded in file name order.
+    pub fn enable_order_index(&mut self) -> PackResult<()> {
+        self.check_writable()?;
+        self.order_index = true;
+        self.save_order()
+    }
+    // Directory of the VecPack as it is now
+    // See PackContext::resolve, the path is only rebased
+    // at the next mutable access.
+    pub(crate) fn dir(&self) -> PathBuf {
+        self.ctx.resolve(&self.path)
+    }
+    // Path of the order index file
+    fn order_path(&self) -> PathBuf {
+        self.dir().join(".order.yml")
+    }
+    // Save member order, if order index is enabled
+    // Only the writer keeps the index, a read-only VecPack
+    // reloading members does not touch it.
+    fn save_order(&self) -> PackResult<()> {
+        if !self.order_index || self.is_read_only() {
+            return Ok(());
+        }
+        let ids = self
+            .data
+            .iter()
+            .map(|i| i.get_id().to_string())
+            .collect::<Vec<String>>();
+        save_data_object(&self.order_path(), ids)
+    }
+    // Apply the persisted member order if there is any.
+    // Members missing from the index keep their relative
+    // order at the end.
+    fn restore_order(&mut self) -> PackResult<()> {
+        let path = self.order_path();
+        if !path.is_file() {
+            return Ok(());
+        }
+        let ids: Vec<String> =
+            serde_yaml::from_str(&std::fs::read_to_string(&path)?)
+                .map_err(PackError::deserialize)?;
+        let positions = ids
+            .iter()
+            .enumerate()
+            .map(|(p, id)| (id.as_str(), p))
+            .collect::<HashMap<&str, usize>>();
+        self.data.sort_by_key(|i| {
+            positions.get(i.get_id()).cloned().unwrap_or(usize::MAX)
+        });
+        self.id_index.lock().unwrap().invalidate();
+        self.order_index = true;
+        Ok(())
+    }
+    /// Enable append-only mode
+    /// Members can be inserted, but never modified or removed.
+    /// Mutable accessors return PackError::Immutable, as_vec_mut()
+    /// and mutable iteration panic. Member files are set read-only.
+    /// The mode is persisted with an .append_only marker file,
+    /// so it cannot be turned off through the API.
+    pub fn enable_append_only(&mut self) -> PackResult<()> {
+        self.check_writable()?;
+        save_data_object(&self.append_only_path(), true)?;
+        self.append_only = true;
+        for pack in &self.data {
+            self.seal(&pack.path)?;
+        }
+        Ok(())
+    }
+    /// Returns true if VecPack is append-only
+    pub fn is_append_only(&self) -> bool {
+        self.append_only
+    }
+    // Path of the append-only marker file
+    fn append_only_path(&self) -> PathBuf {
+        self.dir().join(".append_only")
+    }
+    // Returns PackError::Locked if VecPack is read-only,
+    // or PackError::Immutable if VecPack is append-only
+    fn check_mutable(&self) -> PackResult<()> {
+        self.check_writable()?;
+        if self.append_only {
+            return Err(PackError::Immutable);
+        }
+        Ok(())
+    }
+    // Panics if VecPack is read-only or append-only
+    // Used by accessors that cannot return PackError
+    fn assert_mutable(&self) {
+        if self.is_read_only() {
+            panic!(
+                "VecPack is locked by another writer, it is read-only. \
+                 Path: {}",
+                self.path.display()
+            );
+        }
+        if self.append_only {
+            panic!(
+                "VecPack is append-only, mutable access is not allowed. \
+                 Path: {}",
+                self.path.display()
+            );
+        }
+    }
+    // Set member file read-only in append-only mode
+    fn seal(&self, path: &Path) -> PackResult<()> {
+        if self.append_only {
+            let mut permissions = std::fs::metadata(path)?.permissions();
+            permissions.set_readonly(true);
+            std::fs::set_permissions(path, permissions)?;
+        }
+        Ok(())
+    }
+    // File path of a member by its ID
+    pub(crate) fn member_path(&self, id: &str) -> PathBuf {
+        let mut p = self.path.clone();
+        if let Some(width) = self.shard_width {
+            p.push(shard::shard_name(id, width));
+        }
+        p.push(format!("{}.yml", id));
+        p
+    }
+    // File path of a new member by its ID
+    // Creates its shard directory if needed.
+    pub(crate) fn new_member_path(&self, id: &str) -> PackResult<PathBuf> {
+        check_member_id(id)?;
+        let p = self.member_path(id);
+        if self.shard_width.is_some() {
+            if let Some(parent) = p.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        Ok(p)
+    }
+    // Member by ID
+    pub(crate) fn pack_by_id(&self, id: &str) -> Option<&Pack<T>> {
+        self.position(id).map(|pos| &self.data[pos])
+    }
+    // Position of a member by ID
+    fn position(&self, id: &str) -> Option<usize> {
+        self.id_index.lock().unwrap().position(&self.data, id)
+    }
+    // Add a member to the end
+    fn push_member(&mut self, pack: Pack<T>) {
+        self.id_index
+            .lock()
+            .unwrap()
+            .push(pack.get_id(), self.data.len());
+        self.data.push(pack);
+    }
+    // Called when a member is added to the VecPack
+    fn member_added(&self, pack: &Pack<T>) {
+        self.indexes
+            .lock()
+            .unwrap()
+            .insert(pack.get_id(), &pack.data);
+    }
+    // Called when a member is removed from the VecPack
+    fn member_removed(&self, id: &str) {
+        if let Some(stats) = &self.stats {
+            stats.lock().unwrap().remove(id);
+        }
+        self.indexes.lock().unwrap().remove(id);
+    }
+    // Called when a member is mutably borrowed,
+    // so its data may change.
+    fn member_touched(&self, id: &str) {
+        self.indexes.lock().unwrap().touch(id);
+        self.id_index.lock().unwrap().touch(id);
+    }
+    // Called when all the members are mutably borrowed,
+    // or replaced
+    fn members_touched(&self) {
+        self.indexes.lock().unwrap().touch_all();
+        self.id_index.lock().unwrap().invalidate();
+    }
+    // Attach shared location, used by Registry
+    pub(crate) fn set_location(&mut self, location: Arc<RwLock<PathBuf>>) {
+        self.ctx.set_location(location.clone(), self.path.clone());
+        self.location = Some(location);
+    }
+}
+
 // Deref implementation for VecPack<T>
 // It returns an unmutable reference to &Vec<Pack<T>>
 impl<T> Deref for VecPack<T>
@@ -719,6 +2437,9 @@ where
     type IntoIter = VecPackIterMut<'a, T>;
 
     fn into_iter(self) -> Self::IntoIter {
+        self.sync_location();
+        self.assert_mutable();
+        self.members_touched();
         VecPackIterMut {
             data: &mut self.data,
         }
@@ -727,10 +2448,48 @@ where
 
 impl<'a, T> PackGuard<'a, T>
 where
-    for<'de> T: Serialize + Deserialize<'de> + Default + Sized + Clone + 'a,
+    T: Serialize + Sized + Clone,
 {
     pub fn unpack(&mut self) -> &mut T {
-        &mut self.data
+        // Keep the original data, so it can be discarded
+        if self.keep_backup && self.backup.is_none() {
+            self.backup = Some(self.data.clone());
+        }
+        self.data
+    }
+    /// Commit changes
+    /// Saves data and returns the save error, if any.
+    /// If save fails, data is rolled back, as with Pack::update,
+    /// if the guard keeps a backup.
+    pub fn commit(mut self) -> PackResult<()> {
+        self.finished = true;
+        let res = self.save();
+        if res.is_err() {
+            self.rollback();
+        }
+        res
+    }
+    /// Discard changes
+    /// Nothing is saved. Restores the data if the guard keeps
+    /// a backup; otherwise the changes stay in memory only.
+    pub fn discard(mut self) {
+        self.finished = true;
+        self.rollback();
+    }
+    // Save data
+    // Invalid data is not saved
+    fn save(&mut self) -> PackResult<()> {
+        self.hooks.before_save(self.data);
+        self.hooks
+            .validate(self.data)
+            .inspect_err(|err| self.ctx.save_failed(err))
+            .and_then(|_| self.ctx.save(self.path, &self.data, self.stamp))
+    }
+    // Restore data before the first mutable access
+    fn rollback(&mut self) {
+        if let Some(backup) = self.backup.take() {
+            *self.data = backup;
+        }
     }
 }
 
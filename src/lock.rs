@@ -0,0 +1,196 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Single writer lock
+//!
+//! In single writer mode only one VecPack instance can write a
+//! directory at a time, across processes as well. The writer holds
+//! the .lock file of the directory, which contains its process ID.
+//! When the lock is held by another instance, load_or_init falls
+//! back to a read-only VecPack: its mutating methods, and the saves
+//! of its members, return PackError::Locked. If the owner has
+//! crashed, leaving a stale lock behind, take_over_lock() moves the
+//! lock explicitly. Members of an older schema version are migrated
+//! in memory only then.
+
+use crate::{PackError, PackResult, VecPack, VecPackMember};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// Lock file name in the VecPack directory
+pub(crate) const LOCK_FILE: &str = ".lock";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct LockInfo {
+    // Process ID of the owner
+    pid: u32,
+    // Distinguishes instances of the same process
+    token: u128,
+}
+
+// Held directory lock, released at drop
+#[derive(Debug)]
+pub(crate) struct DirLock {
+    path: PathBuf,
+    info: LockInfo,
+}
+
+impl DirLock {
+    // Try to take the lock; None if it is held
+    fn acquire(path: &Path) -> PackResult<Option<DirLock>> {
+        let info = new_info();
+        match OpenOptions::new().write(true).create_new(true).open(path) {
+            Ok(mut file) => {
+                file.write_all(serde_yaml::to_string(&info)?.as_bytes())?;
+                file.sync_all()?;
+                Ok(Some(DirLock {
+                    path: path.to_path_buf(),
+                    info,
+                }))
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+                Ok(None)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+    // Follow the directory when it is moved with its .lock file
+    pub(crate) fn relocate(&mut self, dir: &Path) {
+        if let Some(name) = self.path.file_name() {
+            self.path = dir.join(name);
+        }
+    }
+    // Take the lock, even if it is held
+    fn take_over(path: &Path) -> PackResult<DirLock> {
+        let info = new_info();
+        crate::save_data_object(path, &info)?;
+        Ok(DirLock {
+            path: path.to_path_buf(),
+            info,
+        })
+    }
+}
+
+impl Drop for DirLock {
+    fn drop(&mut self) {
+        // Keep the lock if it was taken over
+        if read_info(&self.path).as_ref() == Some(&self.info) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn new_info() -> LockInfo {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    LockInfo {
+        pid: std::process::id(),
+        token: nanos,
+    }
+}
+
+fn read_info(path: &Path) -> Option<LockInfo> {
+    let buffer = std::fs::read_to_string(path).ok()?;
+    serde_yaml::from_str(&buffer).ok()
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Enable single writer mode
+    /// Takes the directory lock, or returns PackError::Locked if
+    /// another instance holds it. From now on load_or_init takes
+    /// the lock too, or loads a read-only VecPack.
+    pub fn enable_single_writer(&mut self) -> PackResult<()> {
+        if self.lock.is_none() {
+            self.lock = Some(
+                DirLock::acquire(&self.lock_path())?
+                    .ok_or(PackError::Locked)?,
+            );
+        }
+        self.ctx.set_read_only(false);
+        crate::save_data_object(&self.single_writer_path(), true)
+    }
+    /// True if VecPack is in single writer mode
+    pub fn is_single_writer(&self) -> bool {
+        self.single_writer_path().exists()
+    }
+    /// True if another instance holds the directory lock,
+    /// so this one cannot write.
+    pub fn is_read_only(&self) -> bool {
+        self.ctx.is_read_only()
+    }
+    /// Process ID of the directory lock owner, if any
+    pub fn lock_owner(&self) -> Option<u32> {
+        read_info(&self.lock_path()).map(|info| info.pid)
+    }
+    /// Take over the directory lock
+    /// Meant for stale locks left behind by crashed writers;
+    /// the previous owner is not notified. Reloads the members,
+    /// as the previous owner might have changed them.
+    pub fn take_over_lock(&mut self) -> PackResult<()>
+    where
+        for<'de> T: serde::Deserialize<'de> + Default,
+    {
+        self.lock = Some(DirLock::take_over(&self.lock_path())?);
+        self.ctx.set_read_only(false);
+        self.reload()
+    }
+    // Take the lock at load in single writer mode,
+    // otherwise fall back to read-only.
+    pub(crate) fn load_lock(&mut self) -> PackResult<()> {
+        if self.is_single_writer() && self.lock.is_none() {
+            self.lock = DirLock::acquire(&self.lock_path())?;
+            self.ctx.set_read_only(self.lock.is_none());
+        }
+        Ok(())
+    }
+    // Take the lock again at the current directory, e.g. after it
+    // was copied there without its .lock file. Nothing to do if
+    // the lock was not held.
+    pub(crate) fn reacquire_lock(&mut self) -> PackResult<()> {
+        if self.lock.take().is_some() {
+            self.lock = DirLock::acquire(&self.lock_path())?;
+            self.ctx.set_read_only(self.lock.is_none());
+        }
+        Ok(())
+    }
+    // Returns PackError::Locked if VecPack is read-only
+    pub(crate) fn check_writable(&self) -> PackResult<()> {
+        self.ctx.check_writable()
+    }
+    fn lock_path(&self) -> PathBuf {
+        self.dir().join(LOCK_FILE)
+    }
+    fn single_writer_path(&self) -> PathBuf {
+        self.dir().join(".single_writer")
+    }
+}
@@ -0,0 +1,145 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Logging and save error hooks
+//!
+//! Some failures cannot be returned to the caller, e.g. the save
+//! in PackGuard::drop. With the "log" or "tracing" feature they are
+//! emitted as error records with the "storaget" target, including
+//! the file path and the cause.
+//!
+//! Applications can also handle them, e.g. to alert or retry, with
+//! a save error hook: per Pack or VecPack, or a global one for the
+//! packs without own hook.
+//!
+//! Operations slower than the slow operation threshold are emitted
+//! as warning records.
+
+use crate::metrics::OpKind;
+use crate::{Pack, PackError, VecPack, VecPackMember};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+// Receives the failed saves that cannot be returned as error
+pub(crate) type SaveErrorHook = Arc<dyn Fn(&Path, PackError) + Send + Sync>;
+
+// Hook of the packs without own hook
+fn global_hook() -> &'static RwLock<Option<SaveErrorHook>> {
+    static HOOK: OnceLock<RwLock<Option<SaveErrorHook>>> = OnceLock::new();
+    HOOK.get_or_init(Default::default)
+}
+
+/// Set global save error hook
+/// It receives the path and the error of the failed saves, that
+/// cannot be returned as error (e.g. at PackGuard drop), of every
+/// Pack and VecPack without own hook.
+pub fn set_save_error_hook<F>(hook: F)
+where
+    F: Fn(&Path, PackError) + Send + Sync + 'static,
+{
+    *global_hook().write().unwrap() = Some(Arc::new(hook));
+}
+
+// Report a failed save that cannot be returned as error:
+// log it, then pass it to the pack hook or the global one.
+pub(crate) fn save_failed(
+    hook: Option<SaveErrorHook>,
+    path: &Path,
+    err: PackError,
+) {
+    #[cfg(feature = "log")]
+    log::error!(
+        target: "storaget",
+        "Failed to save {}: {}",
+        path.display(),
+        err
+    );
+    #[cfg(feature = "tracing")]
+    tracing::error!(
+        target: "storaget",
+        path = %path.display(),
+        error = %err,
+        "Failed to save"
+    );
+    if let Some(hook) = hook.or_else(|| global_hook().read().unwrap().clone()) {
+        hook(path, err);
+    }
+}
+
+// Report an operation slower than the threshold
+#[cfg_attr(
+    not(any(feature = "log", feature = "tracing")),
+    allow(unused_variables)
+)]
+pub(crate) fn slow_operation(kind: OpKind, path: &Path, duration: Duration) {
+    #[cfg(feature = "log")]
+    log::warn!(
+        target: "storaget",
+        "Slow {} of {}: {} ms",
+        kind,
+        path.display(),
+        duration.as_millis()
+    );
+    #[cfg(feature = "tracing")]
+    tracing::warn!(
+        target: "storaget",
+        path = %path.display(),
+        op = %kind,
+        duration_ms = duration.as_millis() as u64,
+        "Slow operation"
+    );
+}
+
+impl<T> Pack<T>
+where
+    T: Serialize + Sized + Clone,
+{
+    /// Set save error hook of the Pack
+    /// It receives the failed saves that cannot be returned
+    /// as error. A VecPack member shares the hook of its VecPack.
+    pub fn set_save_error_hook<F>(&mut self, hook: F)
+    where
+        F: Fn(&Path, PackError) + Send + Sync + 'static,
+    {
+        self.ctx.set_error_hook(Arc::new(hook));
+    }
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Set save error hook of the VecPack and its members
+    /// It receives the failed saves that cannot be returned
+    /// as error, e.g. at PackGuard drop.
+    pub fn set_save_error_hook<F>(&mut self, hook: F)
+    where
+        F: Fn(&Path, PackError) + Send + Sync + 'static,
+    {
+        self.ctx.set_error_hook(Arc::new(hook));
+    }
+}
@@ -0,0 +1,334 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Append-only log
+//!
+//! LogPack<T> appends records to a single file, one JSON document
+//! per line, instead of rewriting the whole file as Pack<T> does.
+//! It suits event logs and metrics; iter() replays the records,
+//! oldest first.
+//!
+//! Logs grow forever, unless compacted: compact_before() drops the
+//! records already included e.g. in a snapshot, compact_by_key()
+//! keeps only the latest record per key. A compacted log starts with
+//! a "# base=N" line, N is the number of records dropped from its
+//! front, so record positions stay the same across compactions.
+//!
+//! ```rust,ignore
+//! let mut events: LogPack<Event> = LogPack::open(path)?;
+//! events.append(&Event::started())?;
+//! for event in events.iter()? {
+//!     println!("{:?}", event?);
+//! }
+//! ```
+
+use crate::{atomic, PackError, PackResult, TempConfig};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::hash::Hash;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+/// LogPack<T>
+/// Append-only file of T records
+pub struct LogPack<T> {
+    path: PathBuf,
+    file: File,
+    _record: PhantomData<T>,
+}
+
+impl<T> LogPack<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// Open or create the log file at path
+    /// Its directory is created if it does not exist.
+    /// A partially written last record, e.g. after a crash
+    /// during append, is cut off.
+    pub fn open(path: PathBuf) -> PackResult<LogPack<T>> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)
+            .map_err(|err| PackError::from(err).with_path(&path))?;
+        cut_partial_record(&mut file)?;
+        Ok(LogPack {
+            path,
+            file,
+            _record: PhantomData,
+        })
+    }
+    /// Append a record
+    /// A record is written at once, so records of concurrent
+    /// writers are never interleaved.
+    pub fn append(&mut self, record: &T) -> PackResult<()> {
+        let line = encode(record)?;
+        self.file
+            .write_all(&line)
+            .map_err(|err| PackError::from(err).with_path(&self.path))
+    }
+    /// Append many records with a single write
+    pub fn append_many<'a, I>(&mut self, records: I) -> PackResult<()>
+    where
+        I: IntoIterator<Item = &'a T>,
+        T: 'a,
+    {
+        let mut buffer = Vec::new();
+        for record in records {
+            buffer.extend(encode(record)?);
+        }
+        self.file
+            .write_all(&buffer)
+            .map_err(|err| PackError::from(err).with_path(&self.path))
+    }
+    /// Flush the appended records to the disk
+    pub fn sync(&self) -> PackResult<()> {
+        Ok(self.file.sync_data()?)
+    }
+    /// Iterate over the records, oldest first
+    /// Records are read lazily; a record that cannot be decoded
+    /// is returned as error, and iteration goes on.
+    pub fn iter(&self) -> PackResult<LogIter<T>> {
+        let file = File::open(&self.path)
+            .map_err(|err| PackError::from(err).with_path(&self.path))?;
+        Ok(LogIter {
+            lines: BufReader::new(file).lines(),
+            path: self.path.clone(),
+            _record: PhantomData,
+        })
+    }
+    /// Fold the records into a state, oldest first
+    /// Returns the first record that cannot be decoded as error.
+    pub fn fold<S, F>(&self, init: S, mut f: F) -> PackResult<S>
+    where
+        F: FnMut(&mut S, T),
+    {
+        let mut state = init;
+        for record in self.iter()? {
+            f(&mut state, record?);
+        }
+        Ok(state)
+    }
+    /// Number of records
+    /// Reads the whole file, but does not decode the records.
+    pub fn count(&self) -> PackResult<usize> {
+        let file = File::open(&self.path)?;
+        let mut count = 0;
+        for line in BufReader::new(file).lines() {
+            if is_record(&line?) {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+    /// Number of records dropped from the front by compaction
+    /// The first record of iter() is at this position.
+    pub fn base(&self) -> PackResult<u64> {
+        let file = File::open(&self.path)?;
+        let mut first = String::new();
+        BufReader::new(file).read_line(&mut first)?;
+        Ok(first
+            .trim()
+            .strip_prefix("# base=")
+            .and_then(|base| base.parse().ok())
+            .unwrap_or(0))
+    }
+    /// Drop the records before position, e.g. the ones already
+    /// included in a snapshot
+    /// Positions count from the first record ever appended, so
+    /// records dropped earlier are counted too. The log is
+    /// rewritten atomically; it needs exclusive access, records
+    /// appended by others during compaction can be lost.
+    /// Returns the number of dropped records.
+    pub fn compact_before(&mut self, position: u64) -> PackResult<usize> {
+        let base = self.base()?;
+        if position <= base {
+            return Ok(0);
+        }
+        let skip = (position - base) as usize;
+        let lines = self.record_lines()?;
+        let dropped = skip.min(lines.len());
+        self.rewrite(base + dropped as u64, &lines[dropped..])?;
+        Ok(dropped)
+    }
+    /// Keep only the latest record of every key
+    /// Kept records stay in their order. Positions are not kept,
+    /// so use it for logs that are always replayed in full.
+    /// Returns the number of dropped records.
+    pub fn compact_by_key<K, F>(&mut self, key: F) -> PackResult<usize>
+    where
+        K: Eq + Hash,
+        F: Fn(&T) -> K,
+    {
+        let lines = self.record_lines()?;
+        let mut latest = HashMap::new();
+        for (position, line) in lines.iter().enumerate() {
+            let record: T = serde_json::from_str(line).map_err(|err| {
+                PackError::custom_deserialize(err.to_string())
+                    .with_path(&self.path)
+            })?;
+            latest.insert(key(&record), position);
+        }
+        let mut kept = latest.into_values().collect::<Vec<usize>>();
+        kept.sort_unstable();
+        let dropped = lines.len() - kept.len();
+        if dropped > 0 {
+            let lines = kept
+                .into_iter()
+                .map(|position| lines[position].clone())
+                .collect::<Vec<String>>();
+            let base = self.base()?;
+            self.rewrite(base, &lines)?;
+        }
+        Ok(dropped)
+    }
+    // Raw lines of the records, without decoding them
+    fn record_lines(&self) -> PackResult<Vec<String>> {
+        let file = File::open(&self.path)?;
+        let mut lines = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if is_record(&line) {
+                lines.push(line);
+            }
+        }
+        Ok(lines)
+    }
+    // Replace the log atomically, then reopen it for appending
+    fn rewrite(&mut self, base: u64, lines: &[String]) -> PackResult<()> {
+        let mut buffer = String::new();
+        if base > 0 {
+            buffer.push_str(&format!("# base={}\n", base));
+        }
+        for line in lines {
+            buffer.push_str(line);
+            buffer.push('\n');
+        }
+        atomic::write_atomic(
+            &self.path,
+            buffer.as_bytes(),
+            &TempConfig::default(),
+        )?;
+        self.file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|err| PackError::from(err).with_path(&self.path))?;
+        Ok(())
+    }
+    /// Path of the log file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// LogIter<T>
+/// Replays the records of a LogPack<T>
+pub struct LogIter<T> {
+    lines: std::io::Lines<BufReader<File>>,
+    path: PathBuf,
+    _record: PhantomData<T>,
+}
+
+impl<T> Iterator for LogIter<T>
+where
+    T: DeserializeOwned,
+{
+    type Item = PackResult<T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(err) => {
+                    return Some(
+                        Err(PackError::from(err).with_path(&self.path)),
+                    )
+                }
+            };
+            if !is_record(&line) {
+                continue;
+            }
+            return Some(serde_json::from_str(&line).map_err(|err| {
+                PackError::custom_deserialize(err.to_string())
+                    .with_path(&self.path)
+            }));
+        }
+    }
+}
+
+// Blank lines and "#" comments, e.g. the base, are not records
+fn is_record(line: &str) -> bool {
+    let line = line.trim();
+    !line.is_empty() && !line.starts_with('#')
+}
+
+// Record as a single JSON line
+fn encode<T: Serialize>(record: &T) -> PackResult<Vec<u8>> {
+    let mut line = serde_json::to_vec(record).map_err(|err| {
+        PackError::InternalError(format!("JSON error: {}", err))
+    })?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+// Cut the file after its last complete record
+// Every record ends with a newline, so anything after the
+// last newline is a partially written record.
+fn cut_partial_record(file: &mut File) -> PackResult<()> {
+    let len = file.seek(SeekFrom::End(0))?;
+    if len == 0 {
+        return Ok(());
+    }
+    let mut last = [0u8; 1];
+    file.seek(SeekFrom::End(-1))?;
+    file.read_exact(&mut last)?;
+    if last[0] == b'\n' {
+        return Ok(());
+    }
+    // Find the last newline, reading backwards in chunks
+    let mut end = len;
+    let mut keep = 0;
+    let mut chunk = vec![0u8; 4096];
+    while end > 0 {
+        let start = end.saturating_sub(chunk.len() as u64);
+        let size = (end - start) as usize;
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut chunk[..size])?;
+        if let Some(pos) = chunk[..size].iter().rposition(|b| *b == b'\n') {
+            keep = start + pos as u64 + 1;
+            break;
+        }
+        end = start;
+    }
+    file.set_len(keep)?;
+    Ok(())
+}
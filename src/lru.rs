@@ -0,0 +1,242 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Memory-bounded VecPack
+//!
+//! LruVecPack<T> knows all the member IDs of a VecPack directory,
+//! but keeps only the most recently used members deserialized in
+//! memory. Other members are loaded from disk on demand, and the
+//! least recently used ones are evicted, so huge datasets fit in
+//! constrained environments. Member files are the same as the ones
+//! of VecPack<T>, so the same directory can be opened by both.
+//!
+//! The members in memory are kept by a VecPack<T>, and every write
+//! goes through it, so the directory modes (single writer lock,
+//! append-only, audit, history, format, ...) and the hooks apply
+//! the same way. Order index and replication are saved from the
+//! members in memory, so such directories cannot be opened.
+
+use crate::backend::StorageBackend;
+use crate::{Pack, VecPack, VecPackMember};
+use crate::{PackError, PackHooks, PackResult};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// LruVecPack<T>
+/// VecPack<T> that keeps at most capacity members in memory.
+pub struct LruVecPack<T>
+where
+    T: VecPackMember,
+{
+    // Members in memory, writes go through it
+    inner: VecPack<T>,
+    capacity: usize,
+    // All member IDs with their file path
+    files: HashMap<String, PathBuf>,
+    // Cached IDs by their last use, least recently used first
+    recent: BTreeMap<u64, String>,
+    // Last use of the cached IDs
+    used: HashMap<String, u64>,
+    // Use counter, increased by every access
+    tick: u64,
+}
+
+impl<T> LruVecPack<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    /// Load or init LruVecPack by a given Path
+    /// Only collects the member IDs, members are loaded
+    /// on first access. Capacity must be at least 1.
+    pub fn load_or_init(
+        path: PathBuf,
+        capacity: usize,
+    ) -> PackResult<LruVecPack<T>> {
+        LruVecPack::open(VecPack::new(path)?, capacity)
+    }
+    /// Load or init LruVecPack through a storage backend
+    /// The same as load_or_init, but member files are listed,
+    /// loaded, saved and removed through the backend.
+    pub fn load_or_init_with_backend(
+        path: PathBuf,
+        capacity: usize,
+        backend: Arc<dyn StorageBackend>,
+    ) -> PackResult<LruVecPack<T>> {
+        let inner = VecPack::empty(path);
+        inner.ctx.set_backend(backend);
+        LruVecPack::open(inner, capacity)
+    }
+    // Collect the member IDs of an empty VecPack
+    fn open(mut inner: VecPack<T>, capacity: usize) -> PackResult<Self> {
+        if capacity == 0 {
+            return Err(PackError::InternalError(
+                "LruVecPack capacity must be at least 1".to_string(),
+            ));
+        }
+        inner.load_partial_modes()?;
+        let mut files = HashMap::new();
+        for file in inner.list_members()? {
+            if let Some(id) = file_id(&file) {
+                files.insert(id, file);
+            }
+        }
+        Ok(LruVecPack {
+            inner,
+            capacity,
+            files,
+            recent: BTreeMap::new(),
+            used: HashMap::new(),
+            tick: 0,
+        })
+    }
+    /// Insert a new T to LruVecPack<T>
+    /// Only if ID is not taken
+    pub fn insert(&mut self, item: T) -> PackResult<()> {
+        if self.files.contains_key(item.get_id()) {
+            return Err(PackError::IDTaken);
+        }
+        let id = item.get_id().to_string();
+        self.inner.insert(item)?;
+        let path = self.inner.find_id(&id)?.path.clone();
+        self.files.insert(id.clone(), path);
+        self.touch(&id);
+        self.evict(&id);
+        Ok(())
+    }
+    /// Find ID and returns &Pack<T>
+    /// Loads the member from disk if it is not in memory.
+    pub fn find_id(&mut self, id: &str) -> PackResult<&Pack<T>> {
+        self.load(id)?;
+        self.inner.find_id(id)
+    }
+    /// Find ID and returns &mut Pack<T>
+    /// Loads the member from disk if it is not in memory.
+    pub fn find_id_mut(&mut self, id: &str) -> PackResult<&mut Pack<T>> {
+        self.load(id)?;
+        self.inner.find_id_mut(id)
+    }
+    /// Remove member by ID
+    /// Deletes its file, then returns the removed data.
+    pub fn remove_by_id(&mut self, id: &str) -> PackResult<T> {
+        self.load(id)?;
+        let data = self.inner.remove_by_id(id)?;
+        self.files.remove(id);
+        self.untrack(id);
+        Ok(data)
+    }
+    // Make sure the member is in memory, and mark it
+    // as the most recently used one.
+    fn load(&mut self, id: &str) -> PackResult<()> {
+        if !self.is_cached(id) {
+            let path = match self.files.get(id) {
+                Some(path) => path.clone(),
+                None => return Err(PackError::ObjectNotFound),
+            };
+            let pack = self.inner.load_member(path)?;
+            self.inner.add_member(pack)?;
+            self.inner.save_migrated()?;
+        }
+        self.touch(id);
+        self.evict(id);
+        Ok(())
+    }
+    /// Set lifecycle hooks of the members
+    /// See VecPack::set_hooks.
+    pub fn set_hooks<H>(&mut self, hooks: H)
+    where
+        H: PackHooks<T> + Send + Sync + 'static,
+    {
+        self.inner.set_hooks(hooks);
+    }
+    /// Number of members, including the evicted ones
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+    /// Returns true if there is no member
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+    /// Returns true if ID is a member
+    pub fn contains_id(&self, id: &str) -> bool {
+        self.files.contains_key(id)
+    }
+    /// Member IDs in no particular order
+    pub fn ids(&self) -> Vec<&str> {
+        self.files.keys().map(|id| id.as_str()).collect()
+    }
+    /// Maximum number of members kept in memory
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    /// Number of members currently in memory
+    pub fn cached_len(&self) -> usize {
+        self.inner.len()
+    }
+    /// Returns true if the member is currently in memory
+    pub fn is_cached(&self, id: &str) -> bool {
+        self.inner.position(id).is_some()
+    }
+    /// Returns LruVecPack<T> &Path
+    pub fn get_path(&self) -> &Path {
+        self.inner.get_path()
+    }
+    // Mark ID as the most recently used one
+    fn touch(&mut self, id: &str) {
+        self.tick += 1;
+        if let Some(last) = self.used.insert(id.to_string(), self.tick) {
+            self.recent.remove(&last);
+        }
+        self.recent.insert(self.tick, id.to_string());
+    }
+    // Stop tracking the use of ID
+    fn untrack(&mut self, id: &str) {
+        if let Some(last) = self.used.remove(id) {
+            self.recent.remove(&last);
+        }
+    }
+    // Evict the least recently used members over capacity.
+    // The given ID is kept, as we are about to return it.
+    // Members are saved on every change, so eviction
+    // just drops them from memory.
+    fn evict(&mut self, keep: &str) {
+        while self.inner.len() > self.capacity {
+            let id = match self.recent.values().find(|id| *id != keep) {
+                Some(id) => id.clone(),
+                None => return,
+            };
+            self.untrack(&id);
+            self.inner.forget_member(&id);
+        }
+    }
+}
+
+// Member ID from its file name
+fn file_id(path: &Path) -> Option<String> {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_string())
+}
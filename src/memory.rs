@@ -0,0 +1,236 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Pack abstractions
+//!
+//! PackLike<T> and CollectionLike<T> cover the common Pack<T> and
+//! VecPack<T> operations, so application services can be written
+//! against the traits instead of the filesystem types, e.g.
+//!
+//! ```rust
+//! use storaget::*;
+//!
+//! fn increment(counter: &mut impl PackLike<i32>) -> PackResult<i32> {
+//!     counter.update(|i| {
+//!         *i += 1;
+//!         *i
+//!     })
+//! }
+//!
+//! let mut counter = MemoryPack::new(41);
+//! assert_eq!(increment(&mut counter).unwrap(), 42);
+//! ```
+//!
+//! MemoryPack<T> and MemoryVecPack<T> are the in-memory
+//! implementations; they never touch the filesystem.
+
+use crate::{Pack, PackError, PackResult, VecPack, VecPackMember};
+use serde::{Deserialize, Serialize};
+
+/// PackLike<T>
+/// Single data object storage, like Pack<T>
+pub trait PackLike<T> {
+    /// Reference to the inner data
+    fn unpack(&self) -> &T;
+    /// Update data through closure, then save it.
+    /// If save fails, data is rolled back.
+    fn update<F, R>(&mut self, f: F) -> PackResult<R>
+    where
+        F: FnMut(&mut T) -> R;
+    /// Save data manually
+    fn save(&self) -> PackResult<()>;
+}
+
+/// CollectionLike<T>
+/// Storage of members by ID, like VecPack<T>
+pub trait CollectionLike<T>
+where
+    T: VecPackMember,
+{
+    /// Insert a new member, only if ID is not taken
+    fn insert(&mut self, item: T) -> PackResult<()>;
+    /// Find member by ID
+    fn find_id(&self, id: &str) -> PackResult<&T>;
+    /// Update member by ID through closure, then save it
+    fn update_id<F, R>(&mut self, id: &str, f: F) -> PackResult<R>
+    where
+        F: FnMut(&mut T) -> R;
+    /// Remove member by ID, and return its data
+    fn remove_by_id(&mut self, id: &str) -> PackResult<T>;
+    /// Members in order
+    fn values(&self) -> Vec<&T>;
+    /// Number of members
+    fn len(&self) -> usize;
+    /// True if there is no member
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> PackLike<T> for Pack<T>
+where
+    for<'de> T: Serialize + Deserialize<'de> + Default + Sized + Clone,
+{
+    fn unpack(&self) -> &T {
+        Pack::unpack(self)
+    }
+    fn update<F, R>(&mut self, f: F) -> PackResult<R>
+    where
+        F: FnMut(&mut T) -> R,
+    {
+        Pack::update(self, f)
+    }
+    fn save(&self) -> PackResult<()> {
+        Pack::save(self)
+    }
+}
+
+impl<T> CollectionLike<T> for VecPack<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    fn insert(&mut self, item: T) -> PackResult<()> {
+        VecPack::insert(self, item)
+    }
+    fn find_id(&self, id: &str) -> PackResult<&T> {
+        VecPack::find_id(self, id).map(|pack| pack.unpack())
+    }
+    fn update_id<F, R>(&mut self, id: &str, f: F) -> PackResult<R>
+    where
+        F: FnMut(&mut T) -> R,
+    {
+        self.find_id_mut(id)?.update(f)
+    }
+    fn remove_by_id(&mut self, id: &str) -> PackResult<T> {
+        VecPack::remove_by_id(self, id)
+    }
+    fn values(&self) -> Vec<&T> {
+        self.iter_live().map(|pack| pack.unpack()).collect()
+    }
+    fn len(&self) -> usize {
+        self.iter_live().count()
+    }
+}
+
+/// MemoryPack<T>
+/// In-memory PackLike<T>, saving is a no-op.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryPack<T> {
+    data: T,
+}
+
+impl<T> MemoryPack<T> {
+    /// New MemoryPack<T> with the given data
+    pub fn new(data: T) -> Self {
+        MemoryPack { data }
+    }
+    /// Returns the inner data
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
+
+impl<T> PackLike<T> for MemoryPack<T> {
+    fn unpack(&self) -> &T {
+        &self.data
+    }
+    fn update<F, R>(&mut self, mut f: F) -> PackResult<R>
+    where
+        F: FnMut(&mut T) -> R,
+    {
+        Ok(f(&mut self.data))
+    }
+    fn save(&self) -> PackResult<()> {
+        Ok(())
+    }
+}
+
+/// MemoryVecPack<T>
+/// In-memory CollectionLike<T>, members are kept in insert order.
+#[derive(Debug, Clone)]
+pub struct MemoryVecPack<T> {
+    data: Vec<T>,
+}
+
+impl<T> Default for MemoryVecPack<T> {
+    fn default() -> Self {
+        MemoryVecPack { data: Vec::new() }
+    }
+}
+
+impl<T> MemoryVecPack<T>
+where
+    T: VecPackMember,
+{
+    /// New empty MemoryVecPack<T>
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Returns the members
+    pub fn into_inner(self) -> Vec<T> {
+        self.data
+    }
+}
+
+impl<T> CollectionLike<T> for MemoryVecPack<T>
+where
+    T: VecPackMember,
+{
+    fn insert(&mut self, item: T) -> PackResult<()> {
+        if self.data.iter().any(|i| i.get_id() == item.get_id()) {
+            return Err(PackError::IDTaken);
+        }
+        self.data.push(item);
+        Ok(())
+    }
+    fn find_id(&self, id: &str) -> PackResult<&T> {
+        self.data
+            .iter()
+            .find(|i| i.get_id() == id)
+            .ok_or(PackError::ObjectNotFound)
+    }
+    fn update_id<F, R>(&mut self, id: &str, f: F) -> PackResult<R>
+    where
+        F: FnMut(&mut T) -> R,
+    {
+        self.data
+            .iter_mut()
+            .find(|i| i.get_id() == id)
+            .map(f)
+            .ok_or(PackError::ObjectNotFound)
+    }
+    fn remove_by_id(&mut self, id: &str) -> PackResult<T> {
+        match self.data.iter().position(|i| i.get_id() == id) {
+            Some(p) => Ok(self.data.remove(p)),
+            None => Err(PackError::ObjectNotFound),
+        }
+    }
+    fn values(&self) -> Vec<&T> {
+        self.data.iter().collect()
+    }
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+}
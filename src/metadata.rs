@@ -0,0 +1,74 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Pack metadata
+//!
+//! Every save records when the file was created and last updated
+//! in its header, so the times survive copies, backups and
+//! backends without file times. Pack::metadata() reads them back.
+
+use crate::{Pack, PackError, PackResult};
+use serde::Serialize;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// PackMetadata
+/// Result of Pack::metadata()
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PackMetadata {
+    /// First save of the file
+    pub created_at: Option<SystemTime>,
+    /// Last save of the file
+    pub updated_at: Option<SystemTime>,
+}
+
+impl<T> Pack<T>
+where
+    T: Serialize + Sized,
+{
+    /// Created and last updated time of the Pack<T> file
+    /// Files saved before these were recorded in the header fall
+    /// back to the filesystem times, if the local filesystem is
+    /// used; otherwise they are None.
+    pub fn metadata(&self) -> PackResult<PackMetadata> {
+        let header = self.ctx.header(&self.path).unwrap_or_default();
+        let mut metadata = PackMetadata {
+            created_at: header.created.map(from_millis),
+            updated_at: header.updated.map(from_millis),
+        };
+        if metadata.updated_at.is_none() && self.ctx.backend().is_none() {
+            let path = self.ctx.resolve(&self.path);
+            let fs = std::fs::metadata(&path)
+                .map_err(|err| PackError::from(err).with_path(&path))?;
+            metadata.created_at = fs.created().or_else(|_| fs.modified()).ok();
+            metadata.updated_at = fs.modified().ok();
+        }
+        Ok(metadata)
+    }
+}
+
+// Milliseconds since UNIX EPOCH to SystemTime
+fn from_millis(millis: u128) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(millis as u64)
+}
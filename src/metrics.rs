@@ -0,0 +1,214 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Operational metrics
+//!
+//! Every Pack and VecPack counts its saves, loads and removals,
+//! the bytes written and read, the time spent, and keeps the
+//! slowest operations. op_stats() returns a copy of the counters,
+//! so applications can see what the storage layer costs.
+//! Members of a VecPack share its counters.
+//!
+//! Operations slower than the threshold set by
+//! set_slow_op_threshold() are logged as warnings (with the "log"
+//! or "tracing" feature), to spot failing disks and oversized
+//! packs early.
+
+use crate::{logging, Pack, VecPack, VecPackMember};
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+
+// Number of kept slowest operations
+const SLOWEST: usize = 10;
+
+/// Kind of a storage operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OpKind {
+    Save,
+    Load,
+    Remove,
+}
+
+impl fmt::Display for OpKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpKind::Save => write!(f, "save"),
+            OpKind::Load => write!(f, "load"),
+            OpKind::Remove => write!(f, "remove"),
+        }
+    }
+}
+
+/// A recorded slow operation
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlowOp {
+    pub kind: OpKind,
+    pub path: PathBuf,
+    pub duration: Duration,
+}
+
+/// OpStats
+/// Counters of the storage operations since open, or the
+/// last reset_op_stats()
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpStats {
+    pub saves: u64,
+    pub save_errors: u64,
+    pub loads: u64,
+    pub load_errors: u64,
+    pub removes: u64,
+    pub remove_errors: u64,
+    pub bytes_written: u64,
+    pub bytes_read: u64,
+    /// Total time of the saves
+    pub save_time: Duration,
+    /// Total time of the loads
+    pub load_time: Duration,
+    /// Operations slower than the slow operation threshold
+    pub slow_ops: u64,
+    /// Slowest operations, slowest first
+    pub slowest: Vec<SlowOp>,
+}
+
+// Counters kept in the shared context
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+    stats: Mutex<OpStats>,
+    // Slower operations are logged, if set
+    slow_threshold: RwLock<Option<Duration>>,
+}
+
+impl Metrics {
+    // Record a finished operation
+    // Bytes are only counted for successful ones.
+    pub(crate) fn record(
+        &self,
+        kind: OpKind,
+        path: &Path,
+        duration: Duration,
+        bytes: Option<u64>,
+    ) {
+        let slow = self
+            .slow_threshold
+            .read()
+            .unwrap()
+            .is_some_and(|threshold| duration >= threshold);
+        if slow {
+            logging::slow_operation(kind, path, duration);
+        }
+        let mut stats = self.stats.lock().unwrap();
+        if slow {
+            stats.slow_ops += 1;
+        }
+        let ok = bytes.is_some();
+        let bytes = bytes.unwrap_or(0);
+        match (kind, ok) {
+            (OpKind::Save, true) => {
+                stats.saves += 1;
+                stats.bytes_written += bytes;
+            }
+            (OpKind::Save, false) => stats.save_errors += 1,
+            (OpKind::Load, true) => {
+                stats.loads += 1;
+                stats.bytes_read += bytes;
+            }
+            (OpKind::Load, false) => stats.load_errors += 1,
+            (OpKind::Remove, true) => stats.removes += 1,
+            (OpKind::Remove, false) => stats.remove_errors += 1,
+        }
+        match kind {
+            OpKind::Save => stats.save_time += duration,
+            OpKind::Load => stats.load_time += duration,
+            OpKind::Remove => (),
+        }
+        let slowest = &mut stats.slowest;
+        if slowest.len() < SLOWEST
+            || slowest.last().is_some_and(|op| op.duration < duration)
+        {
+            let op = SlowOp {
+                kind,
+                path: path.to_path_buf(),
+                duration,
+            };
+            let pos = slowest.partition_point(|o| o.duration >= duration);
+            slowest.insert(pos, op);
+            slowest.truncate(SLOWEST);
+        }
+    }
+    pub(crate) fn snapshot(&self) -> OpStats {
+        self.stats.lock().unwrap().clone()
+    }
+    pub(crate) fn reset(&self) {
+        *self.stats.lock().unwrap() = OpStats::default();
+    }
+    pub(crate) fn set_slow_threshold(&self, threshold: Option<Duration>) {
+        *self.slow_threshold.write().unwrap() = threshold;
+    }
+}
+
+// Size of a loaded file, 0 if it is not on the local filesystem
+pub(crate) fn file_len(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+impl<T> Pack<T>
+where
+    T: Serialize + Sized,
+{
+    /// Operational counters of the Pack<T>
+    /// A VecPack member returns the counters of its VecPack.
+    pub fn op_stats(&self) -> OpStats {
+        self.ctx.metrics.snapshot()
+    }
+    /// Log saves and loads slower than threshold as warnings
+    /// None turns it off. A VecPack member shares the threshold
+    /// of its VecPack.
+    pub fn set_slow_op_threshold(&self, threshold: Option<Duration>) {
+        self.ctx.metrics.set_slow_threshold(threshold);
+    }
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Operational counters of the VecPack<T> and its members
+    pub fn op_stats(&self) -> OpStats {
+        self.ctx.metrics.snapshot()
+    }
+    /// Reset the operational counters
+    pub fn reset_op_stats(&self) {
+        self.ctx.metrics.reset()
+    }
+    /// Log operations slower than threshold as warnings,
+    /// e.g. 50ms; None turns it off
+    /// Applies to the VecPack<T> and its members.
+    pub fn set_slow_op_threshold(&self, threshold: Option<Duration>) {
+        self.ctx.metrics.set_slow_threshold(threshold);
+    }
+}
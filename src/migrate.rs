@@ -0,0 +1,140 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Schema migration
+//!
+//! Data types implementing Migratable have a schema version, and
+//! a list of migration functions. Once registered with
+//! register_migrations::<T>(), the schema version is stored in
+//! the file header of every saved T (see header.rs), and
+//! Pack::load_from_path migrates older files step by step
+//! (v1 -> v2 -> ..) to the current version, then saves them back.
+//! Types without registered migrations are version 1.
+
+use crate::{PackError, PackResult};
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+/// Migration
+/// Rewrites the raw YAML value of a stored T
+/// from one schema version to the next one.
+pub type Migration = fn(&mut serde_yaml::Value);
+
+/// Migratable
+/// Schema version and migrations of a data type
+pub trait Migratable {
+    /// Current schema version, starts from 1
+    const SCHEMA_VERSION: u32;
+    /// Migrations in order. The first one migrates
+    /// version 1 to version 2, the second one version 2
+    /// to version 3, etc. So SCHEMA_VERSION - 1 migrations
+    /// are required.
+    fn migrations() -> Vec<Migration>;
+}
+
+struct Schema {
+    version: u32,
+    migrations: Vec<Migration>,
+}
+
+// Registered schemas by type name
+fn registry() -> &'static RwLock<HashMap<&'static str, Schema>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<&'static str, Schema>>> =
+        OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+// Type name of T without references, as data is
+// usually saved by reference.
+fn type_key<T: ?Sized>() -> &'static str {
+    let mut name = std::any::type_name::<T>();
+    while let Some(rest) = name.strip_prefix('&') {
+        name = rest.trim_start_matches("mut ");
+    }
+    name
+}
+
+/// Register the migrations of T
+/// Should be called once at startup, before any T is loaded.
+/// Returns PackError::InternalError if the number of migrations
+/// does not match T::SCHEMA_VERSION.
+pub fn register_migrations<T: Migratable>() -> PackResult<()> {
+    let migrations = T::migrations();
+    if T::SCHEMA_VERSION == 0
+        || migrations.len() as u32 != T::SCHEMA_VERSION - 1
+    {
+        return Err(PackError::InternalError(format!(
+            "Schema version {} of {} requires {} migrations, {} given",
+            T::SCHEMA_VERSION,
+            type_key::<T>(),
+            T::SCHEMA_VERSION.saturating_sub(1),
+            migrations.len()
+        )));
+    }
+    registry().write().unwrap().insert(
+        type_key::<T>(),
+        Schema {
+            version: T::SCHEMA_VERSION,
+            migrations,
+        },
+    );
+    Ok(())
+}
+
+// Current schema version of T
+// Types without registered migrations are version 1.
+pub(crate) fn schema_version<T: ?Sized>() -> u32 {
+    registry()
+        .read()
+        .unwrap()
+        .get(type_key::<T>())
+        .map(|schema| schema.version)
+        .unwrap_or(1)
+}
+
+// Migrate a stored T value from the given schema version
+// to the current one, step by step.
+pub(crate) fn migrate<T: ?Sized>(
+    version: u32,
+    value: &mut serde_yaml::Value,
+) -> PackResult<()> {
+    let registry = registry().read().unwrap();
+    let current = registry.get(type_key::<T>());
+    let latest = current.map(|schema| schema.version).unwrap_or(1);
+    if version == 0 || version > latest {
+        return Err(PackError::custom_deserialize(format!(
+            "Unknown schema version {} of {}, the current one is {}",
+            version,
+            type_key::<T>(),
+            latest
+        )));
+    }
+    if let Some(schema) = current {
+        for migration in &schema.migrations[(version - 1) as usize..] {
+            migration(value);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,94 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Memory-mapped loading
+//!
+//! load_mmap() maps the member file into memory and deserializes
+//! straight from the mapping. It is meant for packs stored in the
+//! binary format=msgpack (see VecPack::convert_format): their body
+//! is decoded in place, without copying the file into a buffer
+//! first, so a very large pack is held in memory once (as T)
+//! rather than twice while loading. Text formats are read through
+//! the mapping, but their parsers buffer the content anyway.
+//!
+//! Mapping a file is only sound while nobody modifies it in place.
+//! storaget never does: every save writes a temp file and renames
+//! it over the target, which leaves the mapped file untouched.
+//! Do not map files that other programs edit in place.
+
+use crate::{
+    header, telemetry, Pack, PackError, PackResult, VecPack, VecPackMember,
+};
+use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+
+impl<T> Pack<T>
+where
+    for<'de> T: Serialize + Deserialize<'de> + Sized + Clone,
+{
+    /// Load Pack<T> from Path through a memory map
+    /// The same as load_from_path, without copying the file
+    /// content into memory first.
+    pub fn load_mmap(path: PathBuf) -> PackResult<Pack<T>> {
+        let pack = Pack::<T>::read_mmap(path)?;
+        pack.save_migrated()?;
+        Ok(pack)
+    }
+    // The same as load_mmap, without saving back migrated data
+    pub(crate) fn read_mmap(path: PathBuf) -> PackResult<Pack<T>> {
+        let file_path = path.clone();
+        telemetry::span("storaget.load", &file_path, || {
+            let file = File::open(&path).map_err(|err| match err.kind() {
+                io::ErrorKind::NotFound => PackError::PathNotFound,
+                _ => err.into(),
+            })?;
+            // Empty files cannot be mapped
+            if file.metadata()?.len() == 0 {
+                return Pack::<T>::decode_reader(path, io::empty());
+            }
+            // Safety: storaget replaces files by rename and never
+            // writes them in place, see the module docs.
+            let map = unsafe { Mmap::map(&file)? };
+            Pack::<T>::decoded(path, header::decode_slice::<T>(&map)?)
+        })
+        .and_then(|pack| pack.load_history().map(|_| pack))
+        .map_err(|err| err.with_path(&file_path))
+    }
+}
+
+impl<T> VecPack<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    /// Load or init VecPack, memory mapping the member files
+    /// The same as load_or_init, but every member is loaded
+    /// by Pack::load_mmap.
+    pub fn load_or_init_mmap(path: PathBuf) -> PackResult<VecPack<T>> {
+        VecPack::load_dir(path, Pack::<T>::read_mmap, |_, err| Err(err))
+    }
+}
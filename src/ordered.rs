@@ -0,0 +1,164 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Ordered VecPack
+//!
+//! OrderedVecPack<T> keeps its members ordered by ID, and
+//! supports range queries, e.g. the invoices of a month or the
+//! log days of a week. IDs are compared as strings, so numeric
+//! and date IDs should be zero-padded (2020-01-05, 000123) to
+//! sort naturally. Member files are the same as the ones of
+//! VecPack<T>, so the same directory can be opened by both.
+//!
+//! ```rust,ignore
+//! let days: OrderedVecPack<LogDay> =
+//!     OrderedVecPack::load_or_init(PathBuf::from("data/days"))?;
+//! for day in days.range("2020-01-01".."2020-01-08") {
+//!     println!("{}: {}", day.get_id(), day.entries.len());
+//! }
+//! ```
+
+use crate::{Pack, PackError, PackResult, VecPack, VecPackMember};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::ops::{Deref, RangeBounds};
+use std::path::PathBuf;
+
+/// OrderedVecPack<T>
+/// VecPack<T> ordered by member ID.
+/// Derefs to the wrapped VecPack<T> for read access; mutation
+/// goes through OrderedVecPack, so the order stays in sync.
+pub struct OrderedVecPack<T>
+where
+    T: VecPackMember,
+{
+    inner: VecPack<T>,
+    // Member IDs in order
+    ids: BTreeSet<String>,
+}
+
+impl<T> OrderedVecPack<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    /// Load or init OrderedVecPack by a given Path
+    pub fn load_or_init(path: PathBuf) -> PackResult<OrderedVecPack<T>> {
+        Ok(OrderedVecPack::from_vecpack(VecPack::load_or_init(path)?))
+    }
+    /// Order an already opened VecPack<T>
+    pub fn from_vecpack(inner: VecPack<T>) -> OrderedVecPack<T> {
+        let ids = inner.ids().map(|id| id.to_string()).collect();
+        OrderedVecPack { inner, ids }
+    }
+    /// Insert a new T
+    /// Only if ID is not taken
+    pub fn insert(&mut self, item: T) -> PackResult<()> {
+        let id = item.get_id().to_string();
+        self.inner.insert(item)?;
+        self.ids.insert(id);
+        Ok(())
+    }
+    /// Remove member by ID
+    /// Deletes its file, then returns the removed data.
+    pub fn remove_by_id(&mut self, id: &str) -> PackResult<T> {
+        let data = self.inner.remove_by_id(id)?;
+        self.ids.remove(id);
+        Ok(data)
+    }
+    /// Update member by ID
+    /// As Pack::update, but f must not change the ID, otherwise
+    /// the member is rolled back and PackError::InternalError
+    /// is returned. Use change_id to rename a member.
+    pub fn update<F, R>(&mut self, id: &str, f: F) -> PackResult<R>
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        self.inner.find_id_mut(id)?.try_update(|data| {
+            let res = f(data);
+            match data.get_id() == id {
+                true => Ok(res),
+                false => Err(PackError::InternalError(format!(
+                    "Update changed the ID of {} to {}",
+                    id,
+                    data.get_id()
+                ))),
+            }
+        })
+    }
+    /// Change the ID of a member
+    /// See VecPack::change_id
+    pub fn change_id(&mut self, old: &str, new: &str) -> PackResult<()> {
+        self.inner.change_id(old, new)?;
+        self.ids.remove(old);
+        self.ids.insert(new.to_string());
+        Ok(())
+    }
+}
+
+impl<T> OrderedVecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Members with ID in range, in ID order
+    /// e.g. range("2020-01".."2020-02") or range("0100"..)
+    pub fn range<'a, R>(&self, range: R) -> impl Iterator<Item = &Pack<T>>
+    where
+        R: RangeBounds<&'a str>,
+    {
+        let bounds = (range.start_bound().cloned(), range.end_bound().cloned());
+        self.ids
+            .range::<str, _>(bounds)
+            .filter_map(move |id| self.inner.pack_by_id(id))
+    }
+    /// Members in ID order
+    pub fn iter_ordered(&self) -> impl Iterator<Item = &Pack<T>> {
+        self.range(..)
+    }
+    /// Member with the smallest ID
+    pub fn first_by_id(&self) -> Option<&Pack<T>> {
+        self.iter_ordered().next()
+    }
+    /// Member with the largest ID
+    pub fn last_by_id(&self) -> Option<&Pack<T>> {
+        self.ids
+            .iter()
+            .next_back()
+            .and_then(|id| self.inner.pack_by_id(id))
+    }
+    /// Returns the wrapped VecPack<T>
+    pub fn into_inner(self) -> VecPack<T> {
+        self.inner
+    }
+}
+
+impl<T> Deref for OrderedVecPack<T>
+where
+    T: VecPackMember,
+{
+    type Target = VecPack<T>;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
@@ -0,0 +1,95 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Parallel iteration
+//!
+//! With the rayon feature, VecPack<T> members can be processed on
+//! all cores. par_iter() borrows the members immutably; mutation
+//! goes through a BatchGuard, so the changed members are saved
+//! once, at commit, and not from the worker threads.
+//! par_insert_many() encodes and writes new member files on all
+//! cores.
+//!
+//! ```rust,ignore
+//! use rayon::prelude::*;
+//!
+//! let total: u64 = cars.par_iter().map(|car| car.hp as u64).sum();
+//! let mut batch = cars.batch_mut()?;
+//! batch.par_iter_mut().for_each(|mut car| car.score = score(&car));
+//! let saved = batch.commit()?;
+//! ```
+
+use crate::{
+    BatchGuard, BatchMember, Pack, PackError, PackResult, VecPack,
+    VecPackMember,
+};
+use rayon::prelude::*;
+use serde::Deserialize;
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember + Sync,
+{
+    /// Parallel iterator over the members
+    pub fn par_iter(&self) -> impl IndexedParallelIterator<Item = &Pack<T>> {
+        self.data.par_iter()
+    }
+}
+
+impl<T> VecPack<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default + Send + Sync,
+{
+    /// Insert many T, writing the files on all cores
+    /// The same as insert_many: the IDs are checked first, members
+    /// are added in the given order, and the failed ones are
+    /// returned with their error.
+    pub fn par_insert_many(
+        &mut self,
+        items: Vec<T>,
+    ) -> PackResult<Vec<(String, PackError)>> {
+        self.insert_many_with(items, |packs| {
+            packs.into_par_iter().map(|p| p.write_new()).collect()
+        })
+    }
+}
+
+impl<'a, T> BatchGuard<'a, T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default + Send + Sync,
+{
+    /// Parallel mutable iterator over all the members
+    /// The members are saved by commit() or at drop, as with
+    /// iter_mut(); only the ones accessed mutably are written.
+    pub fn par_iter_mut(
+        &mut self,
+    ) -> impl IndexedParallelIterator<Item = BatchMember<'_, T>> {
+        let (members, backups) = self.members_mut();
+        members
+            .par_iter_mut()
+            .zip(backups.par_iter_mut())
+            .map(|(pack, backup)| BatchMember::new(&mut pack.data, backup))
+    }
+}
@@ -0,0 +1,235 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Polymorphic collections
+//!
+//! Heterogeneous records can be stored in a VecPack as a serde
+//! internally tagged enum, e.g.
+//!
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//! use storaget::*;
+//!
+//! #[derive(Serialize, Deserialize, Clone)]
+//! #[serde(tag = "kind")]
+//! enum Vehicle {
+//!     Car { id: String, seats: u32 },
+//!     Bike { id: String },
+//! }
+//!
+//! impl Default for Vehicle {
+//!     fn default() -> Self {
+//!         Vehicle::Bike { id: String::new() }
+//!     }
+//! }
+//!
+//! impl VecPackMember for Vehicle {
+//!     fn get_id(&self) -> &str {
+//!         match self {
+//!             Vehicle::Car { id, .. } => id,
+//!             Vehicle::Bike { id } => id,
+//!         }
+//!     }
+//! }
+//!
+//! impl PolyMember for Vehicle {
+//!     fn variant(&self) -> &str {
+//!         match self {
+//!             Vehicle::Car { .. } => "Car",
+//!             Vehicle::Bike { .. } => "Bike",
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! PolyVecPack<T> then adds variant aware loading and queries.
+
+use crate::header::{self, FileHeader, Stamp};
+use crate::{
+    member_files, Pack, PackError, PackResult, VecPack, VecPackMember,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+
+/// This trait defines the requirements
+/// to be a member of a PolyVecPack<T>
+pub trait PolyMember: VecPackMember {
+    /// Variant name
+    /// Should be the same as the serde tag value.
+    fn variant(&self) -> &str;
+}
+
+/// Variant migration
+/// Rewrites the raw YAML mapping of a stored variant
+/// before it is deserialized.
+pub type VariantMigration = fn(&mut serde_yaml::Mapping);
+
+/// PolyMigrations
+/// Per variant migrations applied while loading a PolyVecPack.
+/// Migrations run on every load, so they must be idempotent,
+/// e.g. rename a field only if the old one is present.
+pub struct PolyMigrations {
+    tag: String,
+    migrations: HashMap<String, Vec<VariantMigration>>,
+}
+
+impl PolyMigrations {
+    /// New PolyMigrations
+    /// Requires the serde tag field name.
+    pub fn new(tag: &str) -> Self {
+        PolyMigrations {
+            tag: tag.to_string(),
+            migrations: HashMap::new(),
+        }
+    }
+    /// Add migration to a variant
+    /// Migrations of the same variant run in the order they were added.
+    pub fn add(mut self, variant: &str, migration: VariantMigration) -> Self {
+        self.migrations
+            .entry(variant.to_string())
+            .or_default()
+            .push(migration);
+        self
+    }
+    // Apply variant migrations to a raw value
+    // Returns true if the value has changed.
+    fn apply(&self, value: &mut serde_yaml::Value) -> bool {
+        let mapping = match value.as_mapping_mut() {
+            Some(mapping) => mapping,
+            None => return false,
+        };
+        let tag = serde_yaml::Value::String(self.tag.clone());
+        let variant = match mapping.get(&tag).and_then(|v| v.as_str()) {
+            Some(variant) => variant.to_string(),
+            None => return false,
+        };
+        let before = mapping.clone();
+        if let Some(migrations) = self.migrations.get(&variant) {
+            for migration in migrations {
+                migration(mapping);
+            }
+        }
+        *mapping != before
+    }
+}
+
+/// PolyVecPack<T>
+/// VecPack<T> of an enum tagged T. Derefs to VecPack<T>, so
+/// every VecPack API is available, plus queries by variant.
+pub struct PolyVecPack<T>
+where
+    T: PolyMember,
+{
+    inner: VecPack<T>,
+}
+
+impl<T> PolyVecPack<T>
+where
+    for<'de> T: PolyMember + Deserialize<'de> + Default,
+{
+    /// Load or init PolyVecPack by a given Path
+    /// The same as VecPack::load_or_init
+    pub fn load_or_init(path: PathBuf) -> PackResult<PolyVecPack<T>> {
+        Ok(PolyVecPack {
+            inner: VecPack::load_or_init(path)?,
+        })
+    }
+    /// Load or init PolyVecPack by a given Path, and apply
+    /// per variant migrations to every stored file.
+    /// Migrated files are saved back.
+    pub fn load_or_init_with_migrations(
+        path: PathBuf,
+        migrations: &PolyMigrations,
+    ) -> PackResult<PolyVecPack<T>> {
+        let mut inner: VecPack<T> = VecPack::new(path.clone())?;
+        for file in member_files(&path)? {
+            let bytes = std::fs::read(&file)?;
+            let mut value = header::decode_value(&bytes)?;
+            let migrated = migrations.apply(&mut value);
+            let data: T = serde_yaml::from_value(value)
+                .map_err(PackError::deserialize)?;
+            let pack = Pack {
+                data,
+                path: file,
+                ctx: Default::default(),
+                hooks: Default::default(),
+                stamp: Stamp::of(
+                    &FileHeader::parse_bytes(&bytes)?.unwrap_or_default(),
+                ),
+            };
+            if migrated {
+                pack.save()?;
+            }
+            inner.insert_pack(pack)?;
+        }
+        Ok(PolyVecPack { inner })
+    }
+}
+
+impl<T> PolyVecPack<T>
+where
+    T: PolyMember,
+{
+    /// Members of a given variant
+    pub fn of_variant(&self, variant: &str) -> Vec<&Pack<T>> {
+        self.inner
+            .iter()
+            .filter(|i| i.variant() == variant)
+            .collect()
+    }
+    /// Number of members per variant
+    pub fn count_by_variant(&self) -> HashMap<String, usize> {
+        let mut result = HashMap::new();
+        for i in self.inner.iter() {
+            *result.entry(i.variant().to_string()).or_insert(0) += 1;
+        }
+        result
+    }
+    /// Returns the inner VecPack<T>
+    pub fn into_inner(self) -> VecPack<T> {
+        self.inner
+    }
+}
+
+impl<T> Deref for PolyVecPack<T>
+where
+    T: PolyMember,
+{
+    type Target = VecPack<T>;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for PolyVecPack<T>
+where
+    T: PolyMember,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
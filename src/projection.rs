@@ -0,0 +1,154 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Event sourcing
+//!
+//! Projection<E, S> keeps a state S built from the events E of
+//! a LogPack. Events are appended to the log and applied to the
+//! state; the state is saved into a snapshot Pack, manually or
+//! after every n events, so opening a projection only replays
+//! the events newer than the snapshot. compact() drops the events
+//! included in the snapshot from the log.
+//!
+//! ```rust,ignore
+//! let mut account = Projection::open(path, |balance: &mut i64, e: &Tx| {
+//!     *balance += e.amount
+//! })?;
+//! account.snapshot_every(100);
+//! account.append(Tx { amount: 42 })?;
+//! assert_eq!(*account.state(), 42);
+//! ```
+
+use crate::{LogPack, Pack, PackError, PackResult};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+// Applies an event to the state
+type Apply<E, S> = Box<dyn Fn(&mut S, &E) + Send + Sync>;
+
+// Saved state, and the number of events it includes
+#[derive(Serialize, Deserialize, Default)]
+struct Snapshot<S> {
+    events: u64,
+    state: S,
+}
+
+/// Projection<E, S>
+/// State S folded from the events E of an append-only log
+/// Files in its directory: events.log and snapshot.yml
+pub struct Projection<E, S>
+where
+    S: Serialize,
+{
+    log: LogPack<E>,
+    snapshot: Pack<Snapshot<S>>,
+    apply: Apply<E, S>,
+    // Number of events, including the ones in the snapshot
+    events: u64,
+    // Take a snapshot after this many new events
+    snapshot_every: Option<u64>,
+}
+
+impl<E, S> Projection<E, S>
+where
+    E: Serialize + DeserializeOwned,
+    S: Serialize + DeserializeOwned + Default,
+{
+    /// Open or create the projection in dir
+    /// Loads the snapshot, then applies the newer events of the log.
+    pub fn open<F>(dir: PathBuf, apply: F) -> PackResult<Projection<E, S>>
+    where
+        F: Fn(&mut S, &E) + Send + Sync + 'static,
+    {
+        let log = LogPack::open(dir.join("events.log"))?;
+        let mut snapshot: Pack<Snapshot<S>> =
+            Pack::load_or_init(dir, "snapshot")?;
+        let mut events = snapshot.events;
+        // Events before base are dropped from the log,
+        // they must be in the snapshot.
+        let base = log.base()?;
+        if base > events {
+            return Err(PackError::IntegrityError(format!(
+                "Log starts at event {}, but the snapshot has only {}",
+                base, events
+            )));
+        }
+        let mut state = std::mem::take(&mut snapshot.data.state);
+        for event in log.iter()?.skip((events - base) as usize) {
+            apply(&mut state, &event?);
+            events += 1;
+        }
+        snapshot.data.state = state;
+        Ok(Projection {
+            log,
+            snapshot,
+            apply: Box::new(apply),
+            events,
+            snapshot_every: None,
+        })
+    }
+    /// Append an event to the log, then apply it to the state
+    /// The event is applied only if it was appended.
+    pub fn append(&mut self, event: E) -> PackResult<()> {
+        self.log.append(&event)?;
+        (self.apply)(&mut self.snapshot.data.state, &event);
+        self.events += 1;
+        if let Some(every) = self.snapshot_every {
+            if self.events - self.snapshot.events >= every {
+                self.snapshot()?;
+            }
+        }
+        Ok(())
+    }
+    /// Current state
+    pub fn state(&self) -> &S {
+        &self.snapshot.state
+    }
+    /// Number of events, including the ones in the snapshot
+    pub fn events(&self) -> u64 {
+        self.events
+    }
+    /// Save the current state into the snapshot
+    pub fn snapshot(&mut self) -> PackResult<()> {
+        let events = self.events;
+        self.snapshot.data.events = events;
+        self.snapshot.save()
+    }
+    /// Save a snapshot, then drop its events from the log
+    /// Returns the number of dropped events.
+    pub fn compact(&mut self) -> PackResult<usize> {
+        self.snapshot()?;
+        self.log.compact_before(self.events)
+    }
+    /// Take a snapshot after every n new events
+    pub fn snapshot_every(&mut self, n: u64) {
+        self.snapshot_every = Some(n.max(1));
+    }
+    /// The event log
+    pub fn log(&self) -> &LogPack<E> {
+        &self.log
+    }
+}
@@ -0,0 +1,166 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Prometheus exporter
+//!
+//! Renders the operational counters (see op_stats()) in the
+//! Prometheus text exposition format, to be served e.g. on the
+//! /metrics endpoint of the application. Every sample has a
+//! collection label; render() puts more collections into one
+//! exposition, with every metric family listed once.
+//!
+//! ```rust,ignore
+//! let body = storaget::prometheus::render(&[
+//!     ("cars", &cars.op_stats()),
+//!     ("users", &users.op_stats()),
+//! ]);
+//! ```
+
+use crate::metrics::{OpKind, OpStats};
+use crate::{VecPack, VecPackMember};
+use std::fmt::Write;
+
+// Metric families: name, type, help, value of the sample
+type Family = (
+    &'static str,
+    &'static str,
+    &'static str,
+    fn(&OpStats) -> f64,
+);
+
+const FAMILIES: [Family; 10] = [
+    ("storaget_saves_total", "counter", "Successful saves", |s| {
+        s.saves as f64
+    }),
+    (
+        "storaget_save_errors_total",
+        "counter",
+        "Failed saves",
+        |s| s.save_errors as f64,
+    ),
+    ("storaget_loads_total", "counter", "Successful loads", |s| {
+        s.loads as f64
+    }),
+    (
+        "storaget_load_errors_total",
+        "counter",
+        "Failed loads",
+        |s| s.load_errors as f64,
+    ),
+    (
+        "storaget_removes_total",
+        "counter",
+        "Successful removals",
+        |s| s.removes as f64,
+    ),
+    (
+        "storaget_remove_errors_total",
+        "counter",
+        "Failed removals",
+        |s| s.remove_errors as f64,
+    ),
+    (
+        "storaget_written_bytes_total",
+        "counter",
+        "Bytes saved",
+        |s| s.bytes_written as f64,
+    ),
+    (
+        "storaget_read_bytes_total",
+        "counter",
+        "Bytes loaded",
+        |s| s.bytes_read as f64,
+    ),
+    (
+        "storaget_save_seconds_total",
+        "counter",
+        "Time spent saving",
+        |s| s.save_time.as_secs_f64(),
+    ),
+    (
+        "storaget_load_seconds_total",
+        "counter",
+        "Time spent loading",
+        |s| s.load_time.as_secs_f64(),
+    ),
+];
+
+/// Render the counters of collections in Prometheus text format
+/// Collections are given as (name, counters) pairs.
+pub fn render(collections: &[(&str, &OpStats)]) -> String {
+    let mut out = String::new();
+    for (name, kind, help, value) in FAMILIES.iter() {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        for (collection, stats) in collections {
+            let _ = writeln!(
+                out,
+                "{}{{collection=\"{}\"}} {}",
+                name,
+                escape(collection),
+                value(stats)
+            );
+        }
+    }
+    let name = "storaget_slowest_operation_seconds";
+    let _ = writeln!(out, "# HELP {} Slowest recent operation", name);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    for (collection, stats) in collections {
+        for kind in [OpKind::Save, OpKind::Load, OpKind::Remove] {
+            // Slowest first, so the first of a kind is the slowest
+            if let Some(op) = stats.slowest.iter().find(|op| op.kind == kind) {
+                let _ = writeln!(
+                    out,
+                    "{}{{collection=\"{}\",op=\"{}\"}} {}",
+                    name,
+                    escape(collection),
+                    kind,
+                    op.duration.as_secs_f64()
+                );
+            }
+        }
+    }
+    out
+}
+
+// Escape a label value
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Counters of the VecPack<T> in Prometheus text format
+    /// The collection label is the directory path.
+    pub fn prometheus_metrics(&self) -> String {
+        let collection = self.path.display().to_string();
+        render(&[(&collection, &self.op_stats())])
+    }
+}
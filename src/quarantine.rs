@@ -0,0 +1,107 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Quarantine
+//!
+//! load_or_init() stops at the first file it cannot load. The
+//! loaders here load the rest instead. load_or_init_lossy()
+//! leaves the unreadable files in place, and reports them with
+//! their error. load_or_init_quarantine() moves every
+//! unreadable member file is moved into the corrupt/ sub folder
+//! of the VecPack, where it can be inspected and repaired, then
+//! moved back. A file name already in quarantine gets the time
+//! of the move appended, so nothing is overwritten.
+
+use crate::{unix_millis, Pack, PackError, PackResult, VecPack, VecPackMember};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Name of the quarantine folder in the VecPack directory
+pub const QUARANTINE_DIR: &str = "corrupt";
+
+/// LoadFailure
+/// A member file load_or_init_lossy() could not load
+#[derive(Debug)]
+pub struct LoadFailure {
+    pub path: PathBuf,
+    pub error: PackError,
+}
+
+// Move a file into the quarantine folder of dir
+// Returns its new path.
+fn quarantine(dir: &Path, file: &Path) -> PackResult<PathBuf> {
+    let folder = dir.join(QUARANTINE_DIR);
+    std::fs::create_dir_all(&folder)?;
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    let mut target = folder.join(name.as_ref());
+    if target.exists() {
+        let millis = unix_millis(SystemTime::now());
+        target = folder.join(format!("{}.{}", name, millis));
+    }
+    std::fs::rename(file, &target)?;
+    Ok(target)
+}
+
+impl<T> VecPack<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    /// Load or init VecPack, skipping unreadable files
+    /// The same as load_or_init, but member files that cannot be
+    /// loaded are skipped and reported, so the rest is available
+    /// while they are repaired. The files are not modified.
+    pub fn load_or_init_lossy(
+        path: PathBuf,
+    ) -> PackResult<(VecPack<T>, Vec<LoadFailure>)> {
+        let mut failures = Vec::new();
+        let result =
+            VecPack::load_dir(path, Pack::read_from_path, |file, err| {
+                failures.push(LoadFailure {
+                    path: file.to_path_buf(),
+                    error: err,
+                });
+                Ok(())
+            })?;
+        Ok((result, failures))
+    }
+    /// Load or init VecPack, quarantining unreadable files
+    /// The same as load_or_init, but member files that cannot be
+    /// loaded are moved into the corrupt/ folder, and the rest is
+    /// loaded. Returns the VecPack and the new paths of the
+    /// quarantined files.
+    pub fn load_or_init_quarantine(
+        path: PathBuf,
+    ) -> PackResult<(VecPack<T>, Vec<PathBuf>)> {
+        let mut quarantined = Vec::new();
+        let dir = path.clone();
+        let result =
+            VecPack::load_dir(path, Pack::read_from_path, |file, _| {
+                quarantined.push(quarantine(&dir, file)?);
+                Ok(())
+            })?;
+        Ok((result, quarantined))
+    }
+}
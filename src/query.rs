@@ -0,0 +1,157 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Query builder
+//!
+//! Small query DSL over VecPack<T>, e.g.
+//!
+//! ```rust,ignore
+//! let fast_cars = cars
+//!     .query()
+//!     .filter(|c| c.hp > 200)
+//!     .sort_by(|a, b| b.hp.cmp(&a.hp))
+//!     .limit(10)
+//!     .collect()?;
+//! ```
+
+use crate::{Pack, PackResult, VecPack, VecPackMember};
+use std::cmp::Ordering;
+
+type Filter<'a, T> = Box<dyn Fn(&T) -> bool + 'a>;
+type Compare<'a, T> = Box<dyn FnMut(&T, &T) -> Ordering + 'a>;
+
+/// Query<'a, T>
+/// Built by VecPack::query(), and evaluated by collect().
+/// Filters are applied first, then sort, offset and limit.
+pub struct Query<'a, T>
+where
+    T: VecPackMember,
+{
+    vecpack: &'a VecPack<T>,
+    index: Option<(String, String)>,
+    filters: Vec<Filter<'a, T>>,
+    sort: Option<Compare<'a, T>>,
+    offset: usize,
+    limit: Option<usize>,
+}
+
+impl<'a, T> Query<'a, T>
+where
+    T: VecPackMember,
+{
+    /// Narrow candidates by a secondary index
+    /// Only members whose index key equals value are considered,
+    /// without scanning the whole VecPack.
+    pub fn index(mut self, name: &str, value: &str) -> Self {
+        self.index = Some((name.to_string(), value.to_string()));
+        self
+    }
+    /// Keep members matching the predicate
+    /// More filters can be added, all of them must match.
+    pub fn filter<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&T) -> bool + 'a,
+    {
+        self.filters.push(Box::new(predicate));
+        self
+    }
+    /// Sort result by the compare function
+    pub fn sort_by<F>(mut self, compare: F) -> Self
+    where
+        F: FnMut(&T, &T) -> Ordering + 'a,
+    {
+        self.sort = Some(Box::new(compare));
+        self
+    }
+    /// Skip the first n results
+    pub fn offset(mut self, n: usize) -> Self {
+        self.offset = n;
+        self
+    }
+    /// Return at most n results
+    pub fn limit(mut self, n: usize) -> Self {
+        self.limit = Some(n);
+        self
+    }
+    /// Evaluate query
+    /// Returns PackError if the given index does not exist.
+    pub fn collect(self) -> PackResult<Vec<&'a Pack<T>>> {
+        let Query {
+            vecpack,
+            index,
+            filters,
+            sort,
+            offset,
+            limit,
+        } = self;
+        let candidates: Vec<&'a Pack<T>> = match index {
+            Some((name, value)) => vecpack
+                .index_lookup(&name, &value)?
+                .iter()
+                .filter(|id| !vecpack.is_expired(id))
+                .filter_map(|id| vecpack.pack_by_id(id))
+                .collect(),
+            None => vecpack.iter_live().collect(),
+        };
+        let mut result = candidates
+            .into_iter()
+            .filter(|p| filters.iter().all(|f| f(&p.data)))
+            .collect::<Vec<&'a Pack<T>>>();
+        if let Some(mut compare) = sort {
+            result.sort_by(|a, b| compare(&a.data, &b.data));
+        }
+        Ok(result
+            .into_iter()
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect())
+    }
+    /// Number of matching members
+    /// Offset and limit are applied.
+    pub fn count(self) -> PackResult<usize> {
+        Ok(self.collect()?.len())
+    }
+    /// First matching member
+    pub fn first(self) -> PackResult<Option<&'a Pack<T>>> {
+        Ok(self.limit(1).collect()?.into_iter().next())
+    }
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Start a query over the members
+    pub fn query(&self) -> Query<'_, T> {
+        Query {
+            vecpack: self,
+            index: None,
+            filters: Vec::new(),
+            sort: None,
+            offset: 0,
+            limit: None,
+        }
+    }
+}
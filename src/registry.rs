@@ -0,0 +1,233 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Registry
+//!
+//! A registry is a root directory holding named VecPack
+//! collections, one sub directory per collection, plus a
+//! manifest.yml file that lists the registered collections.
+
+use crate::{Pack, PackError, PackResult, VecPack, VecPackMember};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// Manifest
+/// Stored as manifest.yml in the registry root
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct Manifest {
+    /// Registered collection names
+    pub collections: Vec<String>,
+}
+
+/// Registry
+/// Manages named collections under a single root directory.
+/// VecPacks opened through the registry share their location
+/// with it, so renaming a collection keeps the open handles
+/// valid; they and their members follow the new directory,
+/// immutable accessors included.
+pub struct Registry {
+    root: PathBuf,
+    manifest: Pack<Manifest>,
+    locations: HashMap<String, Arc<RwLock<PathBuf>>>,
+}
+
+impl Registry {
+    /// Load or init registry by a given root path
+    /// If the root or its manifest does not exist,
+    /// then we create them.
+    pub fn load_or_init(root: PathBuf) -> PackResult<Registry> {
+        let manifest = Pack::load_or_init(root.clone(), "manifest")?;
+        Ok(Registry {
+            root,
+            manifest,
+            locations: HashMap::new(),
+        })
+    }
+    /// Open collection by name
+    /// If the collection is not registered yet,
+    /// then we register it in the manifest.
+    pub fn collection<T>(&mut self, name: &str) -> PackResult<VecPack<T>>
+    where
+        for<'de> T: VecPackMember + Deserialize<'de> + Default,
+    {
+        check_name(name)?;
+        if !self.contains(name) {
+            self.manifest
+                .update(|m| m.collections.push(name.to_string()))?;
+        }
+        let location = self.location(name);
+        let path = location.read().unwrap().clone();
+        let mut collection = VecPack::load_or_init(path)?;
+        collection.set_location(location);
+        Ok(collection)
+    }
+    /// Rename collection
+    /// Renames the collection directory, then updates the manifest.
+    /// If the manifest cannot be saved, then the directory rename is
+    /// rolled back. Open handles follow the new location.
+    pub fn rename_collection(
+        &mut self,
+        old: &str,
+        new: &str,
+    ) -> PackResult<()> {
+        check_name(new)?;
+        if !self.contains(old) {
+            return Err(PackError::ObjectNotFound);
+        }
+        if self.contains(new) || self.root.join(new).exists() {
+            return Err(PackError::IDTaken);
+        }
+        let location = self.location(old);
+        // Hold the write lock during the whole operation,
+        // so no handle can sync in the middle of it.
+        let mut current = location.write().unwrap();
+        let new_path = self.root.join(new);
+        std::fs::rename(&*current, &new_path)?;
+        let res = self.manifest.update(|m| {
+            for name in m.collections.iter_mut() {
+                if name == old {
+                    *name = new.to_string();
+                }
+            }
+        });
+        if let Err(err) = res {
+            // Rollback directory rename
+            std::fs::rename(&new_path, &*current)?;
+            return Err(err);
+        }
+        *current = new_path;
+        drop(current);
+        self.locations.remove(old);
+        self.locations.insert(new.to_string(), location);
+        Ok(())
+    }
+    /// Relocate registry root
+    /// Moves the whole registry to new_root, then flips every
+    /// open handle to the new location. new_root must not exist,
+    /// or must be an empty directory, and must not be inside the
+    /// current root. If moving fails, then the registry stays
+    /// where it was, and handles are not touched.
+    pub fn relocate_root(&mut self, new_root: PathBuf) -> PackResult<()> {
+        if absolute(&new_root)?.starts_with(absolute(&self.root)?) {
+            return Err(PackError::InternalError(format!(
+                "Relocation target is inside the registry root: {}",
+                new_root.display()
+            )));
+        }
+        if new_root.exists() {
+            if !new_root.is_dir() || std::fs::read_dir(&new_root)?.count() > 0 {
+                return Err(PackError::InternalError(format!(
+                    "Relocation target is not an empty directory: {}",
+                    new_root.display()
+                )));
+            }
+            std::fs::remove_dir(&new_root)?;
+        }
+        // Lock every open handle location first, so the move
+        // and the flip is atomic from the handles' point of view.
+        let mut locked = self
+            .locations
+            .iter()
+            .map(|(name, location)| (name, location.write().unwrap()))
+            .collect::<Vec<_>>();
+        crate::move_dir(&self.root, &new_root)?;
+        for (name, location) in locked.iter_mut() {
+            **location = new_root.join(name.as_str());
+        }
+        drop(locked);
+        self.manifest.path = new_root.join("manifest.yml");
+        self.root = new_root;
+        Ok(())
+    }
+    /// Registered collection names
+    pub fn collections(&self) -> &Vec<String> {
+        &self.manifest.collections
+    }
+    /// Registry root path
+    pub fn get_path(&self) -> &PathBuf {
+        &self.root
+    }
+    // Current directory of a collection
+    pub(crate) fn collection_path(&self, name: &str) -> PathBuf {
+        match self.locations.get(name) {
+            Some(location) => location.read().unwrap().clone(),
+            None => self.root.join(name),
+        }
+    }
+    // Check whether collection is registered
+    fn contains(&self, name: &str) -> bool {
+        self.manifest.collections.iter().any(|n| n == name)
+    }
+    // Shared location of a collection
+    // Creates one if it does not exist yet.
+    fn location(&mut self, name: &str) -> Arc<RwLock<PathBuf>> {
+        let root = &self.root;
+        self.locations
+            .entry(name.to_string())
+            .or_insert_with(|| Arc::new(RwLock::new(root.join(name))))
+            .clone()
+    }
+}
+
+// Absolute path with symlinks resolved, as far as it exists
+fn absolute(path: &Path) -> PackResult<PathBuf> {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    while !existing.exists() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name);
+                existing = parent;
+            }
+            _ => break,
+        }
+    }
+    let base = match existing.as_os_str().is_empty() {
+        true => std::env::current_dir()?,
+        false => existing.canonicalize()?,
+    };
+    Ok(rest
+        .into_iter()
+        .rev()
+        .fold(base, |path, name| path.join(name)))
+}
+
+// Collection name is used as a directory name,
+// so it must be a single, visible path segment.
+fn check_name(name: &str) -> PackResult<()> {
+    if name.is_empty()
+        || name.starts_with('.')
+        || name.contains(['/', '\\'])
+        || name == "manifest.yml"
+    {
+        return Err(PackError::InternalError(format!(
+            "Invalid collection name: {}",
+            name
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,127 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Reload from disk
+//!
+//! Long running processes can pick up changes made by other
+//! processes, or by manual edits, without reopening the VecPack.
+
+use crate::metrics::{self, OpKind};
+use crate::{Pack, PackError, PackResult, VecPack, VecPackMember};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Instant;
+
+impl<T> VecPack<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    /// Reload VecPack from disk
+    /// Re-reads all the member files and replaces the in-memory
+    /// members, then applies the persisted modes (order index,
+    /// append-only, hash chain, ...) again. If any file cannot be
+    /// loaded, then nothing is changed and the error is returned.
+    /// Members are in the same order as after load_or_init.
+    pub fn reload(&mut self) -> PackResult<()> {
+        self.sync_location();
+        let mut data = Vec::new();
+        for file in self.list_members()? {
+            data.push(self.load_member(file)?);
+        }
+        let ids: HashSet<&str> = data.iter().map(|i| i.get_id()).collect();
+        if let Some(stats) = &self.stats {
+            stats
+                .lock()
+                .unwrap()
+                .retain(|id, _| ids.contains(id.as_str()));
+        }
+        self.data = data;
+        self.members_touched();
+        self.load_modes()
+    }
+    /// Reload a member from disk by ID
+    /// If its file has changed, the member is updated; if the file
+    /// is new, the member is added; if the file is gone, the member
+    /// is removed. Returns ObjectNotFound if the ID is unknown both
+    /// in memory and on disk.
+    pub fn reload_id(&mut self, id: &str) -> PackResult<()> {
+        self.sync_location();
+        let path = match self.pack_by_id(id) {
+            Some(pack) => pack.path.clone(),
+            None => self.member_path(id),
+        };
+        let pos = self.position(id);
+        match (path.is_file(), pos) {
+            (true, Some(pos)) => {
+                self.data[pos] = self.load_member(path)?;
+                self.member_touched(id);
+                Ok(())
+            }
+            (true, None) => {
+                let pack = self.load_member(path)?;
+                if pack.get_id() != id {
+                    return Err(PackError::InternalError(format!(
+                        "File of ID {} contains ID {}",
+                        id,
+                        pack.get_id()
+                    )));
+                }
+                self.add_member(pack)?;
+                self.save_order()
+            }
+            (false, Some(_)) => {
+                self.take_member(id)?;
+                self.save_order()
+            }
+            (false, None) => Err(PackError::ObjectNotFound),
+        }
+    }
+    // Load a member file with the VecPack context and hooks
+    pub(crate) fn load_member(&self, path: PathBuf) -> PackResult<Pack<T>> {
+        self.ctx.record_version(&path);
+        let id = path.file_stem().map(|s| s.to_string_lossy().to_string());
+        let started = Instant::now();
+        let file = path.clone();
+        let pack = match self.ctx.backend() {
+            Some(backend) => Pack::<T>::load_from_backend(path, backend),
+            None => Pack::<T>::read_from_path(path),
+        };
+        self.ctx.metrics.record(
+            OpKind::Load,
+            &file,
+            started.elapsed(),
+            pack.as_ref().ok().map(|_| metrics::file_len(&file)),
+        );
+        let mut pack = pack.map_err(|err| match &id {
+            Some(id) => err.with_id(id),
+            None => err,
+        })?;
+        pack.ctx = self.ctx.clone();
+        pack.hooks = self.hooks.clone();
+        self.hooks.after_load(&mut pack.data);
+        Ok(pack)
+    }
+}
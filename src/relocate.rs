@@ -0,0 +1,200 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Pack and VecPack location
+//!
+//! Read the path of a Pack<T>, rename its file, or move it
+//! into another directory; the history file moves with it.
+//! A whole VecPack<T> directory can be moved by move_storage(),
+//! e.g. to implement a "change data folder" setting.
+
+use crate::lock::LOCK_FILE;
+use crate::{
+    atomic, copy_dir_except, history, Pack, PackError, PackResult, VecPack,
+    VecPackMember,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+impl<T> Pack<T>
+where
+    T: Serialize + Sized,
+{
+    /// Path of the Pack<T> file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// Rename the Pack<T> file to new_id
+    /// The file stays in its directory, with the same extension.
+    /// Members of a VecPack are named by their ID, so rename
+    /// them through the VecPack instead.
+    /// Returns PackError::IDTaken if the target file exists.
+    pub fn rename_file(&mut self, new_id: &str) -> PackResult<()> {
+        let mut to = self.path.clone();
+        to.set_file_name(new_id);
+        if let Some(ext) = self.path.extension() {
+            to.set_extension(ext);
+        }
+        self.relocate(to)
+    }
+    /// Move the Pack<T> file into new_dir
+    /// The directory is created if it does not exist.
+    /// Returns PackError::IDTaken if the target file exists.
+    pub fn move_to(&mut self, new_dir: &Path) -> PackResult<()> {
+        let name = self.path.file_name().ok_or_else(|| {
+            PackError::InternalError("Pack path has no file name".into())
+        })?;
+        std::fs::create_dir_all(new_dir)?;
+        self.relocate(new_dir.join(name))
+    }
+    // Move the file to path, then keep the in-memory path in sync
+    fn relocate(&mut self, to: PathBuf) -> PackResult<()> {
+        if to == self.path {
+            return Ok(());
+        }
+        self.ctx.check_writable()?;
+        match self.ctx.backend() {
+            Some(backend) => {
+                if backend.read(&to).is_ok() {
+                    return Err(PackError::IDTaken);
+                }
+                let bytes = backend.read(&self.path)?;
+                backend.write(&to, &bytes)?;
+                backend.delete(&self.path)?;
+            }
+            None => {
+                if to.exists() {
+                    return Err(PackError::IDTaken);
+                }
+                move_file(&self.path, &to, &self.ctx.temp_config())?;
+                let from_history = history::history_path(&self.path);
+                if from_history.is_file() {
+                    let to_history = history::history_path(&to);
+                    if let Some(dir) = to_history.parent() {
+                        std::fs::create_dir_all(dir)?;
+                    }
+                    move_file(
+                        &from_history,
+                        &to_history,
+                        &self.ctx.temp_config(),
+                    )?;
+                }
+            }
+        }
+        self.ctx.forget_header(&self.path);
+        self.ctx.record_version(&to);
+        self.path = to;
+        Ok(())
+    }
+}
+
+// Move file from one path to another
+// Rename is atomic, but works only on the same filesystem;
+// otherwise the content is written atomically to the target,
+// and the source is removed only after that.
+fn move_file(
+    from: &Path,
+    to: &Path,
+    temp: &atomic::TempConfig,
+) -> PackResult<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    let bytes = std::fs::read(from)?;
+    atomic::write_atomic(to, &bytes, temp)?;
+    std::fs::remove_file(from)?;
+    Ok(())
+}
+
+impl<T> VecPack<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    /// Move the VecPack<T> directory to new_path
+    /// The directory is copied, the copy is verified, then the
+    /// VecPack and its members switch to new_path, and the old
+    /// directory is removed. new_path must not exist, or must be
+    /// an empty directory. If copying or verifying fails, the copy
+    /// is removed and the VecPack stays where it was. If only the
+    /// old directory cannot be removed, the VecPack already uses
+    /// new_path and the error is returned.
+    pub fn move_storage(&mut self, new_path: PathBuf) -> PackResult<()> {
+        self.sync_location();
+        self.check_writable()?;
+        if self.location.is_some() {
+            return Err(PackError::InternalError(
+                "VecPack is managed by a Registry, move it through the \
+                 Registry"
+                    .into(),
+            ));
+        }
+        if self.ctx.backend().is_some() {
+            return Err(PackError::InternalError(
+                "move_storage works only on the local filesystem".into(),
+            ));
+        }
+        if new_path.exists() {
+            if !new_path.is_dir() || std::fs::read_dir(&new_path)?.count() > 0 {
+                return Err(PackError::InternalError(format!(
+                    "Move target is not an empty directory: {}",
+                    new_path.display()
+                )));
+            }
+            std::fs::remove_dir(&new_path)?;
+        }
+        // The lock is not copied, it is taken again at new_path
+        if let Err(err) = copy_dir_except(&self.path, &new_path, &[LOCK_FILE])
+            .and_then(|_| verify_copy(&self.path, &new_path, &[LOCK_FILE]))
+        {
+            let _ = std::fs::remove_dir_all(&new_path);
+            return Err(err);
+        }
+        let old_path = self.relocated(new_path);
+        let res = self.reacquire_lock();
+        std::fs::remove_dir_all(&old_path)?;
+        res
+    }
+}
+
+// Check that every file under from has the same content under to,
+// except the top level entries named in except
+fn verify_copy(from: &Path, to: &Path, except: &[&str]) -> PackResult<()> {
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        if except.iter().any(|name| entry.file_name() == *name) {
+            continue;
+        }
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            verify_copy(&entry.path(), &target, &[])?;
+        } else if std::fs::read(entry.path())? != std::fs::read(&target)? {
+            return Err(PackError::IntegrityError(format!(
+                "Copy of {} differs from the original",
+                entry.path().display()
+            )));
+        }
+    }
+    Ok(())
+}
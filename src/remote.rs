@@ -0,0 +1,350 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Remote storage over HTTP
+//!
+//! Enabled by the `remote` feature. A RemoteServer serves a
+//! storage backend over HTTP, and a RemoteBackend is the client
+//! backend, so a VecPack can be shared by processes on other
+//! machines. The `storaget-server` binary serves a directory.
+//!
+//! Requests (paths are URI encoded, '/' separated):
+//! - GET /files/<path>: file content, or 404
+//! - PUT /files/<path>: write file content
+//! - DELETE /files/<path>: delete file, or 404
+//! - GET /list/<dir>: member file paths, one per line
+//!
+//! An optional token is sent and checked as a Bearer
+//! Authorization header. The protocol has no encryption, run
+//! it behind a TLS proxy on untrusted networks.
+//!
+//! Requests are served by a pool of worker threads, and request
+//! bodies over the size limit are rejected with 413.
+
+use crate::backend::{path_key, uri_encode, FsBackend, StorageBackend};
+use crate::{pool, PackError, PackResult};
+use std::io::Read;
+use std::net::SocketAddr;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tiny_http::{Method, Request, Response, Server};
+
+// Default request body limit
+const MAX_BODY: u64 = 64 * 1024 * 1024;
+
+/// RemoteServer
+/// Serves a storage backend over HTTP
+pub struct RemoteServer {
+    server: Server,
+    backend: Arc<dyn StorageBackend>,
+    root: PathBuf,
+    token: Option<String>,
+    max_body: u64,
+    workers: usize,
+}
+
+impl RemoteServer {
+    /// Bind a server to addr, e.g. "127.0.0.1:7878"
+    /// Use port 0 to bind to a free port.
+    pub fn bind(
+        addr: &str,
+        backend: Arc<dyn StorageBackend>,
+    ) -> PackResult<RemoteServer> {
+        let server = Server::http(addr).map_err(|err| {
+            PackError::BackendError(format!("cannot bind {}: {}", addr, err))
+        })?;
+        Ok(RemoteServer {
+            server,
+            backend,
+            root: PathBuf::new(),
+            token: None,
+            max_body: MAX_BODY,
+            workers: 0,
+        })
+    }
+    /// Bind a server to addr, serving the files under root
+    /// from the local filesystem
+    pub fn serve_dir(addr: &str, root: PathBuf) -> PackResult<RemoteServer> {
+        Ok(RemoteServer::bind(addr, Arc::new(FsBackend::new()))?.root(root))
+    }
+    /// Resolve every requested path under root
+    pub fn root(mut self, root: PathBuf) -> Self {
+        self.root = root;
+        self
+    }
+    /// Require this token from the clients
+    pub fn token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+    /// Reject request bodies larger than max bytes
+    /// Defaults to 64 MiB.
+    pub fn max_body_size(mut self, max: u64) -> Self {
+        self.max_body = max;
+        self
+    }
+    /// Serve requests on this many threads
+    /// 0, the default, means a thread per available core.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+    /// Bound local address
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+    /// Serve requests until the process exits
+    pub fn run(self) {
+        let workers = pool::workers(self.workers);
+        let server = Arc::new(self);
+        let handles = (1..workers)
+            .map(|_| {
+                let server = server.clone();
+                std::thread::spawn(move || server.serve())
+            })
+            .collect::<Vec<_>>();
+        server.serve();
+        for handle in handles {
+            let _ = handle.join();
+        }
+    }
+    // Serve requests on the current thread
+    fn serve(&self) {
+        while let Ok(mut request) = self.server.recv() {
+            let (status, body) = match self.authorized(&request) {
+                false => (401, b"unauthorized".to_vec()),
+                true => match request_parts(&mut request, self.max_body) {
+                    Some(parts) => self.handle(parts),
+                    None => (413, b"request body too large".to_vec()),
+                },
+            };
+            let _ = request
+                .respond(Response::from_data(body).with_status_code(status));
+        }
+    }
+    // Check the token of a request, if required
+    fn authorized(&self, request: &Request) -> bool {
+        let token = match &self.token {
+            Some(token) => token,
+            None => return true,
+        };
+        let auth = request
+            .headers()
+            .iter()
+            .find(|h| h.field.equiv("Authorization"))
+            .map(|h| h.value.to_string())
+            .unwrap_or_default();
+        same_bytes(auth.as_bytes(), format!("Bearer {}", token).as_bytes())
+    }
+    // Status code and body of a request
+    fn handle(
+        &self,
+        (method, url, body): (Method, String, Vec<u8>),
+    ) -> (u16, Vec<u8>) {
+        let (kind, path) = match url.trim_start_matches('/').split_once('/') {
+            Some((kind, path)) => (kind.to_string(), uri_decode(path)),
+            None => return (404, Vec::new()),
+        };
+        // Never serve anything outside root
+        let path = Path::new(&path);
+        if path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+        {
+            return (400, b"invalid path".to_vec());
+        }
+        let path = self.root.join(path);
+        let result = match (kind.as_str(), method) {
+            ("files", Method::Get) => self.backend.read(&path),
+            ("files", Method::Put) => {
+                self.backend.write(&path, &body).map(|_| Vec::new())
+            }
+            ("files", Method::Delete) => {
+                self.backend.delete(&path).map(|_| Vec::new())
+            }
+            ("list", Method::Get) => self.backend.list(&path).map(|files| {
+                files
+                    .iter()
+                    .filter_map(|file| file.strip_prefix(&self.root).ok())
+                    .map(|file| format!("{}\n", path_key(file)))
+                    .collect::<String>()
+                    .into_bytes()
+            }),
+            _ => return (405, Vec::new()),
+        };
+        match result {
+            Ok(content) => (200, content),
+            Err(PackError::PathNotFound) => (404, Vec::new()),
+            Err(err) => (500, err.to_string().into_bytes()),
+        }
+    }
+}
+
+// Method, URL and body of a request
+// None if the body is larger than max.
+fn request_parts(
+    request: &mut Request,
+    max: u64,
+) -> Option<(Method, String, Vec<u8>)> {
+    if request.body_length().map(|len| len as u64 > max) == Some(true) {
+        return None;
+    }
+    let mut body = Vec::new();
+    let _ = request.as_reader().take(max + 1).read_to_end(&mut body);
+    if body.len() as u64 > max {
+        return None;
+    }
+    Some((request.method().clone(), request.url().to_string(), body))
+}
+
+// Compare in constant time, so the time of a failed
+// comparison does not tell how much of a token matched
+fn same_bytes(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+// Decode %XX escapes
+fn uri_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                result.push(byte);
+                i += 3;
+            }
+            None => {
+                result.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&result).to_string()
+}
+
+/// RemoteBackend
+/// Client backend of a RemoteServer
+pub struct RemoteBackend {
+    url: String,
+    token: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl RemoteBackend {
+    /// New RemoteBackend of a server, e.g. "http://10.0.0.2:7878"
+    pub fn new(url: &str) -> Self {
+        RemoteBackend {
+            url: url.trim_end_matches('/').to_string(),
+            token: None,
+            agent: ureq::Agent::new(),
+        }
+    }
+    /// Send this token to the server
+    pub fn token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+    // Send a request
+    // Returns None for 404 Not Found.
+    fn request(
+        &self,
+        method: &str,
+        kind: &str,
+        path: &Path,
+        body: &[u8],
+    ) -> PackResult<Option<Vec<u8>>> {
+        let url = format!(
+            "{}/{}/{}",
+            self.url,
+            kind,
+            uri_encode(&path_key(path), false)
+        );
+        let mut request = self.agent.request(method, &url);
+        if let Some(token) = &self.token {
+            request =
+                request.set("Authorization", &format!("Bearer {}", token));
+        }
+        let response = match method {
+            "PUT" => request.send_bytes(body),
+            _ => request.call(),
+        };
+        match response {
+            Ok(response) => {
+                let mut content = Vec::new();
+                response.into_reader().read_to_end(&mut content)?;
+                Ok(Some(content))
+            }
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(ureq::Error::Status(code, response)) => {
+                Err(PackError::BackendError(format!(
+                    "remote {} {} failed with status {}: {}",
+                    method,
+                    path.display(),
+                    code,
+                    response.into_string().unwrap_or_default()
+                )))
+            }
+            Err(err) => Err(PackError::BackendError(format!(
+                "remote {} {} failed: {}",
+                method,
+                path.display(),
+                err
+            ))),
+        }
+    }
+}
+
+impl StorageBackend for RemoteBackend {
+    fn read(&self, path: &Path) -> PackResult<Vec<u8>> {
+        self.request("GET", "files", path, &[])?
+            .ok_or(PackError::PathNotFound)
+    }
+    fn write(&self, path: &Path, bytes: &[u8]) -> PackResult<()> {
+        self.request("PUT", "files", path, bytes)?;
+        Ok(())
+    }
+    fn list(&self, dir: &Path) -> PackResult<Vec<PathBuf>> {
+        let content = self
+            .request("GET", "list", dir, &[])?
+            .ok_or(PackError::PathNotFound)?;
+        Ok(String::from_utf8_lossy(&content)
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(PathBuf::from)
+            .collect())
+    }
+    fn delete(&self, path: &Path) -> PackResult<()> {
+        self.request("DELETE", "files", path, &[])?
+            .map(|_| ())
+            .ok_or(PackError::PathNotFound)
+    }
+}
@@ -0,0 +1,90 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Read-repair
+//!
+//! In a shared deployment many replicas open the same VecPack
+//! directory, each keeping the members in memory as a local cache.
+//! With read-repair enabled, VecPack remembers the version
+//! (modification time) of every member file it has loaded or saved.
+//! find_id_fresh() checks the file on read, and if another replica
+//! has written a newer version, then the cached member is updated
+//! first, and the stale read is counted.
+
+use crate::{Pack, PackError, PackResult, VecPack, VecPackMember};
+use serde::Deserialize;
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Enable read-repair
+    /// Records the current version of every member file.
+    pub fn enable_read_repair(&mut self) {
+        self.sync_location();
+        let paths = self.data.iter().map(|i| i.path.clone()).collect();
+        self.ctx.track_versions(paths);
+    }
+    /// Returns true if read-repair is enabled
+    pub fn is_read_repair_enabled(&self) -> bool {
+        self.ctx.is_tracking_versions()
+    }
+    /// Number of stale members repaired on read
+    pub fn stale_read_count(&self) -> u64 {
+        self.ctx.stale_read_count()
+    }
+}
+
+impl<T> VecPack<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    /// Find ID with read-repair
+    /// Like find_id, but if the member file has a newer version
+    /// on disk (written by another replica), then the member is
+    /// reloaded first. A member created by another replica is
+    /// loaded, a member removed by another replica is removed,
+    /// and ObjectNotFound is returned.
+    /// Without read-repair enabled it is the same as find_id.
+    pub fn find_id_fresh(&mut self, id: &str) -> PackResult<&Pack<T>> {
+        self.sync_location();
+        if self.ctx.is_tracking_versions() {
+            let path = match self.pack_by_id(id) {
+                Some(pack) => pack.path.clone(),
+                None => self.member_path(id),
+            };
+            if self.ctx.is_stale(&path) {
+                self.ctx.stale_read();
+                match self.reload_id(id) {
+                    Ok(_) | Err(PackError::ObjectNotFound) => (),
+                    Err(err) => return Err(err),
+                }
+                // Removed member has no file to load
+                self.ctx.record_version(&path);
+            }
+        }
+        self.find_id(id)
+    }
+}
@@ -0,0 +1,245 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Replication
+//!
+//! A VecPack can mirror its member files to one or more replica
+//! directories, e.g. on other mounts, for cheap redundancy. Every
+//! successful save and remove is repeated on the replicas. A failed
+//! mirror does not fail the save, it is reported to the save error
+//! hook, and fixed by the next sync_replicas() call.
+//!
+//! Replicas are persisted in the hidden .replicas.yml file and
+//! caught up at every load. Only the member files are replicated,
+//! and only for VecPacks on the local filesystem.
+
+use crate::atomic::{self, TempConfig};
+use crate::{PackResult, VecPack, VecPackMember};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+// Replica directories of a VecPack, kept in its context
+#[derive(Debug, Clone)]
+pub(crate) struct Replicas {
+    // Primary VecPack directory
+    root: PathBuf,
+    dirs: Vec<PathBuf>,
+}
+
+impl Replicas {
+    // Follow the primary directory when it is moved
+    pub(crate) fn relocate(&mut self, root: &Path) {
+        self.root = root.to_path_buf();
+    }
+    // Replica paths of a primary file
+    fn targets(&self, path: &Path) -> Vec<PathBuf> {
+        match path.strip_prefix(&self.root) {
+            Ok(rel) => self.dirs.iter().map(|dir| dir.join(rel)).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+    // Copy a written primary file to every replica
+    // Tries every replica, returns the first error.
+    pub(crate) fn mirror(&self, path: &Path) -> PackResult<()> {
+        let bytes = std::fs::read(path)?;
+        let mut result = Ok(());
+        for target in self.targets(path) {
+            let res = copy_bytes(&bytes, &target);
+            result = result.and(res);
+        }
+        result
+    }
+    // Remove a removed primary file from every replica
+    pub(crate) fn remove(&self, path: &Path) -> PackResult<()> {
+        let mut result = Ok(());
+        for target in self.targets(path) {
+            if target.exists() {
+                result = result.and(std::fs::remove_file(target));
+            }
+        }
+        Ok(result?)
+    }
+}
+
+/// ReplicaIssue
+/// Difference between the primary and a replica directory,
+/// reported by check_replicas()
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplicaIssue {
+    /// Member file is missing from the replica
+    Missing { replica: PathBuf, file: PathBuf },
+    /// Replica file content differs from the primary
+    Different { replica: PathBuf, file: PathBuf },
+    /// Replica has a file that is not a member of the primary
+    Extra { replica: PathBuf, file: PathBuf },
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Enable replication to the given directories
+    /// Replicas are created if needed, and caught up with the
+    /// primary. Replaces the previous replica list.
+    /// Returns the number of replica files fixed by the catch-up.
+    pub fn enable_replication(
+        &mut self,
+        dirs: Vec<PathBuf>,
+    ) -> PackResult<usize> {
+        self.check_writable()?;
+        crate::save_data_object(&self.replicas_path(), &dirs)?;
+        self.set_replicas(dirs);
+        self.sync_replicas()
+    }
+    /// Stop replication
+    /// Replica directories are left as they are.
+    pub fn disable_replication(&mut self) -> PackResult<()> {
+        self.check_writable()?;
+        let path = self.replicas_path();
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        self.ctx.set_replicas(None);
+        Ok(())
+    }
+    /// Replica directories, if replication is enabled
+    pub fn replicas(&self) -> Vec<PathBuf> {
+        self.ctx.replicas().map(|r| r.dirs).unwrap_or_default()
+    }
+    /// Consistency check
+    /// Compares every member file with its copies in the replicas.
+    pub fn check_replicas(&self) -> PackResult<Vec<ReplicaIssue>> {
+        let replicas = match self.ctx.replicas() {
+            Some(replicas) => replicas,
+            None => return Ok(Vec::new()),
+        };
+        let mut primary = BTreeMap::new();
+        for pack in &self.data {
+            let rel = pack.path.strip_prefix(&self.path).unwrap_or(&pack.path);
+            primary.insert(rel.to_path_buf(), std::fs::read(&pack.path)?);
+        }
+        let mut issues = Vec::new();
+        for dir in &replicas.dirs {
+            let issue = |file: &Path| (dir.clone(), file.to_path_buf());
+            for (file, content) in &primary {
+                match std::fs::read(dir.join(file)) {
+                    Ok(copy) if &copy == content => (),
+                    Ok(_) => {
+                        let (replica, file) = issue(file);
+                        issues.push(ReplicaIssue::Different { replica, file });
+                    }
+                    Err(_) => {
+                        let (replica, file) = issue(file);
+                        issues.push(ReplicaIssue::Missing { replica, file });
+                    }
+                }
+            }
+            for file in replica_files(dir, Path::new(""))? {
+                if !primary.contains_key(&file) {
+                    let (replica, file) = issue(&file);
+                    issues.push(ReplicaIssue::Extra { replica, file });
+                }
+            }
+        }
+        Ok(issues)
+    }
+    /// Catch-up sync
+    /// Fixes every issue check_replicas() reports: copies the
+    /// missing and different member files, and removes the
+    /// extra ones. Returns the number of fixed files.
+    pub fn sync_replicas(&self) -> PackResult<usize> {
+        self.check_writable()?;
+        let issues = self.check_replicas()?;
+        for issue in &issues {
+            match issue {
+                ReplicaIssue::Missing { replica, file }
+                | ReplicaIssue::Different { replica, file } => {
+                    let bytes = std::fs::read(self.dir().join(file))?;
+                    copy_bytes(&bytes, &replica.join(file))?;
+                }
+                ReplicaIssue::Extra { replica, file } => {
+                    std::fs::remove_file(replica.join(file))?;
+                }
+            }
+        }
+        Ok(issues.len())
+    }
+    // Load replica list, and catch up the replicas
+    pub(crate) fn load_replication(&mut self) -> PackResult<()> {
+        let path = self.replicas_path();
+        if path.exists() {
+            let dirs = serde_yaml::from_str(&std::fs::read_to_string(path)?)?;
+            self.set_replicas(dirs);
+            // Only the writer catches up the replicas
+            if !self.is_read_only() {
+                self.sync_replicas()?;
+            }
+        }
+        Ok(())
+    }
+    fn set_replicas(&self, dirs: Vec<PathBuf>) {
+        self.ctx.set_replicas(Some(Replicas {
+            root: self.path.clone(),
+            dirs,
+        }));
+    }
+    pub(crate) fn replicas_path(&self) -> PathBuf {
+        self.dir().join(".replicas.yml")
+    }
+}
+
+// Write bytes atomically, creating the parent directory
+fn copy_bytes(bytes: &[u8], target: &Path) -> PackResult<()> {
+    if let Some(dir) = target.parent().filter(|dir| !dir.exists()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    atomic::write_atomic(target, bytes, &TempConfig::default())
+}
+
+// Non-hidden files of a replica, relative to it, recursively
+fn replica_files(dir: &Path, rel: &Path) -> PackResult<Vec<PathBuf>> {
+    let mut result = Vec::new();
+    let entries = match std::fs::read_dir(dir.join(rel)) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(result)
+        }
+        Err(err) => return Err(err.into()),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = rel.join(&name);
+        if entry.file_type()?.is_dir() {
+            result.extend(replica_files(dir, &path)?);
+        } else {
+            result.push(path);
+        }
+    }
+    Ok(result)
+}
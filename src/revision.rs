@@ -0,0 +1,78 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Optimistic concurrency
+//!
+//! Every save bumps the revision stored in the file header.
+//! A client reads the revision with the data (e.g. as an HTTP
+//! ETag), and later saves its edit by update_if_version(), which
+//! fails with PackError::Conflict if anyone has saved since.
+
+use crate::{Pack, PackError, PackResult};
+use serde::{Deserialize, Serialize};
+
+impl<T> Pack<T>
+where
+    T: Serialize + Sized,
+{
+    /// Revision of the Pack<T> file
+    /// Bumped by every save; 0 for files saved before
+    /// revisions were recorded.
+    pub fn revision(&self) -> u64 {
+        match self.ctx.header(&self.path) {
+            Some(header) => {
+                // Saved by others maybe, so the next save follows it
+                self.stamp.set(header.created, header.revision);
+                header.revision.unwrap_or(0)
+            }
+            None => 0,
+        }
+    }
+}
+
+impl<T> Pack<T>
+where
+    for<'de> T: Serialize + Deserialize<'de> + Sized,
+{
+    /// Update Pack<T> if its revision is still expected
+    /// The stored revision is checked right before the update,
+    /// so saves of other Pack<T> instances or processes are
+    /// detected as well. Returns PackError::Conflict if it has
+    /// changed, and then f is not called.
+    pub fn update_if_version<F, R>(
+        &mut self,
+        expected: u64,
+        f: F,
+    ) -> PackResult<R>
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        let found = self.revision();
+        if found != expected {
+            return Err(PackError::Conflict { expected, found });
+        }
+        self.update(f)
+    }
+}
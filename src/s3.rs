@@ -0,0 +1,431 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! S3 compatible object storage backend
+//!
+//! Enabled by the `s3` feature. Packs are stored as objects of a
+//! bucket, so they survive containers with ephemeral local disk.
+//! Works with AWS S3 and S3 compatible stores (MinIO, Ceph, ...)
+//! using path-style requests signed with AWS Signature V4.
+//! Temporary credentials, e.g. of an IAM role in a container, are
+//! supported with a session token.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use storaget::s3::{S3Backend, S3Config};
+//! use storaget::Pack;
+//!
+//! let config = S3Config::new("http://localhost:9000", "packs")
+//!     .credentials("minio", "minio123")
+//!     .prefix("app");
+//! let backend = Arc::new(S3Backend::new(config));
+//! let names: Pack<Vec<String>> =
+//!     Pack::load_or_init_with_backend("data".into(), "names", backend)
+//!         .unwrap();
+//! ```
+
+use crate::backend::{path_key, uri_encode as encode, StorageBackend};
+use crate::quarantine::QUARANTINE_DIR;
+use crate::{PackError, PackResult};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// S3Config
+/// Endpoint, bucket and credentials of an S3Backend
+#[derive(Debug, Clone)]
+pub struct S3Config {
+    endpoint: String,
+    bucket: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+    // Temporary credentials, e.g. of an IAM role
+    session_token: Option<String>,
+    prefix: Option<String>,
+}
+
+impl S3Config {
+    /// New S3Config for a bucket at an endpoint,
+    /// e.g. "https://s3.eu-central-1.amazonaws.com"
+    /// Region defaults to "us-east-1", credentials are empty.
+    pub fn new(endpoint: &str, bucket: &str) -> Self {
+        S3Config {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            bucket: bucket.to_string(),
+            region: "us-east-1".to_string(),
+            access_key: String::new(),
+            secret_key: String::new(),
+            session_token: None,
+            prefix: None,
+        }
+    }
+    /// New S3Config from the standard AWS environment variables
+    /// AWS_ENDPOINT_URL (default AWS S3 of the region), AWS_REGION,
+    /// AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY and AWS_SESSION_TOKEN
+    pub fn from_env(bucket: &str) -> Self {
+        let var = |name: &str| std::env::var(name).ok();
+        let region = var("AWS_REGION").unwrap_or_else(|| "us-east-1".into());
+        let endpoint = var("AWS_ENDPOINT_URL")
+            .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", region));
+        let config = S3Config::new(&endpoint, bucket)
+            .region(&region)
+            .credentials(
+                &var("AWS_ACCESS_KEY_ID").unwrap_or_default(),
+                &var("AWS_SECRET_ACCESS_KEY").unwrap_or_default(),
+            );
+        match var("AWS_SESSION_TOKEN") {
+            Some(token) => config.session_token(&token),
+            None => config,
+        }
+    }
+    /// Set the signing region
+    pub fn region(mut self, region: &str) -> Self {
+        self.region = region.to_string();
+        self
+    }
+    /// Set the access key and the secret key
+    pub fn credentials(mut self, access_key: &str, secret_key: &str) -> Self {
+        self.access_key = access_key.to_string();
+        self.secret_key = secret_key.to_string();
+        self
+    }
+    /// Set the session token of temporary credentials,
+    /// sent as X-Amz-Security-Token
+    pub fn session_token(mut self, token: &str) -> Self {
+        self.session_token = match token.is_empty() {
+            true => None,
+            false => Some(token.to_string()),
+        };
+        self
+    }
+    /// Store every object under this key prefix
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        let prefix = prefix.trim_matches('/');
+        self.prefix = match prefix.is_empty() {
+            true => None,
+            false => Some(prefix.to_string()),
+        };
+        self
+    }
+}
+
+/// S3Backend
+/// Stores files as objects of an S3 bucket.
+/// Object keys are the file paths with '/' separators,
+/// under the configured prefix.
+pub struct S3Backend {
+    config: S3Config,
+    agent: ureq::Agent,
+}
+
+impl S3Backend {
+    /// New S3Backend
+    pub fn new(config: S3Config) -> Self {
+        S3Backend {
+            config,
+            agent: ureq::Agent::new(),
+        }
+    }
+    // Object key of a path
+    fn key(&self, path: &Path) -> String {
+        let key = path_key(path);
+        match &self.config.prefix {
+            Some(prefix) => format!("{}/{}", prefix, key),
+            None => key,
+        }
+    }
+    // Path of an object key
+    fn path(&self, key: &str) -> PathBuf {
+        let key = match &self.config.prefix {
+            Some(prefix) => key
+                .strip_prefix(prefix.as_str())
+                .map(|k| k.trim_start_matches('/'))
+                .unwrap_or(key),
+            None => key,
+        };
+        PathBuf::from(key)
+    }
+    // Send a signed request
+    // Returns None for 404 Not Found.
+    fn request(
+        &self,
+        method: &str,
+        key: &str,
+        query: &[(&str, &str)],
+        body: &[u8],
+    ) -> PackResult<Option<Vec<u8>>> {
+        let uri = format!("/{}/{}", self.config.bucket, encode(key, false));
+        let mut query: Vec<(String, String)> = query
+            .iter()
+            .map(|(k, v)| (encode(k, true), encode(v, true)))
+            .collect();
+        query.sort();
+        let query = query
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+        let url = match query.is_empty() {
+            true => format!("{}{}", self.config.endpoint, uri),
+            false => format!("{}{}?{}", self.config.endpoint, uri, query),
+        };
+        let mut request = self.agent.request(method, &url);
+        for (name, value) in self.sign(method, &uri, &query, body) {
+            request = request.set(&name, &value);
+        }
+        let response = match method {
+            "PUT" => request.send_bytes(body),
+            _ => request.call(),
+        };
+        match response {
+            Ok(response) => {
+                let mut content = Vec::new();
+                response.into_reader().read_to_end(&mut content)?;
+                Ok(Some(content))
+            }
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(ureq::Error::Status(code, response)) => {
+                Err(PackError::BackendError(format!(
+                    "S3 {} {} failed with status {}: {}",
+                    method,
+                    key,
+                    code,
+                    response.into_string().unwrap_or_default()
+                )))
+            }
+            Err(err) => Err(PackError::BackendError(format!(
+                "S3 {} {} failed: {}",
+                method, key, err
+            ))),
+        }
+    }
+    // AWS Signature V4 headers of a request
+    fn sign(
+        &self,
+        method: &str,
+        uri: &str,
+        query: &str,
+        body: &[u8],
+    ) -> Vec<(String, String)> {
+        let (date, time) = utc_now();
+        let amz_date = format!("{}T{}Z", date, time);
+        let payload_hash = hex(&Sha256::digest(body));
+        let host = self
+            .config
+            .endpoint
+            .split("://")
+            .last()
+            .unwrap_or_default()
+            .split('/')
+            .next()
+            .unwrap_or_default();
+        // Canonical headers, sorted by name
+        let mut headers = vec![
+            ("host", host.to_string()),
+            ("x-amz-content-sha256", payload_hash.clone()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(token) = &self.config.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<&str>>()
+            .join(";");
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value))
+            .collect();
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            method, uri, query, canonical_headers, signed_headers, payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.config.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let key = [
+            date.as_str(),
+            self.config.region.as_str(),
+            "s3",
+            "aws4_request",
+        ]
+        .iter()
+        .fold(
+            format!("AWS4{}", self.config.secret_key).into_bytes(),
+            |key, part| hmac(&key, part.as_bytes()),
+        );
+        let signature = hex(&hmac(&key, string_to_sign.as_bytes()));
+        let mut result = vec![
+            ("x-amz-date".to_string(), amz_date),
+            ("x-amz-content-sha256".to_string(), payload_hash),
+            (
+                "Authorization".to_string(),
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    self.config.access_key, scope, signed_headers, signature
+                ),
+            ),
+        ];
+        if let Some(token) = &self.config.session_token {
+            result.push(("x-amz-security-token".to_string(), token.clone()));
+        }
+        result
+    }
+}
+
+impl StorageBackend for S3Backend {
+    fn read(&self, path: &Path) -> PackResult<Vec<u8>> {
+        self.request("GET", &self.key(path), &[], &[])?
+            .ok_or(PackError::PathNotFound)
+    }
+    fn write(&self, path: &Path, bytes: &[u8]) -> PackResult<()> {
+        self.request("PUT", &self.key(path), &[], bytes)?;
+        Ok(())
+    }
+    /// Lists the objects of the directory and of its shard
+    /// subdirectories, sorted by file name.
+    fn list(&self, dir: &Path) -> PackResult<Vec<PathBuf>> {
+        let prefix = format!("{}/", self.key(dir));
+        let mut result = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut query =
+                vec![("list-type", "2"), ("prefix", prefix.as_str())];
+            if let Some(token) = &token {
+                query.push(("continuation-token", token.as_str()));
+            }
+            let body =
+                self.request("GET", "", &query, &[])?.ok_or_else(|| {
+                    PackError::BackendError(format!(
+                        "S3 bucket {} not found",
+                        self.config.bucket
+                    ))
+                })?;
+            let body = String::from_utf8_lossy(&body);
+            for key in xml_values(&body, "Key") {
+                if is_member_key(key.get(prefix.len()..).unwrap_or_default()) {
+                    result.push(self.path(&key));
+                }
+            }
+            token = xml_values(&body, "NextContinuationToken").pop();
+            if token.is_none() {
+                break;
+            }
+        }
+        result.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+        Ok(result)
+    }
+    /// Deletes the object.
+    /// S3 does not report missing objects, so it is checked
+    /// with a HEAD request first.
+    fn delete(&self, path: &Path) -> PackResult<()> {
+        let key = self.key(path);
+        self.request("HEAD", &key, &[], &[])?
+            .ok_or(PackError::PathNotFound)?;
+        self.request("DELETE", &key, &[], &[])?;
+        Ok(())
+    }
+}
+
+// True if key, relative to a VecPack directory, is a member file,
+// directly in it or in one of its shard directories. Hidden files
+// and directories, and quarantined files are skipped.
+fn is_member_key(key: &str) -> bool {
+    let parts: Vec<&str> = key.split('/').collect();
+    let visible = parts.iter().all(|p| !p.is_empty() && !p.starts_with('.'));
+    match parts.as_slice() {
+        [_] => visible,
+        [shard, _] => visible && *shard != QUARANTINE_DIR,
+        _ => false,
+    }
+}
+
+// HMAC-SHA256 of data
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key)
+        .expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Text of every <tag>...</tag> element, unescaped
+fn xml_values(xml: &str, tag: &str) -> Vec<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let mut result = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let end = match rest.find(&close) {
+            Some(end) => end,
+            None => break,
+        };
+        result.push(
+            rest[..end]
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&apos;", "'")
+                .replace("&amp;", "&"),
+        );
+        rest = &rest[end + close.len()..];
+    }
+    result
+}
+
+// Current UTC date (YYYYMMDD) and time (HHMMSS)
+fn utc_now() -> (String, String) {
+    let secs = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (days, rem) = ((secs / 86400) as i64, secs % 86400);
+    // Civil date from days since 1970-01-01
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (
+        format!("{:04}{:02}{:02}", year, month, day),
+        format!("{:02}{:02}{:02}", rem / 3600, rem % 3600 / 60, rem % 60),
+    )
+}
@@ -0,0 +1,152 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Schema diff
+//!
+//! Preflight check before deploying model changes: compares the
+//! current T schema against the stored files, and lists the fields
+//! that would be dropped or defaulted when the files are loaded
+//! and saved back with the new T.
+
+use crate::{header, member_files, PackResult};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// SchemaDiff
+/// Result of schema_diff(). Field names are dot separated
+/// paths, e.g. engine.hp, counted by the number of files
+/// they occur in.
+#[derive(Debug, Clone, Default)]
+pub struct SchemaDiff {
+    /// Number of checked files
+    pub files: usize,
+    /// Fields stored on disk, but unknown to T.
+    /// They would be lost at the next save.
+    pub dropped: BTreeMap<String, usize>,
+    /// Fields of T missing from the stored files.
+    /// They would be filled with default values.
+    pub defaulted: BTreeMap<String, usize>,
+    /// Files that cannot be deserialized as T at all,
+    /// with the error message.
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+impl SchemaDiff {
+    /// True if every file matches the T schema
+    pub fn is_clean(&self) -> bool {
+        self.dropped.is_empty()
+            && self.defaulted.is_empty()
+            && self.failed.is_empty()
+    }
+}
+
+/// Schema diff between T and the files stored in dir
+/// Every file is deserialized as T, serialized back, and
+/// the two field sets are compared. Files are not modified.
+pub fn schema_diff<T>(dir: &Path) -> PackResult<SchemaDiff>
+where
+    for<'de> T: Serialize + Deserialize<'de>,
+{
+    diff_with(dir, |stored| {
+        serde_yaml::from_value::<T>(stored.clone())
+            .and_then(|t| serde_yaml::to_value(&t))
+    })
+}
+
+/// Schema diff between a sample document and the files in dir
+/// The same as schema_diff, but the current schema is given by
+/// a sample, e.g. a T::default() saved by the new code, so it
+/// works without T.
+pub fn schema_diff_sample(
+    dir: &Path,
+    sample: &Value,
+) -> PackResult<SchemaDiff> {
+    diff_with(dir, |_| Ok(sample.clone()))
+}
+
+// Compare the stored files with their current form
+fn diff_with<F>(dir: &Path, current: F) -> PackResult<SchemaDiff>
+where
+    F: Fn(&Value) -> Result<Value, serde_yaml::Error>,
+{
+    let mut result = SchemaDiff::default();
+    for file in member_files(dir)? {
+        result.files += 1;
+        let buffer = std::fs::read(&file)?;
+        let stored = match header::decode_value(&buffer) {
+            Ok(value) => value,
+            Err(err) => {
+                result.failed.push((file, err.to_string()));
+                continue;
+            }
+        };
+        let current = match current(&stored) {
+            Ok(value) => value,
+            Err(err) => {
+                result.failed.push((file, err.to_string()));
+                continue;
+            }
+        };
+        let mut stored_fields = Vec::new();
+        let mut current_fields = Vec::new();
+        collect_fields(&stored, "", &mut stored_fields);
+        collect_fields(&current, "", &mut current_fields);
+        for field in &stored_fields {
+            if !current_fields.contains(field) {
+                *result.dropped.entry(field.clone()).or_insert(0) += 1;
+            }
+        }
+        for field in &current_fields {
+            if !stored_fields.contains(field) {
+                *result.defaulted.entry(field.clone()).or_insert(0) += 1;
+            }
+        }
+    }
+    Ok(result)
+}
+
+// Collect dot separated field paths of nested mappings
+fn collect_fields(value: &Value, prefix: &str, fields: &mut Vec<String>) {
+    if let Value::Mapping(mapping) = value {
+        for (key, value) in mapping {
+            let key = match key {
+                Value::String(key) => key.clone(),
+                other => match serde_yaml::to_string(other) {
+                    Ok(key) => key.trim_start_matches("---").trim().to_string(),
+                    Err(_) => continue,
+                },
+            };
+            let path = if prefix.is_empty() {
+                key
+            } else {
+                format!("{}.{}", prefix, key)
+            };
+            collect_fields(value, &path, fields);
+            fields.push(path);
+        }
+    }
+}
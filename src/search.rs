@@ -0,0 +1,150 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Full-text search
+//!
+//! Optional text index over selected string fields of the
+//! VecPack members. Text is tokenized into lowercase alphanumeric
+//! words, and search results are ranked by TF-IDF.
+
+use crate::{VecPack, VecPackMember};
+use std::collections::HashMap;
+
+/// Text fields function
+/// Returns the texts of a member to index.
+pub type TextFields<T> = Box<dyn Fn(&T) -> Vec<String> + Send + Sync>;
+
+// Inverted index of member texts
+pub(crate) struct TextIndex<T> {
+    fields: TextFields<T>,
+    // Token -> member ID -> term frequency
+    postings: HashMap<String, HashMap<String, usize>>,
+    // Member ID -> its distinct tokens
+    tokens_by_id: HashMap<String, Vec<String>>,
+}
+
+impl<T> TextIndex<T> {
+    pub(crate) fn insert(&mut self, id: &str, data: &T) {
+        self.remove(id);
+        let mut frequencies: HashMap<String, usize> = HashMap::new();
+        for text in (self.fields)(data) {
+            for token in tokenize(&text) {
+                *frequencies.entry(token).or_insert(0) += 1;
+            }
+        }
+        let tokens = frequencies.keys().cloned().collect();
+        for (token, frequency) in frequencies {
+            self.postings
+                .entry(token)
+                .or_default()
+                .insert(id.to_string(), frequency);
+        }
+        self.tokens_by_id.insert(id.to_string(), tokens);
+    }
+    pub(crate) fn remove(&mut self, id: &str) {
+        if let Some(tokens) = self.tokens_by_id.remove(id) {
+            for token in tokens {
+                if let Some(ids) = self.postings.get_mut(&token) {
+                    ids.remove(id);
+                    if ids.is_empty() {
+                        self.postings.remove(&token);
+                    }
+                }
+            }
+        }
+    }
+    pub(crate) fn clear(&mut self) {
+        self.postings.clear();
+        self.tokens_by_id.clear();
+    }
+    // Matching member IDs with their relevance
+    fn search(&self, query: &str) -> Vec<(String, f64)> {
+        let total = self.tokens_by_id.len() as f64;
+        let mut scores: HashMap<&str, f64> = HashMap::new();
+        for token in tokenize(query) {
+            if let Some(ids) = self.postings.get(&token) {
+                let idf = (1.0 + total / ids.len() as f64).ln();
+                for (id, frequency) in ids {
+                    *scores.entry(id).or_insert(0.0) += *frequency as f64 * idf;
+                }
+            }
+        }
+        let mut result = scores
+            .into_iter()
+            .map(|(id, score)| (id.to_string(), score))
+            .collect::<Vec<(String, f64)>>();
+        result.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.0.cmp(&b.0))
+        });
+        result
+    }
+}
+
+// Split text into lowercase alphanumeric words
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Enable full-text search
+    /// fields returns the texts to index for a member, e.g.
+    /// |t| vec![t.title.clone(), t.description.clone()]
+    /// The text index is maintained the same way as secondary
+    /// indexes. Enabling it again replaces the previous one.
+    pub fn enable_text_search<F>(&mut self, fields: F)
+    where
+        F: Fn(&T) -> Vec<String> + Send + Sync + 'static,
+    {
+        let mut text = TextIndex {
+            fields: Box::new(fields),
+            postings: HashMap::new(),
+            tokens_by_id: HashMap::new(),
+        };
+        for pack in &self.data {
+            text.insert(pack.get_id(), &pack.data);
+        }
+        self.indexes.lock().unwrap().text = Some(text);
+    }
+    /// Full-text search
+    /// Returns the IDs of the members matching any word of the query,
+    /// ranked by relevance (highest first). Returns an empty result
+    /// if text search is not enabled.
+    pub fn search(&self, query: &str) -> Vec<(String, f64)> {
+        let mut indexes = self.indexes.lock().unwrap();
+        indexes.refresh(&self.data);
+        match &indexes.text {
+            Some(text) => text.search(query),
+            None => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,140 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Sharded directory layout
+//!
+//! With many members a single flat directory becomes slow on many
+//! filesystems. A sharded VecPack stores its member files in sub
+//! directories named by the hex prefix of the SHA-256 hash of the
+//! member ID, e.g. data/cars/3f/car_1.yml. The layout is persisted in
+//! the .shards.yml file, and handled transparently by insert, load
+//! and remove.
+
+use crate::quarantine::QUARANTINE_DIR;
+use crate::{save_data_object, PackError, PackResult, VecPack, VecPackMember};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// Maximum shard prefix width in hex characters
+pub const MAX_SHARD_WIDTH: usize = 8;
+
+// Path of the sharding config file
+fn shards_path(dir: &Path) -> PathBuf {
+    dir.join(".shards.yml")
+}
+
+// Returns true if the VecPack directory is sharded
+pub(crate) fn is_sharded(dir: &Path) -> bool {
+    shards_path(dir).is_file()
+}
+
+// Persisted shard width of a VecPack directory
+// None if the directory is not sharded.
+pub(crate) fn shard_width(dir: &Path) -> PackResult<Option<usize>> {
+    let path = shards_path(dir);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let width = serde_yaml::from_str(&std::fs::read_to_string(&path)?)
+        .map_err(PackError::deserialize)?;
+    Ok(Some(width))
+}
+
+// Shard sub directories of a VecPack directory
+// Hidden directories (e.g. .trash/) and the quarantine
+// folder are not shards.
+pub(crate) fn shard_dirs(dir: &Path) -> PackResult<Vec<PathBuf>> {
+    let mut result = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let hidden = entry
+            .file_name()
+            .to_str()
+            .map(|n| n.starts_with('.') || n == QUARANTINE_DIR)
+            .unwrap_or(true);
+        if !hidden && entry.file_type()?.is_dir() {
+            result.push(entry.path());
+        }
+    }
+    Ok(result)
+}
+
+// Shard directory name of an ID
+pub(crate) fn shard_name(id: &str, width: usize) -> String {
+    let hash = Sha256::digest(id.as_bytes());
+    let mut name = hash
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    name.truncate(width);
+    name
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Enable sharded directory layout
+    /// Member files are stored in sub directories named by the
+    /// first width hex characters of their ID hash, so 2 means
+    /// 256 shards. Existing members are moved into their shards.
+    /// Calling it again with another width reshards the VecPack.
+    /// The layout is persisted, and it stays enabled on the next load.
+    pub fn enable_sharding(&mut self, width: usize) -> PackResult<()> {
+        if width == 0 || width > MAX_SHARD_WIDTH {
+            return Err(PackError::InternalError(format!(
+                "Shard width must be between 1 and {}, got {}",
+                MAX_SHARD_WIDTH, width
+            )));
+        }
+        self.sync_location();
+        self.check_writable()?;
+        save_data_object(&shards_path(&self.dir()), width)?;
+        self.shard_width = Some(width);
+        for pos in 0..self.data.len() {
+            let to = self.new_member_path(self.data[pos].get_id())?;
+            if to != self.data[pos].path {
+                std::fs::rename(&self.data[pos].path, &to)?;
+                self.data[pos].path = to;
+            }
+        }
+        // Remove shards left empty by resharding
+        for dir in shard_dirs(&self.dir())? {
+            if std::fs::read_dir(&dir)?.next().is_none() {
+                std::fs::remove_dir(&dir)?;
+            }
+        }
+        Ok(())
+    }
+    /// Shard prefix width, or None if VecPack is not sharded
+    pub fn shard_width(&self) -> Option<usize> {
+        self.shard_width
+    }
+    // Load sharding config if there is any
+    pub(crate) fn load_sharding(&mut self) -> PackResult<()> {
+        self.shard_width = shard_width(&self.dir())?;
+        Ok(())
+    }
+}
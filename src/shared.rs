@@ -0,0 +1,200 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Thread-safe handles
+//!
+//! SharedPack<T> and SharedVecPack<T> wrap a Pack<T> or VecPack<T>
+//! into Arc<RwLock<..>>, so they are cloneable, Send and Sync, and
+//! can be shared between the threads of a server. Data is accessed
+//! through closures, so locks are never held by the caller:
+//!
+//! ```rust
+//! use storaget::*;
+//!
+//! let dir = testing::TempDir::new().unwrap();
+//! let counter: SharedPack<i32> =
+//!     SharedPack::load_or_init(dir.path().to_path_buf(), "counter")
+//!         .unwrap();
+//! let handle = counter.clone();
+//! std::thread::spawn(move || handle.update(|i| *i += 1).unwrap())
+//!     .join()
+//!     .unwrap();
+//! assert_eq!(counter.get(|i| *i), 1);
+//! ```
+
+use crate::{Pack, PackResult, VecPack, VecPackMember};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// SharedPack<T>
+/// Cloneable, thread-safe handle of a Pack<T>
+#[derive(Debug)]
+pub struct SharedPack<T>
+where
+    T: Serialize + Sized + Clone,
+{
+    inner: Arc<RwLock<Pack<T>>>,
+}
+
+impl<T> Clone for SharedPack<T>
+where
+    T: Serialize + Sized + Clone,
+{
+    fn clone(&self) -> Self {
+        SharedPack {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> SharedPack<T>
+where
+    for<'de> T: Serialize + Deserialize<'de> + Default + Sized + Clone,
+{
+    /// Load or init SharedPack<T>
+    /// The same as Pack::load_or_init
+    pub fn load_or_init(path: PathBuf, file_id: &str) -> PackResult<Self> {
+        Ok(SharedPack::new(Pack::load_or_init(path, file_id)?))
+    }
+}
+
+impl<T> SharedPack<T>
+where
+    for<'de> T: Serialize + Deserialize<'de> + Sized + Clone,
+{
+    /// New SharedPack<T> of a Pack<T>
+    pub fn new(pack: Pack<T>) -> Self {
+        SharedPack {
+            inner: Arc::new(RwLock::new(pack)),
+        }
+    }
+    /// Access data through closure
+    /// Holds the read lock while f runs.
+    pub fn get<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&T) -> R,
+    {
+        f(self.inner.read().unwrap().unpack())
+    }
+    /// Update data through closure, then save it
+    /// The same as Pack::update, holds the write lock
+    /// while f runs and data is saved.
+    pub fn update<F, R>(&self, f: F) -> PackResult<R>
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        self.inner.write().unwrap().update(f)
+    }
+    /// Read lock of the Pack<T>
+    pub fn read(&self) -> RwLockReadGuard<'_, Pack<T>> {
+        self.inner.read().unwrap()
+    }
+    /// Write lock of the Pack<T>
+    pub fn write(&self) -> RwLockWriteGuard<'_, Pack<T>> {
+        self.inner.write().unwrap()
+    }
+}
+
+/// SharedVecPack<T>
+/// Cloneable, thread-safe handle of a VecPack<T>
+pub struct SharedVecPack<T>
+where
+    T: VecPackMember,
+{
+    inner: Arc<RwLock<VecPack<T>>>,
+}
+
+impl<T> Clone for SharedVecPack<T>
+where
+    T: VecPackMember,
+{
+    fn clone(&self) -> Self {
+        SharedVecPack {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> SharedVecPack<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    /// Load or init SharedVecPack<T>
+    /// The same as VecPack::load_or_init
+    pub fn load_or_init(path: PathBuf) -> PackResult<Self> {
+        Ok(SharedVecPack::new(VecPack::load_or_init(path)?))
+    }
+    /// New SharedVecPack<T> of a VecPack<T>
+    pub fn new(vecpack: VecPack<T>) -> Self {
+        SharedVecPack {
+            inner: Arc::new(RwLock::new(vecpack)),
+        }
+    }
+    /// Find member by ID, and access it through closure
+    /// Holds the read lock while f runs.
+    pub fn find_id<F, R>(&self, id: &str, f: F) -> PackResult<R>
+    where
+        F: FnOnce(&T) -> R,
+    {
+        let vecpack = self.inner.read().unwrap();
+        Ok(f(vecpack.find_id(id)?.unpack()))
+    }
+    /// Update member by ID through closure, then save it
+    /// The same as Pack::update of the member, holds the
+    /// write lock while f runs and data is saved.
+    pub fn update<F, R>(&self, id: &str, f: F) -> PackResult<R>
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        self.inner.write().unwrap().find_id_mut(id)?.update(f)
+    }
+    /// Insert a new member
+    /// The same as VecPack::insert
+    pub fn insert(&self, item: T) -> PackResult<()> {
+        self.inner.write().unwrap().insert(item)
+    }
+    /// Remove member by ID
+    /// The same as VecPack::remove_by_id
+    pub fn remove_by_id(&self, id: &str) -> PackResult<T> {
+        self.inner.write().unwrap().remove_by_id(id)
+    }
+    /// Number of members
+    pub fn len(&self) -> usize {
+        self.inner.read().unwrap().len()
+    }
+    /// True if there is no member
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Read lock of the VecPack<T>
+    pub fn read(&self) -> RwLockReadGuard<'_, VecPack<T>> {
+        self.inner.read().unwrap()
+    }
+    /// Write lock of the VecPack<T>
+    pub fn write(&self) -> RwLockWriteGuard<'_, VecPack<T>> {
+        self.inner.write().unwrap()
+    }
+}
@@ -0,0 +1,265 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Inter-process change notification
+//!
+//! One writer process and many reader processes can share a
+//! VecPack directory. With change notification enabled the writer
+//! bumps a sequence number in the hidden .seq file after every change,
+//! so readers only check this single file, instead of polling the
+//! mtime of every member file.
+//!
+//! On unix a reader can also register a datagram socket in the
+//! hidden .notify/ folder; the writer sends the new sequence number
+//! to every registered socket, so waiting readers wake up promptly.
+
+use crate::{PackError, PackResult, VecPack, VecPackMember};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+#[cfg(unix)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Sequence file is checked this often while waiting
+// without a socket.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+#[cfg(unix)]
+static SOCKET_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// Writer side of the change notification
+pub(crate) struct Notifier {
+    dir: PathBuf,
+}
+
+impl Notifier {
+    pub(crate) fn new(dir: PathBuf) -> Notifier {
+        Notifier { dir }
+    }
+    pub(crate) fn relocate(&mut self, dir: &Path) {
+        self.dir = dir.to_path_buf();
+    }
+    // Bump the sequence number, then wake up the subscribers.
+    // Returns the new sequence number.
+    pub(crate) fn notify(&self) -> PackResult<u64> {
+        let seq = read_seq(&seq_path(&self.dir))? + 1;
+        write_seq(&self.dir, seq)?;
+        #[cfg(unix)]
+        self.wake_up(seq);
+        Ok(seq)
+    }
+    // Send the sequence number to every registered socket.
+    // Sockets of readers gone without cleanup are removed.
+    #[cfg(unix)]
+    fn wake_up(&self, seq: u64) {
+        let entries = match std::fs::read_dir(self.dir.join(".notify")) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        let socket = match UnixDatagram::unbound() {
+            Ok(socket) => socket,
+            Err(_) => return,
+        };
+        let _ = socket.set_nonblocking(true);
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if let Err(err) = socket.send_to(&seq.to_le_bytes(), &path) {
+                if err.kind() == std::io::ErrorKind::ConnectionRefused {
+                    let _ = std::fs::remove_file(&path);
+                }
+            }
+        }
+    }
+}
+
+// Path of the sequence file
+fn seq_path(dir: &Path) -> PathBuf {
+    dir.join(".seq")
+}
+
+// Read sequence number
+// Missing sequence file means 0.
+fn read_seq(path: &Path) -> PackResult<u64> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => content.trim().parse::<u64>().map_err(|e| {
+            PackError::custom_deserialize(format!(
+                "Invalid sequence file {}: {}",
+                path.display(),
+                e
+            ))
+        }),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(0),
+        Err(err) => Err(err.into()),
+    }
+}
+
+// Write sequence number through a temp file and rename,
+// so readers never see a half written number.
+fn write_seq(dir: &Path, seq: u64) -> PackResult<()> {
+    let tmp = dir.join(".seq.tmp");
+    std::fs::write(&tmp, seq.to_string())?;
+    std::fs::rename(&tmp, seq_path(dir))?;
+    Ok(())
+}
+
+/// ChangeWatcher
+/// Reader side of the change notification.
+/// Reports whether the VecPack directory has changed
+/// since the last check.
+pub struct ChangeWatcher {
+    seq_path: PathBuf,
+    last: u64,
+    #[cfg(unix)]
+    socket: Option<(UnixDatagram, PathBuf)>,
+}
+
+impl ChangeWatcher {
+    /// New ChangeWatcher for a VecPack directory
+    /// The current sequence number is the starting point,
+    /// so only later changes are reported.
+    pub fn new(dir: PathBuf) -> PackResult<ChangeWatcher> {
+        let seq_path = seq_path(&dir);
+        let last = read_seq(&seq_path)?;
+        Ok(ChangeWatcher {
+            seq_path,
+            last,
+            #[cfg(unix)]
+            socket: None,
+        })
+    }
+    /// New ChangeWatcher with socket wake up
+    /// Registers a datagram socket in the .notify/ folder,
+    /// so wait_for_change() returns right after the writer
+    /// has notified, instead of at the next poll.
+    /// The socket is removed when the watcher is dropped.
+    #[cfg(unix)]
+    pub fn with_socket(dir: PathBuf) -> PackResult<ChangeWatcher> {
+        let mut watcher = ChangeWatcher::new(dir.clone())?;
+        let folder = dir.join(".notify");
+        std::fs::create_dir_all(&folder)?;
+        let path = folder.join(format!(
+            "{}-{}.sock",
+            std::process::id(),
+            SOCKET_COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_file(&path);
+        let socket = UnixDatagram::bind(&path)?;
+        watcher.socket = Some((socket, path));
+        Ok(watcher)
+    }
+    /// Last seen sequence number
+    pub fn sequence(&self) -> u64 {
+        self.last
+    }
+    /// Returns true if the VecPack has changed since
+    /// the last check, and remembers the new sequence number.
+    pub fn has_changed(&mut self) -> PackResult<bool> {
+        let seq = read_seq(&self.seq_path)?;
+        if seq != self.last {
+            self.last = seq;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+    /// Wait for change
+    /// Blocks until the VecPack changes, or the timeout elapses.
+    /// Returns true if there was a change.
+    pub fn wait_for_change(&mut self, timeout: Duration) -> PackResult<bool> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if self.has_changed()? {
+                return Ok(true);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(false);
+            }
+            self.sleep((deadline - now).min(POLL_INTERVAL));
+        }
+    }
+    // Sleep until the next check
+    // With a socket we return as soon as a notification arrives.
+    fn sleep(&self, duration: Duration) {
+        #[cfg(unix)]
+        {
+            if let Some((socket, _)) = &self.socket {
+                if socket.set_read_timeout(Some(duration)).is_ok() {
+                    let mut buffer = [0u8; 8];
+                    let _ = socket.recv(&mut buffer);
+                    return;
+                }
+            }
+        }
+        std::thread::sleep(duration);
+    }
+}
+
+impl Drop for ChangeWatcher {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        {
+            if let Some((_, path)) = &self.socket {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Enable change notification
+    /// From now on every change (insert, update through a member,
+    /// remove, restore, sort) bumps the sequence number in the .seq
+    /// file. Readers in other processes use ChangeWatcher to learn
+    /// about the changes. The mode is persisted by the .seq file,
+    /// and it stays enabled on the next load.
+    pub fn enable_change_notification(&mut self) -> PackResult<()> {
+        self.check_writable()?;
+        let path = seq_path(&self.dir());
+        if !path.exists() {
+            write_seq(&self.dir(), 0)?;
+        }
+        self.ctx.set_notifier(Notifier::new(self.dir()));
+        Ok(())
+    }
+    /// Returns true if change notification is enabled
+    pub fn is_change_notification_enabled(&self) -> bool {
+        self.ctx.has_notifier()
+    }
+    /// ChangeWatcher for the VecPack directory
+    pub fn watcher(&self) -> PackResult<ChangeWatcher> {
+        ChangeWatcher::new(self.dir())
+    }
+    // Enable change notification if the .seq file exists
+    pub(crate) fn load_notifier(&mut self) {
+        if seq_path(&self.dir()).is_file() {
+            self.ctx.set_notifier(Notifier::new(self.dir()));
+        }
+    }
+}
@@ -0,0 +1,120 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! sled backend
+//!
+//! Enabled by the `sled` feature. Stores packs in an embedded sled
+//! key-value database instead of one file per object, which scales
+//! much better for very large collections. Every VecPack directory
+//! is a sled tree, and members are stored under their file name
+//! ("<id>.yml") as key. The VecPack API stays the same.
+//!
+//! sled flushes its writes in the background (every 500ms by
+//! default) and when the database is dropped; call flush() to make
+//! the writes durable right away.
+
+use crate::backend::StorageBackend;
+use crate::{PackError, PackResult};
+use std::path::{Path, PathBuf};
+
+/// SledBackend
+/// Stores files in a sled database
+#[derive(Clone)]
+pub struct SledBackend {
+    db: ::sled::Db,
+}
+
+impl SledBackend {
+    /// Open or create a sled database at path
+    pub fn open(path: impl AsRef<Path>) -> PackResult<Self> {
+        Ok(SledBackend {
+            db: ::sled::open(path).map_err(sled_error)?,
+        })
+    }
+    /// SledBackend over an already opened database
+    pub fn from_db(db: ::sled::Db) -> Self {
+        SledBackend { db }
+    }
+    /// Flush every pending write to disk
+    pub fn flush(&self) -> PackResult<()> {
+        self.db.flush().map_err(sled_error)?;
+        Ok(())
+    }
+    // Tree of the file's directory, and the file name as key
+    fn locate(&self, path: &Path) -> PackResult<(::sled::Tree, String)> {
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let name = path
+            .file_name()
+            .ok_or(PackError::PathNotFound)?
+            .to_string_lossy()
+            .to_string();
+        Ok((self.tree(dir)?, name))
+    }
+    fn tree(&self, dir: &Path) -> PackResult<::sled::Tree> {
+        self.db
+            .open_tree(dir.to_string_lossy().as_bytes())
+            .map_err(sled_error)
+    }
+}
+
+fn sled_error(err: ::sled::Error) -> PackError {
+    match err {
+        ::sled::Error::Io(err) => err.into(),
+        err => PackError::BackendError(format!("sled: {}", err)),
+    }
+}
+
+impl StorageBackend for SledBackend {
+    fn read(&self, path: &Path) -> PackResult<Vec<u8>> {
+        let (tree, key) = self.locate(path)?;
+        tree.get(key)
+            .map_err(sled_error)?
+            .map(|value| value.to_vec())
+            .ok_or(PackError::PathNotFound)
+    }
+    fn write(&self, path: &Path, bytes: &[u8]) -> PackResult<()> {
+        let (tree, key) = self.locate(path)?;
+        tree.insert(key, bytes).map_err(sled_error)?;
+        Ok(())
+    }
+    fn list(&self, dir: &Path) -> PackResult<Vec<PathBuf>> {
+        let mut result = Vec::new();
+        for key in self.tree(dir)?.iter().keys() {
+            let key = key.map_err(sled_error)?;
+            let name = String::from_utf8_lossy(&key).to_string();
+            if !name.starts_with('.') {
+                result.push(dir.join(name));
+            }
+        }
+        Ok(result)
+    }
+    fn delete(&self, path: &Path) -> PackResult<()> {
+        let (tree, key) = self.locate(path)?;
+        tree.remove(key)
+            .map_err(sled_error)?
+            .map(|_| ())
+            .ok_or(PackError::PathNotFound)
+    }
+}
@@ -0,0 +1,298 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Snapshots
+//!
+//! Point-in-time snapshots of a VecPack, kept in its hidden
+//! .snapshots/ directory as backup_to() archives named by their
+//! creation time. With enable_snapshots() a snapshot is taken
+//! automatically when the interval has passed since the last
+//! one: at load, and after every saved change. Old snapshots
+//! are pruned by a RetentionPolicy, e.g. keep hourly snapshots
+//! for a day and daily ones for a month.
+//!
+//! rollback_to() restores the VecPack to a snapshot.
+
+use crate::backup::{write_archive, RestoreReport, RestoreStrategy};
+use crate::{
+    member_files, unix_millis, PackError, PackResult, VecPack, VecPackMember,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+const HOUR: u128 = 60 * 60 * 1000;
+const DAY: u128 = 24 * HOUR;
+const WEEK: u128 = 7 * DAY;
+
+/// RetentionPolicy
+/// Number of snapshots to keep: the newest snapshot of each of
+/// the last N hours, days and weeks that have a snapshot.
+/// The newest snapshot is always kept.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct RetentionPolicy {
+    pub hourly: usize,
+    pub daily: usize,
+    pub weekly: usize,
+}
+
+impl Default for RetentionPolicy {
+    /// Hourly for a day, daily for a month
+    fn default() -> Self {
+        RetentionPolicy {
+            hourly: 24,
+            daily: 30,
+            weekly: 0,
+        }
+    }
+}
+
+/// Snapshot
+/// A snapshot archive of a VecPack
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    /// Creation time in milliseconds since UNIX EPOCH
+    pub created_at: u128,
+    /// Archive path
+    pub path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct SnapshotConfig {
+    // Seconds between automatic snapshots
+    interval: u64,
+    policy: RetentionPolicy,
+}
+
+// Automatic snapshots of a VecPack, kept in its context
+#[derive(Debug)]
+pub(crate) struct Snapshots {
+    root: PathBuf,
+    config: SnapshotConfig,
+    // Creation time of the last snapshot
+    last: Mutex<Option<u128>>,
+}
+
+impl Snapshots {
+    fn new(root: PathBuf, config: SnapshotConfig) -> PackResult<Snapshots> {
+        let last = list(&root)?.first().map(|s| s.created_at);
+        Ok(Snapshots {
+            root,
+            config,
+            last: Mutex::new(last),
+        })
+    }
+    // The same snapshots, after the VecPack directory has moved
+    pub(crate) fn relocate(&self, root: &Path) -> Snapshots {
+        Snapshots {
+            root: root.to_path_buf(),
+            config: self.config.clone(),
+            last: Mutex::new(*self.last.lock().unwrap()),
+        }
+    }
+    // Take a snapshot of the member files if the interval has passed
+    // Returns true if a snapshot was taken.
+    pub(crate) fn take_if_due(&self) -> PackResult<bool> {
+        let now = unix_millis(SystemTime::now());
+        let mut last = self.last.lock().unwrap();
+        let interval = self.config.interval as u128 * 1000;
+        if last.map(|l| now < l + interval).unwrap_or(false) {
+            return Ok(false);
+        }
+        let mut files = Vec::new();
+        for path in member_files(&self.root)? {
+            let file = path.strip_prefix(&self.root).unwrap_or(&path);
+            files.push((file.to_path_buf(), std::fs::read(&path)?));
+        }
+        write_archive(&archive_path(&self.root, now), files)?;
+        *last = Some(now);
+        prune(&self.root, &self.config.policy)?;
+        Ok(true)
+    }
+}
+
+fn snapshots_dir(root: &Path) -> PathBuf {
+    root.join(".snapshots")
+}
+
+fn archive_path(root: &Path, created_at: u128) -> PathBuf {
+    snapshots_dir(root).join(format!("{}.tar.gz", created_at))
+}
+
+// Snapshots of a VecPack directory, newest first
+fn list(root: &Path) -> PackResult<Vec<Snapshot>> {
+    let dir = snapshots_dir(root);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut result = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let created_at = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_suffix(".tar.gz"))
+            .and_then(|n| n.parse().ok());
+        if let Some(created_at) = created_at {
+            result.push(Snapshot { created_at, path });
+        }
+    }
+    result.sort_by_key(|s| std::cmp::Reverse(s.created_at));
+    Ok(result)
+}
+
+// Remove the snapshots the policy does not keep
+// Returns the number of removed snapshots.
+fn prune(root: &Path, policy: &RetentionPolicy) -> PackResult<usize> {
+    let snapshots = list(root)?;
+    let mut keep = HashSet::new();
+    if let Some(newest) = snapshots.first() {
+        keep.insert(newest.created_at);
+    }
+    for (period, count) in [
+        (HOUR, policy.hourly),
+        (DAY, policy.daily),
+        (WEEK, policy.weekly),
+    ] {
+        let mut periods = HashSet::new();
+        for snapshot in &snapshots {
+            if periods.len() >= count {
+                break;
+            }
+            if periods.insert(snapshot.created_at / period) {
+                keep.insert(snapshot.created_at);
+            }
+        }
+    }
+    let mut removed = 0;
+    for snapshot in snapshots {
+        if !keep.contains(&snapshot.created_at) {
+            std::fs::remove_file(snapshot.path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Enable automatic snapshots
+    /// A snapshot is taken when interval has passed since the
+    /// last one, checked at load and after every saved change.
+    /// Takes the first snapshot if it is due.
+    pub fn enable_snapshots(
+        &mut self,
+        interval: Duration,
+        policy: RetentionPolicy,
+    ) -> PackResult<()> {
+        self.check_writable()?;
+        let config = SnapshotConfig {
+            interval: interval.as_secs(),
+            policy,
+        };
+        crate::save_data_object(&self.snapshot_config_path(), &config)?;
+        let snapshots = Snapshots::new(self.dir(), config)?;
+        snapshots.take_if_due()?;
+        self.ctx.set_snapshots(Some(snapshots));
+        Ok(())
+    }
+    /// Stop automatic snapshots
+    /// Existing snapshots are kept.
+    pub fn disable_snapshots(&mut self) -> PackResult<()> {
+        self.check_writable()?;
+        let path = self.snapshot_config_path();
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        self.ctx.set_snapshots(None);
+        Ok(())
+    }
+    /// Take a snapshot now
+    /// Applies the retention policy if snapshots are enabled.
+    pub fn take_snapshot(&self) -> PackResult<Snapshot> {
+        self.check_writable()?;
+        let dir = self.dir();
+        let mut created_at = unix_millis(SystemTime::now());
+        // Snapshots are named by their creation time
+        while archive_path(&dir, created_at).exists() {
+            created_at += 1;
+        }
+        let path = archive_path(&dir, created_at);
+        self.backup_to(&path)?;
+        if let Some(policy) = self.snapshot_policy()? {
+            prune(&dir, &policy)?;
+        }
+        Ok(Snapshot { created_at, path })
+    }
+    /// Snapshots of the VecPack, newest first
+    pub fn snapshots(&self) -> PackResult<Vec<Snapshot>> {
+        list(&self.dir())
+    }
+    /// Roll back to a snapshot
+    /// Members become the snapshot members, see
+    /// restore_from() with RestoreStrategy::Replace.
+    pub fn rollback_to(
+        &mut self,
+        snapshot: &Snapshot,
+    ) -> PackResult<RestoreReport>
+    where
+        for<'de> T: Deserialize<'de> + Default,
+    {
+        if !snapshot.path.starts_with(snapshots_dir(&self.dir())) {
+            return Err(PackError::PathNotFound);
+        }
+        self.restore_from(&snapshot.path, RestoreStrategy::Replace)
+    }
+    // Load snapshot config, and take a snapshot if due
+    pub(crate) fn load_snapshots(&mut self) -> PackResult<()> {
+        let path = self.snapshot_config_path();
+        if path.exists() {
+            let config = serde_yaml::from_str(&std::fs::read_to_string(path)?)?;
+            let snapshots = Snapshots::new(self.dir(), config)?;
+            // Only the writer takes snapshots
+            if !self.is_read_only() {
+                snapshots.take_if_due()?;
+            }
+            self.ctx.set_snapshots(Some(snapshots));
+        }
+        Ok(())
+    }
+    fn snapshot_policy(&self) -> PackResult<Option<RetentionPolicy>> {
+        let path = self.snapshot_config_path();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let config: SnapshotConfig =
+            serde_yaml::from_str(&std::fs::read_to_string(path)?)?;
+        Ok(Some(config.policy))
+    }
+    fn snapshot_config_path(&self) -> PathBuf {
+        self.dir().join(".snapshots.yml")
+    }
+}
@@ -0,0 +1,155 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! SQLite backend
+//!
+//! Enabled by the `sqlite` feature. Stores packs in a single SQLite
+//! database file instead of thousands of small files. Every member
+//! is a row of the `packs` table, keyed by its directory and ID.
+//! Each write is an atomic SQLite transaction, and lookups by ID go
+//! through the primary key index. The VecPack API stays the same.
+
+use crate::backend::StorageBackend;
+use crate::{PackError, PackResult};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// SqliteBackend
+/// Stores files as rows of a SQLite database
+pub struct SqliteBackend {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteBackend {
+    /// Open or create a SQLite database at path
+    pub fn open(path: impl AsRef<Path>) -> PackResult<Self> {
+        SqliteBackend::from_connection(
+            Connection::open(path).map_err(sqlite_error)?,
+        )
+    }
+    /// New SqliteBackend with an in-memory database
+    pub fn open_in_memory() -> PackResult<Self> {
+        SqliteBackend::from_connection(
+            Connection::open_in_memory().map_err(sqlite_error)?,
+        )
+    }
+    /// SqliteBackend over an already opened connection
+    /// Creates the packs table if it does not exist.
+    pub fn from_connection(conn: Connection) -> PackResult<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS packs (
+                dir TEXT NOT NULL,
+                id TEXT NOT NULL,
+                data BLOB NOT NULL,
+                PRIMARY KEY (dir, id)
+            )",
+        )
+        .map_err(sqlite_error)?;
+        Ok(SqliteBackend {
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+// Directory and ID of a member file path
+// e.g. data/cars/1.yml -> ("data/cars", "1")
+fn row_key(path: &Path) -> PackResult<(String, String)> {
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let id = path.file_stem().ok_or(PackError::PathNotFound)?;
+    Ok((
+        dir.to_string_lossy().to_string(),
+        id.to_string_lossy().to_string(),
+    ))
+}
+
+fn sqlite_error(err: rusqlite::Error) -> PackError {
+    PackError::BackendError(format!("sqlite: {}", err))
+}
+
+impl StorageBackend for SqliteBackend {
+    fn read(&self, path: &Path) -> PackResult<Vec<u8>> {
+        let (dir, id) = row_key(path)?;
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT data FROM packs WHERE dir = ?1 AND id = ?2",
+                params![dir, id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sqlite_error)?
+            .ok_or(PackError::PathNotFound)
+    }
+    fn write(&self, path: &Path, bytes: &[u8]) -> PackResult<()> {
+        let (dir, id) = row_key(path)?;
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO packs (dir, id, data)
+                VALUES (?1, ?2, ?3)",
+                params![dir, id, bytes],
+            )
+            .map_err(sqlite_error)?;
+        Ok(())
+    }
+    fn list(&self, dir: &Path) -> PackResult<Vec<PathBuf>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT id FROM packs WHERE dir = ?1 ORDER BY id")
+            .map_err(sqlite_error)?;
+        let ids = stmt
+            .query_map(params![dir.to_string_lossy()], |row| {
+                row.get::<_, String>(0)
+            })
+            .map_err(sqlite_error)?;
+        let mut result = Vec::new();
+        for id in ids {
+            let id = id.map_err(sqlite_error)?;
+            if !id.starts_with('.') {
+                result.push(dir.join(format!("{}.yml", id)));
+            }
+        }
+        Ok(result)
+    }
+    fn delete(&self, path: &Path) -> PackResult<()> {
+        let (dir, id) = row_key(path)?;
+        let deleted = self
+            .conn
+            .lock()
+            .unwrap()
+            .execute(
+                "DELETE FROM packs WHERE dir = ?1 AND id = ?2",
+                params![dir, id],
+            )
+            .map_err(sqlite_error)?;
+        match deleted {
+            0 => Err(PackError::PathNotFound),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,103 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! OpenTelemetry instrumentation
+//!
+//! With the "otel" feature, storage operations are recorded as spans
+//! of the global OpenTelemetry tracer ("storaget"), and member changes
+//! are added as "storaget.change" events to the active span. Spans are
+//! children of the caller's active span, so they show up in the same
+//! trace as the request that made them. Until the application installs
+//! a tracer provider, the global tracer is a no-op.
+//!
+//! Without the feature these functions only run the operation.
+
+use crate::{ChangeEvent, PackResult};
+use std::path::Path;
+
+#[cfg(feature = "otel")]
+use crate::ChangeKind;
+#[cfg(feature = "otel")]
+use opentelemetry::trace::{get_active_span, Status, TraceContextExt, Tracer};
+#[cfg(feature = "otel")]
+use opentelemetry::{global, KeyValue};
+
+// Name of the instrumentation scope
+#[cfg(feature = "otel")]
+const TRACER: &str = "storaget";
+
+// Run a storage operation inside a span
+#[cfg(feature = "otel")]
+pub(crate) fn span<F, R>(name: &'static str, path: &Path, f: F) -> PackResult<R>
+where
+    F: FnOnce() -> PackResult<R>,
+{
+    global::tracer(TRACER).in_span(name, |cx| {
+        let span = cx.span();
+        span.set_attribute(KeyValue::new(
+            "storaget.path",
+            path.display().to_string(),
+        ));
+        let res = f();
+        if let Err(err) = &res {
+            span.set_status(Status::error(err.to_string()));
+        }
+        res
+    })
+}
+
+#[cfg(not(feature = "otel"))]
+pub(crate) fn span<F, R>(
+    _name: &'static str,
+    _path: &Path,
+    f: F,
+) -> PackResult<R>
+where
+    F: FnOnce() -> PackResult<R>,
+{
+    f()
+}
+
+// Record a member change on the active span
+#[cfg(feature = "otel")]
+pub(crate) fn change_event(event: &ChangeEvent) {
+    let kind = match event.kind {
+        ChangeKind::Created => "created",
+        ChangeKind::Updated => "updated",
+        ChangeKind::Removed => "removed",
+    };
+    get_active_span(|span| {
+        span.add_event(
+            "storaget.change",
+            vec![
+                KeyValue::new("storaget.id", event.id.clone()),
+                KeyValue::new("storaget.kind", kind),
+            ],
+        )
+    });
+}
+
+#[cfg(not(feature = "otel"))]
+pub(crate) fn change_event(_event: &ChangeEvent) {}
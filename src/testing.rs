@@ -0,0 +1,187 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Test fixtures
+//!
+//! Packs in unique temporary directories, removed on drop, so
+//! tests do not interfere with each other through shared paths:
+//!
+//! ```rust
+//! use storaget::testing::TempPack;
+//!
+//! let mut counter: TempPack<i32> = TempPack::new("counter").unwrap();
+//! counter.update(|i| *i += 1).unwrap();
+//! assert_eq!(**counter, 1);
+//! ```
+
+use crate::{Pack, PackResult, VecPack, VecPackMember};
+use serde::{Deserialize, Serialize};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// TempDir
+/// Unique directory under the system temp directory,
+/// removed with its content on drop.
+#[derive(Debug)]
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// Create a new unique temp directory
+    pub fn new() -> PackResult<TempDir> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let path = std::env::temp_dir().join(format!(
+            "storaget-{}-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed),
+            nanos
+        ));
+        std::fs::create_dir_all(&path)?;
+        Ok(TempDir { path })
+    }
+    /// Path of the directory
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// TempPack<T>
+/// Pack<T> in its own TempDir. Derefs to Pack<T>.
+#[derive(Debug)]
+pub struct TempPack<T>
+where
+    T: Serialize + Sized + Clone,
+{
+    // Dropped before dir
+    pack: Pack<T>,
+    dir: TempDir,
+}
+
+impl<T> TempPack<T>
+where
+    for<'de> T: Serialize + Deserialize<'de> + Default + Sized + Clone,
+{
+    /// New Pack<T> with the given file ID in a new TempDir
+    pub fn new(file_id: &str) -> PackResult<TempPack<T>> {
+        let dir = TempDir::new()?;
+        Ok(TempPack {
+            pack: Pack::load_or_init(dir.path().to_path_buf(), file_id)?,
+            dir,
+        })
+    }
+    /// Directory of the Pack
+    pub fn dir(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+impl<T> Deref for TempPack<T>
+where
+    T: Serialize + Sized + Clone,
+{
+    type Target = Pack<T>;
+    fn deref(&self) -> &Self::Target {
+        &self.pack
+    }
+}
+
+impl<T> DerefMut for TempPack<T>
+where
+    T: Serialize + Sized + Clone,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.pack
+    }
+}
+
+/// TempVecPack<T>
+/// VecPack<T> in its own TempDir. Derefs to VecPack<T>.
+pub struct TempVecPack<T>
+where
+    T: VecPackMember,
+{
+    // Dropped before dir
+    vecpack: VecPack<T>,
+    dir: TempDir,
+}
+
+impl<T> TempVecPack<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    /// New empty VecPack<T> in a new TempDir
+    pub fn new() -> PackResult<TempVecPack<T>> {
+        let dir = TempDir::new()?;
+        Ok(TempVecPack {
+            vecpack: VecPack::load_or_init(dir.path().to_path_buf())?,
+            dir,
+        })
+    }
+    /// New VecPack<T> in a new TempDir with the given members
+    pub fn with_items(items: Vec<T>) -> PackResult<TempVecPack<T>> {
+        let mut result = TempVecPack::new()?;
+        for item in items {
+            result.vecpack.insert(item)?;
+        }
+        Ok(result)
+    }
+    /// Directory of the VecPack, e.g. to load
+    /// another instance of it
+    pub fn dir(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+impl<T> Deref for TempVecPack<T>
+where
+    T: VecPackMember,
+{
+    type Target = VecPack<T>;
+    fn deref(&self) -> &Self::Target {
+        &self.vecpack
+    }
+}
+
+impl<T> DerefMut for TempVecPack<T>
+where
+    T: VecPackMember,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.vecpack
+    }
+}
@@ -0,0 +1,245 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Expiring members
+//!
+//! Members can get an expiry time, persisted into .expiry.yml
+//! as ID -> UNIX millis. Expired members are removed by
+//! purge_expired(), and on load, so caches and session stores
+//! do not grow forever. Until then they are hidden from the
+//! read methods (find_id, find, filter, ids, page, iter_live, ...),
+//! but their IDs are still taken. Members without expiry never
+//! expire.
+
+use crate::{
+    save_data_object, unix_millis, ChangeEvent, ChangeKind, Pack, PackError,
+    PackResult, VecPack, VecPackMember,
+};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+impl<T> VecPack<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    /// Insert T, that expires after ttl
+    /// If the expiry cannot be saved, the member is removed,
+    /// so it is never stored without its expiry.
+    pub fn insert_with_ttl(
+        &mut self,
+        item: T,
+        ttl: Duration,
+    ) -> PackResult<()> {
+        let id = item.get_id().to_string();
+        self.insert(item)?;
+        self.set_ttl(&id, ttl).inspect_err(|_| {
+            let _ = self.remove_by_id(&id);
+        })
+    }
+    /// Set member to expire after ttl from now
+    pub fn set_ttl(&mut self, id: &str, ttl: Duration) -> PackResult<()> {
+        self.set_expiry(id, Some(SystemTime::now() + ttl))
+    }
+    /// Set member expiry time, or clear it with None
+    /// Returns PackError::ObjectNotFound if there is no such member.
+    pub fn set_expiry(
+        &mut self,
+        id: &str,
+        expires_at: Option<SystemTime>,
+    ) -> PackResult<()> {
+        self.sync_location();
+        self.check_mutable()?;
+        if self.pack_by_id(id).is_none() {
+            return Err(PackError::ObjectNotFound);
+        }
+        let previous = match expires_at {
+            Some(at) => self.expiry.insert(id.to_string(), unix_millis(at)),
+            None => self.expiry.remove(id),
+        };
+        // Keep the saved expiry in memory if saving fails
+        self.save_expiry().inspect_err(|_| match previous {
+            Some(at) => {
+                self.expiry.insert(id.to_string(), at);
+            }
+            None => {
+                self.expiry.remove(id);
+            }
+        })
+    }
+    /// Expiry time of a member
+    /// None if it never expires, or there is no such member.
+    pub fn expires_at(&self, id: &str) -> Option<SystemTime> {
+        self.expiry
+            .get(id)
+            .map(|millis| UNIX_EPOCH + Duration::from_millis(*millis as u64))
+    }
+    /// Remove expired members
+    /// Returns the IDs of the removed members.
+    pub fn purge_expired(&mut self) -> PackResult<Vec<String>> {
+        self.sync_location();
+        self.check_mutable()?;
+        let now = unix_millis(SystemTime::now());
+        let expired = self
+            .expiry
+            .iter()
+            .filter(|(_, at)| **at <= now)
+            .map(|(id, at)| (id.clone(), *at))
+            .collect::<Vec<(String, u128)>>();
+        let mut removed = Vec::new();
+        for (i, (id, _)) in expired.iter().enumerate() {
+            self.expiry.remove(id);
+            let res = match self.pack_by_id(id) {
+                Some(_) => self.remove_by_id(id).map(|_| true),
+                // Not loaded, e.g. by LruVecPack
+                None => self.remove_unloaded(id),
+            };
+            match res {
+                Ok(true) => removed.push(id.clone()),
+                Ok(false) => (),
+                Err(err) => {
+                    // Keep the expiry of the members not removed yet
+                    self.expiry.extend(expired[i..].iter().cloned());
+                    self.save_expiry()?;
+                    return Err(err);
+                }
+            }
+        }
+        if !expired.is_empty() {
+            self.save_expiry()?;
+        }
+        Ok(removed)
+    }
+    // Delete the file of a member not in memory
+    // Returns false if there is no such file.
+    fn remove_unloaded(&self, id: &str) -> PackResult<bool> {
+        let path = self.member_path(id);
+        if !self.ctx.remove_existing(&path)? {
+            return Ok(false);
+        }
+        self.ctx
+            .member_changed(ChangeEvent::new(id, ChangeKind::Removed));
+        Ok(true)
+    }
+    // Load the persisted expiry, then purge the expired members
+    // A read-only or append-only VecPack cannot remove members,
+    // so it only loads the expiry.
+    pub(crate) fn load_expiry(&mut self) -> PackResult<()> {
+        let path = self.expiry_path();
+        if !path.is_file() {
+            self.expiry.clear();
+            return Ok(());
+        }
+        self.expiry = serde_yaml::from_str(&std::fs::read_to_string(&path)?)
+            .map_err(PackError::deserialize)?;
+        if !self.is_read_only() && !self.append_only {
+            self.purge_expired()?;
+        }
+        Ok(())
+    }
+    // Drop the expiry of a removed member
+    pub(crate) fn forget_expiry(&mut self, id: &str) -> PackResult<()> {
+        match self.expiry.remove(id) {
+            Some(_) => self.save_expiry(),
+            None => Ok(()),
+        }
+    }
+    // Drop the expiry of removed members, saved at once
+    pub(crate) fn forget_expiries(&mut self, ids: &[String]) -> PackResult<()> {
+        let count = self.expiry.len();
+        for id in ids {
+            self.expiry.remove(id);
+        }
+        match self.expiry.len() == count {
+            true => Ok(()),
+            false => self.save_expiry(),
+        }
+    }
+    // Move the expiry of a member to its new ID
+    pub(crate) fn rekey_expiry(
+        &mut self,
+        old: &str,
+        new: &str,
+    ) -> PackResult<()> {
+        match self.expiry.remove(old) {
+            Some(at) => {
+                self.expiry.insert(new.to_string(), at);
+                self.save_expiry()
+            }
+            None => Ok(()),
+        }
+    }
+    fn save_expiry(&self) -> PackResult<()> {
+        let path = self.expiry_path();
+        if self.expiry.is_empty() {
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+            return Ok(());
+        }
+        save_data_object(&path, &self.expiry)
+    }
+    fn expiry_path(&self) -> PathBuf {
+        self.dir().join(".expiry.yml")
+    }
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    // Current time if any member can expire, so
+    // VecPacks without expiry never check it
+    pub(crate) fn expiry_clock(&self) -> Option<u128> {
+        match self.expiry.is_empty() {
+            true => None,
+            false => Some(unix_millis(SystemTime::now())),
+        }
+    }
+    // True if the member has expired by now,
+    // but it is not purged yet
+    pub(crate) fn expired_at(&self, id: &str, now: Option<u128>) -> bool {
+        match (now, self.expiry.get(id)) {
+            (Some(now), Some(at)) => *at <= now,
+            _ => false,
+        }
+    }
+    pub(crate) fn is_expired(&self, id: &str) -> bool {
+        self.expired_at(id, self.expiry_clock())
+    }
+    /// Live members in member order
+    /// Expired members are skipped, even before they are purged.
+    /// iter() through Deref returns every member.
+    pub fn iter_live(&self) -> impl Iterator<Item = &Pack<T>> {
+        let now = self.expiry_clock();
+        self.data
+            .iter()
+            .filter(move |i| !self.expired_at(i.get_id(), now))
+    }
+}
+
+// Persisted expiry, ID -> UNIX millis
+pub(crate) type Expiry = BTreeMap<String, u128>;
@@ -0,0 +1,96 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Storage usage
+//!
+//! VecPack::stats() reports how much disk space the members use,
+//! and when they were modified, so admin UIs do not need to walk
+//! the directory themselves.
+
+use crate::{PackError, PackResult, VecPack, VecPackMember};
+use std::time::SystemTime;
+
+/// Usage of a member file
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberUsage {
+    pub id: String,
+    /// File size in bytes
+    pub bytes: u64,
+    /// Last modification time, if the storage knows it
+    pub modified: Option<SystemTime>,
+}
+
+/// StorageStats
+/// Result of VecPack::stats()
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StorageStats {
+    /// Number of members
+    pub members: usize,
+    /// Total size of the member files in bytes
+    pub total_bytes: u64,
+    /// The largest member file
+    pub largest: Option<MemberUsage>,
+    /// Most recent modification of any member
+    pub last_modified: Option<SystemTime>,
+    /// Every member file, in member order
+    pub files: Vec<MemberUsage>,
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember,
+{
+    /// Storage statistics of the members
+    /// Only the member files are counted, not the hidden files
+    /// (history, trash, snapshots, ...). With a storage backend
+    /// the files are read to get their size, and modification
+    /// times are unknown.
+    pub fn stats(&self) -> PackResult<StorageStats> {
+        let backend = self.ctx.backend();
+        let mut stats = StorageStats::default();
+        for pack in self.data.iter() {
+            let (bytes, modified) = match &backend {
+                Some(backend) => (backend.read(&pack.path)?.len() as u64, None),
+                None => {
+                    let metadata =
+                        std::fs::metadata(&pack.path).map_err(|err| {
+                            PackError::from(err).with_path(&pack.path)
+                        })?;
+                    (metadata.len(), metadata.modified().ok())
+                }
+            };
+            stats.total_bytes += bytes;
+            stats.last_modified = stats.last_modified.max(modified);
+            stats.files.push(MemberUsage {
+                id: pack.get_id().to_string(),
+                bytes,
+                modified,
+            });
+        }
+        stats.members = stats.files.len();
+        stats.largest = stats.files.iter().max_by_key(|f| f.bytes).cloned();
+        Ok(stats)
+    }
+}
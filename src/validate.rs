@@ -0,0 +1,125 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Validation
+//!
+//! Data types implementing Validate are checked before they are
+//! saved. VecPack members are validated automatically, as the
+//! VecPackMember derive returns Validate::validate from
+//! VecPackMember::validator; a manual implementation returns it
+//! itself. A standalone Pack is validated once enable_validation
+//! is called. Pack::save, Pack::update and VecPack::insert reject
+//! invalid data with PackError::ValidationError. Update rolls the
+//! data back. A PackGuard drop cannot return an error, so it skips
+//! saving invalid data.
+
+use crate::{Pack, VecPack, VecPackMember};
+use serde::Serialize;
+
+/// Validation function of T, see VecPackMember::validator
+pub type Validator<T> = fn(&T) -> Result<(), String>;
+
+/// Validate
+/// Checks whether the data is valid to save
+pub trait Validate {
+    /// Returns Err with the reason if data is invalid
+    fn validate(&self) -> Result<(), String>;
+}
+
+impl<T> Pack<T>
+where
+    T: Serialize + Sized + Clone + Validate,
+{
+    /// Enable validation
+    /// From now on invalid data is not saved.
+    pub fn enable_validation(&mut self) {
+        self.hooks.validator = Some(T::validate);
+    }
+}
+
+impl<T> VecPack<T>
+where
+    T: VecPackMember + Validate,
+{
+    /// Enable validation of the members
+    /// From now on invalid members are not inserted or saved,
+    /// even if VecPackMember::validator returns None. Returns the already stored invalid members as
+    /// (ID, reason), so they can be fixed.
+    pub fn enable_validation(&mut self) -> Vec<(String, String)> {
+        self.hooks.validator = Some(T::validate);
+        let mut invalid = Vec::new();
+        for pack in self.data.iter_mut() {
+            pack.hooks.validator = Some(T::validate);
+            if let Err(reason) = pack.data.validate() {
+                invalid.push((pack.get_id().to_string(), reason));
+            }
+        }
+        invalid
+    }
+}
+
+// Validator lookup of the VecPackMember derive
+// Probe<T> has the method of ViaValidate if T implements Validate,
+// otherwise the autoref method of ViaDefault is used, which
+// returns None.
+#[doc(hidden)]
+pub mod probe {
+    use super::{Validate, Validator};
+    use std::marker::PhantomData;
+
+    pub struct Probe<T>(PhantomData<T>);
+
+    impl<T> Probe<T> {
+        pub fn new() -> Self {
+            Probe(PhantomData)
+        }
+    }
+
+    impl<T> Default for Probe<T> {
+        fn default() -> Self {
+            Probe::new()
+        }
+    }
+
+    pub trait ViaValidate<T> {
+        fn validator(&self) -> Option<Validator<T>>;
+    }
+
+    impl<T: Validate> ViaValidate<T> for Probe<T> {
+        fn validator(&self) -> Option<Validator<T>> {
+            Some(T::validate)
+        }
+    }
+
+    pub trait ViaDefault<T> {
+        fn validator(&self) -> Option<Validator<T>>;
+    }
+
+    impl<T> ViaDefault<T> for &Probe<T> {
+        fn validator(&self) -> Option<Validator<T>> {
+            None
+        }
+    }
+}
@@ -0,0 +1,173 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Integrity check
+//!
+//! verify() re-reads every member file of a VecPack, and reports
+//! the files that cannot be deserialized, whose ID does not match
+//! their file name, and the differences between the directory and
+//! the loaded members. Nothing is modified, the report tells what
+//! needs attention. verify_dir() is the same check without T, used
+//! by `storaget check`.
+
+use crate::{header, inspect, PackResult, VecPack, VecPackMember};
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// VerifyIssue
+/// A problem found by verify()
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyIssue {
+    /// File cannot be read or deserialized
+    Unreadable { file: PathBuf, error: String },
+    /// Stored ID differs from the file name
+    IdMismatch { file: PathBuf, id: String },
+    /// Loaded member without file
+    Missing { file: PathBuf },
+    /// File that is not loaded as a member
+    NotLoaded { file: PathBuf },
+}
+
+impl fmt::Display for VerifyIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyIssue::Unreadable { file, error } => {
+                write!(f, "{}: unreadable: {}", file.display(), error)
+            }
+            VerifyIssue::IdMismatch { file, id } => write!(
+                f,
+                "{}: ID {} does not match the file name",
+                file.display(),
+                id
+            ),
+            VerifyIssue::Missing { file } => {
+                write!(f, "{}: loaded member without file", file.display())
+            }
+            VerifyIssue::NotLoaded { file } => {
+                write!(f, "{}: file is not loaded", file.display())
+            }
+        }
+    }
+}
+
+/// VerifyReport
+/// Result of verify()
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VerifyReport {
+    /// Number of checked files
+    pub files: usize,
+    pub issues: Vec<VerifyIssue>,
+}
+
+impl VerifyReport {
+    /// True if no issue was found
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+// File name without extension
+fn file_id(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Verify the member files of dir without knowing their type
+/// Every file is read as a raw value; its id field, if any,
+/// is compared to the file name.
+pub fn verify_dir(dir: &Path) -> PackResult<VerifyReport> {
+    let mut report = VerifyReport::default();
+    for file in inspect::member_files(dir)? {
+        report.files += 1;
+        match inspect::read_member(&file) {
+            Ok(member) => {
+                match member.value.get("id").and_then(Value::as_str) {
+                    Some(id) if id != member.id => {
+                        let id = id.to_string();
+                        report.issues.push(VerifyIssue::IdMismatch { file, id })
+                    }
+                    _ => (),
+                }
+            }
+            Err(err) => {
+                let error = err.to_string();
+                report.issues.push(VerifyIssue::Unreadable { file, error })
+            }
+        }
+    }
+    Ok(report)
+}
+
+impl<T> VecPack<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    /// Integrity check
+    /// Re-reads and deserializes every member file, and compares
+    /// the files with the loaded members. See VerifyReport.
+    pub fn verify(&self) -> PackResult<VerifyReport> {
+        let mut report = VerifyReport::default();
+        let files = self.list_members()?;
+        for file in &files {
+            report.files += 1;
+            let file = file.clone();
+            match self.read_member_file(&file) {
+                Ok(data) if data.get_id() != file_id(&file) => {
+                    let id = data.get_id().to_string();
+                    report.issues.push(VerifyIssue::IdMismatch { file, id });
+                }
+                Ok(_) => {
+                    if self.pack_by_id(&file_id(&file)).is_none() {
+                        report.issues.push(VerifyIssue::NotLoaded { file });
+                    }
+                }
+                Err(err) => {
+                    let error = err.to_string();
+                    report.issues.push(VerifyIssue::Unreadable { file, error });
+                }
+            }
+        }
+        let files: HashSet<&PathBuf> = files.iter().collect();
+        for pack in &self.data {
+            if !files.contains(&pack.path) {
+                let file = pack.path.clone();
+                report.issues.push(VerifyIssue::Missing { file });
+            }
+        }
+        Ok(report)
+    }
+    // Read and decode a member file, without saving migrations
+    fn read_member_file(&self, path: &Path) -> PackResult<T> {
+        let buffer = match self.ctx.backend() {
+            Some(backend) => backend.read(path)?,
+            None => std::fs::read(path)?,
+        };
+        Ok(header::decode::<T>(&buffer)?.0)
+    }
+}
@@ -0,0 +1,142 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Browser storage backend
+//!
+//! Enabled by the `wasm` feature. Persists packs to the browser's
+//! localStorage, so the same domain types and Pack API can run in
+//! a wasm32 front-end. Every file is an item with the key
+//! "<prefix>/<path>". localStorage is synchronous like the other
+//! backends; IndexedDB only has an asynchronous API, so it cannot
+//! back the synchronous Pack API.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use storaget::wasm::LocalStorageBackend;
+//! use storaget::Pack;
+//!
+//! let backend = Arc::new(LocalStorageBackend::new().prefix("todo"));
+//! let todos: Pack<Vec<String>> =
+//!     Pack::load_or_init_with_backend("data".into(), "todos", backend)
+//!         .unwrap();
+//! ```
+
+use crate::backend::{path_key, StorageBackend};
+use crate::{PackError, PackResult};
+use std::path::{Path, PathBuf};
+use web_sys::Storage;
+
+/// LocalStorageBackend
+/// Stores files as localStorage items
+#[derive(Debug, Clone)]
+pub struct LocalStorageBackend {
+    prefix: String,
+}
+
+impl Default for LocalStorageBackend {
+    fn default() -> Self {
+        LocalStorageBackend {
+            prefix: "storaget".to_string(),
+        }
+    }
+}
+
+impl LocalStorageBackend {
+    /// New LocalStorageBackend with the "storaget" key prefix
+    pub fn new() -> Self {
+        LocalStorageBackend::default()
+    }
+    /// Set the key prefix, e.g. the application name
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.trim_matches('/').to_string();
+        self
+    }
+    fn key(&self, path: &Path) -> String {
+        format!("{}/{}", self.prefix, path_key(path))
+    }
+}
+
+// localStorage of the current window
+// Storage is not Send, so it is looked up by every operation.
+fn storage() -> PackResult<Storage> {
+    web_sys::window()
+        .ok_or_else(|| backend_error("no window"))?
+        .local_storage()
+        .map_err(js_error)?
+        .ok_or_else(|| backend_error("localStorage is not available"))
+}
+
+fn backend_error(msg: &str) -> PackError {
+    PackError::BackendError(format!("localStorage: {}", msg))
+}
+
+fn js_error<E: std::fmt::Debug>(err: E) -> PackError {
+    backend_error(&format!("{:?}", err))
+}
+
+impl StorageBackend for LocalStorageBackend {
+    fn read(&self, path: &Path) -> PackResult<Vec<u8>> {
+        storage()?
+            .get_item(&self.key(path))
+            .map_err(js_error)?
+            .map(String::into_bytes)
+            .ok_or(PackError::PathNotFound)
+    }
+    fn write(&self, path: &Path, bytes: &[u8]) -> PackResult<()> {
+        // Items are strings, so format=msgpack files cannot be stored
+        let value = std::str::from_utf8(bytes)
+            .map_err(|_| backend_error("cannot store non UTF-8 data"))?;
+        // Quota exceeded is reported as an exception
+        storage()?
+            .set_item(&self.key(path), value)
+            .map_err(|_| PackError::StorageFull)
+    }
+    fn list(&self, dir: &Path) -> PackResult<Vec<PathBuf>> {
+        let storage = storage()?;
+        let prefix = format!("{}/", self.key(dir));
+        let mut result = Vec::new();
+        for i in 0..storage.length().map_err(js_error)? {
+            let key = match storage.key(i).map_err(js_error)? {
+                Some(key) => key,
+                None => continue,
+            };
+            if let Some(name) = key.strip_prefix(&prefix) {
+                if !name.contains('/') && !name.starts_with('.') {
+                    result.push(dir.join(name));
+                }
+            }
+        }
+        result.sort();
+        Ok(result)
+    }
+    fn delete(&self, path: &Path) -> PackResult<()> {
+        let storage = storage()?;
+        let key = self.key(path);
+        if storage.get_item(&key).map_err(js_error)?.is_none() {
+            return Err(PackError::PathNotFound);
+        }
+        storage.remove_item(&key).map_err(js_error)
+    }
+}
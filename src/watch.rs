@@ -0,0 +1,221 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Filesystem watch
+//!
+//! Available with the "watch" feature. A VecPack can watch its
+//! directory with the notify crate, and apply the changes made by
+//! other processes or by manual edits: changed members are reloaded,
+//! new files are inserted, and deleted files are removed. Applying
+//! the changes returns them as ChangeEvents.
+//!
+//! The VecPack is owned by the caller, so changes are applied when
+//! apply_fs_changes() or wait_fs_changes() is called. Saves made by
+//! the VecPack itself are detected as no-op and are not reported.
+
+use crate::{
+    header, ChangeEvent, ChangeKind, PackError, PackResult, VecPack,
+    VecPackMember,
+};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Mutex;
+use std::time::Duration;
+
+// Events arriving within this time after the first one
+// are applied together.
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+// Running directory watch
+pub(crate) struct FsWatch {
+    // Watch stops when the watcher is dropped
+    _watcher: RecommendedWatcher,
+    // Canonical path of the watched directory,
+    // as watch events have absolute paths.
+    dir: PathBuf,
+    // Behind Mutex, so VecPack stays Sync
+    events: Mutex<Receiver<notify::Result<notify::Event>>>,
+}
+
+impl FsWatch {
+    // Start watching a directory recursively
+    pub(crate) fn start(path: &Path) -> PackResult<FsWatch> {
+        let (sender, events) = channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        })
+        .map_err(watch_error)?;
+        let dir = path.canonicalize()?;
+        watcher
+            .watch(&dir, RecursiveMode::Recursive)
+            .map_err(watch_error)?;
+        Ok(FsWatch {
+            _watcher: watcher,
+            dir,
+            events: Mutex::new(events),
+        })
+    }
+}
+
+fn watch_error(err: notify::Error) -> PackError {
+    PackError::io(std::io::Error::other(format!(
+        "Filesystem watch error: {}",
+        err
+    )))
+}
+
+impl<T> VecPack<T>
+where
+    for<'de> T: VecPackMember + Deserialize<'de> + Default,
+{
+    /// Enable filesystem watch
+    /// Starts watching the VecPack directory (with its shard
+    /// sub directories). Changes are collected in the background,
+    /// and applied by apply_fs_changes() or wait_fs_changes().
+    pub fn enable_watch(&mut self) -> PackResult<()> {
+        self.sync_location();
+        self.watch = Some(FsWatch::start(&self.path)?);
+        Ok(())
+    }
+    /// Stop filesystem watch
+    pub fn disable_watch(&mut self) {
+        self.watch = None;
+    }
+    /// Returns true if filesystem watch is enabled
+    pub fn is_watched(&self) -> bool {
+        self.watch.is_some()
+    }
+    /// Apply the collected filesystem changes without blocking
+    /// Returns the applied changes. Files that cannot be
+    /// deserialized (e.g. still being written by an editor) are
+    /// skipped, and applied on their next change.
+    pub fn apply_fs_changes(&mut self) -> PackResult<Vec<ChangeEvent>> {
+        let mut ids = BTreeSet::new();
+        if let Some(watch) = &self.watch {
+            for event in watch.events.lock().unwrap().try_iter() {
+                self.collect_changed_ids(event, &mut ids)?;
+            }
+        }
+        self.apply_changed_ids(ids)
+    }
+    /// Wait for filesystem changes, then apply them
+    /// Blocks until a change arrives or the timeout elapses.
+    /// Returns an empty Vec on timeout, or if the only changes
+    /// were the own saves of this VecPack.
+    pub fn wait_fs_changes(
+        &mut self,
+        timeout: Duration,
+    ) -> PackResult<Vec<ChangeEvent>> {
+        let mut ids = BTreeSet::new();
+        if let Some(watch) = &self.watch {
+            let events = watch.events.lock().unwrap();
+            if let Ok(event) = events.recv_timeout(timeout) {
+                self.collect_changed_ids(event, &mut ids)?;
+                std::thread::sleep(DEBOUNCE);
+                for event in events.try_iter() {
+                    self.collect_changed_ids(event, &mut ids)?;
+                }
+            }
+        }
+        self.apply_changed_ids(ids)
+    }
+    // Member IDs of the changed files of a watch event
+    fn collect_changed_ids(
+        &self,
+        event: notify::Result<notify::Event>,
+        ids: &mut BTreeSet<String>,
+    ) -> PackResult<()> {
+        let event = event.map_err(watch_error)?;
+        if let notify::EventKind::Access(_) = event.kind {
+            return Ok(());
+        }
+        let dir = match &self.watch {
+            Some(watch) => &watch.dir,
+            None => return Ok(()),
+        };
+        for path in &event.paths {
+            if let Some(id) = member_id(dir, path) {
+                ids.insert(id);
+            }
+        }
+        Ok(())
+    }
+    // Sync the changed members with their files
+    fn apply_changed_ids(
+        &mut self,
+        ids: BTreeSet<String>,
+    ) -> PackResult<Vec<ChangeEvent>> {
+        let mut result = Vec::new();
+        for id in ids {
+            match self.sync_member(&id) {
+                Ok(Some(event)) => result.push(event),
+                Ok(None) | Err(PackError::DeserializeError { .. }) => (),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(result)
+    }
+    // Sync a member with its file
+    // Returns None if they are already the same.
+    fn sync_member(&mut self, id: &str) -> PackResult<Option<ChangeEvent>> {
+        let path = match self.pack_by_id(id) {
+            Some(pack) => pack.path.clone(),
+            None => self.member_path(id),
+        };
+        let kind = match (path.is_file(), self.pack_by_id(id)) {
+            (true, Some(pack)) => {
+                let content = std::fs::read(&path)?;
+                if header::reencode(&pack.data, &content)? == content {
+                    return Ok(None);
+                }
+                ChangeKind::Updated
+            }
+            (true, None) => ChangeKind::Created,
+            (false, Some(_)) => ChangeKind::Removed,
+            (false, None) => return Ok(None),
+        };
+        self.reload_id(id)?;
+        Ok(Some(ChangeEvent::new(id, kind)))
+    }
+}
+
+// Member ID of a changed path, if it is a member file
+// Hidden files and folders (temp files, .trash/, ...) are skipped.
+fn member_id(dir: &Path, path: &Path) -> Option<String> {
+    let rel = path.strip_prefix(dir).ok()?;
+    let visible = rel.components().all(|c| match c {
+        Component::Normal(name) => {
+            !name.to_str().map(|n| n.starts_with('.')).unwrap_or(true)
+        }
+        _ => false,
+    });
+    if !visible || path.extension()?.to_str()? != "yml" {
+        return None;
+    }
+    path.file_stem()?.to_str().map(|s| s.to_string())
+}
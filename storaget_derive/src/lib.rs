@@ -0,0 +1,120 @@
+// The MIT License
+// Copyright 2020 Peter Mezei <mezeipetister@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+// Made with (L) from Hungary
+// If you need any help please contact me
+// at <mezeipetister@gmail.com>
+
+//! Derive macros for storaget
+//!
+//! Re-exported by storaget, so use them from there:
+//!
+//! ```ignore
+//! use storaget::*;
+//!
+//! #[derive(Serialize, Deserialize, Clone, Default, VecPackMember)]
+//! struct Car {
+//!     #[pack(id)]
+//!     id: String,
+//!     name: String,
+//! }
+//! ```
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Index, Member};
+
+/// Derive VecPackMember
+/// The ID field must be marked with #[pack(id)],
+/// and its type must deref to &str, e.g. String.
+/// If the type implements Validate, members are validated.
+#[proc_macro_derive(VecPackMember, attributes(pack))]
+pub fn derive_vecpack_member(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "VecPackMember can only be derived for structs",
+            ))
+        }
+    };
+    let id = id_field(fields)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::storaget::VecPackMember for #name #ty_generics
+            #where_clause
+        {
+            fn get_id(&self) -> &str {
+                &self.#id
+            }
+            fn set_id(&mut self, id: &str) -> ::storaget::PackResult<()> {
+                self.#id = id.into();
+                Ok(())
+            }
+            fn validator() -> Option<::storaget::Validator<Self>> {
+                #[allow(unused_imports)]
+                use ::storaget::validate::probe::{ViaDefault, ViaValidate};
+                (&::storaget::validate::probe::Probe::<Self>::new())
+                    .validator()
+            }
+        }
+    })
+}
+
+// Find the single field marked with #[pack(id)]
+fn id_field(fields: &Fields) -> syn::Result<Member> {
+    let mut result = None;
+    for (index, field) in fields.iter().enumerate() {
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("pack")) {
+            attr.parse_nested_meta(|meta| {
+                if !meta.path.is_ident("id") {
+                    return Err(meta.error("unknown pack attribute"));
+                }
+                if result.is_some() {
+                    return Err(meta.error("only one field can be #[pack(id)]"));
+                }
+                result = Some(match &field.ident {
+                    Some(ident) => Member::Named(ident.clone()),
+                    None => Member::Unnamed(Index::from(index)),
+                });
+                Ok(())
+            })?;
+        }
+    }
+    result.ok_or_else(|| {
+        Error::new(
+            Span::call_site(),
+            "VecPackMember requires a field marked with #[pack(id)]",
+        )
+    })
+}
@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use storaget::*;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, VecPackMember)]
+struct Car {
+    #[pack(id)]
+    id: String,
+    name: String,
+    hp: u32,
+}
+
+fn create_cars(path: &str) -> VecPack<Car> {
+    let mut cars: VecPack<Car> =
+        VecPack::load_or_init(PathBuf::from(path)).unwrap();
+    for (id, name, hp) in [("1", "CarSmall", 150), ("2", "CarBig", 650)] {
+        cars.insert(Car {
+            id: id.to_string(),
+            name: name.to_string(),
+            hp,
+        })
+        .unwrap();
+    }
+    cars
+}
+
+fn storaget(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_storaget"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+#[test]
+fn test_cli_list_show() {
+    let tmp = testing::TempDir::new().unwrap();
+    let path = tmp.path().join("cli_test_list");
+    let dir = path.to_str().unwrap();
+    create_cars(dir);
+    let output = storaget(&["list", dir]);
+    assert!(output.status.success());
+    let lines: Vec<String> =
+        stdout(&output).lines().map(String::from).collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("1\tyaml\tv1\t"));
+
+    let output = storaget(&["show", dir, "2"]);
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("name: CarBig"));
+    assert!(!storaget(&["show", dir, "3"]).status.success());
+    assert_eq!(storaget(&["show", dir]).status.code(), Some(2));
+}
+
+#[test]
+fn test_cli_check() {
+    let tmp = testing::TempDir::new().unwrap();
+    let path = tmp.path().join("cli_test_check");
+    let dir = path.to_str().unwrap();
+    create_cars(dir);
+    assert!(storaget(&["check", dir]).status.success());
+    std::fs::write(format!("{}/3.yml", dir), "id: '4'\nname: Car\nhp: 1\n")
+        .unwrap();
+    std::fs::write(format!("{}/5.yml", dir), "hp: [").unwrap();
+    let output = storaget(&["check", dir]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("3.yml: ID 4 does not match"));
+    assert!(stdout(&output).contains("4 files, 2 problems"));
+    assert_eq!(storaget(&["validate", dir]).status.code(), Some(1));
+}
+
+#[test]
+fn test_cli_convert() {
+    let tmp = testing::TempDir::new().unwrap();
+    let path = tmp.path().join("cli_test_convert");
+    let dir = path.to_str().unwrap();
+    create_cars(dir);
+    let output = storaget(&["convert", dir, "yaml", "json"]);
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with("2 files converted"));
+    let cars: VecPack<Car> = VecPack::load_or_init(PathBuf::from(dir)).unwrap();
+    assert_eq!(cars.format(), Format::Json);
+    assert_eq!(cars.find_id("1").unwrap().hp, 150);
+    assert!(!storaget(&["convert", dir, "yaml", "json"]).status.success());
+    assert!(!storaget(&["convert", dir, "json", "bson"]).status.success());
+}
+
+#[test]
+fn test_cli_schema_diff() {
+    let tmp = testing::TempDir::new().unwrap();
+    let path = tmp.path().join("cli_test_schema_diff");
+    let dir = path.to_str().unwrap();
+    create_cars(dir);
+    let sample_path = tmp.path().join("cli_test_schema_diff_sample.yml");
+    let sample = sample_path.to_str().unwrap();
+    std::fs::write(sample, "id: ''\nname: ''\nhp: 0\n").unwrap();
+    assert!(storaget(&["schema-diff", dir, sample]).status.success());
+    std::fs::write(sample, "id: ''\nname: ''\ntorque: 0\n").unwrap();
+    let output = storaget(&["schema-diff", dir, sample]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("- hp (dropped from 2 files)"));
+    assert!(stdout(&output).contains("+ torque (defaulted in 2 files)"));
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "csv")]
+
+use serde::{Deserialize, Serialize};
+use storaget::*;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct Engine {
+    hp: u32,
+    fuel: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct Car {
+    id: String,
+    name: String,
+    engine: Engine,
+    tags: Vec<String>,
+}
+
+impl VecPackMember for Car {
+    fn get_id(&self) -> &str {
+        &self.id
+    }
+}
+
+#[test]
+fn test_export_csv() {
+    let dir = testing::TempDir::new().unwrap();
+    let mut cars: VecPack<Car> =
+        VecPack::load_or_init(dir.path().join("csv_test_export")).unwrap();
+    cars.insert(Car {
+        id: "1".to_string(),
+        name: "Small, red".to_string(),
+        engine: Engine { hp: 90, fuel: None },
+        tags: vec![],
+    })
+    .unwrap();
+    cars.insert(Car {
+        id: "2".to_string(),
+        name: "Big".to_string(),
+        engine: Engine {
+            hp: 300,
+            fuel: Some("diesel".to_string()),
+        },
+        tags: vec!["4x4".to_string(), "towing".to_string()],
+    })
+    .unwrap();
+    let mut out = Vec::new();
+    assert_eq!(cars.export_csv(&mut out).unwrap(), 2);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "id,name,engine.hp,engine.fuel,tags,tags.0,tags.1\n\
+         1,\"Small, red\",90,,,,\n\
+         2,Big,300,diesel,,4x4,towing\n"
+    );
+}
+
+#[test]
+fn test_import_csv() {
+    let dir = testing::TempDir::new().unwrap();
+    let mut cars: VecPack<Car> =
+        VecPack::load_or_init(dir.path().join("csv_test_import")).unwrap();
+    cars.insert(Car {
+        id: "3".to_string(),
+        ..Car::default()
+    })
+    .unwrap();
+    let input = "id,name,engine.hp,engine.fuel,tags,tags.0,tags.1\n\
+                 1,\"Small, red\",90,,,,\n\
+                 2,Big,300,diesel,,4x4,towing\n\
+                 3,Taken,100,,,,\n\
+                 4,Broken,lots,,,,\n\
+                 1,Again,90,,,,\n";
+    let report = cars
+        .import_from_reader(ImportFormat::Csv, input.as_bytes())
+        .unwrap();
+    assert_eq!(report.inserted, vec!["1".to_string(), "2".to_string()]);
+    let rows: Vec<usize> = report.errors.iter().map(|e| e.row).collect();
+    assert_eq!(rows, vec![3, 4, 5]);
+    assert_eq!(cars.len(), 3);
+    let big = cars.find_id("2").unwrap();
+    assert_eq!(big.engine.hp, 300);
+    assert_eq!(big.engine.fuel.as_deref(), Some("diesel"));
+    assert_eq!(big.tags, vec!["4x4".to_string(), "towing".to_string()]);
+    let small = cars.find_id("1").unwrap();
+    assert_eq!(small.name, "Small, red");
+    assert!(small.tags.is_empty());
+    assert_eq!(small.engine.fuel, None);
+}
@@ -0,0 +1,82 @@
+#![cfg(feature = "log")]
+
+use std::sync::{Mutex, Once};
+use std::time::Duration;
+use storaget::*;
+
+// Collects the error and warning records
+struct Logger {
+    records: Mutex<Vec<String>>,
+    warnings: Mutex<Vec<String>>,
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target() == "storaget"
+    }
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        match record.level() {
+            log::Level::Error => {
+                self.records.lock().unwrap().push(record.args().to_string())
+            }
+            log::Level::Warn => self
+                .warnings
+                .lock()
+                .unwrap()
+                .push(record.args().to_string()),
+            _ => (),
+        }
+    }
+    fn flush(&self) {}
+}
+
+static LOGGER: Logger = Logger {
+    records: Mutex::new(Vec::new()),
+    warnings: Mutex::new(Vec::new()),
+};
+
+// The logger can be set only once per process
+fn init_logger() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Warn);
+    });
+}
+
+#[test]
+fn test_guard_save_failed() {
+    let dir = testing::TempDir::new().unwrap();
+    init_logger();
+
+    let path = dir.path().join("logging_test");
+    let mut counter: Pack<i32> =
+        Pack::load_or_init(path.clone(), "counter").unwrap();
+    // File cannot be replaced by the saved one
+    std::fs::remove_file(path.join("counter.yml")).unwrap();
+    std::fs::create_dir_all(path.join("counter.yml/blocked")).unwrap();
+    *counter.as_mut() = 42;
+    let records = LOGGER.records.lock().unwrap();
+    assert_eq!(records.len(), 1);
+    assert!(records[0].contains("counter.yml"));
+}
+
+#[test]
+fn test_slow_operation_warning() {
+    let dir = testing::TempDir::new().unwrap();
+    init_logger();
+
+    let path = dir.path().join("logging_test_slow");
+    let counter: Pack<i32> =
+        Pack::load_or_init(path.clone(), "counter").unwrap();
+    counter.set_slow_op_threshold(Some(Duration::ZERO));
+    counter.save().unwrap();
+    assert_eq!(counter.op_stats().slow_ops, 1);
+    let warnings = LOGGER.warnings.lock().unwrap();
+    assert!(warnings
+        .iter()
+        .any(|w| w.starts_with("Slow save of") && w.contains("counter.yml")));
+}
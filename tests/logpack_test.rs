@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use storaget::*;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Event {
+    kind: String,
+    value: u32,
+}
+
+fn event(kind: &str, value: u32) -> Event {
+    Event {
+        kind: kind.to_string(),
+        value,
+    }
+}
+
+#[test]
+fn test_logpack_append_iter() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("logpack_test_append/events.log");
+    let _ = std::fs::remove_file(&path);
+    let mut events: LogPack<Event> = LogPack::open(path.clone()).unwrap();
+    events.append(&event("start", 1)).unwrap();
+    events
+        .append_many(&[event("tick", 2), event("tick", 3)])
+        .unwrap();
+    events.sync().unwrap();
+    assert_eq!(events.count().unwrap(), 3);
+    drop(events);
+    // Appends go to the end of the existing file
+    let mut events: LogPack<Event> = LogPack::open(path.clone()).unwrap();
+    events.append(&event("stop", 4)).unwrap();
+    let replayed = events
+        .iter()
+        .unwrap()
+        .collect::<PackResult<Vec<Event>>>()
+        .unwrap();
+    assert_eq!(
+        replayed,
+        vec![
+            event("start", 1),
+            event("tick", 2),
+            event("tick", 3),
+            event("stop", 4)
+        ]
+    );
+    assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 4);
+}
+
+#[test]
+fn test_logpack_partial_record() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("logpack_test_partial/events.log");
+    let _ = std::fs::remove_file(&path);
+    let mut events: LogPack<Event> = LogPack::open(path.clone()).unwrap();
+    events.append(&event("start", 1)).unwrap();
+    drop(events);
+    // Crash in the middle of an append
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    file.write_all(b"{\"kind\":\"ti").unwrap();
+    drop(file);
+    let mut events: LogPack<Event> = LogPack::open(path.clone()).unwrap();
+    events.append(&event("stop", 2)).unwrap();
+    let replayed = events
+        .iter()
+        .unwrap()
+        .collect::<PackResult<Vec<Event>>>()
+        .unwrap();
+    assert_eq!(replayed, vec![event("start", 1), event("stop", 2)]);
+    // Corrupt record is returned as error, the rest is replayed
+    std::fs::write(&path, "{\"kind\":\"start\",\"value\":1}\nnot json\n")
+        .unwrap();
+    let events: LogPack<Event> = LogPack::open(path).unwrap();
+    let replayed = events.iter().unwrap().collect::<Vec<_>>();
+    assert_eq!(replayed.len(), 2);
+    assert!(replayed[1].is_err());
+}
+
+#[test]
+fn test_logpack_compact() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("logpack_test_compact/events.log");
+    let _ = std::fs::remove_file(&path);
+    let mut events: LogPack<Event> = LogPack::open(path.clone()).unwrap();
+    events
+        .append_many(&[
+            event("a", 1),
+            event("b", 1),
+            event("a", 2),
+            event("c", 1),
+            event("b", 2),
+        ])
+        .unwrap();
+    assert_eq!(events.base().unwrap(), 0);
+    assert_eq!(events.compact_before(2).unwrap(), 2);
+    assert_eq!(events.base().unwrap(), 2);
+    // Positions count the dropped records too
+    assert_eq!(events.compact_before(2).unwrap(), 0);
+    assert_eq!(events.compact_before(3).unwrap(), 1);
+    assert_eq!(events.base().unwrap(), 3);
+    // Appends go into the compacted file
+    events.append(&event("c", 2)).unwrap();
+    assert_eq!(events.compact_by_key(|e| e.kind.clone()).unwrap(), 1);
+    let replayed = events
+        .iter()
+        .unwrap()
+        .collect::<PackResult<Vec<Event>>>()
+        .unwrap();
+    assert_eq!(replayed, vec![event("b", 2), event("c", 2)]);
+    assert_eq!(events.count().unwrap(), 2);
+    drop(events);
+    let events: LogPack<Event> = LogPack::open(path).unwrap();
+    assert_eq!(events.base().unwrap(), 3);
+    assert_eq!(events.count().unwrap(), 2);
+}
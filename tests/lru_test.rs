@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+use storaget::*;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct Car {
+    pub id: String,
+    pub hp: u32,
+}
+
+impl VecPackMember for Car {
+    fn get_id(&self) -> &str {
+        &self.id
+    }
+}
+
+fn car(id: &str, hp: u32) -> Car {
+    Car {
+        id: id.to_string(),
+        hp,
+    }
+}
+
+#[test]
+fn test_lru_capacity() {
+    let dir = testing::TempDir::new().unwrap();
+    assert!(LruVecPack::<Car>::load_or_init(
+        dir.path().join("lru_test_capacity"),
+        0
+    )
+    .is_err());
+}
+
+#[test]
+fn test_lru_eviction() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("lru_test_eviction");
+    let mut cars: LruVecPack<Car> =
+        LruVecPack::load_or_init(path.clone(), 2).unwrap();
+    cars.insert(car("a", 100)).unwrap();
+    cars.insert(car("b", 200)).unwrap();
+    cars.insert(car("c", 300)).unwrap();
+    assert!(cars.insert(car("a", 1)).is_err());
+    assert_eq!(cars.len(), 3);
+    assert_eq!(cars.cached_len(), 2);
+    assert!(!cars.is_cached("a"));
+
+    // Evicted member is loaded on demand,
+    // and the least recently used one goes.
+    assert_eq!(cars.find_id("a").unwrap().hp, 100);
+    assert!(cars.is_cached("a"));
+    assert!(!cars.is_cached("b"));
+
+    // Changes are saved, so they survive eviction
+    cars.find_id_mut("b").unwrap().as_mut().hp = 250;
+    cars.find_id("a").unwrap();
+    cars.find_id("c").unwrap();
+    assert!(!cars.is_cached("b"));
+    assert_eq!(cars.find_id("b").unwrap().hp, 250);
+
+    assert_eq!(cars.remove_by_id("c").unwrap().hp, 300);
+    assert!(cars.find_id("c").is_err());
+
+    // Same directory is readable by VecPack
+    let all: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    assert_eq!(all.len(), 2);
+    let cars: LruVecPack<Car> = LruVecPack::load_or_init(path, 1).unwrap();
+    assert_eq!(cars.len(), 2);
+    assert_eq!(cars.cached_len(), 0);
+}
+
+#[test]
+fn test_lru_file_path() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("lru_test_file_path");
+    std::fs::write(&path, "").unwrap();
+    assert!(LruVecPack::<Car>::load_or_init(path, 2).is_err());
+}
+
+struct Double;
+
+impl PackHooks<Car> for Double {
+    fn before_save(&self, data: &mut Car) {
+        data.hp *= 2;
+    }
+}
+
+#[test]
+fn test_lru_modes() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("lru_test_modes");
+    let mut all: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    all.insert(car("a", 100)).unwrap();
+    all.enable_append_only().unwrap();
+    drop(all);
+
+    // Directory modes and hooks apply to LruVecPack too
+    let mut cars: LruVecPack<Car> = LruVecPack::load_or_init(path, 1).unwrap();
+    cars.set_hooks(Double);
+    cars.insert(car("b", 200)).unwrap();
+    assert_eq!(cars.find_id("b").unwrap().hp, 400);
+    assert!(cars.find_id_mut("a").is_err());
+    assert!(cars.remove_by_id("a").is_err());
+    assert_eq!(cars.len(), 2);
+}
+
+#[test]
+fn test_lru_ttl() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("lru_test_ttl");
+    let mut all: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    all.insert(car("a", 100)).unwrap();
+    all.insert(car("b", 200)).unwrap();
+    all.set_ttl("a", Duration::from_secs(3600)).unwrap();
+    all.set_expiry("b", Some(SystemTime::now())).unwrap();
+    drop(all);
+    std::thread::sleep(Duration::from_millis(5));
+
+    // Unloaded members keep their expiry,
+    // the expired ones are removed with their files
+    let cars: LruVecPack<Car> =
+        LruVecPack::load_or_init(path.clone(), 1).unwrap();
+    assert_eq!(cars.ids(), vec!["a"]);
+    drop(cars);
+    let all: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    assert!(all.expires_at("a").is_some());
+    assert_eq!(all.ids().collect::<Vec<&str>>(), vec!["a"]);
+    assert!(!path.join("b.yml").exists());
+}
+
+#[test]
+fn test_lru_backend() {
+    let backend = std::sync::Arc::new(MemoryBackend::new());
+    let path = std::path::PathBuf::from("lru_test_backend");
+    let mut cars: LruVecPack<Car> =
+        LruVecPack::load_or_init_with_backend(path.clone(), 1, backend.clone())
+            .unwrap();
+    cars.insert(car("a", 100)).unwrap();
+    cars.insert(car("b", 200)).unwrap();
+    assert!(!cars.is_cached("a"));
+    assert_eq!(cars.find_id("a").unwrap().hp, 100);
+    assert!(!path.exists());
+    assert_eq!(backend.len(), 2);
+
+    // Reopened members are listed and loaded through the backend
+    let mut cars: LruVecPack<Car> =
+        LruVecPack::load_or_init_with_backend(path.clone(), 1, backend)
+            .unwrap();
+    assert_eq!(cars.len(), 2);
+    assert_eq!(cars.find_id("b").unwrap().hp, 200);
+    assert!(!path.exists());
+}
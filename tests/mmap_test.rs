@@ -0,0 +1,86 @@
+#![cfg(feature = "mmap")]
+
+use serde::{Deserialize, Serialize};
+use storaget::*;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, VecPackMember)]
+struct Car {
+    #[pack(id)]
+    id: String,
+    hp: u32,
+}
+
+#[test]
+fn test_load_mmap() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("mmap_test_pack");
+    let mut pack: Pack<Vec<String>> =
+        Pack::load_or_init(path.clone(), "large").unwrap();
+    pack.update(|d| *d = (0..10_000).map(|i| i.to_string()).collect())
+        .unwrap();
+    let file = path.join("large.yml");
+    let pack: Pack<Vec<String>> = Pack::load_mmap(file.clone()).unwrap();
+    assert_eq!(pack.len(), 10_000);
+    assert_eq!(pack[42], "42");
+
+    // Checksum is verified as well
+    let content = std::fs::read_to_string(&file).unwrap();
+    std::fs::write(&file, content.replace("9999", "9998")).unwrap();
+    assert!(matches!(
+        Pack::<Vec<String>>::load_mmap(file),
+        Err(PackError::IntegrityError(_))
+    ));
+    assert!(matches!(
+        Pack::<Vec<String>>::load_mmap(path.join("missing.yml")),
+        Err(PackError::PathNotFound)
+    ));
+}
+
+#[test]
+fn test_load_or_init_mmap() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("mmap_test_vecpack");
+    let mut cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    for i in 0..10 {
+        cars.insert(Car {
+            id: i.to_string(),
+            hp: i * 100,
+        })
+        .unwrap();
+    }
+    let cars: VecPack<Car> = VecPack::load_or_init_mmap(path).unwrap();
+    assert_eq!(cars.len(), 10);
+    assert_eq!(cars.find_id("7").unwrap().hp, 700);
+}
+
+#[test]
+fn test_load_mmap_msgpack() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("mmap_test_msgpack");
+    let mut cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    for i in 0..10 {
+        cars.insert(Car {
+            id: i.to_string(),
+            hp: i * 100,
+        })
+        .unwrap();
+    }
+    cars.convert_format(Format::Yaml, Format::MessagePack)
+        .unwrap();
+    drop(cars);
+    let cars: VecPack<Car> = VecPack::load_or_init_mmap(path.clone()).unwrap();
+    assert_eq!(cars.format(), Format::MessagePack);
+    assert_eq!(cars.len(), 10);
+    assert_eq!(cars.find_id("7").unwrap().hp, 700);
+
+    // Checksum of the binary body is verified as well
+    let file = path.join("7.yml");
+    let mut content = std::fs::read(&file).unwrap();
+    let last = content.len() - 1;
+    content[last] ^= 1;
+    std::fs::write(&file, content).unwrap();
+    assert!(matches!(
+        Pack::<Car>::load_mmap(file),
+        Err(PackError::IntegrityError(_))
+    ));
+}
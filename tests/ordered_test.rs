@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use storaget::*;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct Invoice {
+    pub id: String,
+    pub total: u32,
+}
+
+impl VecPackMember for Invoice {
+    fn get_id(&self) -> &str {
+        &self.id
+    }
+}
+
+fn invoice(id: &str, total: u32) -> Invoice {
+    Invoice {
+        id: id.to_string(),
+        total,
+    }
+}
+
+fn ids<'a>(packs: impl Iterator<Item = &'a Pack<Invoice>>) -> Vec<&'a str> {
+    packs.map(|i| i.get_id()).collect()
+}
+
+#[test]
+fn test_ordered_range() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("ordered_test_range");
+    let mut invoices: OrderedVecPack<Invoice> =
+        OrderedVecPack::load_or_init(path.clone()).unwrap();
+    for id in ["2020-02-01", "2020-01-15", "2019-12-31", "2020-01-02"] {
+        invoices.insert(invoice(id, 100)).unwrap();
+    }
+    assert!(invoices.insert(invoice("2020-01-02", 1)).is_err());
+    assert_eq!(
+        ids(invoices.range("2020-01".."2020-02")),
+        vec!["2020-01-02", "2020-01-15"]
+    );
+    assert_eq!(
+        ids(invoices.range("2020-01-15"..)),
+        vec!["2020-01-15", "2020-02-01"]
+    );
+    assert_eq!(
+        ids(invoices.iter_ordered()),
+        vec!["2019-12-31", "2020-01-02", "2020-01-15", "2020-02-01"]
+    );
+    assert_eq!(invoices.first_by_id().unwrap().get_id(), "2019-12-31");
+    assert_eq!(invoices.last_by_id().unwrap().get_id(), "2020-02-01");
+
+    invoices.remove_by_id("2020-01-02").unwrap();
+    // Manual VecPackMember without set_id
+    assert!(invoices.change_id("2019-12-31", "2020-01-01").is_err());
+    invoices.update("2020-01-15", |i| i.total = 250).unwrap();
+    assert!(invoices
+        .update("2020-01-15", |i| i.id = "2021-01-01".to_string())
+        .is_err());
+    assert_eq!(invoices.find_id("2020-01-15").unwrap().total, 250);
+    assert_eq!(
+        ids(invoices.range(.."2020-02")),
+        vec!["2019-12-31", "2020-01-15"]
+    );
+
+    // The same directory in order after reload
+    drop(invoices);
+    let invoices: OrderedVecPack<Invoice> =
+        OrderedVecPack::load_or_init(path.clone()).unwrap();
+    assert_eq!(invoices.len(), 3);
+    assert_eq!(
+        ids(invoices.iter_ordered()),
+        vec!["2019-12-31", "2020-01-15", "2020-02-01"]
+    );
+}
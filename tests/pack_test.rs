@@ -1,18 +1,20 @@
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use storaget::*;
 
 #[test]
 fn test_load_or_init() {
+    let dir = testing::TempDir::new().unwrap();
     let meaning_of_life: PackResult<Pack<i32>> =
-        Pack::load_or_init(PathBuf::from("data/pack_test"), "meaning_of_life");
+        Pack::load_or_init(dir.path().join("pack_test"), "meaning_of_life");
     assert_eq!(meaning_of_life.is_ok(), true);
 }
 
 #[test]
 fn test_update() {
+    let dir = testing::TempDir::new().unwrap();
     let mut meaning_of_life: Pack<i32> = Pack::load_or_init(
-        PathBuf::from("data/pack_test"),
+        dir.path().join("pack_test"),
         "meaning_of_life_update",
     )
     .unwrap();
@@ -23,8 +25,9 @@ fn test_update() {
 
 #[test]
 fn test_as_mut() {
+    let dir = testing::TempDir::new().unwrap();
     let mut meaning_of_life: Pack<i32> = Pack::load_or_init(
-        PathBuf::from("data/pack_test"),
+        dir.path().join("pack_test"),
         "meaning_of_life_as_mut",
     )
     .unwrap();
@@ -42,8 +45,9 @@ struct Car {
 
 #[test]
 fn test_as_ref() {
+    let dir = testing::TempDir::new().unwrap();
     let mut meaning_of_life: Pack<Car> = Pack::load_or_init(
-        PathBuf::from("data/pack_test"),
+        dir.path().join("pack_test"),
         "meaning_of_life_as_ref",
     )
     .unwrap();
@@ -57,8 +61,9 @@ fn get_seats(car: &Pack<Car>) -> u32 {
 
 #[test]
 fn test_as_deref() {
+    let dir = testing::TempDir::new().unwrap();
     let mut meaning_of_life: Pack<i32> = Pack::load_or_init(
-        PathBuf::from("data/pack_test"),
+        dir.path().join("pack_test"),
         "meaning_of_life_deref",
     )
     .unwrap();
@@ -71,7 +76,7 @@ fn test_as_deref() {
     // Init it again
     // and read the stored value
     let meaning_of_life: Pack<i32> = Pack::load_or_init(
-        PathBuf::from("data/pack_test"),
+        dir.path().join("pack_test"),
         "meaning_of_life_deref",
     )
     .unwrap();
@@ -81,11 +86,10 @@ fn test_as_deref() {
 
 #[test]
 fn test_vector() {
-    let mut meaning_of_life: Pack<Vec<i32>> = Pack::load_or_init(
-        PathBuf::from("data/pack_test"),
-        "meaning_of_life_vec",
-    )
-    .unwrap();
+    let dir = testing::TempDir::new().unwrap();
+    let mut meaning_of_life: Pack<Vec<i32>> =
+        Pack::load_or_init(dir.path().join("pack_test"), "meaning_of_life_vec")
+            .unwrap();
     (meaning_of_life.as_mut()).push(1);
     (meaning_of_life.as_mut()).push(2);
     (meaning_of_life.as_mut()).push(3);
@@ -94,6 +98,7 @@ fn test_vector() {
 
 #[test]
 fn test_struct() {
+    let dir = testing::TempDir::new().unwrap();
     #[derive(Serialize, Deserialize, Clone, Default)]
     struct Car {
         fuel: String,
@@ -101,7 +106,7 @@ fn test_struct() {
     }
 
     let mut meaning_of_life: Pack<Car> = Pack::load_or_init(
-        PathBuf::from("data/pack_test"),
+        dir.path().join("pack_test"),
         "meaning_of_life_struct",
     )
     .unwrap();
@@ -114,30 +119,29 @@ fn test_struct() {
 
 #[test]
 fn test_get() {
-    let mut meaning_of_life: Pack<i32> = Pack::load_or_init(
-        PathBuf::from("data/pack_test"),
-        "meaning_of_life_get",
-    )
-    .unwrap();
+    let dir = testing::TempDir::new().unwrap();
+    let mut meaning_of_life: Pack<i32> =
+        Pack::load_or_init(dir.path().join("pack_test"), "meaning_of_life_get")
+            .unwrap();
     *(meaning_of_life.as_mut()) = 42;
     assert_eq!(meaning_of_life.get(|i| i.clone()), 42);
 }
 
 #[test]
 fn test_map() {
-    let mut meaning_of_life: Pack<i32> = Pack::load_or_init(
-        PathBuf::from("data/pack_test"),
-        "meaning_of_life_map",
-    )
-    .unwrap();
+    let dir = testing::TempDir::new().unwrap();
+    let mut meaning_of_life: Pack<i32> =
+        Pack::load_or_init(dir.path().join("pack_test"), "meaning_of_life_map")
+            .unwrap();
     *(meaning_of_life.as_mut()) = 42;
     assert_eq!(meaning_of_life.map(|i| i * 2), 84);
 }
 
 #[test]
 fn test_update_iter_1000() {
+    let dir = testing::TempDir::new().unwrap();
     let mut meaning_of_life: Pack<i32> = Pack::load_or_init(
-        PathBuf::from("data/pack_test"),
+        dir.path().join("pack_test"),
         "meaning_of_life_update_iter_1000",
     )
     .unwrap();
@@ -149,8 +153,9 @@ fn test_update_iter_1000() {
 
 #[test]
 fn test_update_iter_10000() {
+    let dir = testing::TempDir::new().unwrap();
     let mut meaning_of_life: Pack<i32> = Pack::load_or_init(
-        PathBuf::from("data/pack_test"),
+        dir.path().join("pack_test"),
         "meaning_of_life_update_iter_10000",
     )
     .unwrap();
@@ -159,3 +164,686 @@ fn test_update_iter_10000() {
     }
     assert_eq!(meaning_of_life.get(|i| i.clone()), 10000);
 }
+
+// Keeps the value in 0..=100
+struct Percent;
+
+impl PackHooks<i32> for Percent {
+    fn before_save(&self, data: &mut i32) {
+        *data = (*data).clamp(0, 100);
+    }
+    fn after_load(&self, data: &mut i32) {
+        *data = (*data).clamp(0, 100);
+    }
+}
+
+#[test]
+fn test_hooks() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("pack_test");
+    let mut percent: Pack<i32> =
+        Pack::load_or_init(path.clone(), "percent_hooks").unwrap();
+    *percent.as_mut() = 250;
+    percent.set_hooks(Percent);
+    assert_eq!(*percent, 100);
+    percent.update(|i| *i = -5).unwrap();
+    assert_eq!(*percent, 0);
+    *percent.as_mut() = 120;
+    assert_eq!(*percent, 100);
+    let percent: Pack<i32> = Pack::load_or_init(path, "percent_hooks").unwrap();
+    assert_eq!(*percent, 100);
+}
+
+#[test]
+fn test_ephemeral_ttl() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("pack_test_ephemeral");
+    let ttl = Lifetime::Ttl(std::time::Duration::from_millis(200));
+    let mut progress: EphemeralPack<i32> =
+        EphemeralPack::load_or_init(path.clone(), "ttl", ttl).unwrap();
+    assert_eq!(*progress.get().unwrap(), 0);
+    assert!(progress.expires_at().is_none());
+    *progress.as_mut().unwrap() = 3;
+    assert!(progress.expires_at().is_some());
+
+    let mut progress: EphemeralPack<i32> =
+        EphemeralPack::load_or_init(path.clone(), "ttl", ttl).unwrap();
+    assert_eq!(*progress.get().unwrap(), 3);
+    std::thread::sleep(std::time::Duration::from_millis(250));
+    assert!(progress.is_expired());
+    assert_eq!(*progress.get().unwrap(), 0);
+    assert!(!path.join("ttl.yml").exists());
+}
+
+#[test]
+fn test_ephemeral_session() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("pack_test_ephemeral");
+    let mut wizard: EphemeralPack<i32> =
+        EphemeralPack::load_or_init(path.clone(), "session", Lifetime::Session)
+            .unwrap();
+    wizard.update(|step| *step = 2).unwrap();
+    assert_eq!(*wizard.get().unwrap(), 2);
+    assert!(!wizard.is_expired());
+    // Next startup
+    let mut wizard: EphemeralPack<i32> =
+        EphemeralPack::load_or_init(path.clone(), "session", Lifetime::Session)
+            .unwrap();
+    assert_eq!(*wizard.get().unwrap(), 0);
+    assert!(!path.join("session.yml").exists());
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct Settings {
+    theme: String,
+    font_size: u32,
+}
+
+impl Migratable for Settings {
+    const SCHEMA_VERSION: u32 = 3;
+    fn migrations() -> Vec<migrate::Migration> {
+        vec![
+            // v1 -> v2: dark_mode became theme
+            |v| {
+                let dark = v["dark_mode"].as_bool().unwrap_or(false);
+                v["theme"] = if dark { "dark" } else { "light" }.into();
+            },
+            // v2 -> v3: font_size is doubled
+            |v| {
+                let size = v["font_size"].as_u64().unwrap_or(0);
+                v["font_size"] = (size * 2).into();
+            },
+        ]
+    }
+}
+
+#[test]
+fn test_migrate() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("pack_test_migrate");
+    std::fs::create_dir_all(&path).unwrap();
+    std::fs::write(path.join("v1.yml"), "---\ndark_mode: true\nfont_size: 6\n")
+        .unwrap();
+    std::fs::write(
+        path.join("v2.yml"),
+        "#!storaget format=yaml schema_version=2\n---\ntheme: light\nfont_size: 7\n",
+    )
+    .unwrap();
+    std::fs::write(
+        path.join("v4.yml"),
+        "#!storaget format=yaml schema_version=4\n---\ntheme: light\nfont_size: 7\n",
+    )
+    .unwrap();
+    register_migrations::<Settings>().unwrap();
+
+    let v1: Pack<Settings> = Pack::load_or_init(path.clone(), "v1").unwrap();
+    assert_eq!(v1.theme, "dark");
+    assert_eq!(v1.font_size, 12);
+    let v2: Pack<Settings> = Pack::load_or_init(path.clone(), "v2").unwrap();
+    assert_eq!(v2.theme, "light");
+    assert_eq!(v2.font_size, 14);
+    let header = FileHeader::read(&path.join("v1.yml")).unwrap().unwrap();
+    assert_eq!(header.schema_version, 3);
+    // Already migrated
+    let v1: Pack<Settings> = Pack::load_or_init(path.clone(), "v1").unwrap();
+    assert_eq!(v1.font_size, 12);
+    assert!(Pack::<Settings>::load_or_init(path, "v4").is_err());
+}
+
+#[test]
+fn test_file_header() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("pack_test_header");
+    let _: Pack<i32> = Pack::load_or_init(path.clone(), "new").unwrap();
+    let header = FileHeader::read(&path.join("new.yml")).unwrap().unwrap();
+    assert_eq!(header.format, header::Format::Yaml);
+    assert_eq!(header.schema_version, 1);
+    assert_eq!(header.crate_version, env!("CARGO_PKG_VERSION"));
+    // File saved without header
+    std::fs::write(path.join("legacy.yml"), "---\n42\n").unwrap();
+    let legacy: Pack<i32> = Pack::load_or_init(path.clone(), "legacy").unwrap();
+    assert_eq!(*legacy, 42);
+    std::fs::write(path.join("json.yml"), "#!storaget format=json\n42\n")
+        .unwrap();
+    let json: Pack<i32> = Pack::load_or_init(path.clone(), "json").unwrap();
+    assert_eq!(*json, 42);
+    std::fs::write(path.join("bson.yml"), "#!storaget format=bson\n42\n")
+        .unwrap();
+    assert!(Pack::<i32>::load_or_init(path, "bson").is_err());
+}
+
+#[test]
+fn test_large_pack() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("pack_test_large");
+    let mut pack: Pack<Vec<String>> =
+        Pack::load_or_init(path.clone(), "large").unwrap();
+    pack.update(|d| *d = (0..100_000).map(|i| format!("item {}", i)).collect())
+        .unwrap();
+    let mut pack: Pack<Vec<String>> =
+        Pack::load_or_init(path.clone(), "large").unwrap();
+    assert_eq!(pack.len(), 100_000);
+    assert_eq!(pack[99_999], "item 99999");
+
+    // History of a pack larger than the read buffer
+    pack.enable_history(None).unwrap();
+    pack.update(|d| d.push("last".to_string())).unwrap();
+    pack.save().unwrap();
+    let history = pack.history().unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].data.len(), 100_000);
+
+    // Corruption at the end of the body is detected while streaming
+    let file = path.join("large.yml");
+    let content = std::fs::read_to_string(&file).unwrap();
+    std::fs::write(&file, content.replace("item 99999", "item 99998")).unwrap();
+    assert!(matches!(
+        Pack::<Vec<String>>::load_or_init(path, "large"),
+        Err(PackError::IntegrityError(_))
+    ));
+}
+
+#[test]
+fn test_checksum() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("pack_test_checksum");
+    let mut pack: Pack<Vec<String>> =
+        Pack::load_or_init(path.clone(), "list").unwrap();
+    pack.update(|d| d.push("first".to_string())).unwrap();
+    let file = path.join("list.yml");
+    let header = FileHeader::read(&file).unwrap().unwrap();
+    assert!(header.checksum.unwrap().starts_with("sha256:"));
+
+    // Corrupted body is detected
+    let content = std::fs::read_to_string(&file).unwrap();
+    std::fs::write(&file, content.replace("first", "fXrst")).unwrap();
+    assert!(matches!(
+        Pack::<Vec<String>>::load_or_init(path.clone(), "list"),
+        Err(PackError::IntegrityError(_))
+    ));
+
+    // Header without checksum skips the check
+    let content = std::fs::read_to_string(&file).unwrap();
+    let (line, body) = content.split_once('\n').unwrap();
+    let line = line.split(" checksum=").next().unwrap();
+    std::fs::write(&file, format!("{}\n{}", line, body)).unwrap();
+    let pack: Pack<Vec<String>> = Pack::load_or_init(path, "list").unwrap();
+    assert_eq!(*pack, vec!["fXrst".to_string()]);
+}
+
+// Has no meaningful Default
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Account {
+    owner: String,
+    balance: i64,
+}
+
+#[test]
+fn test_load_or_init_with() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("pack_test");
+    let mut account: Pack<Account> =
+        Pack::load_or_init_with(path.clone(), "account", || Account {
+            owner: "Peter".to_string(),
+            balance: 0,
+        })
+        .unwrap();
+    assert_eq!(account.owner, "Peter");
+    account.update(|a| a.balance = 100).unwrap();
+    let account: Pack<Account> =
+        Pack::load_or_init_with(path, "account", || unreachable!()).unwrap();
+    assert_eq!(account.balance, 100);
+}
+
+#[test]
+fn test_try_load() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("pack_test_try_load");
+    std::fs::create_dir_all(&path).unwrap();
+    let missing = Pack::<i32>::try_load(path.join("missing.yml")).unwrap();
+    assert!(missing.is_none());
+    assert!(matches!(
+        Pack::<i32>::load_from_path(path.join("missing.yml")),
+        Err(PackError::PathNotFound)
+    ));
+    std::fs::write(path.join("corrupt.yml"), "---\nnot a number\n").unwrap();
+    assert!(matches!(
+        Pack::<i32>::try_load(path.join("corrupt.yml")),
+        Err(PackError::DeserializeError { .. })
+    ));
+    let err =
+        Pack::<i32>::load_from_path(path.join("corrupt.yml")).unwrap_err();
+    assert_eq!(err.path(), Some(path.join("corrupt.yml").as_path()));
+    assert!(err.to_string().contains("corrupt.yml"));
+    assert!(std::error::Error::source(&err).is_some());
+    // Corrupt file is not overwritten
+    assert!(Pack::<i32>::load_or_init(path.clone(), "corrupt").is_err());
+    let content = std::fs::read_to_string(path.join("corrupt.yml")).unwrap();
+    assert_eq!(content, "---\nnot a number\n");
+    let _: Pack<i32> = Pack::load_or_init(path.clone(), "new").unwrap();
+    assert!(Pack::<i32>::try_load(path.join("new.yml"))
+        .unwrap()
+        .is_some());
+}
+
+#[test]
+fn test_save_error_hook() {
+    let dir = testing::TempDir::new().unwrap();
+    use std::sync::{Arc, Mutex};
+    let path = dir.path().join("pack_test_error_hook");
+    let mut counter: Pack<i32> =
+        Pack::load_or_init(path.clone(), "counter").unwrap();
+    let failed = Arc::new(Mutex::new(Vec::new()));
+    let failed_hook = failed.clone();
+    counter.set_save_error_hook(move |path, err| {
+        failed_hook.lock().unwrap().push((path.to_path_buf(), err));
+    });
+    *counter.as_mut() = 1;
+    assert!(failed.lock().unwrap().is_empty());
+    // File cannot be replaced by the saved one
+    std::fs::remove_file(path.join("counter.yml")).unwrap();
+    std::fs::create_dir_all(path.join("counter.yml/blocked")).unwrap();
+    *counter.as_mut() = 2;
+    let failed = failed.lock().unwrap();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].0, path.join("counter.yml"));
+    assert!(matches!(failed[0].1, PackError::IOError { .. }));
+}
+
+#[test]
+fn test_guard_commit_discard() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("pack_test_guard");
+    let mut counter: Pack<i32> =
+        Pack::load_or_init(path.clone(), "counter").unwrap();
+    let mut guard = counter.as_mut();
+    *guard = 7;
+    guard.commit().unwrap();
+    assert_eq!(*counter, 7);
+
+    let mut guard = counter.as_mut_with_backup();
+    *guard = 8;
+    guard.discard();
+    assert_eq!(*counter, 7);
+    let stored: Pack<i32> =
+        Pack::load_or_init(path.clone(), "counter").unwrap();
+    assert_eq!(*stored, 7);
+
+    // Failed commit is reported and rolled back
+    std::fs::remove_file(path.join("counter.yml")).unwrap();
+    std::fs::create_dir_all(path.join("counter.yml/blocked")).unwrap();
+    let mut guard = counter.as_mut_with_backup();
+    *guard = 9;
+    assert!(guard.commit().is_err());
+    assert_eq!(*counter, 7);
+
+    // Without a backup, changes are not saved,
+    // but stay in memory
+    let mut guard = counter.as_mut();
+    *guard = 10;
+    assert!(guard.commit().is_err());
+    assert_eq!(*counter, 10);
+}
+
+#[test]
+fn test_history() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("pack_test_history");
+    let mut counter: Pack<i32> =
+        Pack::load_or_init(path.clone(), "counter").unwrap();
+    counter.enable_history(Some(2)).unwrap();
+    for i in 1..=3 {
+        counter.update(|c| *c = i).unwrap();
+    }
+    // Unchanged data is not recorded
+    counter.save().unwrap();
+    let history: Vec<(u64, i32)> = counter
+        .history()
+        .unwrap()
+        .into_iter()
+        .map(|v| (v.version, v.data))
+        .collect();
+    assert_eq!(history, vec![(2, 1), (3, 2)]);
+
+    counter.revert_to(2).unwrap();
+    assert_eq!(*counter, 1);
+    assert!(matches!(
+        counter.revert_to(1),
+        Err(PackError::ObjectNotFound)
+    ));
+
+    // History mode is restored at load
+    let mut counter: Pack<i32> =
+        Pack::load_or_init(path.clone(), "counter").unwrap();
+    counter.update(|c| *c = 5).unwrap();
+    let history = counter.history().unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[1].version, 5);
+    assert_eq!(history[1].data, 1);
+}
+
+#[test]
+fn test_undo_redo() {
+    let dir = testing::TempDir::new().unwrap();
+    let mut text: Pack<String> =
+        Pack::load_or_init(dir.path().join("pack_test_undo"), "text").unwrap();
+    text.enable_history(None).unwrap();
+    assert!(!text.can_undo().unwrap());
+    for word in ["a", "ab", "abc"] {
+        text.update(|t| *t = word.to_string()).unwrap();
+    }
+    assert!(text.undo().unwrap());
+    assert!(text.undo().unwrap());
+    assert_eq!(*text, "a");
+    assert!(text.can_redo().unwrap());
+    assert!(text.redo().unwrap());
+    assert_eq!(*text, "ab");
+
+    // Undo survives reload
+    let mut text: Pack<String> =
+        Pack::load_or_init(dir.path().join("pack_test_undo"), "text").unwrap();
+    assert_eq!(*text, "ab");
+    assert!(text.redo().unwrap());
+    assert_eq!(*text, "abc");
+    assert!(!text.redo().unwrap());
+
+    // A new change drops the redo stack
+    text.undo().unwrap();
+    text.update(|t| *t = "x".to_string()).unwrap();
+    assert!(!text.can_redo().unwrap());
+    assert!(text.undo().unwrap());
+    assert_eq!(*text, "ab");
+    assert!(text.undo().unwrap());
+    assert!(text.undo().unwrap());
+    assert_eq!(*text, "");
+    assert!(!text.undo().unwrap());
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+struct Engine {
+    hp: u32,
+    tags: Vec<String>,
+}
+
+#[test]
+fn test_diff() {
+    let dir = testing::TempDir::new().unwrap();
+    let mut engine: Pack<Engine> =
+        Pack::load_or_init(dir.path().join("pack_test_diff"), "engine")
+            .unwrap();
+    engine.enable_history(None).unwrap();
+    assert!(engine.diff_with_disk().unwrap().is_empty());
+    {
+        let mut guard = engine.as_mut();
+        guard.hp = 100;
+        guard.tags.push("v8".to_string());
+        assert_eq!(
+            guard.diff_with_disk().unwrap().to_string(),
+            "~ hp: 0 -> 100\n+ tags.0: v8\n"
+        );
+    }
+    assert!(engine.diff_with_disk().unwrap().is_empty());
+    engine.update(|e| e.hp = 150).unwrap();
+    engine.update(|e| e.tags.clear()).unwrap();
+    let diff = engine.diff(2, 3).unwrap();
+    assert_eq!(
+        diff.changes,
+        vec![FieldChange::Changed {
+            path: "hp".to_string(),
+            old: 100.into(),
+            new: 150.into(),
+        }]
+    );
+    assert_eq!(diff.to_string(), "~ hp: 100 -> 150\n");
+    assert_eq!(
+        engine.diff(1, 3).unwrap().to_string(),
+        "~ hp: 0 -> 150\n+ tags.0: v8\n"
+    );
+    assert!(matches!(engine.diff(1, 9), Err(PackError::ObjectNotFound)));
+}
+
+// Neither Clone nor Copy, update must work anyway
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+struct Blob {
+    name: String,
+    bytes: Vec<u8>,
+}
+
+#[test]
+fn test_update_without_clone() {
+    let tmp = testing::TempDir::new().unwrap();
+    let dir = tmp.path().join("pack_test_update_without_clone");
+    let mut blob: Pack<Blob> = Pack::load_or_init(dir.clone(), "blob").unwrap();
+    blob.update(|b| b.name = "first".to_string()).unwrap();
+    assert_eq!(blob.name, "first");
+    // A directory in place of the file, so the save fails
+    let file = dir.join("blob.yml");
+    std::fs::remove_file(&file).unwrap();
+    std::fs::create_dir_all(file.join("inner")).unwrap();
+    let res = blob.update(|b| {
+        b.name = "second".to_string();
+        b.bytes = vec![1, 2, 3];
+    });
+    assert!(res.is_err());
+    // Rolled back to the state before the update
+    assert_eq!(
+        *blob,
+        Blob {
+            name: "first".to_string(),
+            bytes: Vec::new(),
+        }
+    );
+}
+
+#[test]
+fn test_try_update() {
+    let tmp = testing::TempDir::new().unwrap();
+    let dir = tmp.path().join("pack_test_try_update");
+    let mut blob: Pack<Blob> = Pack::load_or_init(dir.clone(), "blob").unwrap();
+    let name = String::from("first");
+    // FnOnce, so the name can be moved into the data
+    let len: PackResult<usize> = blob.try_update(move |b| {
+        b.name = name;
+        Ok(b.name.len())
+    });
+    assert_eq!(len.unwrap(), 5);
+    let res: PackResult<()> = blob.try_update(|b| {
+        b.name = "second".to_string();
+        b.bytes.push(1);
+        Err(PackError::ValidationError("invalid".to_string()))
+    });
+    assert!(matches!(res, Err(PackError::ValidationError(_))));
+    assert_eq!(blob.name, "first");
+    assert!(blob.bytes.is_empty());
+    // Nothing was saved
+    let blob: Pack<Blob> = Pack::load_or_init(dir.clone(), "blob").unwrap();
+    assert_eq!(blob.name, "first");
+}
+
+// Fails to deserialize while REJECT is set
+static REJECT: AtomicBool = AtomicBool::new(false);
+
+#[derive(Serialize, Default)]
+struct Picky {
+    value: u32,
+}
+
+impl<'de> Deserialize<'de> for Picky {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        d: D,
+    ) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Raw {
+            value: u32,
+        }
+        let raw = Raw::deserialize(d)?;
+        if REJECT.load(Ordering::SeqCst) {
+            return Err(serde::de::Error::custom("rejected"));
+        }
+        Ok(Picky { value: raw.value })
+    }
+}
+
+#[test]
+fn test_try_update_rollback_error() {
+    let tmp = testing::TempDir::new().unwrap();
+    let dir = tmp.path().join("pack_test_rollback_error");
+    let mut picky: Pack<Picky> =
+        Pack::load_or_init(dir.clone(), "picky").unwrap();
+    REJECT.store(true, Ordering::SeqCst);
+    let res: PackResult<()> = picky.try_update(|p| {
+        p.value = 1;
+        Err(PackError::ValidationError("invalid".to_string()))
+    });
+    REJECT.store(false, Ordering::SeqCst);
+    // The failed rollback is reported, and the change is kept
+    assert!(matches!(res, Err(PackError::DeserializeError { .. })));
+    assert_eq!(picky.value, 1);
+    let picky: Pack<Picky> = Pack::load_or_init(dir, "picky").unwrap();
+    assert_eq!(picky.value, 0);
+}
+
+#[test]
+fn test_path_rename_move() {
+    let tmp = testing::TempDir::new().unwrap();
+    let dir = tmp.path().join("pack_test_rename_move");
+    let mut blob: Pack<Blob> = Pack::load_or_init(dir.clone(), "blob").unwrap();
+    blob.enable_history(None).unwrap();
+    blob.update(|b| b.name = "first".to_string()).unwrap();
+    assert_eq!(blob.path(), dir.join("blob.yml"));
+    // Target exists
+    let _: Pack<Blob> = Pack::load_or_init(dir.clone(), "other").unwrap();
+    assert!(matches!(blob.rename_file("other"), Err(PackError::IDTaken)));
+    blob.rename_file("renamed").unwrap();
+    assert_eq!(blob.path(), dir.join("renamed.yml"));
+    assert!(!dir.join("blob.yml").exists());
+    assert!(dir.join(".history").join("renamed.yml").is_file());
+    blob.move_to(&dir.join("moved")).unwrap();
+    assert_eq!(blob.path(), dir.join("moved").join("renamed.yml"));
+    assert!(!dir.join("renamed.yml").exists());
+    // Saves go to the new path, and history moved with the file
+    blob.update(|b| b.name = "second".to_string()).unwrap();
+    assert_eq!(blob.history().unwrap().len(), 2);
+    let blob: Pack<Blob> =
+        Pack::load_or_init(dir.join("moved"), "renamed").unwrap();
+    assert_eq!(blob.name, "second");
+}
+
+#[test]
+fn test_metadata() {
+    let tmp = testing::TempDir::new().unwrap();
+    let dir = tmp.path().join("pack_test_metadata");
+    let mut blob: Pack<Blob> = Pack::load_or_init(dir.clone(), "blob").unwrap();
+    let first = blob.metadata().unwrap();
+    let created = first.created_at.unwrap();
+    assert_eq!(first.updated_at, Some(created));
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    blob.update(|b| b.name = "first".to_string()).unwrap();
+    // Times are kept in the file, so a new load sees them too
+    let blob: Pack<Blob> = Pack::load_or_init(dir.clone(), "blob").unwrap();
+    let second = blob.metadata().unwrap();
+    assert_eq!(second.created_at, Some(created));
+    assert!(second.updated_at.unwrap() > created);
+    // File without header falls back to the file times
+    std::fs::write(dir.join("plain.yml"), "---\nname: plain\nbytes: []\n")
+        .unwrap();
+    let plain: Pack<Blob> = Pack::load_or_init(dir.clone(), "plain").unwrap();
+    assert_eq!(plain.name, "plain");
+    assert!(plain.metadata().unwrap().updated_at.is_some());
+}
+
+#[test]
+fn test_update_if_version() {
+    let tmp = testing::TempDir::new().unwrap();
+    let dir = tmp.path().join("pack_test_update_if_version");
+    let mut blob: Pack<Blob> = Pack::load_or_init(dir.clone(), "blob").unwrap();
+    assert_eq!(blob.revision(), 1);
+    blob.update(|b| b.name = "first".to_string()).unwrap();
+    assert_eq!(blob.revision(), 2);
+    // Two clients editing the same file
+    let mut other: Pack<Blob> =
+        Pack::load_or_init(dir.clone(), "blob").unwrap();
+    let etag = other.revision();
+    blob.update_if_version(etag, |b| b.name = "ours".to_string())
+        .unwrap();
+    assert_eq!(blob.revision(), 3);
+    let res = other.update_if_version(etag, |b| b.name = "theirs".to_string());
+    assert!(matches!(
+        res,
+        Err(PackError::Conflict {
+            expected: 2,
+            found: 3
+        })
+    ));
+    assert_eq!(other.name, "first");
+    let blob: Pack<Blob> = Pack::load_or_init(dir.clone(), "blob").unwrap();
+    assert_eq!(blob.name, "ours");
+}
+
+#[test]
+fn test_revision_in_memory() {
+    let tmp = testing::TempDir::new().unwrap();
+    let dir = tmp.path().join("pack_test_revision_in_memory");
+    let file = dir.join("blob.yml");
+    let mut blob: Pack<Blob> = Pack::load_or_init(dir.clone(), "blob").unwrap();
+    let created = FileHeader::read(&file).unwrap().unwrap().created;
+    for _ in 0..3 {
+        blob.update(|b| b.name.push('x')).unwrap();
+    }
+    let header = FileHeader::read(&file).unwrap().unwrap();
+    assert_eq!(header.revision, Some(4));
+    assert_eq!(header.created, created);
+    // Saves do not read the header back
+    let content = std::fs::read_to_string(&file).unwrap();
+    std::fs::write(&file, content.replace("revision=4", "revision=40"))
+        .unwrap();
+    blob.update(|b| b.name.push('x')).unwrap();
+    let header = FileHeader::read(&file).unwrap().unwrap();
+    assert_eq!(header.revision, Some(5));
+    // Loaded with the stored revision
+    let mut other: Pack<Blob> =
+        Pack::load_or_init(dir.clone(), "blob").unwrap();
+    other.update(|b| b.name.push('y')).unwrap();
+    assert_eq!(other.revision(), 6);
+    // Checked updates follow the saves of others
+    blob.update_if_version(6, |b| b.name.push('z')).unwrap();
+    assert_eq!(blob.revision(), 7);
+    assert_eq!(FileHeader::read(&file).unwrap().unwrap().created, created);
+}
+
+#[test]
+fn test_update_resolving() {
+    let tmp = testing::TempDir::new().unwrap();
+    let dir = tmp.path().join("pack_test_update_resolving");
+    let mut ours: Pack<Engine> =
+        Pack::load_or_init(dir.clone(), "engine").unwrap();
+    let mut theirs: Pack<Engine> =
+        Pack::load_or_init(dir.clone(), "engine").unwrap();
+    let etag = ours.revision();
+    theirs.update(|e| e.tags.push("theirs".into())).unwrap();
+    // No conflict, theirs is up to date
+    let merge = ConflictStrategy::merge(|ours: Engine, theirs: Engine| {
+        let mut tags = theirs.tags;
+        tags.extend(ours.tags);
+        Engine { tags, ..ours }
+    });
+    theirs
+        .update_resolving(theirs.revision(), &merge, |e| e.hp = 10)
+        .unwrap();
+    // Our edit is merged with the stored version
+    ours.update_resolving(etag, &merge, |e| {
+        e.hp = 20;
+        e.tags.push("ours".into());
+    })
+    .unwrap();
+    assert_eq!(ours.hp, 20);
+    assert_eq!(ours.tags, vec!["theirs", "ours"]);
+    // Theirs drops our edit
+    let res =
+        theirs.update_resolving(etag, &ConflictStrategy::Theirs, |e| e.hp = 30);
+    assert!(res.is_ok());
+    assert_eq!(theirs.hp, 20);
+    let stored: Pack<Engine> =
+        Pack::load_or_init(dir.clone(), "engine").unwrap();
+    assert_eq!(*stored, *ours);
+}
@@ -1,5 +1,4 @@
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
 use storaget::*;
 
 #[derive(Serialize, Deserialize, Clone, Default)]
@@ -61,21 +60,22 @@ impl TryFrom for CarV2 {
 
 #[test]
 fn test_try_load_or_init() {
+    let dir = testing::TempDir::new().unwrap();
     let mut meaning_of_life: Pack<CarV0> = Pack::try_load_or_init(
-        PathBuf::from("data/pack_try_from_test"),
+        dir.path().join("pack_try_from_test"),
         "meaning_of_life",
     )
     .unwrap();
     meaning_of_life.as_mut().number_of_seats = 4;
     assert_eq!(meaning_of_life.id, 0);
     let meaning_of_life_v1: Pack<CarV1> = Pack::try_load_or_init(
-        PathBuf::from("data/pack_try_from_test"),
+        dir.path().join("pack_try_from_test"),
         "meaning_of_life",
     )
     .unwrap();
     assert_eq!(meaning_of_life_v1.seats_number, 4);
     let meaning_of_life_v2: Pack<CarV2> = Pack::try_load_or_init(
-        PathBuf::from("data/pack_try_from_test"),
+        dir.path().join("pack_try_from_test"),
         "meaning_of_life",
     )
     .unwrap();
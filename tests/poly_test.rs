@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+use storaget::*;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "kind")]
+enum Vehicle {
+    Car { id: String, seats: u32 },
+    Bike { id: String, gears: u32 },
+}
+
+impl Default for Vehicle {
+    fn default() -> Self {
+        Vehicle::Bike {
+            id: String::new(),
+            gears: 0,
+        }
+    }
+}
+
+impl VecPackMember for Vehicle {
+    fn get_id(&self) -> &str {
+        match self {
+            Vehicle::Car { id, .. } => id,
+            Vehicle::Bike { id, .. } => id,
+        }
+    }
+}
+
+impl PolyMember for Vehicle {
+    fn variant(&self) -> &str {
+        match self {
+            Vehicle::Car { .. } => "Car",
+            Vehicle::Bike { .. } => "Bike",
+        }
+    }
+}
+
+#[test]
+fn test_of_variant() {
+    let dir = testing::TempDir::new().unwrap();
+    let mut vehicles: PolyVecPack<Vehicle> =
+        PolyVecPack::load_or_init(dir.path().join("poly_test_variant"))
+            .unwrap();
+    vehicles
+        .insert(Vehicle::Car {
+            id: "1".to_string(),
+            seats: 4,
+        })
+        .unwrap();
+    vehicles
+        .insert(Vehicle::Bike {
+            id: "2".to_string(),
+            gears: 21,
+        })
+        .unwrap();
+    vehicles
+        .insert(Vehicle::Car {
+            id: "3".to_string(),
+            seats: 2,
+        })
+        .unwrap();
+    assert_eq!(vehicles.of_variant("Car").len(), 2);
+    assert_eq!(vehicles.of_variant("Bike").len(), 1);
+    assert_eq!(vehicles.count_by_variant().get("Car"), Some(&2));
+}
+
+#[test]
+fn test_variant_migration() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("poly_test_migration");
+    std::fs::create_dir_all(&path).unwrap();
+    // Old schema: Car stored its seats as number_of_seats
+    std::fs::write(
+        path.join("1.yml"),
+        "---\nkind: Car\nid: \"1\"\nnumber_of_seats: 5\n",
+    )
+    .unwrap();
+    std::fs::write(
+        path.join("2.yml"),
+        "---\nkind: Bike\nid: \"2\"\ngears: 3\n",
+    )
+    .unwrap();
+
+    let migrations = PolyMigrations::new("kind").add("Car", |car| {
+        if let Some(seats) = car.remove(&"number_of_seats".into()) {
+            car.insert("seats".into(), seats);
+        }
+    });
+    let vehicles: PolyVecPack<Vehicle> =
+        PolyVecPack::load_or_init_with_migrations(path.clone(), &migrations)
+            .unwrap();
+    match vehicles.find_id("1").unwrap().unpack() {
+        Vehicle::Car { seats, .. } => assert_eq!(*seats, 5),
+        _ => panic!("Wrong variant"),
+    }
+    // Migrated file is saved back
+    let vehicles: PolyVecPack<Vehicle> =
+        PolyVecPack::load_or_init(path).unwrap();
+    assert_eq!(vehicles.len(), 2);
+}
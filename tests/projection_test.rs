@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use storaget::*;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Tx {
+    amount: i64,
+}
+
+fn apply(balance: &mut i64, tx: &Tx) {
+    *balance += tx.amount;
+}
+
+#[test]
+fn test_logpack_fold() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("projection_test_fold/tx.log");
+    let _ = std::fs::remove_file(&path);
+    let mut log: LogPack<Tx> = LogPack::open(path).unwrap();
+    log.append_many(&[Tx { amount: 10 }, Tx { amount: -3 }])
+        .unwrap();
+    let balance = log.fold(0, |balance, tx| *balance += tx.amount).unwrap();
+    assert_eq!(balance, 7);
+}
+
+#[test]
+fn test_projection() {
+    let tmp = testing::TempDir::new().unwrap();
+    let dir = tmp.path().join("projection_test");
+    let mut account: Projection<Tx, i64> =
+        Projection::open(dir.clone(), apply).unwrap();
+    account.snapshot_every(2);
+    for amount in [10, 20, 30] {
+        account.append(Tx { amount }).unwrap();
+    }
+    assert_eq!(*account.state(), 60);
+    assert_eq!(account.events(), 3);
+    drop(account);
+    // Snapshot has the first two events
+    let snapshot = std::fs::read_to_string(dir.join("snapshot.yml")).unwrap();
+    assert!(snapshot.contains("events: 2"));
+    assert!(snapshot.contains("state: 30"));
+    // The third one is replayed from the log
+    let mut account: Projection<Tx, i64> =
+        Projection::open(dir.clone(), apply).unwrap();
+    assert_eq!(*account.state(), 60);
+    account.append(Tx { amount: -5 }).unwrap();
+    account.snapshot().unwrap();
+    drop(account);
+    let account: Projection<Tx, i64> =
+        Projection::open(dir.clone(), apply).unwrap();
+    assert_eq!(*account.state(), 55);
+    assert_eq!(account.events(), 4);
+    assert_eq!(account.log().count().unwrap(), 4);
+}
+
+#[test]
+fn test_projection_compact() {
+    let tmp = testing::TempDir::new().unwrap();
+    let dir = tmp.path().join("projection_test_compact");
+    let mut account: Projection<Tx, i64> =
+        Projection::open(dir.clone(), apply).unwrap();
+    for amount in [10, 20, 30] {
+        account.append(Tx { amount }).unwrap();
+    }
+    assert_eq!(account.compact().unwrap(), 3);
+    account.append(Tx { amount: 1 }).unwrap();
+    assert_eq!(account.log().count().unwrap(), 1);
+    drop(account);
+    let mut account: Projection<Tx, i64> =
+        Projection::open(dir.clone(), apply).unwrap();
+    assert_eq!(*account.state(), 61);
+    assert_eq!(account.events(), 4);
+    // Compaction again only drops the new events
+    assert_eq!(account.compact().unwrap(), 1);
+    drop(account);
+    // Snapshot lost, the log cannot rebuild the state
+    std::fs::remove_file(dir.join("snapshot.yml")).unwrap();
+    let res: PackResult<Projection<Tx, i64>> =
+        Projection::open(dir.clone(), apply);
+    assert!(matches!(res, Err(PackError::IntegrityError(_))));
+}
@@ -0,0 +1,43 @@
+#![cfg(feature = "prometheus")]
+
+use serde::{Deserialize, Serialize};
+use storaget::*;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, VecPackMember)]
+struct Car {
+    #[pack(id)]
+    id: String,
+    hp: u32,
+}
+
+#[test]
+fn test_prometheus_metrics() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("prometheus_test");
+    let mut cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    for id in ["1", "2"] {
+        cars.insert(Car {
+            id: id.to_string(),
+            hp: 100,
+        })
+        .unwrap();
+    }
+    let body = cars.prometheus_metrics();
+    let collection = path.display();
+    assert!(body.contains("# TYPE storaget_saves_total counter\n"));
+    assert!(body.contains(&format!(
+        "storaget_saves_total{{collection=\"{}\"}} 2\n",
+        collection
+    )));
+    assert!(body.contains(&format!(
+        "storaget_slowest_operation_seconds{{collection=\"{}\",op=\"save\"}}",
+        collection
+    )));
+    assert!(!body.contains("op=\"load\""));
+    // Families are listed once for more collections
+    let stats = cars.op_stats();
+    let body = prometheus::render(&[("cars", &stats), ("say \"hi\"", &stats)]);
+    assert_eq!(body.matches("# TYPE storaget_saves_total").count(), 1);
+    assert!(body.contains("storaget_saves_total{collection=\"cars\"} 2\n"));
+    assert!(body.contains("collection=\"say \\\"hi\\\"\""));
+}
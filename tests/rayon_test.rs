@@ -0,0 +1,113 @@
+#![cfg(feature = "rayon")]
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use storaget::*;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct Car {
+    pub id: String,
+    pub hp: u32,
+}
+
+impl VecPackMember for Car {
+    fn get_id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Validate for Car {
+    fn validate(&self) -> Result<(), String> {
+        match self.hp {
+            13 => Err("unlucky car".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn create_cars(path: PathBuf, count: u32) -> VecPack<Car> {
+    let mut cars: VecPack<Car> = VecPack::load_or_init(path).unwrap();
+    let items = (0..count)
+        .map(|i| Car {
+            id: i.to_string(),
+            hp: i,
+        })
+        .collect();
+    assert!(cars.insert_many(items).unwrap().is_empty());
+    cars
+}
+
+#[test]
+fn test_par_iter() {
+    let dir = testing::TempDir::new().unwrap();
+    let cars = create_cars(dir.path().join("rayon_test_par_iter"), 100);
+    let total: u32 = cars.par_iter().map(|car| car.hp).sum();
+    assert_eq!(total, (0..100).sum::<u32>());
+    let ids: Vec<&str> = cars.par_iter().map(|car| car.get_id()).collect();
+    assert_eq!(ids[42], "42");
+}
+
+#[test]
+fn test_par_iter_mut() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("rayon_test_par_iter_mut");
+    let mut cars = create_cars(path.clone(), 100);
+    let mut batch = cars.batch_mut().unwrap();
+    batch
+        .par_iter_mut()
+        .filter(|car| car.hp % 2 == 0)
+        .for_each(|mut car| car.hp += 1000);
+    assert_eq!(batch.modified().len(), 50);
+    assert_eq!(batch.commit().unwrap(), 50);
+    let cars: VecPack<Car> = VecPack::load_or_init(path).unwrap();
+    assert_eq!(cars.find_id("2").unwrap().hp, 1002);
+    assert_eq!(cars.find_id("3").unwrap().hp, 3);
+}
+
+#[test]
+fn test_par_insert_many() {
+    let dir = testing::TempDir::new().unwrap();
+    let items = || {
+        (0..200)
+            .map(|i| Car {
+                id: i.to_string(),
+                hp: i,
+            })
+            .chain(std::iter::once(Car {
+                id: ".hidden".to_string(),
+                hp: 1,
+            }))
+            .collect::<Vec<Car>>()
+    };
+    let path = dir.path().join("rayon_test_par_insert_many");
+    let mut cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    let mut seq: VecPack<Car> =
+        VecPack::load_or_init(dir.path().join("rayon_test_insert_many"))
+            .unwrap();
+    assert!(cars.enable_validation().is_empty());
+    assert!(seq.enable_validation().is_empty());
+
+    // The same results as the sequential insert_many
+    let failed = cars.par_insert_many(items()).unwrap();
+    let seq_failed = seq.insert_many(items()).unwrap();
+    let ids = |failed: &[(String, PackError)]| {
+        failed
+            .iter()
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<String>>()
+    };
+    assert_eq!(ids(&failed), vec!["13".to_string(), ".hidden".to_string()]);
+    assert_eq!(ids(&failed), ids(&seq_failed));
+    assert!(cars.ids().eq(seq.ids()));
+    assert!(matches!(
+        cars.par_insert_many(vec![Car {
+            id: "1".to_string(),
+            hp: 1,
+        }]),
+        Err(PackError::IDTaken)
+    ));
+    let cars: VecPack<Car> = VecPack::load_or_init(path).unwrap();
+    assert_eq!(cars.len(), 199);
+    assert_eq!(cars.find_id("42").unwrap().hp, 42);
+}
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use storaget::*;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct User {
+    pub id: String,
+    pub name: String,
+}
+
+impl User {
+    pub fn new(id: &str, name: &str) -> Self {
+        User {
+            id: id.to_string(),
+            name: name.to_string(),
+        }
+    }
+}
+
+impl VecPackMember for User {
+    fn get_id(&self) -> &str {
+        &self.id
+    }
+}
+
+#[test]
+fn test_rename_collection() {
+    let dir = testing::TempDir::new().unwrap();
+    let root = dir.path().join("registry_test_rename");
+    let mut db = Registry::load_or_init(root.clone()).unwrap();
+    let mut users: VecPack<User> = db.collection("users").unwrap();
+    users.insert(User::new("1", "Peter")).unwrap();
+
+    db.rename_collection("users", "customers").unwrap();
+    assert_eq!(db.collections(), &vec!["customers".to_string()]);
+    assert!(!root.join("users").exists());
+
+    // Open handle follows the new location
+    users.insert(User::new("2", "Mary")).unwrap();
+    users.find_id_mut("1").unwrap().as_mut().name = "Pete".to_string();
+    assert!(root.join("customers").join("2.yml").exists());
+
+    // Manifest is persisted
+    let mut db = Registry::load_or_init(root).unwrap();
+    assert!(db.rename_collection("users", "other").is_err());
+    let customers: VecPack<User> = db.collection("customers").unwrap();
+    assert_eq!(customers.len(), 2);
+    assert_eq!(customers.find_id("1").unwrap().name, "Pete");
+}
+
+#[test]
+fn test_rename_collection_taken() {
+    let dir = testing::TempDir::new().unwrap();
+    let mut db =
+        Registry::load_or_init(dir.path().join("registry_test_taken")).unwrap();
+    let _: VecPack<User> = db.collection("a").unwrap();
+    let _: VecPack<User> = db.collection("b").unwrap();
+    assert!(db.rename_collection("a", "b").is_err());
+    assert!(db.rename_collection("a", ".hidden").is_err());
+    assert_eq!(db.collections().len(), 2);
+}
+
+#[test]
+fn test_relocate_root() {
+    let dir = testing::TempDir::new().unwrap();
+    let root = dir.path().join("registry_test_relocate");
+    let new_root = dir.path().join("registry_test_relocate_moved/root");
+    let mut db = Registry::load_or_init(root.clone()).unwrap();
+    let mut users: VecPack<User> = db.collection("users").unwrap();
+    users.insert(User::new("1", "Peter")).unwrap();
+
+    db.relocate_root(new_root.clone()).unwrap();
+    assert!(!root.exists());
+    assert_eq!(db.get_path(), &new_root);
+
+    // Open handle is flipped to the new location
+    users.insert(User::new("2", "Mary")).unwrap();
+    assert!(new_root.join("users").join("2.yml").exists());
+
+    // Registry keeps working after relocation
+    let _: VecPack<User> = db.collection("admins").unwrap();
+    let db = Registry::load_or_init(new_root).unwrap();
+    assert_eq!(db.collections().len(), 2);
+}
+
+#[test]
+fn test_moved_handle_immutable_access() {
+    let dir = testing::TempDir::new().unwrap();
+    let root = dir.path().join("registry_test_moved_handle");
+    let new_root = dir.path().join("registry_test_moved_handle_new");
+    let mut db = Registry::load_or_init(root.clone()).unwrap();
+    let mut users: VecPack<User> = db.collection("users").unwrap();
+    users.insert(User::new("1", "Peter")).unwrap();
+    users.insert(User::new("2", "Mary")).unwrap();
+
+    // Members saved through immutable access after a rename
+    db.rename_collection("users", "customers").unwrap();
+    users.find_id("1").unwrap().save().unwrap();
+    for user in users.iter() {
+        user.save().unwrap();
+    }
+    assert!(!root.join("users").exists());
+    assert!(root.join("customers").join("1.yml").exists());
+    assert_eq!(users.find_id("2").unwrap().revision(), 2);
+
+    // And after a root relocation
+    db.relocate_root(new_root.clone()).unwrap();
+    users.find_id("2").unwrap().save().unwrap();
+    assert!(!root.exists());
+    assert_eq!(users.find_id("2").unwrap().revision(), 3);
+    let customers: VecPack<User> = db.collection("customers").unwrap();
+    assert_eq!(customers.len(), 2);
+}
+
+#[test]
+fn test_moved_handle_trash() {
+    let dir = testing::TempDir::new().unwrap();
+    let root = dir.path().join("registry_test_moved_trash");
+    let mut db = Registry::load_or_init(root.clone()).unwrap();
+    let mut users: VecPack<User> = db.collection("users").unwrap();
+    users.insert(User::new("1", "Peter")).unwrap();
+    users.soft_remove("1").unwrap();
+
+    // Trash is purged in the new directory
+    db.rename_collection("users", "customers").unwrap();
+    let trash = root.join("customers").join(".trash");
+    assert_eq!(std::fs::read_dir(&trash).unwrap().count(), 1);
+    assert_eq!(
+        users
+            .purge_trash(std::time::Duration::from_secs(0))
+            .unwrap(),
+        1
+    );
+    assert_eq!(std::fs::read_dir(&trash).unwrap().count(), 0);
+}
+
+#[test]
+fn test_relocate_root_not_empty() {
+    let dir = testing::TempDir::new().unwrap();
+    let root = dir.path().join("registry_test_relocate_fail");
+    let mut db = Registry::load_or_init(root.clone()).unwrap();
+    let _: VecPack<User> = db.collection("users").unwrap();
+    // Target is not empty
+    let target = dir.path().join("registry_test_relocate_fail_target");
+    std::fs::create_dir_all(&target).unwrap();
+    std::fs::write(target.join("file"), "content").unwrap();
+    assert!(db.relocate_root(target).is_err());
+    assert!(root.join("manifest.yml").exists());
+}
+
+#[test]
+fn test_relocate_root_inside() {
+    let dir = testing::TempDir::new().unwrap();
+    let root = dir.path().join("registry_test_relocate_inside");
+    let mut db = Registry::load_or_init(root.clone()).unwrap();
+    let mut users: VecPack<User> = db.collection("users").unwrap();
+    users.insert(User::new("peter", "Peter")).unwrap();
+    for target in [root.clone(), root.join("moved"), root.join("users/../b")] {
+        assert!(db.relocate_root(target).is_err());
+    }
+    assert!(!root.join("moved").exists());
+    assert!(!root.join("b").exists());
+    assert_eq!(db.get_path(), &root);
+    assert!(users.find_id("peter").is_ok());
+}
+
+#[test]
+fn test_support_bundle() {
+    let dir = testing::TempDir::new().unwrap();
+    let root = dir.path().join("registry_test_support_bundle");
+    let mut db = Registry::load_or_init(root.clone()).unwrap();
+    let mut users: VecPack<User> = db.collection("users").unwrap();
+    users.insert(User::new("peter", "Peter")).unwrap();
+    users.insert(User::new("mary", "Mary")).unwrap();
+    users.enable_append_only().unwrap();
+    std::fs::write(root.join("users").join("broken.yml"), "name: [").unwrap();
+
+    let mut buffer = Vec::new();
+    db.dump_support_bundle(&mut buffer).unwrap();
+    let bundle = SupportBundle::load(buffer.as_slice()).unwrap();
+    assert_eq!(bundle.collections, db.support_bundle().unwrap().collections);
+    let users = &bundle.collections[0];
+    assert_eq!(users.name, "users");
+    assert_eq!(users.members, 3);
+    assert_eq!(users.modes, vec!["append_only".to_string()]);
+    assert_eq!(users.unreadable.len(), 1);
+    // Nothing of the data gets into the bundle
+    let text = format!("{}{:?}", bundle, bundle);
+    for secret in &["Peter", "Mary", "peter", "broken"] {
+        assert!(!text.contains(secret));
+    }
+}
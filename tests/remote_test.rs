@@ -0,0 +1,94 @@
+#![cfg(feature = "remote")]
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use storaget::remote::{RemoteBackend, RemoteServer};
+use storaget::*;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct Car {
+    id: String,
+    hp: u32,
+}
+
+impl VecPackMember for Car {
+    fn get_id(&self) -> &str {
+        &self.id
+    }
+}
+
+#[test]
+fn test_remote_backend() {
+    let dir = testing::TempDir::new().unwrap();
+    let server = RemoteServer::serve_dir("127.0.0.1:0", dir.path().into())
+        .unwrap()
+        .token("secret");
+    let url = format!("http://{}", server.local_addr().unwrap());
+    std::thread::spawn(move || server.run());
+    let path = PathBuf::from("cars");
+    {
+        let backend = Arc::new(RemoteBackend::new(&url).token("secret"));
+        let mut cars: VecPack<Car> =
+            VecPack::load_or_init_with_backend(path.clone(), backend).unwrap();
+        for i in 0..10 {
+            cars.insert(Car {
+                id: i.to_string(),
+                hp: i,
+            })
+            .unwrap();
+        }
+        cars.find_id_mut("1").unwrap().as_mut().unpack().hp = 110;
+        cars.remove_by_id("2").unwrap();
+    }
+    assert!(!path.exists());
+    assert!(dir.path().join("cars/1.yml").exists());
+    // Another process loads the same VecPack
+    let backend = Arc::new(RemoteBackend::new(&url).token("secret"));
+    let cars: VecPack<Car> =
+        VecPack::load_or_init_with_backend(path.clone(), backend).unwrap();
+    assert_eq!(cars.len(), 9);
+    assert_eq!(cars.find_id("1").unwrap().hp, 110);
+    // Wrong token is rejected
+    let backend = RemoteBackend::new(&url).token("wrong");
+    assert!(matches!(
+        backend.read(&path.join("1.yml")),
+        Err(PackError::BackendError(_))
+    ));
+}
+
+#[test]
+fn test_remote_limits() {
+    let dir = testing::TempDir::new().unwrap();
+    let server = RemoteServer::serve_dir("127.0.0.1:0", dir.path().into())
+        .unwrap()
+        .token("secret")
+        .max_body_size(64)
+        .workers(2);
+    let url = format!("http://{}", server.local_addr().unwrap());
+    std::thread::spawn(move || server.run());
+    let backend = RemoteBackend::new(&url).token("secret");
+    backend
+        .write(&PathBuf::from("small.yml"), &[b'a'; 64])
+        .unwrap();
+    assert!(backend
+        .write(&PathBuf::from("big.yml"), &[b'a'; 65])
+        .is_err());
+    assert!(!dir.path().join("big.yml").exists());
+    // Token is checked as a whole
+    let backend = RemoteBackend::new(&url).token("secre");
+    assert!(backend.read(&PathBuf::from("small.yml")).is_err());
+}
+
+#[test]
+fn test_server_requires_token() {
+    let dir = testing::TempDir::new().unwrap();
+    let output =
+        std::process::Command::new(env!("CARGO_BIN_EXE_storaget-server"))
+            .arg(dir.path())
+            .env_remove("STORAGET_TOKEN")
+            .output()
+            .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--insecure"));
+}
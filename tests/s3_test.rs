@@ -0,0 +1,226 @@
+#![cfg(feature = "s3")]
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use storaget::s3::{S3Backend, S3Config};
+use storaget::*;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct Car {
+    id: String,
+    hp: u32,
+}
+
+impl VecPackMember for Car {
+    fn get_id(&self) -> &str {
+        &self.id
+    }
+}
+
+// Objects of the fake S3 server by key
+type Objects = Arc<Mutex<BTreeMap<String, Vec<u8>>>>;
+// Security tokens received by the fake S3 server
+type Tokens = Arc<Mutex<Vec<String>>>;
+
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap();
+            result.push(u8::from_str_radix(hex, 16).unwrap());
+            i += 3;
+        } else {
+            result.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(result).unwrap()
+}
+
+// Minimal in-memory S3 server, returns its endpoint
+fn fake_s3() -> (String, Objects, Tokens) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let objects = Objects::default();
+    let store = objects.clone();
+    let tokens = Tokens::default();
+    let received = tokens.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let mut parts = line.split_whitespace();
+            let method = parts.next().unwrap().to_string();
+            let target = parts.next().unwrap().to_string();
+            let (mut length, mut signed) = (0, false);
+            let (mut token, mut signed_token) = (None, false);
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                let header = header.trim().to_lowercase();
+                if header.is_empty() {
+                    break;
+                }
+                if let Some(value) = header.strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+                if header.starts_with("authorization: aws4-hmac-sha256") {
+                    signed = true;
+                    signed_token = header.contains("x-amz-security-token");
+                }
+                if let Some(value) =
+                    header.strip_prefix("x-amz-security-token:")
+                {
+                    token = Some(value.trim().to_string());
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let (path, query) =
+                target.split_once('?').unwrap_or((target.as_str(), ""));
+            let key = decode(path.trim_start_matches("/bucket/"));
+            // Token must be signed, as with AWS
+            if let Some(token) = token {
+                signed = signed && signed_token;
+                received.lock().unwrap().push(token);
+            }
+            let mut objects = store.lock().unwrap();
+            let (status, content) = match (signed, method.as_str()) {
+                (false, _) => ("403 Forbidden", Vec::new()),
+                (_, "GET") if !query.is_empty() => {
+                    let prefix = query
+                        .split('&')
+                        .find_map(|q| q.strip_prefix("prefix="))
+                        .map(decode)
+                        .unwrap_or_default();
+                    let delimiter = query.contains("delimiter=");
+                    let keys: String = objects
+                        .keys()
+                        .filter(|k| k.starts_with(&prefix))
+                        .filter(|k| {
+                            !delimiter || !k[prefix.len()..].contains('/')
+                        })
+                        .map(|k| {
+                            format!("<Contents><Key>{}</Key></Contents>", k)
+                        })
+                        .collect();
+                    let xml = format!(
+                        "<ListBucketResult>{}</ListBucketResult>",
+                        keys
+                    );
+                    ("200 OK", xml.into_bytes())
+                }
+                (_, "GET") => match objects.get(&key) {
+                    Some(content) => ("200 OK", content.clone()),
+                    None => ("404 Not Found", Vec::new()),
+                },
+                (_, "PUT") => {
+                    objects.insert(key, body);
+                    ("200 OK", Vec::new())
+                }
+                (_, "HEAD") => match objects.contains_key(&key) {
+                    true => ("200 OK", Vec::new()),
+                    false => ("404 Not Found", Vec::new()),
+                },
+                (_, "DELETE") => {
+                    objects.remove(&key);
+                    ("204 No Content", Vec::new())
+                }
+                _ => ("400 Bad Request", Vec::new()),
+            };
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                status,
+                content.len()
+            )
+            .unwrap();
+            stream.write_all(&content).unwrap();
+        }
+    });
+    (endpoint, objects, tokens)
+}
+
+#[test]
+fn test_s3_backend() {
+    let (endpoint, objects, tokens) = fake_s3();
+    let config = S3Config::new(&endpoint, "bucket")
+        .credentials("key", "secret")
+        .prefix("app");
+    let backend = Arc::new(S3Backend::new(config));
+    let path = PathBuf::from("cars");
+    {
+        let mut cars: VecPack<Car> =
+            VecPack::load_or_init_with_backend(path.clone(), backend.clone())
+                .unwrap();
+        for (id, hp) in [("1", 100), ("2", 200)] {
+            cars.insert(Car {
+                id: id.to_string(),
+                hp,
+            })
+            .unwrap();
+        }
+        cars.find_id_mut("1").unwrap().as_mut().unpack().hp = 110;
+        cars.remove_by_id("2").unwrap();
+    }
+    assert!(!path.exists());
+    let keys: Vec<String> = objects.lock().unwrap().keys().cloned().collect();
+    assert_eq!(keys, vec!["app/cars/1.yml".to_string()]);
+    let cars: VecPack<Car> =
+        VecPack::load_or_init_with_backend(path, backend).unwrap();
+    assert_eq!(cars.len(), 1);
+    assert_eq!(cars.find_id("1").unwrap().hp, 110);
+    assert!(tokens.lock().unwrap().is_empty());
+}
+
+#[test]
+fn test_s3_session_token() {
+    let (endpoint, objects, tokens) = fake_s3();
+    std::env::set_var("AWS_ENDPOINT_URL", &endpoint);
+    std::env::set_var("AWS_ACCESS_KEY_ID", "key");
+    std::env::set_var("AWS_SECRET_ACCESS_KEY", "secret");
+    std::env::set_var("AWS_SESSION_TOKEN", "session");
+    let backend = Arc::new(S3Backend::new(S3Config::from_env("bucket")));
+    let mut cars: VecPack<Car> =
+        VecPack::load_or_init_with_backend("cars".into(), backend).unwrap();
+    cars.insert(Car {
+        id: "1".to_string(),
+        hp: 100,
+    })
+    .unwrap();
+    assert!(objects.lock().unwrap().contains_key("cars/1.yml"));
+    let tokens = tokens.lock().unwrap();
+    assert!(!tokens.is_empty());
+    assert!(tokens.iter().all(|token| token == "session"));
+}
+
+#[test]
+fn test_s3_list_and_delete() {
+    let (endpoint, objects, _) = fake_s3();
+    let config =
+        S3Config::new(&endpoint, "bucket").credentials("key", "secret");
+    let backend = S3Backend::new(config);
+    let dir = PathBuf::from("cars");
+    for path in ["2.yml", "ab/1.yml", ".trash/3.yml", "corrupt/4.yml"] {
+        backend.write(&dir.join(path), b"hp: 1").unwrap();
+    }
+    // Members in shard directories are listed as well
+    assert_eq!(
+        backend.list(&dir).unwrap(),
+        vec![dir.join("ab/1.yml"), dir.join("2.yml")]
+    );
+    backend.delete(&dir.join("ab/1.yml")).unwrap();
+    assert!(!objects.lock().unwrap().contains_key("cars/ab/1.yml"));
+    assert!(matches!(
+        backend.delete(&dir.join("ab/1.yml")),
+        Err(PackError::PathNotFound)
+    ));
+}
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+use storaget::*;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct Engine {
+    hp: u32,
+    #[serde(default)]
+    torque: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct Car {
+    id: String,
+    #[serde(default)]
+    color: String,
+    engine: Engine,
+}
+
+#[test]
+fn test_schema_diff() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("schema_test_diff");
+    std::fs::create_dir_all(&path).unwrap();
+    std::fs::write(
+        path.join("1.yml"),
+        "---\nid: \"1\"\nseats: 4\nengine:\n  hp: 100\n",
+    )
+    .unwrap();
+    std::fs::write(
+        path.join("2.yml"),
+        "---\nid: \"2\"\ncolor: red\nengine:\n  hp: 90\n  torque: 200\n",
+    )
+    .unwrap();
+    std::fs::write(path.join("3.yml"), "---\nid: \"3\"\n").unwrap();
+
+    let diff = schema_diff::<Car>(&path).unwrap();
+    assert_eq!(diff.files, 3);
+    assert!(!diff.is_clean());
+    assert_eq!(diff.dropped.get("seats"), Some(&1));
+    assert_eq!(diff.defaulted.get("color"), Some(&1));
+    assert_eq!(diff.defaulted.get("engine.torque"), Some(&1));
+    // engine is required
+    assert_eq!(diff.failed.len(), 1);
+}
@@ -0,0 +1,49 @@
+#![cfg(feature = "sled")]
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use storaget::sled_backend::SledBackend;
+use storaget::*;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct Car {
+    id: String,
+    hp: u32,
+}
+
+impl VecPackMember for Car {
+    fn get_id(&self) -> &str {
+        &self.id
+    }
+}
+
+#[test]
+fn test_sled_backend() {
+    let dir = testing::TempDir::new().unwrap();
+    let db = dir.path().join("db");
+    let path = PathBuf::from("cars");
+    {
+        let backend = Arc::new(SledBackend::open(&db).unwrap());
+        let mut cars: VecPack<Car> =
+            VecPack::load_or_init_with_backend(path.clone(), backend.clone())
+                .unwrap();
+        for i in 0..100 {
+            cars.insert(Car {
+                id: i.to_string(),
+                hp: i,
+            })
+            .unwrap();
+        }
+        cars.find_id_mut("1").unwrap().as_mut().unpack().hp = 110;
+        cars.remove_by_id("2").unwrap();
+        backend.flush().unwrap();
+    }
+    assert!(!path.exists());
+    let backend = Arc::new(SledBackend::open(&db).unwrap());
+    let cars: VecPack<Car> =
+        VecPack::load_or_init_with_backend(path, backend).unwrap();
+    assert_eq!(cars.len(), 99);
+    assert_eq!(cars.find_id("1").unwrap().hp, 110);
+    assert!(cars.find_id("2").is_err());
+}
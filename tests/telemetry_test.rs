@@ -0,0 +1,55 @@
+#![cfg(feature = "otel")]
+
+use opentelemetry::global;
+use opentelemetry::trace::Tracer;
+use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+use serde::{Deserialize, Serialize};
+use storaget::*;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct Car {
+    pub id: String,
+    pub hp: u32,
+}
+
+impl VecPackMember for Car {
+    fn get_id(&self) -> &str {
+        &self.id
+    }
+}
+
+#[test]
+fn test_storage_spans() {
+    let dir = testing::TempDir::new().unwrap();
+    let exporter = InMemorySpanExporter::default();
+    let provider = SdkTracerProvider::builder()
+        .with_simple_exporter(exporter.clone())
+        .build();
+    global::set_tracer_provider(provider);
+
+    let mut cars: VecPack<Car> =
+        VecPack::load_or_init(dir.path().join("telemetry_test_spans")).unwrap();
+    global::tracer("app").in_span("request", |_| {
+        cars.insert(Car {
+            id: "1".to_string(),
+            hp: 100,
+        })
+        .unwrap();
+        cars.remove_by_id("1").unwrap();
+    });
+
+    let spans = exporter.get_finished_spans().unwrap();
+    let request = spans.iter().find(|s| s.name == "request").unwrap();
+    let request_id = request.span_context.span_id();
+    for name in &["storaget.save", "storaget.remove"] {
+        let span = spans.iter().find(|s| s.name == *name).unwrap();
+        assert_eq!(span.parent_span_id, request_id);
+    }
+    let changes = request
+        .events
+        .events
+        .iter()
+        .filter(|e| e.name == "storaget.change")
+        .count();
+    assert_eq!(changes, 2);
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 use storaget::*;
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
@@ -19,6 +21,18 @@ impl VecPackMember for Car {
     fn get_id(&self) -> &str {
         &self.id
     }
+    fn validator() -> Option<Validator<Self>> {
+        Some(Car::validate)
+    }
+}
+
+impl Validate for Car {
+    fn validate(&self) -> Result<(), String> {
+        if self.hp == 0 {
+            return Err(format!("car {} has no hp", self.id));
+        }
+        Ok(())
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
@@ -46,8 +60,9 @@ impl VecPackMember for Robot {
 
 #[test]
 fn test_vecpack_load_or_init() {
+    let dir = testing::TempDir::new().unwrap();
     let meaning_of_life: PackResult<VecPack<Car>> =
-        VecPack::load_or_init(PathBuf::from("data/vecpack_test_load_or_init"));
+        VecPack::load_or_init(dir.path().join("vecpack_test_load_or_init"));
     assert_eq!(meaning_of_life.is_ok(), true);
     assert_eq!((*meaning_of_life.unwrap()).len(), 0);
 }
@@ -69,9 +84,9 @@ fn create_dummy_vecpack(path: PathBuf) -> VecPack<Car> {
 
 #[test]
 fn test_vecpack_insert() {
+    let dir = testing::TempDir::new().unwrap();
     let mut meaning_of_life: VecPack<Car> =
-        VecPack::load_or_init(PathBuf::from("data/vecpack_test_insert"))
-            .unwrap();
+        VecPack::load_or_init(dir.path().join("vecpack_test_insert")).unwrap();
     meaning_of_life
         .insert(Car::new("1".to_string(), "CarSmall".to_string(), 150))
         .unwrap();
@@ -87,15 +102,16 @@ fn test_vecpack_insert() {
 
 #[test]
 fn test_vecpack_as_mut() {
-    let mut cars =
-        create_dummy_vecpack(PathBuf::from("data/vecpack_test_as_mut"));
+    let dir = testing::TempDir::new().unwrap();
+    let mut cars = create_dummy_vecpack(dir.path().join("vecpack_test_as_mut"));
     cars.into_iter().for_each(|i| i.as_mut().hp = 1);
     assert_eq!(cars.get(0).unwrap().hp, 1);
 }
 
 #[test]
 fn test_vecpack_find_id() {
-    let cars = create_dummy_vecpack(PathBuf::from("data/vecpack_test_find_id"));
+    let dir = testing::TempDir::new().unwrap();
+    let cars = create_dummy_vecpack(dir.path().join("vecpack_test_find_id"));
     assert_eq!(cars.find_id("3").is_ok(), true);
     assert_eq!(cars.find_id("1").unwrap().unpack().hp, 150);
     assert_eq!(cars.find_id("2").unwrap().unpack().hp, 650);
@@ -104,9 +120,10 @@ fn test_vecpack_find_id() {
 
 #[test]
 fn test_vecpack_find_id_mut_update() {
-    let mut cars = create_dummy_vecpack(PathBuf::from(
-        "data/vecpack_test_find_id_mut_update",
-    ));
+    let dir = testing::TempDir::new().unwrap();
+    let mut cars = create_dummy_vecpack(
+        dir.path().join("vecpack_test_find_id_mut_update"),
+    );
     cars.find_id_mut("1").unwrap().update(|i| i.hp = 1).unwrap();
     cars.find_id_mut("2")
         .unwrap()
@@ -124,9 +141,10 @@ fn test_vecpack_find_id_mut_update() {
 
 #[test]
 fn test_vecpack_find_id_mut_as_mut() {
-    let mut cars = create_dummy_vecpack(PathBuf::from(
-        "data/vecpack_test_find_id_mut_as_mut",
-    ));
+    let dir = testing::TempDir::new().unwrap();
+    let mut cars = create_dummy_vecpack(
+        dir.path().join("vecpack_test_find_id_mut_as_mut"),
+    );
     cars.find_id_mut("1").unwrap().as_mut().hp = 1;
     cars.find_id_mut("2").unwrap().as_mut().hp = 11;
     cars.find_id_mut("3").unwrap().as_mut().hp = 111;
@@ -140,9 +158,9 @@ fn test_vecpack_find_id_mut_as_mut() {
 
 #[test]
 fn test_id_str() {
+    let dir = testing::TempDir::new().unwrap();
     let mut robots: VecPack<Robot> =
-        VecPack::load_or_init(PathBuf::from("data/vecpack_test_id_str"))
-            .unwrap();
+        VecPack::load_or_init(dir.path().join("vecpack_test_id_str")).unwrap();
 
     robots
         .insert(Robot::new("a".to_string(), "robot_a".to_string(), true))
@@ -159,9 +177,9 @@ fn test_id_str() {
 
 #[test]
 fn test_mut_ref() {
+    let dir = testing::TempDir::new().unwrap();
     let mut robots: VecPack<Robot> =
-        VecPack::load_or_init(PathBuf::from("data/vecpack_test_mut_red"))
-            .unwrap();
+        VecPack::load_or_init(dir.path().join("vecpack_test_mut_red")).unwrap();
     robots
         .insert(Robot::new(
             "firstone".to_string(),
@@ -174,3 +192,2181 @@ fn test_mut_ref() {
     robots.get_mut(0).unwrap().as_mut().name = "Mini Roboto".to_string();
     assert_eq!(robots.get(0).unwrap().name, "Mini Roboto");
 }
+
+#[test]
+fn test_stats_by_id() {
+    let dir = testing::TempDir::new().unwrap();
+    let mut cars = create_dummy_vecpack(dir.path().join("vecpack_test_stats"));
+    // Stats are opt-in
+    cars.find_id("1").unwrap();
+    assert!(cars.stats_by_id("1").is_none());
+
+    cars.enable_stats();
+    cars.find_id("1").unwrap();
+    cars.find_id("1").unwrap();
+    cars.find_id_mut("1").unwrap().as_mut().hp = 1;
+    let stats = cars.stats_by_id("1").unwrap();
+    assert_eq!(stats.reads, 2);
+    assert_eq!(stats.writes, 1);
+    assert!(stats.last_read.is_some());
+    assert!(cars.stats_by_id("2").is_none());
+}
+
+#[test]
+fn test_remove_by_id() {
+    let dir = testing::TempDir::new().unwrap();
+    let mut cars = create_dummy_vecpack(dir.path().join("vecpack_test_remove"));
+    let removed = cars.remove_by_id("2").unwrap();
+    assert_eq!(removed.hp, 650);
+    assert_eq!(cars.len(), 2);
+    assert!(cars.remove_by_id("2").is_err());
+
+    let cars: VecPack<Car> =
+        VecPack::load_or_init(dir.path().join("vecpack_test_remove")).unwrap();
+    assert_eq!(cars.len(), 2);
+}
+
+#[test]
+fn test_soft_remove_restore() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_soft_remove");
+    let mut cars = create_dummy_vecpack(path.clone());
+    cars.soft_remove("1").unwrap();
+    cars.soft_remove("2").unwrap();
+    assert!(cars.find_id("1").is_err());
+
+    // Trash is not loaded as a member
+    let mut cars: VecPack<Car> = VecPack::load_or_init(path).unwrap();
+    assert_eq!(cars.len(), 1);
+
+    cars.restore("1").unwrap();
+    assert_eq!(cars.find_id("1").unwrap().hp, 150);
+    assert!(cars.restore("1").is_err());
+
+    // Only "2" left in the trash
+    assert_eq!(cars.purge_trash(Duration::from_secs(3600)).unwrap(), 0);
+    assert_eq!(cars.purge_trash(Duration::from_secs(0)).unwrap(), 1);
+    assert!(cars.restore("2").is_err());
+}
+
+#[test]
+fn test_insert_many() {
+    let dir = testing::TempDir::new().unwrap();
+    let mut cars =
+        create_dummy_vecpack(dir.path().join("vecpack_test_insert_many"));
+    let items = (4..104)
+        .map(|i| Car::new(i.to_string(), "Bulk".to_string(), i))
+        .collect::<Vec<Car>>();
+    let failures = cars.insert_many(items).unwrap();
+    assert!(failures.is_empty());
+    assert_eq!(cars.len(), 103);
+    assert_eq!(cars.find_id("50").unwrap().hp, 50);
+
+    // Taken or duplicated IDs reject the whole batch
+    let taken = vec![
+        Car::new("200".to_string(), "New".to_string(), 1),
+        Car::new("1".to_string(), "Taken".to_string(), 1),
+    ];
+    assert!(cars.insert_many(taken).is_err());
+    let duplicated = vec![
+        Car::new("300".to_string(), "New".to_string(), 1),
+        Car::new("300".to_string(), "New".to_string(), 1),
+    ];
+    assert!(cars.insert_many(duplicated).is_err());
+    assert_eq!(cars.len(), 103);
+}
+
+#[test]
+fn test_import_json() {
+    let dir = testing::TempDir::new().unwrap();
+    let mut cars =
+        create_dummy_vecpack(dir.path().join("vecpack_test_import_json"));
+    let input = r#"[
+        {"id": "4", "name": "CarFast", "hp": 400},
+        {"id": "1", "name": "Taken", "hp": 1},
+        {"id": "5", "name": "NoHp"},
+        {"id": "", "name": "NoID", "hp": 1},
+        {"id": "6", "name": "CarSlow", "hp": 60}
+    ]"#;
+    let report = cars
+        .import_from_reader(ImportFormat::Json, input.as_bytes())
+        .unwrap();
+    assert_eq!(report.inserted, vec!["4".to_string(), "6".to_string()]);
+    let rows: Vec<usize> = report.errors.iter().map(|e| e.row).collect();
+    assert_eq!(rows, vec![2, 3, 4]);
+    assert_eq!(report.errors[0].id.as_deref(), Some("1"));
+    assert_eq!(cars.len(), 5);
+    assert_eq!(cars.find_id("6").unwrap().hp, 60);
+
+    // Input that is not a JSON array fails as a whole
+    assert!(cars
+        .import_from_reader(ImportFormat::Json, "{}".as_bytes())
+        .is_err());
+}
+
+#[test]
+fn test_page() {
+    let dir = testing::TempDir::new().unwrap();
+    let mut cars = create_dummy_vecpack(dir.path().join("vecpack_test_page"));
+    cars.insert(Car::new("4".to_string(), "CarFast".to_string(), 400))
+        .unwrap();
+    assert_eq!(cars.page(0, 3).len(), 3);
+    assert_eq!(cars.page(3, 3).len(), 1);
+    assert_eq!(cars.page(1, 2)[0].get_id(), cars.get(1).unwrap().get_id());
+    assert!(cars.page(10, 3).is_empty());
+
+    // Walk through all the members with cursors
+    let mut ids = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let page = cars.page_after(cursor.as_deref(), 3).unwrap();
+        ids.extend(page.items.iter().map(|i| i.get_id().to_string()));
+        match page.next {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    assert_eq!(ids.len(), 4);
+    assert!(cars.page_after(Some("missing"), 3).is_err());
+}
+
+#[test]
+fn test_sort_by_order_index() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_order_index");
+    let mut cars = create_dummy_vecpack(path.clone());
+    cars.enable_order_index().unwrap();
+    cars.sort_by(|a, b| b.hp.cmp(&a.hp)).unwrap();
+    cars.insert(Car::new("0".to_string(), "CarTiny".to_string(), 50))
+        .unwrap();
+    let ids = |cars: &VecPack<Car>| {
+        cars.iter()
+            .map(|i| i.get_id().to_string())
+            .collect::<Vec<String>>()
+    };
+    assert_eq!(ids(&cars), vec!["2", "3", "1", "0"]);
+
+    // Order is restored on load, and index stays enabled
+    let mut cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    assert_eq!(ids(&cars), vec!["2", "3", "1", "0"]);
+    cars.remove_by_id("3").unwrap();
+    let cars: VecPack<Car> = VecPack::load_or_init(path).unwrap();
+    assert_eq!(ids(&cars), vec!["2", "1", "0"]);
+}
+
+#[test]
+fn test_load_file_name_order() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_file_name_order");
+    let mut cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    for id in &["c", "a", "b"] {
+        cars.insert(Car::new(id.to_string(), "Car".to_string(), 1))
+            .unwrap();
+    }
+    let cars: VecPack<Car> = VecPack::load_or_init(path).unwrap();
+    let ids = cars.iter().map(|i| i.get_id()).collect::<Vec<&str>>();
+    assert_eq!(ids, vec!["a", "b", "c"]);
+}
+
+#[test]
+fn test_append_only() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_append_only");
+    let mut cars = create_dummy_vecpack(path.clone());
+    cars.enable_append_only().unwrap();
+    cars.insert(Car::new("4".to_string(), "CarNew".to_string(), 1))
+        .unwrap();
+    assert!(cars.find_id_mut("1").is_err());
+    assert!(cars.remove_by_id("1").is_err());
+    assert!(cars.soft_remove("4").is_err());
+    let file = std::fs::metadata(path.join("4.yml")).unwrap();
+    assert!(file.permissions().readonly());
+
+    // Mode is persisted
+    let cars: VecPack<Car> = VecPack::load_or_init(path).unwrap();
+    assert!(cars.is_append_only());
+    assert_eq!(cars.len(), 4);
+}
+
+#[test]
+#[should_panic]
+fn test_append_only_as_vec_mut() {
+    let dir = testing::TempDir::new().unwrap();
+    let mut cars =
+        create_dummy_vecpack(dir.path().join("vecpack_test_append_only_panic"));
+    cars.enable_append_only().unwrap();
+    cars.as_vec_mut();
+}
+
+#[test]
+fn test_find_filter_any_all() {
+    let dir = testing::TempDir::new().unwrap();
+    let cars = create_dummy_vecpack(dir.path().join("vecpack_test_find"));
+    assert_eq!(cars.find(|c| c.hp > 200).unwrap().get_id(), "2");
+    assert!(cars.find(|c| c.hp > 1000).is_none());
+    assert_eq!(cars.filter(|c| c.hp > 200).len(), 2);
+    assert!(cars.any(|c| c.name == "CarBig"));
+    assert!(cars.all(|c| c.hp >= 150));
+    assert!(!cars.all(|c| c.hp > 150));
+}
+
+#[test]
+fn test_hash_chain() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_hash_chain");
+    let mut cars = create_dummy_vecpack(path.clone());
+    // Requires append-only mode
+    assert!(cars.enable_hash_chain().is_err());
+    cars.enable_append_only().unwrap();
+    cars.enable_hash_chain().unwrap();
+    cars.insert(Car::new("4".to_string(), "CarNew".to_string(), 1))
+        .unwrap();
+    assert_eq!(cars.chain().unwrap().len(), 4);
+    assert!(cars.verify_chain().is_ok());
+
+    // Chain is persisted
+    let cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    assert!(cars.verify_chain().is_ok());
+
+    // Retroactive modification is detected
+    let file = path.join("2.yml");
+    std::fs::remove_file(&file).unwrap();
+    std::fs::write(&file, "---\nid: \"2\"\nname: CarBig\nhp: 1\n").unwrap();
+    assert!(cars.verify_chain().is_err());
+}
+
+#[test]
+fn test_reserve_id() {
+    let dir = testing::TempDir::new().unwrap();
+    let mut cars =
+        create_dummy_vecpack(dir.path().join("vecpack_test_reserve_id"));
+    assert!(cars.reserve_id("1").is_err());
+    let reservation = cars.reserve_id("4").unwrap();
+    assert!(cars.reserve_id("4").is_err());
+    assert!(!cars.check_id_available("4"));
+    assert!(cars
+        .insert(Car::new("4".to_string(), "Other".to_string(), 1))
+        .is_err());
+    reservation
+        .commit(&mut cars, Car::new("4".to_string(), "Mine".to_string(), 1))
+        .unwrap();
+    assert_eq!(cars.find_id("4").unwrap().name, "Mine");
+
+    // Dropped reservation releases the ID
+    let reservation = cars.reserve_id("5").unwrap();
+    drop(reservation);
+    assert!(cars.check_id_available("5"));
+}
+
+#[test]
+fn test_secondary_index() {
+    let dir = testing::TempDir::new().unwrap();
+    let mut cars =
+        create_dummy_vecpack(dir.path().join("vecpack_test_secondary_index"));
+    cars.add_index("name", |c| c.name.clone());
+    assert_eq!(
+        cars.find_by_index("name", "CarBig").unwrap()[0].get_id(),
+        "2"
+    );
+    assert!(cars.find_by_index("missing", "CarBig").is_err());
+
+    // Maintained on insert, update and remove
+    cars.insert(Car::new("4".to_string(), "CarBig".to_string(), 700))
+        .unwrap();
+    assert_eq!(cars.find_by_index("name", "CarBig").unwrap().len(), 2);
+    cars.find_id_mut("2")
+        .unwrap()
+        .update(|c| c.name = "CarHuge".to_string())
+        .unwrap();
+    assert_eq!(cars.find_by_index("name", "CarBig").unwrap().len(), 1);
+    assert_eq!(cars.find_by_index("name", "CarHuge").unwrap().len(), 1);
+    cars.remove_by_id("4").unwrap();
+    assert!(cars.find_by_index("name", "CarBig").unwrap().is_empty());
+    cars.into_iter()
+        .for_each(|c| c.as_mut().name = "Same".to_string());
+    assert_eq!(cars.find_by_index("name", "Same").unwrap().len(), 3);
+    assert!(cars.remove_index("name"));
+}
+
+#[test]
+fn test_check_ids_available() {
+    let dir = testing::TempDir::new().unwrap();
+    let cars = create_dummy_vecpack(
+        dir.path().join("vecpack_test_check_ids_available"),
+    );
+    let _reservation = cars.reserve_id("5").unwrap();
+    let candidates = vec!["1", "4", "3", "5", "6"];
+    assert_eq!(cars.check_ids_available(candidates), vec!["1", "3", "5"]);
+    let candidates = ["10".to_string(), "2".to_string()];
+    assert_eq!(
+        cars.check_ids_available(candidates.iter().map(|i| i.as_str())),
+        vec!["2"]
+    );
+}
+
+#[test]
+fn test_query() {
+    let dir = testing::TempDir::new().unwrap();
+    let mut cars = create_dummy_vecpack(dir.path().join("vecpack_test_query"));
+    cars.insert(Car::new("4".to_string(), "CarBig".to_string(), 900))
+        .unwrap();
+    let result = cars
+        .query()
+        .filter(|c| c.hp > 200)
+        .sort_by(|a, b| b.hp.cmp(&a.hp))
+        .limit(2)
+        .collect()
+        .unwrap();
+    let ids = result.iter().map(|i| i.get_id()).collect::<Vec<&str>>();
+    assert_eq!(ids, vec!["4", "2"]);
+    assert_eq!(cars.query().offset(1).count().unwrap(), 3);
+
+    cars.add_index("name", |c| c.name.clone());
+    let big = cars
+        .query()
+        .index("name", "CarBig")
+        .filter(|c| c.hp < 800)
+        .first()
+        .unwrap();
+    assert_eq!(big.unwrap().get_id(), "2");
+    assert!(cars.query().index("missing", "").collect().is_err());
+}
+
+#[test]
+fn test_text_search() {
+    let dir = testing::TempDir::new().unwrap();
+    let mut robots: VecPack<Robot> =
+        VecPack::load_or_init(dir.path().join("vecpack_test_search")).unwrap();
+    robots
+        .insert(Robot::new(
+            "a".to_string(),
+            "Red kitchen robot".to_string(),
+            true,
+        ))
+        .unwrap();
+    robots
+        .insert(Robot::new("b".to_string(), "Blue robot".to_string(), false))
+        .unwrap();
+    robots
+        .insert(Robot::new(
+            "c".to_string(),
+            "Red red car".to_string(),
+            false,
+        ))
+        .unwrap();
+    assert!(robots.search("red").is_empty());
+
+    robots.enable_text_search(|r| vec![r.name.clone()]);
+    let ids = |result: Vec<(String, f64)>| {
+        result
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect::<Vec<String>>()
+    };
+    assert_eq!(ids(robots.search("RED")), vec!["c", "a"]);
+    assert_eq!(ids(robots.search("kitchen robot")), vec!["a", "b"]);
+    assert!(robots.search("green").is_empty());
+
+    // Updates are picked up
+    robots
+        .find_id_mut("b")
+        .unwrap()
+        .update(|r| r.name = "Green robot".to_string())
+        .unwrap();
+    assert_eq!(ids(robots.search("green")), vec!["b"]);
+    robots.remove_by_id("c").unwrap();
+    assert_eq!(ids(robots.search("red")), vec!["a"]);
+}
+
+#[test]
+fn test_group_by() {
+    let dir = testing::TempDir::new().unwrap();
+    let mut robots: VecPack<Robot> =
+        VecPack::load_or_init(dir.path().join("vecpack_test_group_by"))
+            .unwrap();
+    for (id, can_speak) in &[("a", true), ("b", false), ("c", true)] {
+        robots
+            .insert(Robot::new(id.to_string(), "Robot".to_string(), *can_speak))
+            .unwrap();
+    }
+    robots.group_by("speaking", |r| r.can_speak.to_string());
+    assert_eq!(
+        robots.members_in_group("speaking", "true").unwrap().len(),
+        2
+    );
+    robots
+        .insert(Robot::new("d".to_string(), "Robot".to_string(), false))
+        .unwrap();
+    let sizes = robots.group_sizes("speaking").unwrap();
+    assert_eq!(sizes.get("true"), Some(&2));
+    assert_eq!(sizes.get("false"), Some(&2));
+    assert!(robots.members_in_group("missing", "true").is_err());
+}
+
+#[test]
+fn test_change_notification() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_change_notification");
+    let mut cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    cars.enable_change_notification().unwrap();
+    let mut watcher = ChangeWatcher::new(path.clone()).unwrap();
+    assert!(!watcher.has_changed().unwrap());
+
+    cars.insert(Car::new("1".to_string(), "Car".to_string(), 100))
+        .unwrap();
+    assert!(watcher.has_changed().unwrap());
+    assert!(!watcher.has_changed().unwrap());
+
+    // Member updates through the guard are reported too
+    cars.find_id_mut("1").unwrap().as_mut().hp = 120;
+    assert!(watcher.has_changed().unwrap());
+
+    // Enabled state is persisted
+    let mut cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    assert!(cars.is_change_notification_enabled());
+    assert_eq!(cars.len(), 1);
+    cars.remove_by_id("1").unwrap();
+    assert!(watcher.wait_for_change(Duration::from_millis(10)).unwrap());
+    assert!(!watcher.wait_for_change(Duration::from_millis(10)).unwrap());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_change_notification_socket() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_change_notification_socket");
+    let mut cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    cars.enable_change_notification().unwrap();
+    let mut watcher = ChangeWatcher::with_socket(path).unwrap();
+    let writer = std::thread::spawn(move || {
+        cars.insert(Car::new("1".to_string(), "Car".to_string(), 100))
+            .unwrap();
+    });
+    assert!(watcher.wait_for_change(Duration::from_secs(5)).unwrap());
+    writer.join().unwrap();
+    assert_eq!(watcher.sequence(), 1);
+}
+
+#[test]
+fn test_sharding() {
+    let tmp = testing::TempDir::new().unwrap();
+    // Member files directly in the dir, and in its sub directories
+    let layout = |path: &PathBuf| {
+        let mut flat = 0;
+        let mut sharded = Vec::new();
+        for entry in std::fs::read_dir(path).unwrap() {
+            let entry = entry.unwrap().path();
+            let name = entry.file_name().unwrap().to_str().unwrap().to_string();
+            if name.starts_with('.') {
+                continue;
+            }
+            if entry.is_dir() {
+                let count = std::fs::read_dir(&entry).unwrap().count();
+                sharded.push((name, count));
+            } else {
+                flat += 1;
+            }
+        }
+        (flat, sharded)
+    };
+    let path = tmp.path().join("vecpack_test_sharding");
+    let mut cars = create_dummy_vecpack(path.clone());
+    assert_eq!(layout(&path).0, 3);
+    assert!(cars.enable_sharding(0).is_err());
+    cars.enable_sharding(2).unwrap();
+    assert_eq!(cars.shard_width(), Some(2));
+    cars.insert(Car::new("4".to_string(), "Car".to_string(), 100))
+        .unwrap();
+    let (flat, shards) = layout(&path);
+    assert_eq!(flat, 0);
+    assert!(shards.iter().all(|(name, _)| name.len() == 2));
+    assert_eq!(shards.iter().map(|(_, count)| count).sum::<usize>(), 4);
+
+    // Layout is persisted, and members are found on load
+    let mut cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    assert_eq!(cars.shard_width(), Some(2));
+    assert_eq!(cars.len(), 4);
+    assert_eq!(cars.find_id("4").unwrap().hp, 100);
+    cars.remove_by_id("4").unwrap();
+
+    // Resharding moves the files
+    cars.enable_sharding(1).unwrap();
+    let (flat, shards) = layout(&path);
+    assert_eq!(flat, 0);
+    assert!(shards.iter().all(|(name, _)| name.len() == 1));
+    let cars: VecPack<Car> = VecPack::load_or_init(path).unwrap();
+    assert_eq!(cars.len(), 3);
+}
+
+#[test]
+fn test_storage_full_error() {
+    let err: PackError =
+        std::io::Error::from(std::io::ErrorKind::StorageFull).into();
+    assert!(matches!(err, PackError::StorageFull));
+    let err: PackError =
+        std::io::Error::from(std::io::ErrorKind::NotFound).into();
+    assert!(matches!(err, PackError::IOError { .. }));
+    match err {
+        PackError::IOError { source, .. } => {
+            assert_eq!(source.kind(), std::io::ErrorKind::NotFound)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// MemoryBackend that fails writes with ENOSPC once full
+#[derive(Default)]
+struct FullBackend {
+    files: MemoryBackend,
+    full: std::sync::atomic::AtomicBool,
+}
+
+impl StorageBackend for FullBackend {
+    fn read(&self, path: &std::path::Path) -> PackResult<Vec<u8>> {
+        self.files.read(path)
+    }
+    fn write(&self, path: &std::path::Path, bytes: &[u8]) -> PackResult<()> {
+        match self.full.load(std::sync::atomic::Ordering::Relaxed) {
+            true => {
+                Err(std::io::Error::from(std::io::ErrorKind::StorageFull)
+                    .into())
+            }
+            false => self.files.write(path, bytes),
+        }
+    }
+    fn list(&self, dir: &std::path::Path) -> PackResult<Vec<PathBuf>> {
+        self.files.list(dir)
+    }
+    fn delete(&self, path: &std::path::Path) -> PackResult<()> {
+        self.files.delete(path)
+    }
+}
+
+#[test]
+fn test_storage_full_insert() {
+    let backend = Arc::new(FullBackend::default());
+    let mut cars: VecPack<Car> = VecPack::load_or_init_with_backend(
+        PathBuf::from("memory/storage_full"),
+        backend.clone(),
+    )
+    .unwrap();
+    for (id, hp) in [("1", 150), ("2", 650), ("3", 250)] {
+        cars.insert(Car::new(id.to_string(), "Car".to_string(), hp))
+            .unwrap();
+    }
+    backend
+        .full
+        .store(true, std::sync::atomic::Ordering::Relaxed);
+    let res = cars.insert(Car::new("4".to_string(), "Car".to_string(), 100));
+    assert!(matches!(res, Err(PackError::StorageFull)));
+    assert_eq!(cars.storage_full_count(), 1);
+    // Reads keep working
+    assert_eq!(cars.len(), 3);
+    assert_eq!(cars.find_id("1").unwrap().hp, 150);
+}
+
+#[test]
+fn test_temp_config() {
+    let tmp = testing::TempDir::new().unwrap();
+    let path = tmp.path().join("vecpack_test_temp_config");
+    let temp_dir = tmp.path().join("vecpack_test_temp_config_tmp");
+    let mut cars = create_dummy_vecpack(path.clone());
+    assert_eq!(cars.temp_config(), TempConfig::new());
+    assert!(cars
+        .set_temp_config(TempConfig::new().dir(PathBuf::from("/proc/storaget")))
+        .is_err());
+
+    let temp = TempConfig::new().dir(temp_dir.clone()).prefix("cars_");
+    cars.set_temp_config(temp.clone()).unwrap();
+    assert_eq!(cars.temp_config(), temp);
+    cars.insert(Car::new("4".to_string(), "Car".to_string(), 100))
+        .unwrap();
+    cars.find_id_mut("1").unwrap().as_mut().hp = 160;
+    // Temp files are renamed into place
+    assert_eq!(std::fs::read_dir(&temp_dir).unwrap().count(), 0);
+    let cars: VecPack<Car> = VecPack::load_or_init(path).unwrap();
+    assert_eq!(cars.len(), 4);
+    assert_eq!(cars.find_id("1").unwrap().hp, 160);
+}
+
+#[test]
+fn test_shared_temp_dir() {
+    let tmp = testing::TempDir::new().unwrap();
+    let temp = TempConfig::new().dir(tmp.path().join("temp"));
+    // Collections with the same member ID write concurrently
+    let handles: Vec<_> = (0..2)
+        .map(|i| {
+            let path = tmp.path().join(format!("vecpack_test_shared_{}", i));
+            let temp = temp.clone();
+            std::thread::spawn(move || {
+                let mut cars = create_dummy_vecpack(path.clone());
+                cars.set_temp_config(temp).unwrap();
+                for hp in 1..=50 {
+                    cars.find_id_mut("1")
+                        .unwrap()
+                        .update(|c| c.hp = i * 1000 + hp)
+                        .unwrap();
+                }
+                path
+            })
+        })
+        .collect();
+    for (i, handle) in handles.into_iter().enumerate() {
+        let cars: VecPack<Car> =
+            VecPack::load_or_init(handle.join().unwrap()).unwrap();
+        assert_eq!(cars.find_id("1").unwrap().hp, i as u32 * 1000 + 50);
+    }
+}
+
+#[test]
+fn test_reload() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_reload");
+    let mut cars = create_dummy_vecpack(path.clone());
+    cars.add_index("name", |c| c.name.clone());
+    // Another process changes the directory
+    let mut other: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    other.find_id_mut("1").unwrap().as_mut().name = "CarTiny".to_string();
+    other.remove_by_id("2").unwrap();
+    other
+        .insert(Car::new("4".to_string(), "CarHuge".to_string(), 900))
+        .unwrap();
+
+    cars.reload_id("1").unwrap();
+    assert_eq!(cars.find_id("1").unwrap().name, "CarTiny");
+    assert_eq!(cars.find_by_index("name", "CarTiny").unwrap().len(), 1);
+    cars.reload_id("2").unwrap();
+    assert!(cars.find_id("2").is_err());
+    assert!(cars.reload_id("2").is_err());
+
+    other
+        .insert(Car::new("5".to_string(), "CarFast".to_string(), 500))
+        .unwrap();
+    cars.reload().unwrap();
+    assert_eq!(cars.len(), 4);
+    assert_eq!(cars.find_id("4").unwrap().hp, 900);
+    assert_eq!(cars.find_by_index("name", "CarFast").unwrap().len(), 1);
+    assert!(cars.find_by_index("name", "CarBig").unwrap().is_empty());
+}
+
+#[cfg(feature = "watch")]
+#[test]
+fn test_fs_watch() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_fs_watch");
+    let mut cars = create_dummy_vecpack(path.clone());
+    cars.enable_watch().unwrap();
+    assert!(cars.is_watched());
+
+    // Own saves are not reported
+    cars.find_id_mut("1").unwrap().as_mut().hp = 160;
+    assert!(cars
+        .wait_fs_changes(Duration::from_millis(200))
+        .unwrap()
+        .is_empty());
+
+    let mut other: VecPack<Car> = VecPack::load_or_init(path).unwrap();
+    other.find_id_mut("2").unwrap().as_mut().hp = 700;
+    other.remove_by_id("3").unwrap();
+    other
+        .insert(Car::new("4".to_string(), "Car".to_string(), 100))
+        .unwrap();
+    let mut events = Vec::new();
+    for _ in 0..20 {
+        events
+            .extend(cars.wait_fs_changes(Duration::from_millis(100)).unwrap());
+        if events.len() >= 3 {
+            break;
+        }
+    }
+    events.sort_by(|a, b| a.id.cmp(&b.id));
+    assert_eq!(
+        events,
+        vec![
+            ChangeEvent::new("2", ChangeKind::Updated),
+            ChangeEvent::new("3", ChangeKind::Removed),
+            ChangeEvent::new("4", ChangeKind::Created),
+        ]
+    );
+    assert_eq!(cars.find_id("2").unwrap().hp, 700);
+    assert!(cars.find_id("3").is_err());
+    assert_eq!(cars.len(), 3);
+}
+
+#[test]
+fn test_subscribe() {
+    let dir = testing::TempDir::new().unwrap();
+    let mut cars: VecPack<Car> =
+        VecPack::load_or_init(dir.path().join("vecpack_test_subscribe"))
+            .unwrap();
+    let events = cars.subscribe();
+    let dropped = cars.subscribe();
+    drop(dropped);
+    cars.insert(Car::new("1".to_string(), "Car".to_string(), 100))
+        .unwrap();
+    cars.find_id_mut("1").unwrap().as_mut().hp = 120;
+    cars.find_id_mut("1")
+        .unwrap()
+        .update(|c| c.hp = 130)
+        .unwrap();
+    cars.remove_by_id("1").unwrap();
+    assert_eq!(
+        events.try_iter().collect::<Vec<ChangeEvent>>(),
+        vec![
+            ChangeEvent::new("1", ChangeKind::Created),
+            ChangeEvent::new("1", ChangeKind::Updated),
+            ChangeEvent::new("1", ChangeKind::Updated),
+            ChangeEvent::new("1", ChangeKind::Removed),
+        ]
+    );
+}
+
+#[test]
+fn test_read_repair() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_read_repair");
+    let mut cars = create_dummy_vecpack(path.clone());
+    // Without read-repair it is the same as find_id
+    assert_eq!(cars.find_id_fresh("1").unwrap().hp, 150);
+    cars.enable_read_repair();
+    assert!(cars.is_read_repair_enabled());
+
+    // Own writes are not stale
+    cars.find_id_mut("1").unwrap().as_mut().hp = 160;
+    cars.insert(Car::new("4".to_string(), "Car".to_string(), 100))
+        .unwrap();
+    cars.remove_by_id("4").unwrap();
+    assert_eq!(cars.find_id_fresh("1").unwrap().hp, 160);
+    assert_eq!(cars.stale_read_count(), 0);
+
+    // Another replica writes the same directory
+    let mut replica: VecPack<Car> =
+        VecPack::load_or_init(path.clone()).unwrap();
+    replica.find_id_mut("2").unwrap().as_mut().hp = 700;
+    replica.remove_by_id("3").unwrap();
+    replica
+        .insert(Car::new("5".to_string(), "Car".to_string(), 500))
+        .unwrap();
+    assert_eq!(cars.find_id_fresh("2").unwrap().hp, 700);
+    assert!(cars.find_id_fresh("3").is_err());
+    assert_eq!(cars.find_id_fresh("5").unwrap().hp, 500);
+    assert_eq!(cars.stale_read_count(), 3);
+    assert_eq!(cars.find_id_fresh("2").unwrap().hp, 700);
+    assert_eq!(cars.stale_read_count(), 3);
+}
+
+#[test]
+fn test_hooks() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_hooks");
+    let mut cars = create_dummy_vecpack(path.clone());
+    cars.set_hooks(
+        FnHooks::new()
+            .before_save(|c: &mut Car| c.name = c.name.to_uppercase())
+            .after_load(|c: &mut Car| {
+                if c.name.is_empty() {
+                    c.name = "UNKNOWN".to_string();
+                }
+            }),
+    );
+    cars.insert(Car::new("4".to_string(), "car".to_string(), 100))
+        .unwrap();
+    assert_eq!(cars.find_id("4").unwrap().name, "CAR");
+    cars.find_id_mut("1").unwrap().as_mut().name = "small".to_string();
+    assert_eq!(cars.find_id("1").unwrap().name, "SMALL");
+
+    // Another process stores a member without name
+    let mut other: VecPack<Car> = VecPack::load_or_init(path).unwrap();
+    assert_eq!(other.find_id("1").unwrap().name, "SMALL");
+    other
+        .insert(Car::new("5".to_string(), String::new(), 100))
+        .unwrap();
+    cars.reload_id("5").unwrap();
+    assert_eq!(cars.find_id("5").unwrap().name, "UNKNOWN");
+}
+
+#[test]
+fn test_validate() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_validate");
+    let mut cars = create_dummy_vecpack(path.clone());
+    assert!(cars.enable_validation().is_empty());
+    let res = cars.insert(Car::new("4".to_string(), "car".to_string(), 0));
+    assert!(matches!(res, Err(PackError::ValidationError(_))));
+    assert!(cars.find_id("4").is_err());
+    assert!(!path.join("4.yml").exists());
+
+    let res = cars.find_id_mut("1").unwrap().update(|c| c.hp = 0);
+    assert!(matches!(res, Err(PackError::ValidationError(_))));
+    assert_eq!(cars.find_id("1").unwrap().hp, 150);
+    // Guard drop skips saving invalid data
+    cars.find_id_mut("2").unwrap().as_mut().hp = 0;
+    let other: VecPack<Car> = VecPack::load_or_init(path).unwrap();
+    assert_ne!(other.find_id("2").unwrap().hp, 0);
+}
+
+#[test]
+fn test_validate_without_opt_in() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_validate_auto");
+    let mut cars = create_dummy_vecpack(path.clone());
+    let res = cars.insert(Car::new("4".to_string(), "car".to_string(), 0));
+    assert!(matches!(res, Err(PackError::ValidationError(_))));
+    assert!(!path.join("4.yml").exists());
+    let res = cars.find_id_mut("1").unwrap().update(|c| c.hp = 0);
+    assert!(matches!(res, Err(PackError::ValidationError(_))));
+    assert_eq!(cars.find_id("1").unwrap().hp, 150);
+
+    // Reloaded members are validated as well
+    let mut cars: VecPack<Car> = VecPack::load_or_init(path).unwrap();
+    let res = cars.find_id_mut("2").unwrap().update(|c| c.hp = 0);
+    assert!(matches!(res, Err(PackError::ValidationError(_))));
+}
+
+// Service written against the CollectionLike trait
+fn tune_car<C: CollectionLike<Car>>(cars: &mut C, id: &str) -> PackResult<u32> {
+    cars.update_id(id, |c| {
+        c.hp += 10;
+        c.hp
+    })
+}
+
+#[test]
+fn test_collection_like() {
+    let dir = testing::TempDir::new().unwrap();
+    let mut cars = create_dummy_vecpack(dir.path().join("vecpack_test_like"));
+    assert_eq!(tune_car(&mut cars, "1").unwrap(), 160);
+    assert_eq!(cars.find_id("1").unwrap().hp, 160);
+
+    let mut fake = MemoryVecPack::new();
+    fake.insert(Car::new("1".to_string(), "Fake".to_string(), 50))
+        .unwrap();
+    assert!(CollectionLike::insert(
+        &mut fake,
+        Car::new("1".to_string(), "Fake".to_string(), 50)
+    )
+    .is_err());
+    assert_eq!(tune_car(&mut fake, "1").unwrap(), 60);
+    assert!(tune_car(&mut fake, "2").is_err());
+    assert_eq!(fake.len(), 1);
+    assert_eq!(fake.remove_by_id("1").unwrap().hp, 60);
+    assert!(fake.is_empty());
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, VecPackMember)]
+struct Plane {
+    name: String,
+    #[pack(id)]
+    serial: String,
+}
+
+impl Validate for Plane {
+    fn validate(&self) -> Result<(), String> {
+        match self.name.is_empty() {
+            true => Err(format!("plane {} has no name", self.serial)),
+            false => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn test_derive_vecpack_member() {
+    let dir = testing::TempDir::new().unwrap();
+    let mut planes: VecPack<Plane> =
+        VecPack::load_or_init(dir.path().join("vecpack_test_derive")).unwrap();
+    planes
+        .insert(Plane {
+            name: "Cessna".to_string(),
+            serial: "HA-1".to_string(),
+        })
+        .unwrap();
+    assert_eq!(planes.find_id("HA-1").unwrap().get_id(), "HA-1");
+    // The derive validates types implementing Validate
+    let res = planes.insert(Plane {
+        name: String::new(),
+        serial: "HA-2".to_string(),
+    });
+    assert!(matches!(res, Err(PackError::ValidationError(_))));
+}
+
+#[test]
+fn test_error_context() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_error_context");
+    let mut cars = create_dummy_vecpack(path.clone());
+    std::fs::write(path.join("1.yml"), "---\nid: [\n").unwrap();
+    let err = cars.reload_id("1").unwrap_err();
+    assert_eq!(err.id(), Some("1"));
+    assert_eq!(err.path(), Some(path.join("1.yml").as_path()));
+}
+
+#[test]
+fn test_single_writer() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_single_writer");
+    let mut writer = create_dummy_vecpack(path.clone());
+    writer.enable_single_writer().unwrap();
+    assert!(writer.is_single_writer());
+    assert_eq!(writer.lock_owner(), Some(std::process::id()));
+
+    // Second instance falls back to read-only
+    let mut reader: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    assert!(reader.is_read_only());
+    assert_eq!(reader.len(), 3);
+    assert!(matches!(
+        reader.insert(Car::new("4".to_string(), "Car".to_string(), 100)),
+        Err(PackError::Locked)
+    ));
+    assert!(matches!(reader.find_id_mut("1"), Err(PackError::Locked)));
+    writer
+        .insert(Car::new("4".to_string(), "Car".to_string(), 100))
+        .unwrap();
+
+    // Lock is released when the writer is dropped
+    drop(writer);
+    assert!(!path.join(".lock").exists());
+    let mut writer: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    assert!(!writer.is_read_only());
+    writer.remove_by_id("4").unwrap();
+
+    // Explicit takeover of a stale lock
+    std::mem::forget(writer);
+    let mut next: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    assert!(next.is_read_only());
+    next.take_over_lock().unwrap();
+    assert!(!next.is_read_only());
+    assert_eq!(next.len(), 3);
+    next.remove_by_id("3").unwrap();
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct Gear {
+    id: String,
+    size: u32,
+}
+
+impl VecPackMember for Gear {
+    fn get_id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Migratable for Gear {
+    const SCHEMA_VERSION: u32 = 2;
+    fn migrations() -> Vec<migrate::Migration> {
+        // v1 -> v2: size is doubled
+        vec![|v| {
+            let size = v["size"].as_u64().unwrap_or(0);
+            v["size"] = (size * 2).into();
+        }]
+    }
+}
+
+#[test]
+fn test_migrate_read_only() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_migrate_read_only");
+    register_migrations::<Gear>().unwrap();
+    let mut writer: VecPack<Gear> =
+        VecPack::load_or_init(path.clone()).unwrap();
+    writer.enable_single_writer().unwrap();
+    let old = "---\nid: g1\nsize: 2\n";
+    std::fs::write(path.join("g1.yml"), old).unwrap();
+
+    // Read-only instance migrates in memory only
+    let reader: VecPack<Gear> = VecPack::load_or_init(path.clone()).unwrap();
+    assert!(reader.is_read_only());
+    assert_eq!(reader.find_id("g1").unwrap().size, 4);
+    assert_eq!(std::fs::read_to_string(path.join("g1.yml")).unwrap(), old);
+    drop(reader);
+
+    // Append-only as well
+    writer.enable_append_only().unwrap();
+    drop(writer);
+    let gears: VecPack<Gear> = VecPack::load_or_init(path.clone()).unwrap();
+    assert_eq!(gears.find_id("g1").unwrap().size, 4);
+    assert_eq!(std::fs::read_to_string(path.join("g1.yml")).unwrap(), old);
+
+    // The writer saves the migrated data back
+    std::fs::remove_file(path.join(".append_only")).unwrap();
+    drop(gears);
+    let gears: VecPack<Gear> = VecPack::load_or_init(path.clone()).unwrap();
+    assert_eq!(gears.find_id("g1").unwrap().size, 4);
+    let header = FileHeader::read(&path.join("g1.yml")).unwrap().unwrap();
+    assert_eq!(header.schema_version, 2);
+    drop(gears);
+    let gears: VecPack<Gear> = VecPack::load_or_init(path).unwrap();
+    assert_eq!(gears.find_id("g1").unwrap().size, 4);
+}
+
+#[test]
+fn test_single_writer_mutators() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_single_writer_mutators");
+    let mut writer = create_dummy_vecpack(path.clone());
+    writer.enable_single_writer().unwrap();
+    writer.soft_remove("3").unwrap();
+    let before = std::fs::read_to_string(path.join("1.yml")).unwrap();
+
+    let mut reader: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    assert!(reader.is_read_only());
+    let locked = |res: PackResult<()>| matches!(res, Err(PackError::Locked));
+    assert!(locked(reader.restore("3")));
+    assert!(locked(reader.sort_by(|a, b| b.id.cmp(&a.id))));
+    assert!(locked(reader.enable_sharding(2)));
+    assert!(locked(
+        reader.purge_trash(Duration::from_secs(0)).map(|_| ())
+    ));
+    assert!(locked(reader.enable_order_index()));
+    assert!(locked(reader.enable_append_only()));
+    assert!(locked(reader.enable_audit()));
+    assert!(locked(reader.set_temp_config(TempConfig::new())));
+    assert!(locked(reader.enable_history(None)));
+    assert!(locked(reader.take_snapshot().map(|_| ())));
+    assert!(locked(
+        reader
+            .convert_format(Format::Yaml, Format::Json)
+            .map(|_| ())
+    ));
+    let pack = Pack::load_from_path(path.join("2.yml")).unwrap();
+    assert!(locked(reader.insert_pack(pack)));
+    // Saves through a member are rejected as well
+    assert!(locked(reader.find_id("1").unwrap().save()));
+    assert!(locked(reader.save_all().remove(0).1));
+
+    assert_eq!(std::fs::read_to_string(path.join("1.yml")).unwrap(), before);
+    assert!(!path.join("3.yml").exists());
+    assert!(!path.join(".order.yml").exists());
+    assert!(!path.join(".append_only").exists());
+    assert_eq!(writer.len(), 2);
+}
+
+#[test]
+fn test_shared_vecpack() {
+    let dir = testing::TempDir::new().unwrap();
+    fn assert_send_sync<S: Send + Sync + Clone>(_: &S) {}
+    let cars = SharedVecPack::new(create_dummy_vecpack(
+        dir.path().join("vecpack_test_shared"),
+    ));
+    assert_send_sync(&cars);
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let cars = cars.clone();
+            std::thread::spawn(move || {
+                for _ in 0..10 {
+                    cars.update("1", |c| c.hp += 1).unwrap();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(cars.find_id("1", |c| c.hp).unwrap(), 190);
+    // Update takes FnOnce, so it can move its captures
+    let name = "CarShared".to_string();
+    cars.update("1", move |c| c.name = name).unwrap();
+    assert_eq!(cars.find_id("1", |c| c.name.clone()).unwrap(), "CarShared");
+    cars.insert(Car::new("4".to_string(), "Car".to_string(), 100))
+        .unwrap();
+    assert_eq!(cars.len(), 4);
+    assert_eq!(cars.remove_by_id("4").unwrap().hp, 100);
+    assert!(cars.find_id("4", |c| c.hp).is_err());
+}
+
+#[test]
+fn test_actor() {
+    let dir = testing::TempDir::new().unwrap();
+    let cars = create_dummy_vecpack(dir.path().join("vecpack_test_actor"));
+    let actor = cars.spawn_actor();
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let actor = actor.clone();
+            std::thread::spawn(move || {
+                for _ in 0..10 {
+                    actor.update("1", |c| c.hp += 1).unwrap();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(actor.get("1").unwrap().hp, 190);
+    actor
+        .insert(Car::new("4".to_string(), "Car".to_string(), 100))
+        .unwrap();
+    assert_eq!(actor.call(|cars| Ok(cars.len())).unwrap(), 4);
+    assert_eq!(actor.remove("4").unwrap().hp, 100);
+    assert!(matches!(actor.get("4"), Err(PackError::ObjectNotFound)));
+}
+
+#[test]
+fn test_temp_vecpack() {
+    let dir;
+    {
+        let mut cars: testing::TempVecPack<Car> =
+            testing::TempVecPack::with_items(vec![Car::new(
+                "1".to_string(),
+                "Car".to_string(),
+                100,
+            )])
+            .unwrap();
+        cars.insert(Car::new("2".to_string(), "Car".to_string(), 200))
+            .unwrap();
+        dir = cars.dir().to_path_buf();
+        let other: VecPack<Car> = VecPack::load_or_init(dir.clone()).unwrap();
+        assert_eq!(other.len(), 2);
+        let other: testing::TempVecPack<Car> =
+            testing::TempVecPack::new().unwrap();
+        assert_ne!(other.dir(), dir);
+        assert!(other.is_empty());
+    }
+    assert!(!dir.exists());
+}
+
+#[test]
+fn test_memory_backend() {
+    let backend = MemoryBackend::new();
+    let path = PathBuf::from("memory/cars");
+    {
+        let mut cars: VecPack<Car> = VecPack::load_or_init_with_backend(
+            path.clone(),
+            Arc::new(backend.clone()),
+        )
+        .unwrap();
+        cars.insert(Car::new("1".to_string(), "Car".to_string(), 100))
+            .unwrap();
+        cars.insert(Car::new("2".to_string(), "Car".to_string(), 200))
+            .unwrap();
+        cars.find_id_mut("1").unwrap().as_mut().unpack().hp = 110;
+        cars.remove_by_id("2").unwrap();
+        // Member larger than the write buffer
+        cars.insert(Car::new("3".to_string(), "x".repeat(100_000), 300))
+            .unwrap();
+    }
+    assert!(!path.exists());
+    assert_eq!(backend.len(), 2);
+    let cars: VecPack<Car> =
+        VecPack::load_or_init_with_backend(path, Arc::new(backend)).unwrap();
+    assert_eq!(cars.len(), 2);
+    assert_eq!(cars.find_id("1").unwrap().hp, 110);
+    assert_eq!(cars.find_id("3").unwrap().name.len(), 100_000);
+}
+
+#[test]
+fn test_replication() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_replication");
+    let replica = dir.path().join("vecpack_test_replication_copy");
+    let mut cars = create_dummy_vecpack(path.clone());
+    assert_eq!(cars.enable_replication(vec![replica.clone()]).unwrap(), 3);
+    assert_eq!(cars.replicas(), vec![replica.clone()]);
+    cars.insert(Car::new("4".to_string(), "Car".to_string(), 100))
+        .unwrap();
+    cars.find_id_mut("1").unwrap().as_mut().unpack().hp = 110;
+    cars.remove_by_id("2").unwrap();
+    assert!(replica.join("4.yml").exists());
+    assert!(!replica.join("2.yml").exists());
+    assert!(cars.check_replicas().unwrap().is_empty());
+
+    // Replica drifts while the primary is offline
+    drop(cars);
+    std::fs::remove_file(replica.join("1.yml")).unwrap();
+    std::fs::write(replica.join("3.yml"), "id: '3'").unwrap();
+    std::fs::write(replica.join("9.yml"), "id: '9'").unwrap();
+    let cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    assert!(cars.check_replicas().unwrap().is_empty());
+    let copy: VecPack<Car> = VecPack::load_or_init(replica.clone()).unwrap();
+    assert_eq!(copy.len(), 3);
+    assert_eq!(copy.find_id("1").unwrap().hp, 110);
+
+    std::fs::remove_file(replica.join("4.yml")).unwrap();
+    assert_eq!(
+        cars.check_replicas().unwrap(),
+        vec![ReplicaIssue::Missing {
+            replica: replica.clone(),
+            file: PathBuf::from("4.yml"),
+        }]
+    );
+    assert_eq!(cars.sync_replicas().unwrap(), 1);
+}
+
+#[test]
+fn test_backup_to() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_backup");
+    let archive = dir.path().join("vecpack_test_backup.tar.gz");
+    let cars = create_dummy_vecpack(path);
+    assert_eq!(cars.backup_to(&archive).unwrap(), 3);
+    let file = std::fs::File::open(&archive).unwrap();
+    let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    let mut names: Vec<String> = tar
+        .entries()
+        .unwrap()
+        .map(|e| e.unwrap().path().unwrap().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["1.yml", "2.yml", "3.yml"]);
+}
+
+#[test]
+fn test_restore_from() {
+    let dir = testing::TempDir::new().unwrap();
+    let archive = dir.path().join("vecpack_test_restore.tar.gz");
+    let mut cars =
+        create_dummy_vecpack(dir.path().join("vecpack_test_restore"));
+    cars.backup_to(&archive).unwrap();
+    cars.find_id_mut("1").unwrap().as_mut().unpack().hp = 1;
+    cars.remove_by_id("2").unwrap();
+    cars.insert(Car::new("4".to_string(), "Car".to_string(), 100))
+        .unwrap();
+
+    // ID conflict fails before any change
+    assert!(matches!(
+        cars.restore_from(
+            &archive,
+            RestoreStrategy::Merge(ConflictPolicy::Fail)
+        ),
+        Err(PackError::IDTaken)
+    ));
+    assert_eq!(cars.len(), 3);
+
+    let report = cars
+        .restore_from(
+            &archive,
+            RestoreStrategy::Merge(ConflictPolicy::KeepExisting),
+        )
+        .unwrap();
+    assert_eq!(report.inserted, vec!["2".to_string()]);
+    assert_eq!(report.skipped, vec!["1".to_string(), "3".to_string()]);
+    assert_eq!(cars.find_id("1").unwrap().hp, 1);
+
+    let report = cars
+        .restore_from(&archive, RestoreStrategy::Replace)
+        .unwrap();
+    assert_eq!(report.removed, vec!["4".to_string()]);
+    assert_eq!(report.overwritten.len(), 3);
+    assert_eq!(cars.len(), 3);
+    assert_eq!(cars.find_id("1").unwrap().hp, 150);
+
+    // Archive with an invalid member changes nothing
+    let archive_of = |name: &str, files: &[(&str, &str)]| {
+        let path = dir.path().join(name);
+        let file = std::fs::File::create(&path).unwrap();
+        let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(
+            file,
+            flate2::Compression::default(),
+        ));
+        for (name, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_cksum();
+            tar.append_data(&mut header, name, content.as_bytes())
+                .unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+        path
+    };
+    let broken = archive_of(
+        "vecpack_test_restore_broken.tar.gz",
+        &[("5.yml", "id: '5'\nname: Car\nhp: 5\n"), ("6.yml", "hp: [")],
+    );
+    assert!(matches!(
+        cars.restore_from(&broken, RestoreStrategy::Replace),
+        Err(PackError::DeserializeError { .. })
+    ));
+    assert_eq!(cars.len(), 3);
+    assert!(cars.find_id("5").is_err());
+
+    // Failing validation or duplicated IDs change nothing either
+    assert!(cars.enable_validation().is_empty());
+    let invalid = archive_of(
+        "vecpack_test_restore_invalid.tar.gz",
+        &[("1.yml", "id: '1'\nname: Car\nhp: 0\n")],
+    );
+    assert!(matches!(
+        cars.restore_from(&invalid, RestoreStrategy::Replace),
+        Err(PackError::ValidationError(_))
+    ));
+    let twice = archive_of(
+        "vecpack_test_restore_twice.tar.gz",
+        &[
+            ("1.yml", "id: '1'\nname: Car\nhp: 1\n"),
+            ("a/1.yml", "id: '1'\nname: Car\nhp: 2\n"),
+        ],
+    );
+    assert!(matches!(
+        cars.restore_from(&twice, RestoreStrategy::Replace),
+        Err(PackError::IDTaken)
+    ));
+    assert_eq!(cars.len(), 3);
+    assert_eq!(cars.find_id("1").unwrap().hp, 150);
+
+    // Expired member is overwritten, and it is live again
+    cars.set_expiry("2", Some(std::time::SystemTime::now()))
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(5));
+    assert!(cars.find_id("2").is_err());
+    let report = cars
+        .restore_from(&archive, RestoreStrategy::Replace)
+        .unwrap();
+    assert_eq!(report.overwritten.len(), 3);
+    assert_eq!(cars.find_id("2").unwrap().hp, 650);
+}
+
+#[test]
+fn test_load_or_init_lossy() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_lossy");
+    create_dummy_vecpack(path.clone());
+    std::fs::write(path.join("1.yml"), "id: '1'\nhp: [").unwrap();
+    std::fs::write(path.join("3.yml"), "id: '3'\nname: Car\n").unwrap();
+    let (cars, failures) =
+        VecPack::<Car>::load_or_init_lossy(path.clone()).unwrap();
+    assert_eq!(cars.len(), 1);
+    assert_eq!(cars.find_id("2").unwrap().name, "CarBig");
+    assert_eq!(failures.len(), 2);
+    assert_eq!(failures[0].path, path.join("1.yml"));
+    assert!(matches!(
+        failures[1].error,
+        PackError::DeserializeError { .. }
+    ));
+    // Files are left in place
+    assert!(path.join("1.yml").is_file());
+}
+
+#[test]
+fn test_load_or_init_quarantine() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_quarantine");
+    create_dummy_vecpack(path.clone());
+    std::fs::write(path.join("2.yml"), "id: '2'\nhp: [").unwrap();
+
+    // A corrupt file is an error, not a panic
+    assert!(VecPack::<Car>::load_or_init(path.clone()).is_err());
+
+    let (cars, quarantined) =
+        VecPack::<Car>::load_or_init_quarantine(path.clone()).unwrap();
+    assert_eq!(cars.len(), 2);
+    assert!(cars.find_id("2").is_err());
+    assert_eq!(quarantined, vec![path.join("corrupt").join("2.yml")]);
+    assert!(quarantined[0].is_file());
+    assert!(!path.join("2.yml").exists());
+
+    // Quarantine folder is not loaded
+    let cars: VecPack<Car> = VecPack::load_or_init(path).unwrap();
+    assert_eq!(cars.len(), 2);
+}
+
+#[test]
+fn test_verify() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_verify");
+    let cars = create_dummy_vecpack(path.clone());
+    assert!(cars.verify().unwrap().is_ok());
+    std::fs::write(path.join("1.yml"), "id: '1'\nhp: [").unwrap();
+    std::fs::write(path.join("2.yml"), "id: '7'\nname: Car\nhp: 1\n").unwrap();
+    std::fs::write(path.join("4.yml"), "id: '4'\nname: Car\nhp: 1\n").unwrap();
+    std::fs::remove_file(path.join("3.yml")).unwrap();
+    let report = cars.verify().unwrap();
+    assert_eq!(report.files, 3);
+    assert_eq!(report.issues.len(), 4);
+    assert!(matches!(
+        &report.issues[0],
+        VerifyIssue::Unreadable { file, .. } if file.ends_with("1.yml")
+    ));
+    assert_eq!(
+        report.issues[1],
+        VerifyIssue::IdMismatch {
+            file: path.join("2.yml"),
+            id: "7".to_string()
+        }
+    );
+    assert_eq!(
+        report.issues[2],
+        VerifyIssue::NotLoaded {
+            file: path.join("4.yml")
+        }
+    );
+    assert_eq!(
+        report.issues[3],
+        VerifyIssue::Missing {
+            file: path.join("3.yml")
+        }
+    );
+    // Nothing is modified
+    assert_eq!(cars.len(), 3);
+    assert_eq!(cars.find_id("1").unwrap().hp, 150);
+}
+
+#[test]
+fn test_convert_format() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_convert");
+    let mut cars = create_dummy_vecpack(path.clone());
+    let report = cars.convert_format(Format::Yaml, Format::Json).unwrap();
+    assert_eq!(report.converted, 3);
+    assert!(report.backup.is_file());
+    let content = std::fs::read_to_string(path.join("1.yml")).unwrap();
+    assert!(content.starts_with("#!storaget format=json"));
+    assert!(content.contains("\"name\": \"CarSmall\""));
+
+    // Format is kept by the following saves and after reload
+    cars.insert(Car::new("4".to_string(), "CarFast".to_string(), 400))
+        .unwrap();
+    let mut cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    assert_eq!(cars.format(), Format::Json);
+    assert_eq!(cars.len(), 4);
+    assert_eq!(cars.find_id("4").unwrap().hp, 400);
+    let header = FileHeader::read(&path.join("4.yml")).unwrap().unwrap();
+    assert_eq!(header.format, Format::Json);
+
+    // Files not in the from format fail before any change
+    assert!(cars.convert_format(Format::Yaml, Format::Json).is_err());
+
+    cars.convert_format(Format::Json, Format::Yaml).unwrap();
+    let content = std::fs::read_to_string(path.join("1.yml")).unwrap();
+    assert!(content.starts_with("#!storaget format=yaml"));
+    let cars: VecPack<Car> = VecPack::load_or_init(path).unwrap();
+    assert_eq!(cars.format(), Format::Yaml);
+    assert_eq!(cars.find_id("2").unwrap().name, "CarBig");
+}
+
+#[test]
+fn test_convert_format_msgpack() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_convert_msgpack");
+    let mut cars = create_dummy_vecpack(path.clone());
+    let file = path.join("1.yml");
+    let before = FileHeader::read(&file).unwrap().unwrap();
+    cars.convert_format(Format::Yaml, Format::MessagePack)
+        .unwrap();
+    // Only the header line is text
+    let content = std::fs::read(&file).unwrap();
+    assert!(content.starts_with(b"#!storaget format=msgpack"));
+    assert!(std::str::from_utf8(&content).is_err());
+    // Conversion keeps the save times and the revision
+    let after = FileHeader::read(&file).unwrap().unwrap();
+    assert_eq!(after.revision, before.revision);
+    assert_eq!(after.created, before.created);
+    assert_eq!(after.updated, before.updated);
+
+    // Saves keep the format, history included
+    cars.enable_history(None).unwrap();
+    cars.find_id_mut("1").unwrap().as_mut().unpack().hp = 160;
+    let cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    assert_eq!(cars.format(), Format::MessagePack);
+    assert_eq!(cars.find_id("1").unwrap().hp, 160);
+    let history = cars.find_id("1").unwrap().history().unwrap();
+    assert_eq!(history[0].data.hp, 150);
+    let header = FileHeader::read(&file).unwrap().unwrap();
+    assert_eq!(header.revision, before.revision.map(|r| r + 1));
+
+    // Converted back without the member type
+    drop(cars);
+    convert::convert_dir(&path, Format::MessagePack, Format::Yaml).unwrap();
+    let content = std::fs::read_to_string(&file).unwrap();
+    assert!(content.contains("name: CarSmall"));
+}
+
+#[test]
+fn test_dump_json() {
+    let dir = testing::TempDir::new().unwrap();
+    let dump = dir.path().join("vecpack_test_dump.json");
+    let cars = create_dummy_vecpack(dir.path().join("vecpack_test_dump"));
+    assert_eq!(cars.dump_json(&dump).unwrap(), 3);
+    let info = VecPack::<Car>::dump_info(&dump).unwrap();
+    assert_eq!(info.count, 3);
+    assert_eq!(info.source, cars.get_path().display().to_string());
+    assert!(info.member_type.ends_with("Car"));
+
+    let mut copy: VecPack<Car> =
+        VecPack::load_or_init(dir.path().join("vecpack_test_dump_copy"))
+            .unwrap();
+    let report = copy.load_dump(&dump, RestoreStrategy::Replace).unwrap();
+    assert_eq!(report.inserted.len(), 3);
+    assert_eq!(copy.len(), 3);
+    assert_eq!(copy.find_id("2").unwrap().name, "CarBig");
+
+    // Invalid dump changes nothing
+    std::fs::write(&dump, "{\"info\": {}, \"members\": []}").unwrap();
+    assert!(copy.load_dump(&dump, RestoreStrategy::Replace).is_err());
+    assert_eq!(copy.len(), 3);
+}
+
+#[test]
+fn test_snapshots() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_snapshots");
+    let mut cars = create_dummy_vecpack(path.clone());
+    cars.enable_snapshots(
+        Duration::from_secs(3600),
+        RetentionPolicy::default(),
+    )
+    .unwrap();
+    let snapshots = cars.snapshots().unwrap();
+    assert_eq!(snapshots.len(), 1);
+
+    // Interval has not passed yet
+    cars.find_id_mut("1").unwrap().as_mut().unpack().hp = 1;
+    cars.remove_by_id("2").unwrap();
+    assert_eq!(cars.snapshots().unwrap(), snapshots);
+
+    let report = cars.rollback_to(&snapshots[0]).unwrap();
+    assert_eq!(report.inserted, vec!["2".to_string()]);
+    assert_eq!(cars.len(), 3);
+    assert_eq!(cars.find_id("1").unwrap().hp, 150);
+
+    // Newest snapshot of the hour is kept
+    let snapshot = cars.take_snapshot().unwrap();
+    assert_eq!(cars.snapshots().unwrap(), vec![snapshot]);
+
+    // Every change is snapshotted with zero interval
+    let policy = RetentionPolicy {
+        hourly: 0,
+        daily: 0,
+        weekly: 0,
+    };
+    cars.enable_snapshots(Duration::from_secs(0), policy)
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(5));
+    cars.insert(Car::new("4".to_string(), "Car".to_string(), 100))
+        .unwrap();
+    let snapshots = cars.snapshots().unwrap();
+    assert_eq!(snapshots.len(), 1);
+    drop(cars);
+    let mut cars: VecPack<Car> = VecPack::load_or_init(path).unwrap();
+    cars.disable_snapshots().unwrap();
+    cars.remove_by_id("4").unwrap();
+    cars.rollback_to(&cars.snapshots().unwrap()[0]).unwrap();
+    assert_eq!(cars.find_id("4").unwrap().hp, 100);
+}
+
+#[test]
+fn test_audit_log() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_audit");
+    let mut cars = create_dummy_vecpack(path.clone());
+    cars.enable_audit().unwrap();
+    assert!(cars.is_audited());
+    cars.set_audit_actor("alice");
+    cars.insert(Car::new("4".to_string(), "Car".to_string(), 100))
+        .unwrap();
+    cars.find_id_mut("1").unwrap().as_mut().unpack().hp = 110;
+    cars.set_audit_actor("bob");
+    cars.remove_by_id("2").unwrap();
+    drop(cars);
+
+    let cars: VecPack<Car> = VecPack::load_or_init(path).unwrap();
+    let log: Vec<(String, ChangeKind, String)> = cars
+        .audit_iter()
+        .unwrap()
+        .map(|e| (e.actor, e.kind, e.id))
+        .collect();
+    assert_eq!(
+        log,
+        vec![
+            ("alice".to_string(), ChangeKind::Created, "4".to_string()),
+            ("alice".to_string(), ChangeKind::Updated, "1".to_string()),
+            ("bob".to_string(), ChangeKind::Removed, "2".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_batch_mut() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_batch_mut");
+    let mut cars = create_dummy_vecpack(path.clone());
+    let mut batch = cars.batch_mut().unwrap();
+    for mut car in batch.iter_mut() {
+        if car.hp > 600 {
+            car.name = "CarFast".to_string();
+        }
+    }
+    batch.get_mut("3").unwrap().hp = 250;
+    assert!(batch.get_mut("9").is_err());
+    // Members read only are not saved
+    assert_eq!(batch.modified(), vec!["2".to_string(), "3".to_string()]);
+    assert_eq!(batch.commit().unwrap(), 2);
+    // Dropped guard saves too
+    cars.batch_mut().unwrap().get_mut("1").unwrap().hp = 160;
+    // Discarded changes are restored
+    let mut batch = cars.batch_mut().unwrap();
+    batch.get_mut("3").unwrap().hp = 1;
+    batch.discard().unwrap();
+    assert_eq!(cars.find_id("3").unwrap().hp, 250);
+    drop(cars);
+    let cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    assert_eq!(cars.find_id("1").unwrap().hp, 160);
+    assert_eq!(cars.find_id("2").unwrap().name, "CarFast");
+    assert_eq!(cars.find_id("3").unwrap().hp, 250);
+}
+
+#[test]
+fn test_save_all() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_save_all");
+    let cars = create_dummy_vecpack(path.clone());
+    let results = cars.save_all();
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(|(_, res)| res.is_ok()));
+    // A directory in place of a member file, so its save fails
+    std::fs::remove_file(path.join("2.yml")).unwrap();
+    std::fs::create_dir_all(path.join("2.yml").join("inner")).unwrap();
+    let failed = cars
+        .save_all()
+        .into_iter()
+        .filter(|(_, res)| res.is_err())
+        .map(|(id, _)| id)
+        .collect::<Vec<String>>();
+    assert_eq!(failed, vec!["2".to_string()]);
+    assert!(path.join("1.yml").is_file());
+    assert!(path.join("3.yml").is_file());
+}
+
+#[test]
+fn test_move_storage() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_move_storage");
+    let new_path = dir.path().join("vecpack_test_move_storage_new");
+    let mut cars = create_dummy_vecpack(path.clone());
+    // Target must be empty
+    std::fs::create_dir_all(&new_path).unwrap();
+    std::fs::write(new_path.join("other.txt"), "other").unwrap();
+    assert!(cars.move_storage(new_path.clone()).is_err());
+    assert!(path.join("1.yml").is_file());
+    std::fs::remove_file(new_path.join("other.txt")).unwrap();
+    cars.move_storage(new_path.clone()).unwrap();
+    assert!(!path.exists());
+    assert_eq!(cars.get_path(), new_path.as_path());
+    // Members are saved into the new directory
+    cars.find_id_mut("2")
+        .unwrap()
+        .update(|c| c.hp = 700)
+        .unwrap();
+    assert!(!path.exists());
+    drop(cars);
+    let cars: VecPack<Car> = VecPack::load_or_init(new_path.clone()).unwrap();
+    assert_eq!(cars.len(), 3);
+    assert_eq!(cars.find_id("2").unwrap().hp, 700);
+}
+
+#[test]
+fn test_move_storage_audit_lock() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_move_storage_audit");
+    let new_path = dir.path().join("vecpack_test_move_storage_audit_new");
+    let mut cars = create_dummy_vecpack(path.clone());
+    cars.enable_single_writer().unwrap();
+    cars.enable_audit().unwrap();
+    cars.insert(Car::new("4".into(), "Car4".into(), 400))
+        .unwrap();
+    cars.move_storage(new_path.clone()).unwrap();
+    assert!(!path.exists());
+    // Audit entries go to the moved log
+    cars.insert(Car::new("5".into(), "Car5".into(), 500))
+        .unwrap();
+    let ids: Vec<String> = cars.audit_iter().unwrap().map(|e| e.id).collect();
+    assert_eq!(ids, vec!["4", "5"]);
+    assert!(!path.exists());
+    // The lock is held at the new location, and released at drop
+    assert!(!cars.is_read_only());
+    assert_eq!(cars.lock_owner(), Some(std::process::id()));
+    let reader: VecPack<Car> = VecPack::load_or_init(new_path.clone()).unwrap();
+    assert!(reader.is_read_only());
+    drop(reader);
+    drop(cars);
+    assert!(!new_path.join(".lock").exists());
+    let cars: VecPack<Car> = VecPack::load_or_init(new_path.clone()).unwrap();
+    assert!(!cars.is_read_only());
+    assert_eq!(cars.len(), 5);
+}
+
+#[test]
+fn test_hidden_id_rejected() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_hidden_id");
+    let mut planes: VecPack<Plane> =
+        VecPack::load_or_init(path.clone()).unwrap();
+    let plane = |serial: &str| Plane {
+        name: "Cessna".to_string(),
+        serial: serial.to_string(),
+    };
+    assert!(matches!(
+        planes.insert(plane(".hidden")),
+        Err(PackError::InternalError(_))
+    ));
+    assert!(matches!(
+        planes.upsert(plane(".hidden")),
+        Err(PackError::InternalError(_))
+    ));
+    planes.insert(plane("HA-1")).unwrap();
+    assert!(matches!(
+        planes.change_id("HA-1", ".hidden"),
+        Err(PackError::InternalError(_))
+    ));
+    assert_eq!(planes.find_id("HA-1").unwrap().serial, "HA-1");
+    assert!(!path.join(".hidden.yml").exists());
+    drop(planes);
+    let planes: VecPack<Plane> = VecPack::load_or_init(path).unwrap();
+    assert_eq!(planes.ids().collect::<Vec<_>>(), vec!["HA-1"]);
+}
+
+#[test]
+fn test_change_id() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_change_id");
+    let mut planes: VecPack<Plane> =
+        VecPack::load_or_init(path.clone()).unwrap();
+    for serial in ["HA-1", "HA-2"] {
+        planes
+            .insert(Plane {
+                name: "Cessna".to_string(),
+                serial: serial.to_string(),
+            })
+            .unwrap();
+    }
+    assert!(matches!(
+        planes.change_id("HA-1", "HA-2"),
+        Err(PackError::IDTaken)
+    ));
+    assert!(matches!(
+        planes.change_id("HA-9", "HA-3"),
+        Err(PackError::ObjectNotFound)
+    ));
+    planes.change_id("HA-1", "HA-3").unwrap();
+    assert!(planes.find_id("HA-1").is_err());
+    assert_eq!(planes.find_id("HA-3").unwrap().serial, "HA-3");
+    assert!(!path.join("HA-1.yml").exists());
+    assert!(path.join("HA-3.yml").is_file());
+    drop(planes);
+    let planes: VecPack<Plane> = VecPack::load_or_init(path.clone()).unwrap();
+    assert_eq!(planes.len(), 2);
+    assert_eq!(planes.find_id("HA-3").unwrap().name, "Cessna");
+    // Manual implementation without set_id
+    let mut cars = create_dummy_vecpack(path.join("cars"));
+    assert!(cars.change_id("1", "4").is_err());
+    assert!(cars.find_id("1").is_ok());
+}
+
+#[test]
+fn test_ttl() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_ttl");
+    let mut cars = create_dummy_vecpack(path.clone());
+    assert_eq!(cars.expires_at("1"), None);
+    cars.set_ttl("1", Duration::from_secs(3600)).unwrap();
+    cars.set_expiry("2", Some(std::time::SystemTime::now()))
+        .unwrap();
+    cars.insert_with_ttl(
+        Car::new("4".to_string(), "CarTiny".to_string(), 50),
+        Duration::from_millis(0),
+    )
+    .unwrap();
+    assert!(cars.expires_at("1").is_some());
+    assert!(cars.set_ttl("9", Duration::from_secs(1)).is_err());
+    std::thread::sleep(Duration::from_millis(5));
+    // Expired members are hidden before purge
+    assert!(cars.find_id("2").is_err());
+    assert!(cars.find_id_mut("4").is_err());
+    assert!(!cars.contains_id("2"));
+    assert_eq!(cars.ids().collect::<Vec<&str>>(), vec!["1", "3"]);
+    assert_eq!(cars.iter_live().count(), 2);
+    assert_eq!(cars.iter().rev().len(), 4);
+    assert!(cars.find(|c| c.id == "2").is_none());
+    assert_eq!(cars.filter(|_| true).len(), 2);
+    assert!(cars.all(|c| c.id != "4"));
+    assert_eq!(cars.page(1, 10)[0].get_id(), "3");
+    let page = cars.page_after(None, 1).unwrap();
+    assert_eq!(page.next.as_deref(), Some("1"));
+    let page = cars.page_after(Some("1"), 1).unwrap();
+    assert_eq!(page.items[0].get_id(), "3");
+    assert_eq!(page.next, None);
+    // Expired IDs are still taken until purged
+    assert!(!cars.check_id_available("2"));
+    assert!(matches!(
+        cars.insert_many(vec![Car::new("2".to_string(), "Car".to_string(), 1)]),
+        Err(PackError::IDTaken)
+    ));
+    assert!(cars.reserve_id("2").is_err());
+    assert_eq!(cars.len(), 2);
+    assert_eq!(cars.as_vec().len(), 4);
+    let mut removed = cars.purge_expired().unwrap();
+    removed.sort();
+    assert_eq!(removed, vec!["2".to_string(), "4".to_string()]);
+    assert_eq!(cars.len(), 2);
+    assert!(cars.purge_expired().unwrap().is_empty());
+    // Expired on load
+    cars.set_expiry("3", Some(std::time::SystemTime::now()))
+        .unwrap();
+    drop(cars);
+    std::thread::sleep(Duration::from_millis(5));
+    let mut cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    assert_eq!(cars.len(), 1);
+    assert!(cars.expires_at("1").is_some());
+    // Removed member forgets its expiry
+    cars.remove_by_id("1").unwrap();
+    cars.insert(Car::new("1".to_string(), "CarSmall".to_string(), 150))
+        .unwrap();
+    assert_eq!(cars.expires_at("1"), None);
+    // Expired member is created again
+    cars.set_expiry("1", Some(std::time::SystemTime::now()))
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(5));
+    let car = cars
+        .get_or_insert_with("1", || {
+            Car::new("1".to_string(), "CarNew".to_string(), 10)
+        })
+        .unwrap();
+    assert_eq!(car.name, "CarNew");
+    assert_eq!(cars.ids().collect::<Vec<&str>>(), vec!["1"]);
+    assert_eq!(cars.expires_at("1"), None);
+    assert!(!path.join(".expiry.yml").exists());
+
+    // Member is not inserted without its expiry
+    std::fs::create_dir(path.join(".expiry.yml")).unwrap();
+    assert!(cars
+        .insert_with_ttl(
+            Car::new("5".to_string(), "Car".to_string(), 50),
+            Duration::from_secs(60),
+        )
+        .is_err());
+    assert!(cars.find_id("5").is_err());
+    assert_eq!(cars.expires_at("5"), None);
+    assert!(!path.join("5.yml").exists());
+}
+
+#[test]
+fn test_op_stats() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_op_stats");
+    let mut cars = create_dummy_vecpack(path.clone());
+    let stats = cars.op_stats();
+    assert_eq!(stats.saves, 3);
+    assert_eq!(stats.loads, 0);
+    assert!(stats.bytes_written > 0);
+    assert_eq!(stats.slowest.len(), 3);
+    assert!(stats
+        .slowest
+        .windows(2)
+        .all(|w| w[0].duration >= w[1].duration));
+    cars.remove_by_id("3").unwrap();
+    // Members share the counters of their VecPack
+    let stats = cars.find_id("1").unwrap().op_stats();
+    assert_eq!(stats.removes, 1);
+    drop(cars);
+    let cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    let stats = cars.op_stats();
+    assert_eq!(stats.loads, 2);
+    assert_eq!(stats.saves, 0);
+    assert_eq!(
+        stats.bytes_read,
+        std::fs::metadata(path.join("1.yml")).unwrap().len()
+            + std::fs::metadata(path.join("2.yml")).unwrap().len()
+    );
+    cars.reset_op_stats();
+    assert_eq!(cars.op_stats(), OpStats::default());
+}
+
+#[test]
+fn test_slow_op_threshold() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_slow_op");
+    let mut cars = create_dummy_vecpack(path.clone());
+    assert_eq!(cars.op_stats().slow_ops, 0);
+    cars.set_slow_op_threshold(Some(Duration::ZERO));
+    cars.find_id_mut("1")
+        .unwrap()
+        .update(|c| c.hp = 160)
+        .unwrap();
+    cars.remove_by_id("2").unwrap();
+    assert_eq!(cars.op_stats().slow_ops, 2);
+    cars.set_slow_op_threshold(Some(Duration::from_secs(3600)));
+    cars.find_id_mut("1")
+        .unwrap()
+        .update(|c| c.hp = 170)
+        .unwrap();
+    assert_eq!(cars.op_stats().slow_ops, 2);
+}
+
+#[test]
+fn test_storage_stats() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_storage_stats");
+    let mut cars = create_dummy_vecpack(path.clone());
+    cars.find_id_mut("2")
+        .unwrap()
+        .update(|c| c.name = "CarBig".repeat(20))
+        .unwrap();
+    let stats = cars.stats().unwrap();
+    assert_eq!(stats.members, 3);
+    assert_eq!(stats.files.len(), 3);
+    let size = |id: &str| {
+        std::fs::metadata(path.join(format!("{}.yml", id)))
+            .unwrap()
+            .len()
+    };
+    assert_eq!(stats.total_bytes, size("1") + size("2") + size("3"));
+    let largest = stats.largest.unwrap();
+    assert_eq!(largest.id, "2");
+    assert_eq!(largest.bytes, size("2"));
+    assert!(stats.last_modified.is_some());
+    assert_eq!(
+        stats.last_modified,
+        stats.files.iter().filter_map(|f| f.modified).max()
+    );
+    let empty: VecPack<Car> =
+        VecPack::load_or_init(path.join("empty")).unwrap();
+    assert_eq!(empty.stats().unwrap(), StorageStats::default());
+}
+
+#[test]
+fn test_resolve_replicas() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_resolve_replicas");
+    let replica = dir.path().join("vecpack_test_resolve_replicas_copy");
+    let mut cars = create_dummy_vecpack(path.clone());
+    cars.enable_replication(vec![replica.clone()]).unwrap();
+    // Replica diverges while the primary is open
+    let mut copy: VecPack<Car> =
+        VecPack::load_or_init(replica.clone()).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    copy.find_id_mut("1").unwrap().as_mut().unpack().hp = 900;
+    copy.find_id_mut("3").unwrap().as_mut().unpack().name = "Copy".into();
+    assert_eq!(cars.check_replicas().unwrap().len(), 2);
+    // Their newer versions win
+    assert_eq!(cars.resolve_replicas(&ConflictStrategy::Newest).unwrap(), 2);
+    assert_eq!(cars.find_id("1").unwrap().hp, 900);
+    assert_eq!(cars.find_id("3").unwrap().name, "Copy");
+    assert!(cars.check_replicas().unwrap().is_empty());
+
+    copy.reload().unwrap();
+    copy.find_id_mut("2").unwrap().as_mut().unpack().hp = 100;
+    assert_eq!(cars.resolve_replicas(&ConflictStrategy::Ours).unwrap(), 1);
+    assert_eq!(cars.find_id("2").unwrap().hp, 650);
+    copy.reload().unwrap();
+    assert_eq!(copy.find_id("2").unwrap().hp, 650);
+
+    copy.find_id_mut("2").unwrap().as_mut().unpack().name = "Merged".into();
+    cars.find_id_mut("2").unwrap().as_mut().unpack().hp = 700;
+    // The primary save was mirrored, so diverge the replica again
+    copy.find_id_mut("2").unwrap().as_mut().unpack().hp = 1;
+    let merge = ConflictStrategy::merge(|ours: Car, theirs: Car| Car {
+        name: theirs.name,
+        ..ours
+    });
+    assert_eq!(cars.resolve_replicas(&merge).unwrap(), 1);
+    let car = cars.find_id("2").unwrap();
+    assert_eq!((car.name.as_str(), car.hp), ("Merged", 700));
+    copy.reload().unwrap();
+    assert_eq!(copy.find_id("2").unwrap().hp, 700);
+}
+
+#[test]
+fn test_upsert() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_upsert");
+    let mut cars = create_dummy_vecpack(path.clone());
+    let events = cars.subscribe();
+    assert_eq!(
+        cars.upsert(Car::new("4".into(), "CarNew".into(), 90))
+            .unwrap(),
+        ChangeKind::Created
+    );
+    assert_eq!(
+        cars.upsert(Car::new("1".into(), "CarSmaller".into(), 120))
+            .unwrap(),
+        ChangeKind::Updated
+    );
+    assert_eq!(cars.len(), 4);
+    assert_eq!(cars.find_id("1").unwrap().name, "CarSmaller");
+    let kinds: Vec<ChangeKind> = events.try_iter().map(|e| e.kind).collect();
+    assert_eq!(kinds, vec![ChangeKind::Created, ChangeKind::Updated]);
+    // Failed update keeps the member
+    assert!(cars.enable_validation().is_empty());
+    assert!(matches!(
+        cars.upsert(Car::new("2".into(), "CarBroken".into(), 0)),
+        Err(PackError::ValidationError(_))
+    ));
+    assert_eq!(cars.find_id("2").unwrap().hp, 650);
+    drop(cars);
+    let cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    assert_eq!(cars.find_id("1").unwrap().hp, 120);
+    assert_eq!(cars.find_id("4").unwrap().name, "CarNew");
+}
+
+#[test]
+fn test_get_or_insert_with() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_get_or_insert_with");
+    let mut cars = create_dummy_vecpack(path.clone());
+    let car = cars
+        .get_or_insert_with("1", || panic!("ID 1 exists"))
+        .unwrap();
+    assert_eq!(car.hp, 150);
+    let car = cars
+        .get_or_insert_with("4", || Car::new("4".into(), "Default".into(), 1))
+        .unwrap();
+    car.as_mut().unpack().hp = 100;
+    assert_eq!(cars.len(), 4);
+    assert!(matches!(
+        cars.get_or_insert_with("5", || Car::new("6".into(), "".into(), 1)),
+        Err(PackError::InternalError(_))
+    ));
+    assert!(cars.find_id("5").is_err());
+    assert!(cars.find_id("6").is_err());
+    drop(cars);
+    let cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    assert_eq!(cars.find_id("4").unwrap().hp, 100);
+}
+
+#[test]
+fn test_contains_id_ids() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_contains_id");
+    let mut cars = create_dummy_vecpack(path.clone());
+    assert!(cars.contains_id("2"));
+    assert!(!cars.contains_id("4"));
+    let _reservation = cars.reserve_id("4").unwrap();
+    assert!(!cars.contains_id("4"));
+    assert_eq!(cars.ids().collect::<Vec<&str>>(), vec!["1", "2", "3"]);
+    cars.remove_by_id("2").unwrap();
+    assert!(!cars.contains_id("2"));
+    assert_eq!(cars.ids().collect::<Vec<&str>>(), vec!["1", "3"]);
+}
+
+#[test]
+fn test_id_lookup() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_id_lookup");
+    let mut cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    let items = (0..2000)
+        .map(|i| Car::new(i.to_string(), "Car".into(), 100))
+        .collect();
+    assert!(cars.insert_many(items).unwrap().is_empty());
+    assert!(!cars.check_id_available("1999"));
+    assert_eq!(cars.find_id("1500").unwrap().id, "1500");
+    // Removes shift the later members
+    cars.remove_by_id("10").unwrap();
+    assert!(cars.find_id("10").is_err());
+    assert_eq!(cars.find_id("11").unwrap().id, "11");
+    assert_eq!(cars.find_id("1999").unwrap().id, "1999");
+    // ID changed through a mutable borrow
+    cars.find_id_mut("20").unwrap().as_mut().unpack().id = "x".into();
+    assert!(cars.find_id("20").is_err());
+    assert_eq!(cars.find_id("x").unwrap().id, "x");
+    cars.sort_by(|a, b| b.id.cmp(&a.id)).unwrap();
+    assert_eq!(cars.find_id("5").unwrap().id, "5");
+    assert_eq!(cars.first().unwrap().id, "x");
+    assert!(cars.check_id_available("20"));
+    cars.insert(Car::new("y".into(), "Car".into(), 1)).unwrap();
+    assert_eq!(cars.find_id("y").unwrap().hp, 1);
+}
+
+#[test]
+fn test_find_ids() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_find_ids");
+    let cars = create_dummy_vecpack(path.clone());
+    let (found, missing) = cars.find_ids(&["3", "9", "1", "7"]);
+    let found: Vec<&str> = found.iter().map(|i| i.get_id()).collect();
+    assert_eq!(found, vec!["3", "1"]);
+    assert_eq!(missing, vec!["9", "7"]);
+    let ids = vec!["2".to_string()];
+    let (found, missing) = cars.find_ids(&ids);
+    assert_eq!(found[0].hp, 650);
+    assert!(missing.is_empty());
+}
+
+#[test]
+fn test_retain_remove_where() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_retain");
+    let mut cars = create_dummy_vecpack(path.clone());
+    cars.enable_order_index().unwrap();
+    cars.insert_with_ttl(
+        Car::new("4".into(), "CarTiny".into(), 50),
+        Duration::from_secs(60),
+    )
+    .unwrap();
+    cars.set_ttl("2", Duration::from_secs(60)).unwrap();
+    let removed = cars.remove_where(|car| car.hp < 200).unwrap();
+    let removed: Vec<&str> = removed.iter().map(|i| i.id.as_str()).collect();
+    assert_eq!(removed, vec!["1", "4"]);
+    assert!(!path.join("1.yml").exists());
+    assert!(cars.expires_at("4").is_none());
+    assert!(cars.expires_at("2").is_some());
+    assert_eq!(cars.find_id("3").unwrap().id, "3");
+    let removed = cars.retain(|car| car.name == "CarBig").unwrap();
+    assert_eq!(removed.len(), 1);
+    assert_eq!(removed[0].id, "3");
+    assert!(cars.retain(|_| true).unwrap().is_empty());
+    drop(cars);
+    let cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    assert_eq!(cars.ids().collect::<Vec<&str>>(), vec!["2"]);
+}
+
+#[test]
+fn test_load_or_init_parallel() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_load_parallel");
+    let mut cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    for i in 0..200 {
+        cars.insert(Car::new(format!("{}", i), format!("Car{}", i), i + 1))
+            .unwrap();
+    }
+    drop(cars);
+    let sequential: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    let parallel: VecPack<Car> =
+        VecPack::load_or_init_parallel(path.clone()).unwrap();
+    assert_eq!(parallel.len(), 200);
+    assert_eq!(
+        parallel.ids().collect::<Vec<&str>>(),
+        sequential.ids().collect::<Vec<&str>>()
+    );
+    assert_eq!(parallel.find_id("42").unwrap().hp, 43);
+    drop(parallel);
+    std::fs::write(path.join("7.yml"), "not: [a car").unwrap();
+    assert!(VecPack::<Car>::load_or_init_parallel(path.clone()).is_err());
+}
+
+#[test]
+fn test_commit_parallel() {
+    let dir = testing::TempDir::new().unwrap();
+    let path = dir.path().join("vecpack_test_commit_parallel");
+    let mut cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    for i in 1..=100 {
+        cars.insert(Car::new(format!("{}", i), format!("Car{}", i), i))
+            .unwrap();
+    }
+    assert!(cars.enable_validation().is_empty());
+    let mut batch = cars.batch_mut().unwrap();
+    for mut car in batch.iter_mut() {
+        match car.hp % 10 {
+            0 => car.hp = 0,
+            _ => car.hp += 1000,
+        }
+    }
+    let report = batch.commit_parallel(4);
+    assert!(!report.is_ok());
+    assert_eq!(report.saved.len(), 90);
+    let failed: Vec<&str> =
+        report.failed.iter().map(|f| f.0.as_str()).collect();
+    assert_eq!(
+        failed,
+        vec!["10", "20", "30", "40", "50", "60", "70", "80", "90", "100"]
+    );
+    assert!(report
+        .failed
+        .iter()
+        .all(|(_, err)| matches!(err, PackError::ValidationError(_))));
+    // Failed members are rolled back
+    assert_eq!(cars.find_id("10").unwrap().hp, 10);
+    assert!(cars.save_all_parallel(0).is_ok());
+    drop(cars);
+    let cars: VecPack<Car> = VecPack::load_or_init(path.clone()).unwrap();
+    assert_eq!(cars.find_id("7").unwrap().hp, 1007);
+    assert_eq!(cars.find_id("20").unwrap().hp, 20);
+}